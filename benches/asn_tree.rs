@@ -0,0 +1,111 @@
+//! Benchmarks loading and querying the ASN trie with a full routeviews table,
+//! from the text file and from the compiled ASN database.
+//!
+//! Set ROUTEVIEWS_V4 and/or ROUTEVIEWS_V6 to CAIDA routeviews prefix2as files, e.g.
+//! `ROUTEVIEWS_V4=routeviews-rv2-20210601-1200.pfx2as cargo bench --bench asn_tree`.
+//! Without them a synthetic IPv4 table of full table size is generated.
+
+use std::env::{self, temp_dir};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+use yarrp_toolkit::analytics::ASNAttribution;
+
+const SYNTHETIC_PREFIXES: u32 = 900_000;
+const LOOKUPS: usize = 10_000;
+
+/// writes prefixes between /8 and /24 with random origins in routeviews format
+fn synthetic_table() -> PathBuf {
+    let path = temp_dir().join("yarrp_toolkit_bench_routeviews.pfx2as");
+    if path.exists() {
+        return path;
+    }
+
+    let mut rng = Pcg64::seed_from_u64(42);
+    let mut writer = BufWriter::new(File::create(&path).unwrap());
+    for _ in 0..SYNTHETIC_PREFIXES {
+        let prefix_len: u32 = rng.gen_range(8..=24);
+        let network = rng.gen::<u32>() & (u32::MAX << (32 - prefix_len));
+        writeln!(writer, "{}\t{}\t{}", Ipv4Addr::from(network), prefix_len, rng.gen_range(1..400_000)).unwrap();
+    }
+    writer.flush().unwrap();
+    path
+}
+
+fn random_addresses(is_v4: bool) -> Vec<IpAddr> {
+    let mut rng = Pcg64::seed_from_u64(7);
+    (0..LOOKUPS).map(|_| {
+        if is_v4 {
+            IpAddr::V4(Ipv4Addr::from(rng.gen::<u32>()))
+        } else {
+            // most announced IPv6 space is within 2000::/4
+            IpAddr::V6(Ipv6Addr::from((rng.gen::<u128>() >> 4) | (0x2u128 << 124)))
+        }
+    }).collect()
+}
+
+fn tables() -> Vec<(&'static str, String, bool)> {
+    let mut tables = Vec::new();
+    if let Ok(path) = env::var("ROUTEVIEWS_V4") {
+        tables.push(("v4", path, true));
+    }
+    if let Ok(path) = env::var("ROUTEVIEWS_V6") {
+        tables.push(("v6", path, false));
+    }
+    if tables.is_empty() {
+        tables.push(("synthetic_v4", synthetic_table().to_str().unwrap().to_string(), true));
+    }
+    tables
+}
+
+fn load_attribution(path: &str, is_v4: bool) -> ASNAttribution {
+    let root = if is_v4 { "0.0.0.0/0" } else { "::/0" };
+    let mut attribution = ASNAttribution::new(root);
+    if attribution.load_routeviews_bgp(path).is_err() {
+        panic!("Could not load {}", path);
+    }
+    attribution
+}
+
+fn bench_asn_tree(c: &mut Criterion) {
+    for (name, path, is_v4) in tables() {
+        let mut group = c.benchmark_group("asn_tree");
+        group.sample_size(10);
+        group.bench_with_input(BenchmarkId::new("load", name), &path, |b, path| {
+            b.iter(|| load_attribution(path, is_v4))
+        });
+
+        let attribution = load_attribution(&path, is_v4);
+        let database = temp_dir().join(format!("yarrp_toolkit_bench_{}.asndb", name));
+        let database = database.to_str().unwrap();
+        if attribution.write_asn_database(database, &path).is_err() {
+            panic!("Could not write {}", database);
+        }
+        group.bench_with_input(BenchmarkId::new("load_database", name), &path, |b, path| {
+            b.iter(|| {
+                let mut cached = ASNAttribution::new(if is_v4 { "0.0.0.0/0" } else { "::/0" });
+                assert!(matches!(cached.load_asn_database(database, path), Ok(true)));
+                cached
+            })
+        });
+        group.finish();
+
+        let addresses = random_addresses(is_v4);
+        c.bench_with_input(BenchmarkId::new("asn_tree/lookup_10k", name), &addresses, |b, addresses| {
+            b.iter(|| {
+                for address in addresses {
+                    black_box(attribution.get_asn_for_ip(address));
+                }
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_asn_tree);
+criterion_main!(benches);
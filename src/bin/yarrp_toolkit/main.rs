@@ -10,7 +10,7 @@ extern crate rand_pcg;
 extern crate serde_derive;
 
 // Add own mods to the structure
-use yarrp_toolkit::{read_lines, get_correct_mode};
+use yarrp_toolkit::{read_input_file, get_correct_mode};
 use yarrp_toolkit::structs::Config;
 
 // Mode Imports
@@ -116,36 +116,22 @@ fn read_input_files(config: &mut Config) {
     info!("Working with {} input files.", config.input_files.len());
     let mut file_number = 0;
 
+    let quiet = config.quiet;
+    let line_count = config.line_count;
+
     for file_path in &config.input_files {
         info!("Using file {}", file_path);
 
-        if let Ok(lines) = read_lines(&file_path) {
-            // Consumes the iterator, returns an (Optional) String
-            for line in lines {
-                if let Ok(str_line) = line {
-
-                    if str_line.starts_with('#') {
-                        // remove leading '#' and resulting whitespaces
-                        let str_line = str_line[1..].trim();
-                        config.mode_item.parse_comment_line(&str_line);
-                    } else {
-                        config.mode_item.parse_string_line(&str_line);
-                    }
-
-                    if !config.quiet && pb.inc() == pb.total{
-                        pb.total = pb.total + config.line_count;
-                    }
-                } else {
-                    error!("Could not read line?");
-                }
-
+        let result = read_input_file(config.mode_item.as_mut(), &file_path, file_number, || {
+            if !quiet && pb.inc() == pb.total {
+                pb.total = pb.total + line_count;
             }
-        } else {
+        });
+        if let Err(_) = result {
             error!("Input file not found!");
             exit(1);
         }
 
-        config.mode_item.do_file_rotate(file_number, &file_path);
         info!("File ({}) {} finished.", file_number, &file_path);
         file_number += 1;
 
@@ -0,0 +1,141 @@
+pub mod as2org {
+    use std::collections::{HashMap, HashSet};
+    use std::path::Path;
+    use itertools::Itertools;
+    use log::{info, warn};
+
+    use crate::read_lines;
+    use crate::structs::{ASNOrigin, YarrpError};
+
+    enum AS2OrgSection {
+        Unknown,
+        Organizations,
+        AutonomousSystems,
+    }
+
+    /// Maps ASNs to their organisation, read from a CAIDA as2org file.
+    /// ASNs missing from the file are treated as an organisation of their own named AS<asn>.
+    pub struct AS2Org {
+        asn_to_org: HashMap<String, String>,
+        org_names: HashMap<String, String>,
+    }
+
+    impl AS2Org {
+        /// reads the pipe separated as2org format, the organisation block (org_id|changed|org_name|country|source)
+        /// and the AS block (aut|changed|aut_name|org_id|opaque_id|source) are told apart by their "# format:" lines
+        pub fn load(path: &Path) -> Result<AS2Org, YarrpError> {
+            info!("Loading as2org data");
+            let mut asn_to_org = HashMap::new();
+            let mut org_names = HashMap::new();
+            let mut section = AS2OrgSection::Unknown;
+
+            for line in read_lines(path)? {
+                let line = line?;
+                if let Some(format) = line.strip_prefix("# format:") {
+                    section = match format.split('|').next() {
+                        Some("org_id") => AS2OrgSection::Organizations,
+                        Some("aut") => AS2OrgSection::AutonomousSystems,
+                        _ => AS2OrgSection::Unknown,
+                    };
+                    continue;
+                }
+
+                if line.starts_with('#') || line.is_empty() {
+                    continue;
+                }
+
+                let fields: Vec<&str> = line.split('|').collect();
+                match section {
+                    AS2OrgSection::Organizations if fields.len() >= 3 => {
+                        org_names.insert(fields[0].to_string(), fields[2].to_string());
+                    }
+                    AS2OrgSection::AutonomousSystems if fields.len() >= 4 => {
+                        asn_to_org.insert(fields[0].to_string(), fields[3].to_string());
+                    }
+                    _ => warn!("Skipping as2org line {}", line),
+                }
+            }
+
+            info!("Loaded {} ASN of {} organisations", asn_to_org.len(), org_names.len());
+            Ok(AS2Org {
+                asn_to_org,
+                org_names,
+            })
+        }
+
+        pub fn get_org(&self, asn: &str) -> String {
+            match self.asn_to_org.get(asn) {
+                Some(org) => org.clone(),
+                None => format!("AS{}", asn),
+            }
+        }
+
+        pub fn get_org_name(&self, org: &str) -> &str {
+            self.org_names.get(org).map(|name| name.as_str()).unwrap_or("")
+        }
+
+        fn org_set(&self, asns: &[String]) -> ASNOrigin {
+            let mut orgs: Vec<String> = asns.iter().map(|asn| self.get_org(asn)).unique().collect();
+            orgs.sort();
+
+            match orgs.len() {
+                0 => ASNOrigin::Unknown,
+                1 => ASNOrigin::Single(orgs.remove(0)),
+                _ => ASNOrigin::AsSet(orgs),
+            }
+        }
+
+        /// maps the ASNs of an origin to organisations, a MOAS prefix or AS set within one organisation becomes a single origin
+        pub fn org_origin(&self, origin: &ASNOrigin) -> ASNOrigin {
+            match origin {
+                ASNOrigin::Unknown => ASNOrigin::Unknown,
+                ASNOrigin::Single(asn) => ASNOrigin::Single(self.get_org(asn)),
+                ASNOrigin::AsSet(members) => self.org_set(members),
+                ASNOrigin::Moas(origins) => {
+                    let mut org_origins: Vec<ASNOrigin> = origins.iter().map(|origin| self.org_origin(origin)).unique().collect();
+                    if org_origins.len() == 1 {
+                        return org_origins.remove(0);
+                    }
+
+                    // origins of one organisation collapse to a single one
+                    let orgs: HashSet<String> = org_origins.iter().flat_map(|origin| origin.asns()).collect();
+                    if orgs.len() == 1 {
+                        return ASNOrigin::Single(orgs.into_iter().next().unwrap());
+                    }
+                    ASNOrigin::Moas(org_origins)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analytics::AS2Org;
+    use crate::helpers::test_helper::TempFixture;
+    use crate::structs::{ASNOrigin, ASNOriginKind};
+
+    #[test]
+    fn org_origin_collapses_sibling_asn() {
+        let file = TempFixture::file("as2org.txt", "# name: AS Org\n\
+            # format:org_id|changed|org_name|country|source\n\
+            ORG-A|20210101|Example A|AT|RIPE\n\
+            ORG-B|20210101|Example B|US|ARIN\n\
+            # format:aut|changed|aut_name|org_id|opaque_id|source\n\
+            64496|20210101|A-ONE|ORG-A|x|RIPE\n\
+            64497|20210101|A-TWO|ORG-A|x|RIPE\n\
+            64498|20210101|B-ONE|ORG-B|x|ARIN\n");
+
+        let as2org = AS2Org::load(file.path()).ok().unwrap();
+        assert_eq!(as2org.get_org("64497"), "ORG-A");
+        assert_eq!(as2org.get_org_name("ORG-B"), "Example B");
+        assert_eq!(as2org.get_org("64499"), "AS64499", "Unmapped ASN should be its own organisation");
+
+        let moas = ASNOrigin::parse("64496_64497");
+        assert_eq!(as2org.org_origin(&moas), ASNOrigin::Single("ORG-A".to_string()));
+
+        let moas = ASNOrigin::parse("64496_64497,64498");
+        assert_eq!(as2org.org_origin(&moas).kind(), Some(ASNOriginKind::Moas));
+        assert_eq!(as2org.org_origin(&moas).asns(), vec!["ORG-A".to_string(), "ORG-B".to_string()]);
+    }
+}
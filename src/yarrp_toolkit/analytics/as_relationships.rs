@@ -0,0 +1,175 @@
+pub mod as_relationships {
+    use std::collections::{HashMap, HashSet};
+    use std::fmt::{self, Display, Formatter};
+    use std::path::Path;
+    use log::{info, warn};
+
+    use crate::read_lines;
+    use crate::analytics::AS2Org;
+    use crate::structs::{ASNOrigin, YarrpError, flatten_origins, stable_sorted};
+
+    /// Relationship between two ASes, siblings are taken from as2org data
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum ASRelationship {
+        CustomerProvider,
+        Peer,
+        Sibling,
+    }
+
+    /// Classification of a loop by the relationships of the ASes its routers map to
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum LoopASRelationship {
+        /// loop within a single AS
+        SingleAS,
+        CustomerProvider,
+        Peer,
+        Sibling,
+        /// AS pairs with different relationships
+        Mixed,
+        /// no relationship known for any AS pair or no router of the loop attributed
+        Unknown,
+    }
+
+    pub const LOOP_AS_RELATIONSHIPS: [LoopASRelationship; 6] = [LoopASRelationship::SingleAS, LoopASRelationship::CustomerProvider,
+        LoopASRelationship::Peer, LoopASRelationship::Sibling, LoopASRelationship::Mixed, LoopASRelationship::Unknown];
+
+    impl Display for LoopASRelationship {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            let relationship = match self {
+                LoopASRelationship::SingleAS => "single_as",
+                LoopASRelationship::CustomerProvider => "customer_provider",
+                LoopASRelationship::Peer => "peer",
+                LoopASRelationship::Sibling => "sibling",
+                LoopASRelationship::Mixed => "mixed",
+                LoopASRelationship::Unknown => "unknown",
+            };
+            write!(f, "{}", relationship)
+        }
+    }
+
+    /// AS relationships read from a CAIDA as-rel file
+    pub struct ASRelationships {
+        relationships: HashMap<(String, String), ASRelationship>,
+    }
+
+    impl ASRelationships {
+        /// reads the as-rel format <provider-as>|<customer-as>|-1 and <peer-as>|<peer-as>|0,
+        /// the serial-2 source column is ignored
+        pub fn load(path: &Path) -> Result<ASRelationships, YarrpError> {
+            info!("Loading AS relationships");
+            let mut relationships = HashMap::new();
+
+            for line in read_lines(path)? {
+                let line = line?;
+                if line.starts_with('#') || line.is_empty() {
+                    continue;
+                }
+
+                let fields: Vec<&str> = line.split('|').collect();
+                if fields.len() < 3 {
+                    warn!("Skipping as-rel line {}", line);
+                    continue;
+                }
+
+                let relationship = match fields[2] {
+                    "-1" => ASRelationship::CustomerProvider,
+                    "0" => ASRelationship::Peer,
+                    _ => {
+                        warn!("Skipping as-rel line {}", line);
+                        continue;
+                    }
+                };
+                relationships.insert(ASRelationships::key(fields[0], fields[1]), relationship);
+            }
+
+            info!("Loaded {} AS relationships", relationships.len());
+            Ok(ASRelationships {
+                relationships
+            })
+        }
+
+        fn key(first: &str, second: &str) -> (String, String) {
+            if first <= second {
+                (first.to_string(), second.to_string())
+            } else {
+                (second.to_string(), first.to_string())
+            }
+        }
+
+        /// relationship of two ASes in either direction, ASes of the same organisation are siblings
+        pub fn get_relationship(&self, first: &str, second: &str, as2org: Option<&AS2Org>) -> Option<ASRelationship> {
+            if let Some(as2org) = as2org {
+                if as2org.get_org(first) == as2org.get_org(second) {
+                    return Some(ASRelationship::Sibling);
+                }
+            }
+            self.relationships.get(&ASRelationships::key(first, second)).copied()
+        }
+
+        /// classifies a loop by the relationships between the distinct ASNs of its origins, also within a MOAS prefix
+        /// or AS set, AS pairs without known relationship are ignored unless no pair is known
+        pub fn classify_loop(&self, origins: &HashSet<ASNOrigin>, as2org: Option<&AS2Org>) -> LoopASRelationship {
+            let asns = flatten_origins(origins);
+            match asns.len() {
+                0 => return LoopASRelationship::Unknown,
+                1 => return LoopASRelationship::SingleAS,
+                _ => {}
+            }
+
+            let asns = stable_sorted(&asns);
+            let mut found = HashSet::new();
+            for (index, first) in asns.iter().enumerate() {
+                for second in &asns[index + 1..] {
+                    if let Some(relationship) = self.get_relationship(first, second, as2org) {
+                        found.insert(relationship);
+                    }
+                }
+            }
+
+            match found.len() {
+                0 => LoopASRelationship::Unknown,
+                1 => match found.into_iter().next().unwrap() {
+                    ASRelationship::CustomerProvider => LoopASRelationship::CustomerProvider,
+                    ASRelationship::Peer => LoopASRelationship::Peer,
+                    ASRelationship::Sibling => LoopASRelationship::Sibling,
+                },
+                _ => LoopASRelationship::Mixed,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::analytics::{ASRelationships, LoopASRelationship};
+    use crate::structs::ASNOrigin;
+    use crate::helpers::test_helper::TempFixture;
+
+    fn origins(asns: &[&str]) -> HashSet<ASNOrigin> {
+        asns.iter().map(|asn| ASNOrigin::parse(asn)).collect()
+    }
+
+    #[test]
+    fn classify_loops_by_as_pairs() {
+        let file = TempFixture::file("as_rel.txt", "# source:topology|BGP\n\
+            64496|64497|-1\n\
+            64498|64497|0|bgp\n\
+            64499|64500|-1\n");
+
+        let relationships = ASRelationships::load(file.path()).ok().unwrap();
+        assert_eq!(relationships.classify_loop(&origins(&["64496"]), None), LoopASRelationship::SingleAS);
+        assert_eq!(relationships.classify_loop(&origins(&["64497", "64496"]), None), LoopASRelationship::CustomerProvider);
+        assert_eq!(relationships.classify_loop(&origins(&["64497", "64498"]), None), LoopASRelationship::Peer);
+        assert_eq!(relationships.classify_loop(&origins(&["64496", "64500"]), None), LoopASRelationship::Unknown);
+        assert_eq!(relationships.classify_loop(&origins(&["64496", "64497", "64498"]), None), LoopASRelationship::Mixed,
+                   "Unknown pair 64496-64498 should be ignored");
+        assert_eq!(relationships.classify_loop(&origins(&["64499_64496", "64500"]), None), LoopASRelationship::CustomerProvider);
+        assert_eq!(relationships.classify_loop(&HashSet::new(), None), LoopASRelationship::Unknown,
+                   "A loop without attributed router is not within a single AS");
+        assert_eq!(relationships.classify_loop(&origins(&["64496_64497"]), None), LoopASRelationship::CustomerProvider,
+                   "A loop within one MOAS prefix spans both of its ASes");
+        assert_eq!(relationships.classify_loop(&origins(&["64496", "64496_64497"]), None), LoopASRelationship::CustomerProvider);
+        assert_eq!(relationships.classify_loop(&origins(&["64496", "64496,64496"]), None), LoopASRelationship::SingleAS);
+    }
+}
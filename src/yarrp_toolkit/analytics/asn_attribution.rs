@@ -1,22 +1,28 @@
 pub mod asn_attribution {
-    use crate::structs::{ASNTreeRoot, YarrpError, ASNTree, MapSetString, ASNRouterEntry, ASNLoopEntry, MapVecT, ASNShadowedResults, ASNShadowedOutput};
+    use crate::structs::{ASNTreeRoot, ASNTreeNode, YarrpError, ASNTree, MapSetString, MapSetT, ASNRouterEntry, ASNLoopEntry, MapVecT, ASNShadowedResults, ASNShadowedOutput, stable_sorted};
+    use crate::structs::{AtomicFile, atomic_csv_writer, commit_csv, MrtRibReader, ASNDatabase, write_asn_database};
+    use crate::structs::{ASNOrigin, ASNOriginKind, ASNMembers, MapASNMembers, MapOrigin, MapSetOrigin, flatten_origins, merge_origins};
     use ipnet::IpNet;
     use std::process::exit;
-    use log::{error, info, trace};
+    use log::{error, info, trace, warn};
     use std::str::FromStr;
     use std::path::{Path, PathBuf};
     use crate::read_lines;
     use itertools::Itertools;
     use std::collections::{HashMap, HashSet};
     use std::net::IpAddr;
+    use std::io::{BufReader, Write};
     use std::fs::File;
-    use std::io::Write;
     use std::fmt::Display;
     use std::hash::Hash;
     use crate::traits::IpAddrExt;
+    use crate::analytics::{AS2Org, ASRelationships, LoopASRelationship, RoaTable, RpkiState, GeoAttribution};
 
     pub struct ASNAttribution {
         root: ASNTreeRoot,
+        as2org: Option<AS2Org>,
+        as_relationships: Option<ASRelationships>,
+        roas: Option<RoaTable>,
     }
 
     impl ASNAttribution {
@@ -39,9 +45,50 @@ pub mod asn_attribution {
             }
             ASNAttribution {
                 root: root_node,
+                as2org: None,
+                as_relationships: None,
+                roas: None,
             }
         }
 
+        pub fn load_as2org(&mut self, path: &str) -> Result<(), YarrpError> {
+            self.as2org = Some(AS2Org::load(Path::new(path))?);
+            Ok(())
+        }
+
+        pub fn get_as2org(&self) -> Option<&AS2Org> {
+            self.as2org.as_ref()
+        }
+
+        pub fn load_as_relationships(&mut self, path: &str) -> Result<(), YarrpError> {
+            self.as_relationships = Some(ASRelationships::load(Path::new(path))?);
+            Ok(())
+        }
+
+        /// classifies a loop by the relationships of its origins as built by build_loops_to_asn,
+        /// returns None without as-rel data
+        pub fn classify_loop(&self, loop_origins: &HashSet<ASNOrigin>) -> Option<LoopASRelationship> {
+            let as_relationships = self.as_relationships.as_ref()?;
+            Some(as_relationships.classify_loop(loop_origins, self.as2org.as_ref()))
+        }
+
+        pub fn load_roas(&mut self, path: &str) -> Result<(), YarrpError> {
+            self.roas = Some(RoaTable::load(Path::new(path))?);
+            Ok(())
+        }
+
+        pub fn has_roas(&self) -> bool {
+            self.roas.is_some()
+        }
+
+        /// origin validation state of the most specific announced prefix containing the address,
+        /// returns None without ROAs or if no announced prefix covers the address
+        pub fn get_rpki_state_for_ip(&self, ip_addr: &IpAddr) -> Option<RpkiState> {
+            let roas = self.roas.as_ref()?;
+            let node = self.root.find_node(ip_addr)?;
+            Some(roas.validate(node.get_network(), node.get_origin()))
+        }
+
         pub fn load_routeviews_bgp(&mut self, path: &str) -> Result<(), YarrpError> {
             info!("Loading BGP data into ASNTree");
             let path = Path::new(path);
@@ -66,105 +113,217 @@ pub mod asn_attribution {
             Ok(())
         }
 
+        /// builds the tree from an uncompressed MRT TABLE_DUMP_V2 RIB dump,
+        /// prefixes of the other address family and prefixes without origin are skipped
+        pub fn load_mrt_rib(&mut self, path: &str) -> Result<(), YarrpError> {
+            info!("Loading MRT RIB dump into ASNTree");
+            let root_net = *self.root.get_network();
+            let reader = MrtRibReader::new(BufReader::new(File::open(path)?));
+
+            let mut without_origin = 0;
+            for entry in reader {
+                let entry = entry?;
+                if !root_net.contains(&entry.network) {
+                    continue;
+                }
+
+                if entry.origins.is_empty() {
+                    without_origin += 1;
+                    continue;
+                }
+
+                if !self.root.add_network(entry.network, &entry.asn_string()) {
+                    error!("Could not add network {} to tree!", &entry.network);
+                }
+            }
+
+            if without_origin > 0 {
+                info!("Skipped {} prefixes without origin AS", without_origin);
+            }
+            info!("Added {} networks to the root tree!", self.root.get_num_children());
+
+            Ok(())
+        }
+
+        /// loads the tree from a database compiled from source_path,
+        /// returns false if the database is missing, unreadable or was built from another source or address family
+        pub fn load_asn_database(&mut self, database_path: &str, source_path: &str) -> Result<bool, YarrpError> {
+            let database_path = Path::new(database_path);
+            if !database_path.exists() {
+                info!("No ASN database at {}", database_path.to_str().unwrap_or(""));
+                return Ok(false);
+            }
+
+            let database = match ASNDatabase::open(database_path) {
+                Ok(database) => database,
+                Err(_) => {
+                    warn!("Could not read ASN database, falling back to source");
+                    return Ok(false);
+                }
+            };
+
+            let header = database.header();
+            if header.network != *self.root.get_network() {
+                info!("ASN database covers {}, not {}", header.network, self.root.get_network());
+                return Ok(false);
+            }
+
+            if !header.matches_source(Path::new(source_path))? {
+                info!("ASN database is stale, {} changed", source_path);
+                return Ok(false);
+            }
+
+            info!("Loading ASN database compiled at {}", header.created);
+            self.root = database.build_tree()?;
+            info!("Added {} networks to the root tree!", self.root.get_num_children());
+            Ok(true)
+        }
+
+        /// compiles the current tree into a database for source_path
+        pub fn write_asn_database(&self, database_path: &str, source_path: &str) -> Result<(), YarrpError> {
+            info!("Writing ASN database to {}", database_path);
+            write_asn_database(Path::new(database_path), &self.root, Path::new(source_path))
+        }
+
         pub fn get_asn_for_ip(&self, ip_addr: &IpAddr) -> Option<Vec<String>> {
             let mut asn = None;
 
             if let Some(asn_node) = self.root.find_node(ip_addr) {
-                let asn_vec = asn_node.get_asn().clone();
-                asn = Some(asn_vec);
+                asn = Some(asn_node.get_asn());
             }
 
             return asn;
         }
 
+        /// most specific announced prefix containing the address
+        pub fn get_announced_node(&self, ip_addr: &IpAddr) -> Option<&ASNTreeNode> {
+            self.root.find_node(ip_addr)
+        }
+
+        /// all announced prefixes with their origin, covering prefixes before the prefixes within them
+        pub fn get_announced_networks(&self) -> Vec<(&IpNet, &ASNOrigin)> {
+            self.root.announced_networks()
+        }
+
+        /// origin of the most specific announced prefix containing the address
+        pub fn get_origin_for_ip(&self, ip_addr: &IpAddr) -> ASNOrigin {
+            match self.root.find_node(ip_addr) {
+                Some(node) => node.get_origin().clone(),
+                None => ASNOrigin::Unknown
+            }
+        }
+
         ///
         ///
         /// # Arguments
         ///
         /// * `routers`:
         ///
-        /// returns: Result<(HashMap<String, HashSet<String, RandomState>, RandomState>, HashMap<String, HashSet<String, RandomState>, RandomState>), YarrpError>
-        ///     Result<(asn to router, router to asn), YarrpError>
+        /// returns: Result<(HashMap<String, ASNMembers>, HashMap<String, ASNOrigin>), YarrpError>
+        ///     Result<(asn to routers split by kind of attribution, router to origin), YarrpError>
         /// # Examples
         ///
         /// ```
         ///
         /// ```
-        pub fn build_routers_to_asn(&mut self, routers: &HashMap<String, HashSet<String>>)
-                                    -> Result<(HashMap<String, HashSet<String>>, HashMap<String, HashSet<String>>), YarrpError> {
-            let mut asn_to_router = HashMap::new();
-            let mut router_to_asn = HashMap::new();
-
-            for (router, _loops) in routers {
-                let router_ip = IpAddr::from_str(&router)?;
-
-                if !router_to_asn.contains_key(router) {
-                    router_to_asn.insert(router.clone(), HashSet::new());
-                }
-
-                let asn_set;
-                if let Some(set) = router_to_asn.get_mut(router) {
-                    asn_set = set;
-                } else {
-                    error!("Could not get hashset!");
-                    return Err(YarrpError::CouldNotWriteError);
+        pub fn build_routers_to_asn<T>(&mut self, routers: &HashMap<T, HashSet<String>>)
+                                       -> Result<(MapASNMembers, MapOrigin), YarrpError>
+            where T: Display + IpAddrExt {
+            let mut asn_to_router: MapASNMembers = HashMap::new();
+            let mut router_to_origin: MapOrigin = HashMap::new();
+
+            for router in routers.keys() {
+                let origin = self.get_origin_for_ip(&router.to_ipaddr()?);
+                let router = router.to_string();
+
+                for (asn, kind) in origin.attributions() {
+                    asn_to_router.entry(asn.clone()).or_default().insert(kind, &router);
                 }
+                router_to_origin.insert(router, origin);
+            }
 
-                if let Some(leaf) = self.root.find_node(&router_ip) {
-                    for asn_string in leaf.get_asn() {
-                        asn_set.insert(asn_string.clone());
-
-                        if !asn_to_router.contains_key(asn_string) {
-                            asn_to_router.insert(asn_string.clone(), HashSet::new());
-                        }
+            Ok((asn_to_router, router_to_origin))
+        }
 
-                        if let Some(asn_entry) = asn_to_router.get_mut(asn_string) {
-                            asn_entry.insert(router.clone());
-                        }
-                    }
+        /// maps the router origins to organisations, returns None without as2org data
+        ///
+        /// returns: Option<(organisation to routers split by kind of attribution, router to organisation origin)>
+        pub fn build_routers_to_org(&self, routers_to_origin: &MapOrigin) -> Option<(MapASNMembers, MapOrigin)> {
+            let as2org = self.as2org.as_ref()?;
+            let mut org_to_router: MapASNMembers = HashMap::new();
+            let mut router_to_org: MapOrigin = HashMap::new();
+
+            for (router, origin) in routers_to_origin {
+                let org_origin = as2org.org_origin(origin);
+                for (org, kind) in org_origin.attributions() {
+                    org_to_router.entry(org.clone()).or_default().insert(kind, router);
                 }
+                router_to_org.insert(router.clone(), org_origin);
             }
 
-            Ok((asn_to_router, router_to_asn))
+            Some((org_to_router, router_to_org))
         }
 
-        pub fn build_loops_to_asn(&self, loops: &MapSetString, routers_to_asn: &MapSetString)
-                                  -> Result<(MapSetString, MapSetString), YarrpError> {
-            let mut l2a: MapSetString = HashMap::new();
-            let mut a2l: MapSetString = HashMap::new();
+        /// collects the distinct known origins of the loop members, a MOAS prefix or AS set counts as one origin
+        pub fn build_loops_to_asn<T>(&self, loops: &MapSetT<T>, routers_to_origin: &MapOrigin)
+                                     -> Result<(MapASNMembers, MapSetOrigin), YarrpError>
+            where T: Display {
+            let mut l2a: MapSetOrigin = HashMap::new();
+            let mut a2l: MapASNMembers = HashMap::new();
 
             for (loop_id, members) in loops {
-                if !l2a.contains_key(loop_id) {
-                    l2a.insert(loop_id.clone(), HashSet::new());
-                }
-
-                if let Some(set) = l2a.get_mut(loop_id) {
-                    for router in members {
-                        if let Some(router_asn) = routers_to_asn.get(router) {
-                            for asn in router_asn {
-                                set.insert(asn.clone());
+                let origins = l2a.entry(loop_id.clone()).or_default();
 
-                                if !a2l.contains_key(asn) {
-                                    a2l.insert(asn.clone(), HashSet::new());
-                                }
+                for router in members {
+                    if let Some(origin) = routers_to_origin.get(&router.to_string()) {
+                        if !origin.is_known() {
+                            continue;
+                        }
 
-                                if let Some(loop_set) = a2l.get_mut(asn) {
-                                    loop_set.insert(loop_id.clone());
-                                }
-                            }
+                        for (asn, kind) in origin.attributions() {
+                            a2l.entry(asn.clone()).or_default().insert(kind, loop_id);
                         }
+                        origins.insert(origin.clone());
                     }
                 }
             }
             Ok((a2l, l2a))
         }
 
-        pub fn build_shadowed_asn_to_loop<T>(&self, loop_destinations: &MapVecT<T>, loops_to_asn: &MapSetString, output_path: &PathBuf)
+        /// with geo_attribution given, the country of each shadowed net is added
+        pub fn build_shadowed_asn_to_loop<T>(&self, loop_destinations: &MapVecT<T>, loops_to_asn: &MapSetOrigin, output_path: &PathBuf, geo_attribution: Option<&GeoAttribution>)
                                              -> Result<ASNShadowedResults, YarrpError>
             where T: 'static + Display + Ord + Copy + Eq + Clone + Hash + IpAddrExt + FromStr
         {
             info!("Creating shadowed asn attribution");
-            let mut writer = csv::Writer::from_path(output_path)?;
+            let (shadowed_count, _per_asn) = self.build_shadowed_to_loop(loop_destinations, loops_to_asn, output_path, None, geo_attribution)?;
+            Ok(shadowed_count)
+        }
+
+        /// organisation level version of build_shadowed_asn_to_loop, loops_to_org has to be built from build_routers_to_org
+        ///
+        /// returns: Result<(counts, shadowed nets per organisation), YarrpError>
+        pub fn build_shadowed_org_to_loop<T>(&self, loop_destinations: &MapVecT<T>, loops_to_org: &MapSetOrigin, output_path: &PathBuf, geo_attribution: Option<&GeoAttribution>)
+                                             -> Result<(ASNShadowedResults, HashMap<String, u64>), YarrpError>
+            where T: 'static + Display + Ord + Copy + Eq + Clone + Hash + IpAddrExt + FromStr
+        {
+            info!("Creating shadowed organisation attribution");
+            if self.as2org.is_none() {
+                error!("No as2org data loaded!");
+                return Err(YarrpError::NotFoundError);
+            }
+            self.build_shadowed_to_loop(loop_destinations, loops_to_org, output_path, self.as2org.as_ref(), geo_attribution)
+        }
+
+        /// compares the origin of each shadowed net with the origins of its loop,
+        /// with as2org given origins are compared on organisation level
+        fn build_shadowed_to_loop<T>(&self, loop_destinations: &MapVecT<T>, loops_to_asn: &MapSetOrigin, output_path: &PathBuf, as2org: Option<&AS2Org>,
+                                     geo_attribution: Option<&GeoAttribution>)
+                                     -> Result<(ASNShadowedResults, HashMap<String, u64>), YarrpError>
+            where T: 'static + Display + Ord + Copy + Eq + Clone + Hash + IpAddrExt + FromStr
+        {
+            let mut shadowed_per_asn: HashMap<String, u64> = HashMap::new();
+            let mut writer = atomic_csv_writer(output_path)?;
 
             let mut shadowed_count = ASNShadowedResults {
                 shadowed_asn_is_with_loop: 0,
@@ -172,16 +331,19 @@ pub mod asn_attribution {
                 shadowed_asn_is_unknown: 0,
                 shadowed_asn_with_single_asn: 0,
                 shadowed_asn_with_multiple_asn: 0,
+                shadowed_asn_is_moas: 0,
+                shadowed_asn_is_as_set: 0,
+                shadowed_rpki_valid: 0,
+                shadowed_rpki_invalid: 0,
+                shadowed_rpki_unknown: 0,
             };
             // let mut shadowed_to_asn = HashMap::new();
 
-            for (loop_id, shadowed) in loop_destinations {
-                let current_loop_asn: HashSet<String>;
-                if let Some(value) = loops_to_asn.get(loop_id) {
-                    current_loop_asn = value.clone();
-                } else {
-                    current_loop_asn = HashSet::new();
-                }
+            for loop_id in stable_sorted(loop_destinations.keys()) {
+                let shadowed = &loop_destinations[loop_id];
+                let loop_origins = loops_to_asn.get(loop_id);
+                let current_loop_asn = flatten_origins(loop_origins.into_iter().flatten());
+                let current_loop_origins = merge_origins(loop_origins.into_iter().flatten()).len();
 
                 for shadowed_net in shadowed {
                     let ip_addr = shadowed_net.to_ipaddr()?;
@@ -202,22 +364,45 @@ pub mod asn_attribution {
                         asn_in_loop: false,
                         all_asn_in_loop: false,
                         num_asn: 0,
+                        origin_type: String::new(),
                         loop_asn: current_loop_asn.len() as u8,
+                        loop_origins: current_loop_origins as u8,
+                        rpki_state: String::from("undefined"),
+                        country: geo_attribution.and_then(|geo| geo.get_country_for_ip(&ip_addr)).unwrap_or_else(|| String::from("undefined")),
                     };
 
+                    // validated on AS level, also for organisation level attribution
+                    if let Some(state) = self.get_rpki_state_for_ip(&ip_addr) {
+                        match state {
+                            RpkiState::Valid => shadowed_count.shadowed_rpki_valid += 1,
+                            RpkiState::Invalid => shadowed_count.shadowed_rpki_invalid += 1,
+                            RpkiState::NotFound => shadowed_count.shadowed_rpki_unknown += 1,
+                        }
+                        shadowed_csv_obj.rpki_state = state.to_string();
+                    }
+
                     // grab asn
-                    let shadowed_asn: Vec<String>;
-                    if let Some(node) = self.root.find_node(&shadowed_csv_obj.shadowed_net) {
-                        shadowed_asn = node.get_asn().clone();
-                    } else {
-                        shadowed_asn = Vec::new();
+                    let mut shadowed_origin = self.get_origin_for_ip(&shadowed_csv_obj.shadowed_net);
+                    if let Some(as2org) = as2org {
+                        shadowed_origin = as2org.org_origin(&shadowed_origin);
+                    }
+                    let shadowed_asn = shadowed_origin.asns();
+                    for asn in &shadowed_asn {
+                        *shadowed_per_asn.entry(asn.clone()).or_default() += 1;
                     }
 
-                    // if multiple shadowed asn
-                    match shadowed_asn.len() {
-                        0 => {},
-                        1 => shadowed_count.shadowed_asn_with_single_asn += 1,
-                        _ => shadowed_count.shadowed_asn_with_multiple_asn += 1
+                    // multiple shadowed asn are either a MOAS prefix or an AS set
+                    match shadowed_origin.kind() {
+                        None => {},
+                        Some(ASNOriginKind::Single) => shadowed_count.shadowed_asn_with_single_asn += 1,
+                        Some(ASNOriginKind::Moas) => {
+                            shadowed_count.shadowed_asn_with_multiple_asn += 1;
+                            shadowed_count.shadowed_asn_is_moas += 1;
+                        }
+                        Some(ASNOriginKind::AsSet) => {
+                            shadowed_count.shadowed_asn_with_multiple_asn += 1;
+                            shadowed_count.shadowed_asn_is_as_set += 1;
+                        }
                     }
 
                     // basic pre condition checks dont have to do a lot if there are no found asn
@@ -249,6 +434,7 @@ pub mod asn_attribution {
                         }
 
                         shadowed_csv_obj.num_asn = shadowed_asn.len() as u8;
+                        shadowed_csv_obj.origin_type = shadowed_origin.kind().map(|kind| kind.to_string()).unwrap_or_default();
                         shadowed_csv_obj.all_asn_in_loop = found_all_asn;
                         shadowed_csv_obj.asn_in_loop = found_asn_in_loop;
                         shadowed_csv_obj.asn_entries = shadowed_asn;
@@ -268,28 +454,35 @@ pub mod asn_attribution {
                     // }
                 }
             }
-            Ok(shadowed_count)
+            commit_csv(writer)?;
+            Ok((shadowed_count, shadowed_per_asn))
         }
 
+        /// ASNs of the shadowed net and its preceding router, preceding routers that did not answer
+        /// are written as Unknown-<ttl> and stay unattributed
         pub fn get_shadowed_preceding_asn(&self, shadowed: &str, preceding: &str) -> Result<(Vec<String>, Vec<String>), YarrpError> {
             let shadowed_ip = IpAddr::from_str(shadowed)?;
-            let preceding_ip = IpAddr::from_str(preceding)?;
 
             let shadowed_vec;
             let preceding_vec;
 
             if let Some(value) = self.root.find_node(&shadowed_ip) {
-                shadowed_vec = value.get_asn().clone();
+                shadowed_vec = value.get_asn();
             } else {
                 shadowed_vec = Vec::new();
                 trace!("No node found for shadowed {}", &shadowed_ip);
             }
 
-            if let Some(value) = self.root.find_node(&preceding_ip) {
-                preceding_vec = value.get_asn().clone();
+            if let Ok(preceding_ip) = IpAddr::from_str(preceding) {
+                if let Some(value) = self.root.find_node(&preceding_ip) {
+                    preceding_vec = value.get_asn();
+                } else {
+                    preceding_vec = Vec::new();
+                    trace!("No node found for preceding {}", &preceding_ip);
+                }
             } else {
                 preceding_vec = Vec::new();
-                trace!("No node found for preceding {}", &preceding_ip);
+                trace!("Preceding router {} is no address", preceding);
             }
 
             Ok((shadowed_vec, preceding_vec))
@@ -310,11 +503,11 @@ pub mod asn_attribution {
         }
 
         pub fn write_item_to_asn_csv(&self, output_path: &Path, x2a: &MapSetString) -> Result<(), YarrpError> {
-            if let Ok(mut output_file) = File::create(output_path) {
-                for (key, values) in x2a {
+            if let Ok(mut output_file) = AtomicFile::create(output_path) {
+                for key in stable_sorted(x2a.keys()) {
                     let mut record = Vec::new();
                     record.push(key.clone());
-                    for value in values {
+                    for value in stable_sorted(&x2a[key]) {
                         record.push(value.clone());
                     }
 
@@ -327,6 +520,7 @@ pub mod asn_attribution {
                         exit(1);
                     }
                 }
+                output_file.commit()?;
             } else {
                 error!("Could not open or create output file!");
             }
@@ -335,7 +529,8 @@ pub mod asn_attribution {
             Ok(())
         }
 
-        pub fn write_asn_csv(&self, output_path: &Path, a2r: &MapSetString, a2l: &MapSetString) -> Result<(), YarrpError> {
+        /// routers and loops per ASN, and split into those attributed by a single origin, through a MOAS prefix or an AS set
+        pub fn write_asn_csv(&self, output_path: &Path, a2r: &MapASNMembers, a2l: &MapASNMembers) -> Result<(), YarrpError> {
             let mut keys = HashSet::new();
             for key in a2r.keys() {
                 keys.insert(key.clone());
@@ -345,45 +540,80 @@ pub mod asn_attribution {
                 keys.insert(key.clone());
             }
 
-            let mut csv_writer = csv::Writer::from_path(output_path)?;
+            let mut csv_writer = atomic_csv_writer(output_path)?;
 
-            if let Err(_e) = csv_writer.write_record(&["asn", "routers", "loops"]) {
+            if let Err(_e) = csv_writer.write_record(&["asn", "routers", "loops", "single_routers", "single_loops", "moas_routers", "moas_loops", "as_set_routers", "as_set_loops"]) {
                 error!("Could not write header row for asn.csv!");
                 return Err(YarrpError::CouldNotWriteError);
             }
 
-            for key in keys {
-                let routers;
-                let loops;
-                if let Some(set) = a2r.get(&key) {
-                    routers = set.len() as u64;
-                } else {
-                    routers = 0;
-                }
+            let empty = ASNMembers::default();
+            for key in stable_sorted(&keys) {
+                let routers = a2r.get(key).unwrap_or(&empty);
+                let loops = a2l.get(key).unwrap_or(&empty);
 
-                if let Some(set) = a2l.get(&key) {
-                    loops = set.len() as u64;
-                } else {
-                    loops = 0;
+                let record = [key.clone(),
+                    routers.all().len().to_string(), loops.all().len().to_string(),
+                    routers.single.len().to_string(), loops.single.len().to_string(),
+                    routers.moas.len().to_string(), loops.moas.len().to_string(),
+                    routers.as_set.len().to_string(), loops.as_set.len().to_string()];
+                csv_writer.write_record(&record)?;
+            }
+            commit_csv(csv_writer)
+        }
+
+        /// routers, loops and shadowed nets per organisation, like write_asn_csv with the organisation name
+        pub fn write_org_csv(&self, output_path: &Path, o2r: &MapASNMembers, o2l: &MapASNMembers, shadowed: &HashMap<String, u64>) -> Result<(), YarrpError> {
+            let as2org = match &self.as2org {
+                Some(as2org) => as2org,
+                None => {
+                    error!("No as2org data loaded!");
+                    return Err(YarrpError::NotFoundError);
                 }
+            };
+
+            let mut keys = HashSet::new();
+            keys.extend(o2r.keys().cloned());
+            keys.extend(o2l.keys().cloned());
+            keys.extend(shadowed.keys().cloned());
+
+            let mut csv_writer = atomic_csv_writer(output_path)?;
+
+            if let Err(_e) = csv_writer.write_record(&["org", "name", "routers", "loops", "shadowed", "single_routers", "single_loops", "moas_routers", "moas_loops", "as_set_routers", "as_set_loops"]) {
+                error!("Could not write header row for org.csv!");
+                return Err(YarrpError::CouldNotWriteError);
+            }
 
-                let record = [&key, &routers.to_string(), &loops.to_string()];
+            let empty = ASNMembers::default();
+            for key in stable_sorted(&keys) {
+                let routers = o2r.get(key).unwrap_or(&empty);
+                let loops = o2l.get(key).unwrap_or(&empty);
+
+                let record = [key.clone(), as2org.get_org_name(key).to_string(),
+                    routers.all().len().to_string(), loops.all().len().to_string(),
+                    shadowed.get(key).unwrap_or(&0).to_string(),
+                    routers.single.len().to_string(), loops.single.len().to_string(),
+                    routers.moas.len().to_string(), loops.moas.len().to_string(),
+                    routers.as_set.len().to_string(), loops.as_set.len().to_string()];
                 csv_writer.write_record(&record)?;
             }
-            Ok(())
+            commit_csv(csv_writer)
         }
 
-        pub fn write_asn_router_entries(&self, output_path: &Path, r2a: &MapSetString, router_persistent: &HashSet<String>) -> Result<(), YarrpError> {
+        pub fn write_asn_router_entries(&self, output_path: &Path, r2a: &MapOrigin, router_persistent: &HashSet<String>) -> Result<(), YarrpError> {
             info!("Writing ASN Router Entries!");
-            let mut writer = csv::Writer::from_path(output_path)?;
-            for (router, asn) in r2a {
+            let mut writer = atomic_csv_writer(output_path)?;
+            for router in stable_sorted(r2a.keys()) {
+                let origin = &r2a[router];
                 let persistent = router_persistent.contains(router);
+                let asn = flatten_origins(std::iter::once(origin));
 
                 let mut storage = ASNRouterEntry {
                     router_ip: router.clone(),
                     number_asn: asn.len() as u64,
+                    origin_type: origin.kind().map(|kind| kind.to_string()).unwrap_or_else(|| "undefined".to_string()),
                     is_persistent: persistent,
-                    asn_list: asn.clone(),
+                    asn_list: asn,
                 };
 
                 if storage.asn_list.len() == 0 {
@@ -393,41 +623,130 @@ pub mod asn_attribution {
                     error!("{}", _e);
                 }
             }
-            Ok(())
+            commit_csv(writer)
         }
 
-        pub fn write_asn_loop_entries(&self, output_path: &Path, loop_members: &MapSetString, r2a: &MapSetString, loop_persistence: &HashSet<String>) -> Result<(), YarrpError> {
+        pub fn write_asn_loop_entries<T>(&self, output_path: &Path, loop_members: &MapSetT<T>, r2a: &MapOrigin, loop_persistence: &HashSet<String>) -> Result<(), YarrpError>
+            where T: Display {
             info!("Writing ASN Loop Entries!");
-            let mut writer = csv::Writer::from_path(output_path)?;
+            let mut writer = atomic_csv_writer(output_path)?;
 
-            for (loop_id, members) in loop_members {
+            for loop_id in stable_sorted(loop_members.keys()) {
+                let members = &loop_members[loop_id];
                 let persistent = loop_persistence.contains(loop_id);
                 let mut all_assigned = true;
 
                 let mut asn_list = HashSet::new();
+                let mut origins = HashSet::new();
                 for member in members {
-                    if let Some(asn_set) = r2a.get(member) {
-                        if asn_set.len() == 0 {
+                    if let Some(origin) = r2a.get(&member.to_string()) {
+                        if !origin.is_known() {
                             all_assigned = false;
                             asn_list.insert("undefined".to_string());
                         } else {
-                            for asn in asn_set {
-                                asn_list.insert(asn.clone());
-                            }
+                            asn_list.extend(origin.asns());
+                            origins.insert(origin);
                         }
                     }
                 }
 
+                // origins sharing an ASN are one domain, so a loop within a single MOAS prefix spans one origin
                 let storage = ASNLoopEntry {
                     loop_id: loop_id.clone(),
                     number_asn: asn_list.len() as u64,
+                    number_origins: merge_origins(origins.iter().cloned()).len() as u64,
+                    number_moas: origins.iter().filter(|origin| origin.kind() == Some(ASNOriginKind::Moas)).count() as u64,
+                    number_as_set: origins.iter().filter(|origin| origin.kind() == Some(ASNOriginKind::AsSet)).count() as u64,
                     is_persistent: persistent,
                     all_routers_assigned: all_assigned,
                     asn_list,
                 };
                 writer.serialize(storage)?;
             }
-            Ok(())
+            commit_csv(writer)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use std::time::{Duration, SystemTime};
+    use crate::analytics::ASNAttribution;
+    use crate::structs::{ASNOrigin, MapOrigin, MapSetString, ShadowedPrecedingCounter};
+    use crate::helpers::test_helper::TempFixture;
+
+    #[test]
+    fn unknown_preceding_router_is_unattributed() {
+        let pfx2as = TempFixture::file("preceding.pfx2as", "192.0.2.0\t24\t64496\n198.51.100.0\t24\t64497\n");
+        let mut attribution = ASNAttribution::new("0.0.0.0/0");
+        attribution.load_routeviews_bgp(pfx2as.to_str()).ok().unwrap();
+
+        let (shadowed, preceding) = attribution.get_shadowed_preceding_asn("198.51.100.1", "192.0.2.7").ok().unwrap();
+        assert_eq!((shadowed, preceding), (vec!["64497".to_string()], vec!["64496".to_string()]));
+
+        let (shadowed, preceding) = attribution.get_shadowed_preceding_asn("198.51.100.1", "Unknown-7").ok().unwrap();
+        assert_eq!(shadowed, vec!["64497".to_string()]);
+        assert!(preceding.is_empty());
+        assert!(attribution.get_shadowed_preceding_asn("Unknown-7", "192.0.2.7").is_err());
+
+        let mut counter = ShadowedPrecedingCounter::new();
+        counter.add(&shadowed, &preceding, &[vec!["64497".to_string()]], 1);
+        let table = counter.destination_domain_table();
+        assert!(table.contains(&("preceding_router_not_in_dest_domain", 1)));
+        assert!(counter.loop_domains_table().contains(&("preceding_not_same_domain", 1)));
+    }
+
+    #[test]
+    fn stale_database_falls_back_to_source() {
+        let source = TempFixture::file("stale.pfx2as", "192.0.2.0\t24\t64496\n");
+        let database = TempFixture::new("stale.asndb");
+        let load = |root_net: &str| ASNAttribution::new(root_net).load_asn_database(database.to_str(), source.to_str()).ok().unwrap();
+        assert!(!load("0.0.0.0/0"), "No database yet");
+
+        let mut attribution = ASNAttribution::new("0.0.0.0/0");
+        attribution.load_routeviews_bgp(source.to_str()).ok().unwrap();
+        attribution.write_asn_database(database.to_str(), source.to_str()).ok().unwrap();
+
+        let mut cached = ASNAttribution::new("0.0.0.0/0");
+        assert!(cached.load_asn_database(database.to_str(), source.to_str()).ok().unwrap());
+        assert_eq!(cached.get_asn_for_ip(&IpAddr::from_str("192.0.2.1").unwrap()), Some(vec!["64496".to_string()]));
+        assert!(!load("::/0"), "Database of another address family");
+
+        // a touched source with the same content is still current
+        let touched = fs::OpenOptions::new().write(true).open(source.path()).unwrap();
+        touched.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000)).unwrap();
+        drop(touched);
+        assert!(load("0.0.0.0/0"));
+
+        fs::write(source.path(), "192.0.2.0\t24\t64497\n").unwrap();
+        assert!(!load("0.0.0.0/0"), "Source of the same size with another content");
+
+        fs::write(database.path(), "not a database").unwrap();
+        assert!(!load("0.0.0.0/0"), "Unreadable databases fall back to the source");
+    }
+
+    #[test]
+    fn loop_entries_merge_origins_sharing_an_asn() {
+        let attribution = ASNAttribution::new("0.0.0.0/0");
+        let mut routers_to_asn: MapOrigin = HashMap::new();
+        routers_to_asn.insert("192.0.2.1".to_string(), ASNOrigin::parse("64496"));
+        routers_to_asn.insert("192.0.2.2".to_string(), ASNOrigin::parse("64496_64497"));
+        routers_to_asn.insert("198.51.100.1".to_string(), ASNOrigin::parse("64498"));
+        routers_to_asn.insert("198.51.100.2".to_string(), ASNOrigin::Unknown);
+
+        let mut loop_members: MapSetString = HashMap::new();
+        loop_members.insert("a".to_string(), vec!["192.0.2.1", "192.0.2.2"].into_iter().map(String::from).collect());
+        loop_members.insert("b".to_string(), vec!["192.0.2.1", "198.51.100.1", "198.51.100.2"].into_iter().map(String::from).collect());
+
+        let output = TempFixture::new("loop_asn.csv");
+        attribution.write_asn_loop_entries(output.path(), &loop_members, &routers_to_asn, &HashSet::new()).ok().unwrap();
+        assert_eq!(fs::read_to_string(output.path()).unwrap(),
+                   "loop_id,number_asn,number_origins,number_moas,number_as_set,is_persistent,all_routers_assigned,asn_list\n\
+                   a,2,1,1,0,false,true,64496;64497\n\
+                   b,3,2,0,0,false,false,64496;64498;undefined\n");
+    }
+}
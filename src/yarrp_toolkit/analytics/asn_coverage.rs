@@ -0,0 +1,154 @@
+pub mod asn_coverage {
+    use std::collections::{HashMap, HashSet};
+    use std::net::IpAddr;
+    use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+    use log::info;
+
+    use crate::analytics::ASNAttribution;
+    use crate::structs::ASNTree;
+
+    /// address space is counted in /24 (IPv4) or /48 (IPv6) blocks
+    fn block_len(address: &IpAddr) -> u8 {
+        match address {
+            IpAddr::V4(_) => 24,
+            IpAddr::V6(_) => 48,
+        }
+    }
+
+    fn block_of(address: &IpAddr) -> IpNet {
+        // prefix lengths of 24 and 48 are always valid
+        match address {
+            IpAddr::V4(address) => IpNet::V4(Ipv4Net::new(*address, 24).unwrap().trunc()),
+            IpAddr::V6(address) => IpNet::V6(Ipv6Net::new(*address, 48).unwrap().trunc()),
+        }
+    }
+
+    #[derive(Default)]
+    struct AnnouncedSpace {
+        prefixes: u64,
+        blocks: u64,
+        /// blocks partially announced by prefixes longer than the block length
+        partial_blocks: HashSet<IpNet>,
+    }
+
+    /// Announced address space per origin AS compared with the addresses of a target list.
+    /// Prefixes of MOAS and AS set origins count for all of their ASes, like the addresses attributed to them.
+    #[derive(Default)]
+    pub struct ASNCoverage {
+        announced: HashMap<String, AnnouncedSpace>,
+        touched_prefixes: HashMap<String, HashSet<IpNet>>,
+        covered_blocks: HashMap<String, HashSet<IpNet>>,
+    }
+
+    impl ASNCoverage {
+        /// sums up the announced space of each AS, prefixes within another prefix of the same AS are not counted twice
+        pub fn new(attribution: &ASNAttribution) -> ASNCoverage {
+            let mut prefixes_per_asn: HashMap<String, Vec<IpNet>> = HashMap::new();
+            for (network, origin) in attribution.get_announced_networks() {
+                for asn in origin.asns() {
+                    prefixes_per_asn.entry(asn).or_default().push(*network);
+                }
+            }
+
+            let mut coverage = ASNCoverage::default();
+            for (asn, mut prefixes) in prefixes_per_asn {
+                // covering prefixes are ordered before the prefixes within them
+                prefixes.sort();
+                let space = coverage.announced.entry(asn).or_default();
+                let mut covering: Option<IpNet> = None;
+
+                for prefix in prefixes {
+                    space.prefixes += 1;
+                    if covering.map(|covering| covering.contains(&prefix)).unwrap_or(false) {
+                        continue;
+                    }
+                    covering = Some(prefix);
+
+                    let address = prefix.network();
+                    let block_len = block_len(&address);
+                    if prefix.prefix_len() <= block_len {
+                        space.blocks += 1u64 << (block_len - prefix.prefix_len());
+                    } else if space.partial_blocks.insert(block_of(&address)) {
+                        space.blocks += 1;
+                    }
+                }
+            }
+
+            info!("Announced space of {} ASN", coverage.announced.len());
+            coverage
+        }
+
+        pub fn add_address(&mut self, attribution: &ASNAttribution, address: &IpAddr) {
+            if let Some(node) = attribution.get_announced_node(address) {
+                let block = block_of(address);
+                for asn in node.get_asn() {
+                    self.touched_prefixes.entry(asn.clone()).or_default().insert(*node.get_network());
+                    self.covered_blocks.entry(asn).or_default().insert(block);
+                }
+            }
+        }
+
+        /// all ASNs originating a prefix
+        pub fn announcing_asns(&self) -> impl Iterator<Item = &String> {
+            self.announced.keys()
+        }
+
+        pub fn get_announced_prefixes(&self, asn: &str) -> u64 {
+            self.announced.get(asn).map(|space| space.prefixes).unwrap_or(0)
+        }
+
+        /// announced /24 or /48 blocks, partially announced blocks count as one
+        pub fn get_announced_blocks(&self, asn: &str) -> u64 {
+            self.announced.get(asn).map(|space| space.blocks).unwrap_or(0)
+        }
+
+        /// distinct announced prefixes the addresses were attributed to
+        pub fn get_touched_prefixes(&self, asn: &str) -> u64 {
+            self.touched_prefixes.get(asn).map(|prefixes| prefixes.len() as u64).unwrap_or(0)
+        }
+
+        /// distinct /24 or /48 blocks of the addresses attributed to the AS
+        pub fn get_covered_blocks(&self, asn: &str) -> u64 {
+            self.covered_blocks.get(asn).map(|blocks| blocks.len() as u64).unwrap_or(0)
+        }
+
+        pub fn get_block_coverage(&self, asn: &str) -> f64 {
+            match self.get_announced_blocks(asn) {
+                0 => 0.0,
+                blocks => self.get_covered_blocks(asn) as f64 / blocks as f64
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use crate::analytics::{ASNAttribution, ASNCoverage};
+    use crate::helpers::test_helper::TempFixture;
+
+    #[test]
+    fn announced_and_covered_blocks() {
+        let routeviews = TempFixture::file("asn_coverage.pfx2as", "10.0.0.0\t22\t64496\n10.0.1.0\t24\t64496\n10.0.8.0\t25\t64496\n10.0.8.128\t25\t64496\n\
+            192.0.2.0\t24\t64497_64498\n");
+        let mut attribution = ASNAttribution::new("0.0.0.0/0");
+        attribution.load_routeviews_bgp(routeviews.to_str()).ok().unwrap();
+
+        let mut coverage = ASNCoverage::new(&attribution);
+        for address in &["10.0.0.1", "10.0.0.2", "10.0.1.1", "10.0.8.200", "192.0.2.1", "203.0.113.1"] {
+            coverage.add_address(&attribution, &IpAddr::from_str(address).unwrap());
+        }
+
+        assert_eq!(coverage.get_announced_prefixes("64496"), 4);
+        assert_eq!(coverage.get_announced_blocks("64496"), 5, "The /24 within the /22 and both halves of a /24 count once");
+        assert_eq!(coverage.get_touched_prefixes("64496"), 3);
+        assert_eq!(coverage.get_covered_blocks("64496"), 3);
+        assert_eq!(coverage.get_block_coverage("64496"), 0.6);
+
+        assert_eq!(coverage.get_announced_blocks("64498"), 1);
+        assert_eq!(coverage.get_block_coverage("64498"), 1.0);
+        assert_eq!(coverage.get_block_coverage("64499"), 0.0);
+        assert_eq!(coverage.announcing_asns().count(), 3);
+    }
+}
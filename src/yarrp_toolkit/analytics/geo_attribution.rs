@@ -0,0 +1,207 @@
+pub mod geo_attribution {
+    use std::collections::{HashMap, HashSet};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::path::Path;
+    use std::process::exit;
+    use std::str::FromStr;
+    use ipnet::{IpNet, Ipv4Subnets, Ipv6Subnets};
+    use log::{error, info, warn};
+
+    use crate::structs::{ASNTreeRoot, ASNTree, ASNOrigin, MapSetString, YarrpError, CountryEntry, atomic_csv_writer, commit_csv, stable_sorted};
+
+    /// Attributes addresses to the country of the most specific range of an offline geolocation database.
+    /// The ranges are kept in the prefix trie of the ASN attribution, with the country code as origin.
+    pub struct GeoAttribution {
+        root: ASNTreeRoot,
+    }
+
+    impl GeoAttribution {
+        pub fn new(root_str: &str) -> GeoAttribution {
+            let root_net: IpNet;
+
+            if let Ok(net) = IpNet::from_str(root_str) {
+                root_net = net;
+            } else {
+                error!("Could not parse root network {}!", root_str);
+                exit(1);
+            }
+
+            let root_node;
+            if let Ok(node) = ASNTreeRoot::new(root_net) {
+                root_node = node;
+            } else {
+                error!("Could not initialize root node!");
+                exit(1);
+            }
+            GeoAttribution {
+                root: root_node,
+            }
+        }
+
+        fn parse_address(&self, value: &str) -> Option<IpAddr> {
+            let value = value.trim();
+            if let Ok(address) = IpAddr::from_str(value) {
+                return Some(address);
+            }
+
+            // IP2Location style integer addresses of the loaded address family
+            match self.root.get_network() {
+                IpNet::V4(_) => value.parse::<u32>().ok().map(|address| IpAddr::V4(Ipv4Addr::from(address))),
+                IpNet::V6(_) => value.parse::<u128>().ok().map(|address| IpAddr::V6(Ipv6Addr::from(address))),
+            }
+        }
+
+        fn range_networks(start: IpAddr, end: IpAddr) -> Vec<IpNet> {
+            match (start, end) {
+                (IpAddr::V4(start), IpAddr::V4(end)) => Ipv4Subnets::new(start, end, 0).map(IpNet::V4).collect(),
+                (IpAddr::V6(start), IpAddr::V6(end)) => Ipv6Subnets::new(start, end, 0).map(IpNet::V6).collect(),
+                _ => Vec::new(),
+            }
+        }
+
+        /// reads a CSV without header of either network,country or start,end,country lines,
+        /// start and end are addresses or integers like in the IP2Location CSV, further columns are ignored.
+        /// Ranges of the other address family and unknown countries (- or empty) are skipped.
+        pub fn load(&mut self, path: &Path) -> Result<(), YarrpError> {
+            info!("Loading geolocation data");
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_path(path)?;
+
+            let mut num_ranges: u64 = 0;
+            let mut num_skipped: u64 = 0;
+            for record in reader.records() {
+                let record = record?;
+                let first = record.get(0).unwrap_or("");
+
+                let (networks, country) = if first.contains('/') {
+                    match IpNet::from_str(first.trim()) {
+                        Ok(network) => (vec![network.trunc()], record.get(1)),
+                        Err(_) => {
+                            warn!("Skipping geolocation line {:?}", record);
+                            continue;
+                        }
+                    }
+                } else {
+                    let start = self.parse_address(first);
+                    let end = record.get(1).and_then(|end| self.parse_address(end));
+                    match (start, end) {
+                        (Some(start), Some(end)) => (GeoAttribution::range_networks(start, end), record.get(2)),
+                        _ => {
+                            warn!("Skipping geolocation line {:?}", record);
+                            continue;
+                        }
+                    }
+                };
+
+                let country = country.unwrap_or("").trim().to_uppercase();
+                if country.is_empty() || country == "-" {
+                    num_skipped += 1;
+                    continue;
+                }
+
+                let mut added = false;
+                for network in networks {
+                    if self.root.get_network().contains(&network) {
+                        self.root.add_origin(network, ASNOrigin::Single(country.clone()));
+                        added = true;
+                    }
+                }
+
+                if added {
+                    num_ranges += 1;
+                } else {
+                    num_skipped += 1;
+                }
+            }
+
+            info!("Loaded {} geolocation ranges, skipped {}", num_ranges, num_skipped);
+            Ok(())
+        }
+
+        /// country code of the most specific range containing the address
+        pub fn get_country_for_ip(&self, ip_addr: &IpAddr) -> Option<String> {
+            let node = self.root.find_node(ip_addr)?;
+            node.get_asn().into_iter().next()
+        }
+
+        /// one line per country with routers, loops and shadowed nets located in it,
+        /// a loop counts for every country of its members
+        pub fn write_country_csv(&self, output_path: &Path, routers_to_country: &HashMap<String, String>,
+                                 loops_to_country: &MapSetString, shadowed_per_country: &HashMap<String, u64>) -> Result<(), YarrpError> {
+            let mut routers: HashMap<&String, u64> = HashMap::new();
+            for country in routers_to_country.values() {
+                *routers.entry(country).or_insert(0) += 1;
+            }
+
+            let mut loops: HashMap<&String, u64> = HashMap::new();
+            for countries in loops_to_country.values() {
+                for country in countries {
+                    *loops.entry(country).or_insert(0) += 1;
+                }
+            }
+
+            let countries: HashSet<&String> = routers.keys().chain(loops.keys()).cloned()
+                .chain(shadowed_per_country.keys())
+                .collect();
+
+            let mut writer = atomic_csv_writer(output_path)?;
+            for country in stable_sorted(countries) {
+                writer.serialize(CountryEntry {
+                    country: country.to_string(),
+                    routers: *routers.get(country).unwrap_or(&0),
+                    loops: *loops.get(country).unwrap_or(&0),
+                    shadowed: *shadowed_per_country.get(country).unwrap_or(&0),
+                })?;
+            }
+            commit_csv(writer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use crate::analytics::GeoAttribution;
+    use crate::helpers::test_helper::TempFixture;
+
+    fn country(geo: &GeoAttribution, address: &str) -> Option<String> {
+        geo.get_country_for_ip(&IpAddr::from_str(address).unwrap())
+    }
+
+    #[test]
+    fn ranges_to_countries() {
+        let ranges = TempFixture::file("geo.csv", "\"ip_from\",\"ip_to\",\"country_code\",\"country_name\"\n\
+            \"167772160\",\"184549375\",\"US\",\"United States of America\"\n\
+            10.0.1.0,10.0.2.127,at,Austria\n\
+            10.0.3.0/24,DE\n\
+            10.0.4.0,10.0.4.255,-,-\n\
+            2001:db8::,2001:db8::ffff,NL\n");
+
+        let mut geo = GeoAttribution::new("0.0.0.0/0");
+        geo.load(ranges.path()).ok().unwrap();
+
+        assert_eq!(country(&geo, "10.200.0.1"), Some("US".to_string()));
+        assert_eq!(country(&geo, "10.0.1.1"), Some("AT".to_string()));
+        assert_eq!(country(&geo, "10.0.2.127"), Some("AT".to_string()));
+        assert_eq!(country(&geo, "10.0.2.128"), Some("US".to_string()), "Range ends within a /24");
+        assert_eq!(country(&geo, "10.0.3.1"), Some("DE".to_string()));
+        assert_eq!(country(&geo, "10.0.4.1"), Some("US".to_string()), "Unknown countries are skipped");
+        assert_eq!(country(&geo, "192.0.2.1"), None);
+
+        let routers: HashMap<String, String> = vec![("10.0.1.1", "AT"), ("10.0.3.1", "DE"), ("10.0.3.2", "DE")].into_iter()
+            .map(|(router, country)| (router.to_string(), country.to_string())).collect();
+        let mut loops: HashMap<String, HashSet<String>> = HashMap::new();
+        loops.insert("loop".to_string(), vec!["AT".to_string(), "DE".to_string()].into_iter().collect());
+        let mut shadowed = HashMap::new();
+        shadowed.insert("US".to_string(), 3);
+
+        let output = TempFixture::new("country.csv");
+        geo.write_country_csv(output.path(), &routers, &loops, &shadowed).ok().unwrap();
+        assert_eq!(fs::read_to_string(output.path()).unwrap(), "country,routers,loops,shadowed\nAT,1,1,0\nDE,2,1,0\nUS,0,0,3\n");
+    }
+}
@@ -0,0 +1,164 @@
+pub mod loop_amplification {
+    use std::collections::{HashMap, HashSet};
+    use std::path::Path;
+    use log::info;
+
+    use crate::structs::{SimpleLoopOutput, YarrpError, LoopImpactEntry, atomic_csv_writer, commit_csv, stable_cmp};
+
+    /// common initial TTLs of end hosts, 64 (Linux, macOS), 128 (Windows) and 255 (network equipment)
+    pub const INITIAL_TTLS: [u8; 3] = [64, 128, 255];
+
+    /// persistent loops weigh twice as much as loops seen in a single scan
+    const PERSISTENCE_WEIGHT: f64 = 2.0;
+
+    /// expected traversals of the looping link by a single packet entering the loop at loop_start_ttl
+    pub fn amplification(initial_ttl: u8, loop_start_ttl: u8, loop_len: u8) -> f64 {
+        if loop_len == 0 || initial_ttl <= loop_start_ttl {
+            return 0.0;
+        }
+        (initial_ttl - loop_start_ttl) as f64 / loop_len as f64
+    }
+
+    /// amplification for the initial TTL of 64 times log2(1 + shadowed destinations),
+    /// doubled for persistent loops
+    pub fn impact_score(amplification_64: f64, shadowed: u64, persistent: bool) -> f64 {
+        let weight = match persistent {
+            true => PERSISTENCE_WEIGHT,
+            false => 1.0
+        };
+        amplification_64 * (1.0 + shadowed as f64).log2() * weight
+    }
+
+    struct LoopPosition {
+        loop_start_ttl: u8,
+        loop_len: u8,
+    }
+
+    /// Bandwidth amplification of loops, from the earliest start of each loop over all preceding routers
+    pub struct LoopAmplification {
+        positions: HashMap<String, LoopPosition>,
+    }
+
+    impl LoopAmplification {
+        pub fn new(loop_info: &HashMap<String, Vec<SimpleLoopOutput>>) -> LoopAmplification {
+            let mut positions = HashMap::new();
+            for (loop_id, entries) in loop_info {
+                // the earliest start leaves the most TTL to spend within the loop
+                if let Some(entry) = entries.iter().min_by_key(|entry| entry.loop_start_ttl) {
+                    positions.insert(loop_id.clone(), LoopPosition {
+                        loop_start_ttl: entry.loop_start_ttl,
+                        loop_len: entry.loop_len,
+                    });
+                }
+            }
+            info!("Loop positions of {} loops", positions.len());
+            LoopAmplification { positions }
+        }
+
+        pub fn len(&self) -> usize {
+            self.positions.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.positions.is_empty()
+        }
+
+        /// amplification for every initial TTL of INITIAL_TTLS, None for loops without loop information
+        pub fn get_amplification(&self, loop_id: &str) -> Option<[f64; 3]> {
+            let position = self.positions.get(loop_id)?;
+            let mut factors = [0.0; 3];
+            for (factor, initial_ttl) in factors.iter_mut().zip(INITIAL_TTLS.iter()) {
+                *factor = amplification(*initial_ttl, position.loop_start_ttl, position.loop_len);
+            }
+            Some(factors)
+        }
+
+        pub fn get_impact_score(&self, loop_id: &str, shadowed: u64, persistent: bool) -> Option<f64> {
+            let factors = self.get_amplification(loop_id)?;
+            Some(impact_score(factors[0], shadowed, persistent))
+        }
+
+        /// the top loops by impact score, ties by loop id, loops without loop information are left out
+        pub fn write_impact_csv(&self, output_path: &Path, shadowed: &HashMap<String, u64>,
+                                persistent_loops: &HashSet<String>, top: usize) -> Result<(), YarrpError> {
+            let mut entries: Vec<LoopImpactEntry> = Vec::new();
+            for (loop_id, position) in &self.positions {
+                let factors = self.get_amplification(loop_id).unwrap();
+                let loop_shadowed = *shadowed.get(loop_id).unwrap_or(&0);
+                let loop_persistent = persistent_loops.contains(loop_id);
+                entries.push(LoopImpactEntry {
+                    rank: 0,
+                    loop_id: loop_id.clone(),
+                    loop_start_ttl: position.loop_start_ttl,
+                    loop_len: position.loop_len,
+                    amplification_64: factors[0],
+                    amplification_128: factors[1],
+                    amplification_255: factors[2],
+                    shadowed: loop_shadowed,
+                    persistent: loop_persistent,
+                    impact_score: impact_score(factors[0], loop_shadowed, loop_persistent),
+                });
+            }
+
+            entries.sort_by(|a, b| b.impact_score.partial_cmp(&a.impact_score).unwrap()
+                .then_with(|| stable_cmp(&a.loop_id, &b.loop_id)));
+
+            let mut writer = atomic_csv_writer(output_path)?;
+            for (rank, mut entry) in entries.into_iter().take(top).enumerate() {
+                entry.rank = rank as u64 + 1;
+                writer.serialize(entry)?;
+            }
+            commit_csv(writer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use crate::analytics::{LoopAmplification, amplification};
+    use crate::structs::SimpleLoopOutput;
+    use crate::helpers::test_helper::TempFixture;
+
+    fn entry(loop_id: &str, preceding_router: &str, loop_len: u8, loop_start_ttl: u8) -> SimpleLoopOutput {
+        SimpleLoopOutput {
+            loop_id: loop_id.to_string(),
+            preceding_router: preceding_router.to_string(),
+            shadowed_nets: 1,
+            loop_len,
+            loop_start_ttl,
+            loop_stop_ttl: 32,
+            preceding_router_ttl: loop_start_ttl - 1,
+        }
+    }
+
+    #[test]
+    fn amplification_and_impact() {
+        assert_eq!(amplification(64, 10, 2), 27.0);
+        assert_eq!(amplification(64, 64, 2), 0.0);
+        assert_eq!(amplification(64, 10, 0), 0.0);
+
+        let mut loop_info = HashMap::new();
+        loop_info.insert("a".to_string(), vec![entry("a", "192.0.2.1", 2, 12), entry("a", "192.0.2.2", 2, 10)]);
+        loop_info.insert("b".to_string(), vec![entry("b", "192.0.2.3", 3, 31)]);
+        loop_info.insert("c".to_string(), vec![entry("c", "192.0.2.4", 2, 10)]);
+        let amplification = LoopAmplification::new(&loop_info);
+
+        assert_eq!(amplification.get_amplification("a"), Some([27.0, 59.0, 122.5]), "The earliest start counts");
+        assert_eq!(amplification.get_amplification("b"), Some([11.0, 97.0 / 3.0, 224.0 / 3.0]));
+        assert_eq!(amplification.get_amplification("d"), None);
+        assert_eq!(amplification.get_impact_score("a", 3, false), Some(54.0));
+        assert_eq!(amplification.get_impact_score("a", 3, true), Some(108.0));
+
+        let shadowed: HashMap<String, u64> = vec![("a", 3), ("b", 7), ("c", 3)].into_iter()
+            .map(|(loop_id, shadowed)| (loop_id.to_string(), shadowed)).collect();
+        let output = TempFixture::new("impact.csv");
+        let persistent_loops: HashSet<String> = vec!["c".to_string()].into_iter().collect();
+        amplification.write_impact_csv(output.path(), &shadowed, &persistent_loops, 2).ok().unwrap();
+        assert_eq!(fs::read_to_string(output.path()).unwrap(),
+                   "rank,loop_id,loop_start_ttl,loop_len,amplification_64,amplification_128,amplification_255,shadowed,persistent,impact_score\n\
+                   1,c,10,2,27.0,59.0,122.5,3,true,108.0\n\
+                   2,a,10,2,27.0,59.0,122.5,3,false,54.0\n");
+    }
+}
@@ -2,7 +2,8 @@ pub mod loop_analysis {
     use std::collections::{HashMap, HashSet};
     use crate::structs::YarrpLine;
     use crate::structs::Route;
-    use crate::analytics::{LoopStatistics, LoopStorage, LoopImperiled};
+    use crate::structs::DetailFormat;
+    use crate::analytics::{LoopStatistics, LoopStorage, LoopImperiled, LoopEvidence, EVIDENCE};
     use itertools::sorted;
     use log::{error};
     use std::fmt::Display;
@@ -10,6 +11,7 @@ pub mod loop_analysis {
     use crate::traits::IpAddrExt;
     use std::str::FromStr;
     use std::process::exit;
+    use std::path::PathBuf;
 
     pub struct LoopAnalysis<T> {
         pub ttl_map: HashMap<T, HashMap<u8, Vec<YarrpLine<T>>>>,
@@ -18,12 +20,14 @@ pub mod loop_analysis {
         pub loop_statistics: LoopStatistics,
         pub loop_storage: LoopStorage<T>,
         loop_imperiled: LoopImperiled<T>,
+        loop_evidence: LoopEvidence<T>,
     }
 
     impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> LoopAnalysis<T> {
-        pub fn new(only_full_loops: bool, storage_path: String, imperiled_routers: &str, imperiled_blocklist: &str) -> LoopAnalysis<T> {
+        pub fn new(only_full_loops: bool, storage_path: String, imperiled_routers: &str, imperiled_blocklist: &str, detail_format: DetailFormat, evidence_limit: u64) -> LoopAnalysis<T> {
 
-            let loop_storage = LoopStorage::new(only_full_loops, storage_path.clone());
+            let mut loop_storage = LoopStorage::new(only_full_loops, storage_path.clone());
+            loop_storage.detail_format = detail_format;
 
             let imperiled_storage;
             let statistics_storage;
@@ -42,13 +46,24 @@ pub mod loop_analysis {
                 exit(1);
             }
 
+            let mut evidence_storage = PathBuf::from(&storage_path).join(EVIDENCE);
+            if evidence_limit > 0 {
+                if let Ok(path) = loop_storage.get_storage_sub_dir(EVIDENCE) {
+                    evidence_storage = path;
+                } else {
+                    error!("Could not create path for evidence traces!");
+                    exit(1);
+                }
+            }
+
             LoopAnalysis {
                 ttl_map: HashMap::new(),
                 looping_destinations: HashSet::new(),
                 looping_routers: HashSet::new(),
                 loop_statistics: LoopStatistics::new(only_full_loops, statistics_storage),
                 loop_storage,
-                loop_imperiled: LoopImperiled::from_router_file(&imperiled_routers, &imperiled_blocklist, &imperiled_storage)
+                loop_imperiled: LoopImperiled::from_router_file(&imperiled_routers, &imperiled_blocklist, &imperiled_storage, detail_format),
+                loop_evidence: LoopEvidence::new(evidence_limit, evidence_storage, only_full_loops),
             }
         }
 
@@ -79,7 +94,9 @@ pub mod loop_analysis {
         }
 
         pub fn generate_loop_stats(&mut self, min_ttl: u8, max_ttl: u8) {
-            for (_key, value) in &self.ttl_map {
+            // routes are handled ordered by destination, so detail files are written in a stable order
+            for key in sorted(self.ttl_map.keys()) {
+                let value = &self.ttl_map[key];
                 let mut route = Route::new(&value, min_ttl, max_ttl);
 
                 if route.is_looping {
@@ -88,6 +105,9 @@ pub mod loop_analysis {
                     if let Err(_) = self.loop_storage.add_route_information(&route) {
                         error!("Could not add route information for route to {}", route.destination);
                     }
+                    if let Err(_) = self.loop_evidence.add_route(&route, value) {
+                        error!("Could not keep evidence for route to {}", route.destination);
+                    }
                 }
 
                 // Add imperiled check if so desired
@@ -97,6 +117,11 @@ pub mod loop_analysis {
 
             self.loop_storage.update_statistics();
 
+            if let Err(_) = self.loop_evidence.write() {
+                error!("Could not write evidence traces!");
+                exit(1);
+            }
+
             if let Err(_) = self.loop_statistics.write_csv() {
                 error!("Could not write loop statistics file!");
             }
@@ -108,7 +133,7 @@ pub mod loop_analysis {
 
         pub fn print_summary(&self) {
             let percentage = self.loop_statistics.get_loop_percentage() * 100.0;
-            let credibility = self.loop_statistics.average_credibility * 100.0;
+            let credibility = self.loop_statistics.average_credibility() * 100.0;
 
             // General Stats
             println!("Data line count: {}", self.loop_statistics.number_of_routes);
@@ -119,7 +144,7 @@ pub mod loop_analysis {
             println!("Full Loops found: {}", self.loop_statistics.number_of_full_loops);
             println!("Percentage of routes containing loops: {:.02}%", percentage);
             println!("Unique Routers involved: {}", self.looping_routers.len());
-            println!("Average Loop Length: {:.02}", self.loop_statistics.average_loop_length);
+            println!("Average Loop Length: {:.02}", self.loop_statistics.average_loop_length());
             println!("Imperiled Nets: {}", self.loop_statistics.number_of_imperiled);
             println!("Routes with spammer: {}", self.loop_statistics.number_of_spammers);
             println!("Routes with load balancers {}", self.loop_statistics.number_of_load_balancers);
@@ -133,15 +158,87 @@ pub mod loop_analysis {
 
             println!();
             println!("Average credibility: {:.02}%", credibility);
+            println!("Median credibility: {:.01}%", self.loop_statistics.credibility_quantile(0.5) * 100.0);
             println!("Credibility Quantiles:");
-            println!(" <25%: {:10}", self.loop_statistics.credibility_quantils.0);
-            println!(" <50%: {:10}", self.loop_statistics.credibility_quantils.1);
-            println!(" <75%: {:10}", self.loop_statistics.credibility_quantils.2);
-            println!(">=75%: {:10}", self.loop_statistics.credibility_quantils.3);
+            let credibility_quantils = self.loop_statistics.credibility_quantils();
+            println!(" <25%: {:10}", credibility_quantils.0);
+            println!(" <50%: {:10}", credibility_quantils.1);
+            println!(" <75%: {:10}", credibility_quantils.2);
+            println!(">=75%: {:10}", credibility_quantils.3);
 
             println!();
             println!("Imperiled Routes: ");
             self.loop_imperiled.print_stats();
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use crate::analytics::LoopAnalysis;
+    use crate::structs::{YarrpLine, DetailFormat};
+    use crate::helpers::test_helper::{init, MIN_TTL, MAX_TTL, TempFixture};
+
+    fn run_loop_analysis(storage_path: &Path) {
+        let mut analysis = LoopAnalysis::<Ipv4Addr>::new(false, storage_path.to_str().unwrap().to_string(), "", "", DetailFormat::Text, 0);
+
+        // each destination runs into one of five loops starting at ttl 10
+        for destination in 1..60 {
+            let pair = destination % 5;
+            for ttl in MIN_TTL..=MAX_TTL {
+                let hop;
+                if ttl < 10 {
+                    hop = format!("192.0.{}.{}", pair, ttl);
+                } else {
+                    hop = format!("10.0.{}.{}", pair, 1 + ttl % 2);
+                }
+                let line = format!("198.51.100.{} 1 1 11 0 {} {} 590 0 36 84 63 0 0 27", destination, ttl, hop);
+                analysis.add_ttl(YarrpLine::new(&line).unwrap());
+            }
+        }
+        analysis.generate_loop_stats(MIN_TTL, MAX_TTL);
+    }
+
+    fn read_tree(path: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>, base: &Path) {
+        for entry in fs::read_dir(path).unwrap() {
+            let entry_path = entry.unwrap().path();
+            if entry_path.is_dir() {
+                read_tree(&entry_path, files, base);
+            } else {
+                let relative = entry_path.strip_prefix(base).unwrap().to_path_buf();
+                files.push((relative, fs::read(&entry_path).unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn identical_runs_write_identical_projects() {
+        init();
+
+        let first = TempFixture::new("stable_a");
+        let second = TempFixture::new("stable_b");
+        run_loop_analysis(first.path());
+        run_loop_analysis(second.path());
+
+        let mut first_files = Vec::new();
+        let mut second_files = Vec::new();
+        read_tree(first.path(), &mut first_files, first.path());
+        read_tree(second.path(), &mut second_files, second.path());
+        first_files.sort();
+        second_files.sort();
+
+        assert_eq!(first_files.len(), 5 + 5, "Expected 5 loop files next to the project files");
+        for ((first_name, first_bytes), (second_name, second_bytes)) in first_files.iter().zip(second_files.iter()) {
+            assert_eq!(first_name, second_name);
+            assert_eq!(first_bytes, second_bytes, "{} differs between identical runs", first_name.to_str().unwrap());
+        }
+
+        let identifiers = fs::read_to_string(first.join("identifiers.id")).unwrap();
+        let mut sorted_lines: Vec<&str> = identifiers.lines().collect();
+        sorted_lines.sort();
+        assert_eq!(identifiers.lines().collect::<Vec<&str>>(), sorted_lines, "identifiers.id should be ordered by loop id");
+    }
+}
@@ -0,0 +1,165 @@
+pub mod loop_evidence {
+    use std::collections::HashMap;
+    use std::fmt::Display;
+    use std::hash::Hash;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use itertools::sorted;
+    use log::{info, trace};
+
+    use crate::analytics::LoopStorage;
+    use crate::traits::IpAddrExt;
+    use crate::structs::{AtomicFile, EvidenceTrace, Route, YarrpError, YarrpLine, count_evidence_traces};
+
+    /// Keeps up to sample_limit complete traces per loop id as evidence/<loop_id>.trace.
+    /// Traces are taken in the order routes are handled, an appended project keeps its existing samples.
+    pub struct LoopEvidence<T> {
+        sample_limit: u64,
+        evidence_path: PathBuf,
+        only_full_loops: bool,
+        sample_counts: HashMap<String, u64>,
+        pending: HashMap<String, Vec<EvidenceTrace<T>>>,
+    }
+
+    impl<T: Display + FromStr + Ord + Copy + Clone + Hash + IpAddrExt> LoopEvidence<T> {
+        pub fn new(sample_limit: u64, evidence_path: PathBuf, only_full_loops: bool) -> LoopEvidence<T> {
+            LoopEvidence {
+                sample_limit,
+                evidence_path,
+                only_full_loops,
+                sample_counts: HashMap::new(),
+                pending: HashMap::new(),
+            }
+        }
+
+        pub fn is_enabled(&self) -> bool {
+            self.sample_limit > 0
+        }
+
+        fn evidence_file(&self, loop_id: &str) -> PathBuf {
+            self.evidence_path.join(format!("{}.trace", loop_id))
+        }
+
+        /// keeps the trace of a looping route if its loop has less than sample_limit samples,
+        /// only loops that are stored in the project are sampled
+        pub fn add_route(&mut self, route: &Route<T>, answers: &HashMap<u8, Vec<YarrpLine<T>>>) -> Result<(), YarrpError> {
+            if !self.is_enabled() || !route.is_looping || (self.only_full_loops && !route.has_full_loop) {
+                return Ok(());
+            }
+
+            let loop_id = match LoopStorage::create_loop_identifier(&route.get_loop_routers()) {
+                Ok(value) => value,
+                Err(_) => return Err(YarrpError::CouldNotParseError),
+            };
+            let loop_id = loop_id.as_str();
+
+            if !self.sample_counts.contains_key(loop_id) {
+                let existing = count_evidence_traces(&self.evidence_file(loop_id))?;
+                self.sample_counts.insert(loop_id.to_string(), existing);
+            }
+
+            let count = self.sample_counts.get_mut(loop_id).unwrap();
+            if *count >= self.sample_limit {
+                return Ok(());
+            }
+            *count += 1;
+
+            trace!("Keeping trace to {} as evidence for {}", route.destination, loop_id);
+            let evidence = EvidenceTrace::new(route.destination, route.loop_start, route.loop_end, answers);
+            self.pending.entry(loop_id.to_string()).or_default().push(evidence);
+            Ok(())
+        }
+
+        /// appends the pending traces to the evidence files, ordered by loop id
+        pub fn write(&mut self) -> Result<(), YarrpError> {
+            if self.pending.is_empty() {
+                return Ok(());
+            }
+
+            info!("Writing evidence traces for {} loops", self.pending.len());
+            for loop_id in sorted(self.pending.keys()) {
+                let mut file = AtomicFile::append(&self.evidence_file(loop_id))?;
+                for evidence in &self.pending[loop_id] {
+                    evidence.write_to(&mut file)?;
+                }
+                file.commit()?;
+            }
+            self.pending.clear();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::net::Ipv4Addr;
+    use std::path::PathBuf;
+    use crate::analytics::{LoopEvidence, LoopStorage};
+    use crate::structs::{EvidenceTrace, Route, YarrpLine, count_evidence_traces, read_evidence_file};
+    use crate::helpers::test_helper::{TempFixture, MIN_TTL, MAX_TTL};
+
+    /// answers of a route to 198.51.100.destination running into loop pair from ttl 10 on
+    fn looping_answers(destination: u8, pair: u8) -> HashMap<u8, Vec<YarrpLine<Ipv4Addr>>> {
+        let mut answers = HashMap::new();
+        for ttl in MIN_TTL..=MAX_TTL {
+            let hop = match ttl < 10 {
+                true => format!("192.0.{}.{}", pair, ttl),
+                false => format!("10.0.{}.{}", pair, 1 + ttl % 2)
+            };
+            let line = format!("198.51.100.{} 1 1 11 0 {} {} 590 0 36 84 63 0 0 27", destination, ttl, hop);
+            answers.insert(ttl, vec![YarrpLine::new(&line).unwrap()]);
+        }
+        answers
+    }
+
+    fn add_routes(evidence: &mut LoopEvidence<Ipv4Addr>, destinations: &[(u8, u8)]) {
+        for (destination, pair) in destinations {
+            let answers = looping_answers(*destination, *pair);
+            let route = Route::new(&answers, MIN_TTL, MAX_TTL);
+            evidence.add_route(&route, &answers).ok().unwrap();
+        }
+    }
+
+    fn evidence_file(evidence_path: &TempFixture, pair: u8) -> PathBuf {
+        let answers = looping_answers(1, pair);
+        let route = Route::new(&answers, MIN_TTL, MAX_TTL);
+        let loop_id = LoopStorage::create_loop_identifier(&route.get_loop_routers()).ok().unwrap();
+        evidence_path.join(format!("{}.trace", loop_id))
+    }
+
+    #[test]
+    fn sampling_limit_per_loop() {
+        let evidence_path = TempFixture::dir("evidence");
+        let first_loop = evidence_file(&evidence_path, 0);
+        let second_loop = evidence_file(&evidence_path, 1);
+
+        let mut evidence = LoopEvidence::new(2, evidence_path.path().to_path_buf(), false);
+        add_routes(&mut evidence, &[(1, 0), (2, 0), (3, 0), (4, 1)]);
+        evidence.write().ok().unwrap();
+        assert_eq!(count_evidence_traces(&first_loop).ok().unwrap(), 2, "Only two samples of the first loop should be kept");
+        assert_eq!(count_evidence_traces(&second_loop).ok().unwrap(), 1);
+
+        let traces: Vec<EvidenceTrace<Ipv4Addr>> = read_evidence_file(&first_loop).ok().unwrap();
+        let destinations: Vec<String> = traces.iter().map(|trace| trace.destination.to_string()).collect();
+        assert_eq!(destinations, vec!["198.51.100.1", "198.51.100.2"], "Samples are taken in the order routes are handled");
+
+        // a later run keeps the existing samples and fills up to its limit
+        let mut evidence = LoopEvidence::new(3, evidence_path.path().to_path_buf(), false);
+        add_routes(&mut evidence, &[(5, 0), (6, 0), (7, 1)]);
+        evidence.write().ok().unwrap();
+        assert_eq!(count_evidence_traces(&first_loop).ok().unwrap(), 3);
+        assert_eq!(count_evidence_traces(&second_loop).ok().unwrap(), 2);
+    }
+
+    #[test]
+    fn disabled_without_limit() {
+        let evidence_path = TempFixture::dir("evidence");
+        let mut evidence = LoopEvidence::new(0, evidence_path.path().to_path_buf(), false);
+        assert!(!evidence.is_enabled());
+        add_routes(&mut evidence, &[(1, 0)]);
+        evidence.write().ok().unwrap();
+        assert_eq!(fs::read_dir(evidence_path.path()).unwrap().count(), 0);
+    }
+}
@@ -8,7 +8,7 @@ pub mod loop_imperiled {
     use std::path::Path;
 
     use crate::traits::IpAddrExt;
-    use crate::structs::{Route, CountingFile, CountingEntity, CountingVoid};
+    use crate::structs::{Route, CountingFile, CountingEntity, CountingVoid, DetailFormat, OutputPolicy};
     use crate::analytics::LoopStorage;
     use crate::modes::read_blocklist;
 
@@ -18,7 +18,7 @@ pub mod loop_imperiled {
     }
 
     impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> LoopImperiled<T> {
-        pub fn from_router_file(router_path: &str, blocklist_file: &str, output_path: &Path) -> LoopImperiled<T> {
+        pub fn from_router_file(router_path: &str, blocklist_file: &str, output_path: &Path, detail_format: DetailFormat) -> LoopImperiled<T> {
             // check if path exists, if so read all ip addresses of routers from it
 
             let blocklist;
@@ -76,17 +76,17 @@ pub mod loop_imperiled {
                 filtered_router_list = router_list;
             }
 
-            LoopImperiled::<T>::new(filtered_router_list, output_path)
+            LoopImperiled::<T>::new(filtered_router_list, output_path, detail_format)
         }
 
-        pub fn new(router_list: HashSet<T>, output_path: &Path) -> LoopImperiled<T> {
+        pub fn new(router_list: HashSet<T>, output_path: &Path, detail_format: DetailFormat) -> LoopImperiled<T> {
             info!("Imperiled analysis working with {} routers", router_list.len());
 
             let mut router_map: HashMap<T, CountingEntity> = HashMap::new();
 
             for router in &router_list {
                 let router_file = output_path.join(format!("{}.imp", router));
-                if let Some(counting_file) = CountingFile::new(&router_file) {
+                if let Some(counting_file) = CountingFile::new_details(&router_file, detail_format, OutputPolicy::Append) {
                     let counting_entity = CountingEntity::from(counting_file);
                     router_map.insert(router.clone(), counting_entity);
                 } else {
@@ -0,0 +1,305 @@
+pub mod loop_incidents {
+    use std::collections::{HashMap, HashSet};
+    use std::fmt::Display;
+    use std::hash::Hash;
+    use std::path::Path;
+    use log::info;
+
+    use crate::structs::{MapOrigin, MapSetString, MapSetT, MapVecT, YarrpError, IncidentEntry, atomic_csv_writer, commit_csv, stable_sorted};
+
+    /// rounds of label propagation before communities are taken as they are
+    const MAX_PROPAGATION_ROUNDS: usize = 100;
+
+    /// Loops and routers of one connected component of the loop router graph
+    pub struct Incident {
+        pub loops: Vec<String>,
+        pub routers: Vec<String>,
+    }
+
+    struct UnionFind {
+        parent: Vec<usize>,
+        rank: Vec<u8>,
+    }
+
+    impl UnionFind {
+        fn new(size: usize) -> UnionFind {
+            UnionFind {
+                parent: (0..size).collect(),
+                rank: vec![0; size],
+            }
+        }
+
+        fn find(&mut self, node: usize) -> usize {
+            let mut root = node;
+            while self.parent[root] != root {
+                root = self.parent[root];
+            }
+
+            // path compression
+            let mut node = node;
+            while self.parent[node] != root {
+                let next = self.parent[node];
+                self.parent[node] = root;
+                node = next;
+            }
+            root
+        }
+
+        fn union(&mut self, a: usize, b: usize) {
+            let a = self.find(a);
+            let b = self.find(b);
+            if a == b {
+                return;
+            }
+
+            if self.rank[a] < self.rank[b] {
+                self.parent[a] = b;
+            } else if self.rank[a] > self.rank[b] {
+                self.parent[b] = a;
+            } else {
+                self.parent[b] = a;
+                self.rank[a] += 1;
+            }
+        }
+    }
+
+    /// Groups loops sharing routers into incidents, the connected components of the bipartite loop router graph.
+    /// Within an incident, label propagation splits loosely coupled groups of loops into communities.
+    /// Incidents and communities are numbered from 1 in the order of their first loop id.
+    pub struct LoopIncidents {
+        incidents: Vec<Incident>,
+        loop_incident: HashMap<String, usize>,
+        router_incident: HashMap<String, usize>,
+        loop_community: HashMap<String, usize>,
+        router_community: HashMap<String, usize>,
+        num_communities: usize,
+    }
+
+    impl LoopIncidents {
+        pub fn new<T: Display>(loop_members: &MapSetT<T>) -> LoopIncidents {
+            // routers are numbered and written by their address
+            let loop_members: MapSetString = loop_members.iter()
+                .map(|(loop_id, members)| (loop_id.clone(), members.iter().map(|member| member.to_string()).collect()))
+                .collect();
+
+            // loops come first, so the first node of a component is its first loop
+            let loops = stable_sorted(loop_members.keys());
+            let routers = stable_sorted(loop_members.values().flatten().collect::<HashSet<&String>>());
+            let router_index: HashMap<&String, usize> = routers.iter().enumerate()
+                .map(|(index, router)| (*router, loops.len() + index))
+                .collect();
+
+            let num_nodes = loops.len() + routers.len();
+            let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); num_nodes];
+            let mut union_find = UnionFind::new(num_nodes);
+            for (loop_index, loop_id) in loops.iter().enumerate() {
+                for router in stable_sorted(&loop_members[*loop_id]) {
+                    let router_index = router_index[router];
+                    neighbours[loop_index].push(router_index);
+                    neighbours[router_index].push(loop_index);
+                    union_find.union(loop_index, router_index);
+                }
+            }
+
+            let components = LoopIncidents::number(&(0..num_nodes).map(|node| union_find.find(node)).collect::<Vec<usize>>());
+            let communities = LoopIncidents::number(&LoopIncidents::propagate_labels(&neighbours));
+
+            let num_incidents = components.iter().max().cloned().unwrap_or(0);
+            let mut incidents: Vec<Incident> = (0..num_incidents).map(|_| Incident { loops: Vec::new(), routers: Vec::new() }).collect();
+            let mut loop_incident = HashMap::new();
+            let mut loop_community = HashMap::new();
+            for (index, loop_id) in loops.iter().enumerate() {
+                incidents[components[index] - 1].loops.push(loop_id.to_string());
+                loop_incident.insert(loop_id.to_string(), components[index]);
+                loop_community.insert(loop_id.to_string(), communities[index]);
+            }
+
+            let mut router_incident = HashMap::new();
+            let mut router_community = HashMap::new();
+            for (index, router) in routers.iter().enumerate() {
+                let index = loops.len() + index;
+                incidents[components[index] - 1].routers.push(router.to_string());
+                router_incident.insert(router.to_string(), components[index]);
+                router_community.insert(router.to_string(), communities[index]);
+            }
+
+            let num_communities = communities.iter().max().cloned().unwrap_or(0);
+            info!("Grouped {} loops into {} incidents and {} communities", loops.len(), incidents.len(), num_communities);
+            LoopIncidents {
+                incidents,
+                loop_incident,
+                router_incident,
+                loop_community,
+                router_community,
+                num_communities,
+            }
+        }
+
+        /// asynchronous label propagation in node order, ties go to the smallest label
+        fn propagate_labels(neighbours: &[Vec<usize>]) -> Vec<usize> {
+            let mut labels: Vec<usize> = (0..neighbours.len()).collect();
+
+            for _round in 0..MAX_PROPAGATION_ROUNDS {
+                let mut changed = false;
+                for (node, node_neighbours) in neighbours.iter().enumerate() {
+                    let mut counts: HashMap<usize, usize> = HashMap::new();
+                    for neighbour in node_neighbours {
+                        *counts.entry(labels[*neighbour]).or_insert(0) += 1;
+                    }
+
+                    let best = counts.into_iter()
+                        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                        .map(|(label, _count)| label);
+                    if let Some(label) = best {
+                        if label != labels[node] {
+                            labels[node] = label;
+                            changed = true;
+                        }
+                    }
+                }
+
+                if !changed {
+                    break;
+                }
+            }
+            labels
+        }
+
+        /// renumbers the groups of the nodes from 1, in the order of their first node
+        fn number(groups: &[usize]) -> Vec<usize> {
+            let mut numbers: HashMap<usize, usize> = HashMap::new();
+            groups.iter().map(|group| {
+                let next = numbers.len() + 1;
+                *numbers.entry(*group).or_insert(next)
+            }).collect()
+        }
+
+        pub fn len(&self) -> usize {
+            self.incidents.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.incidents.is_empty()
+        }
+
+        pub fn num_communities(&self) -> usize {
+            self.num_communities
+        }
+
+        /// incidents with their id
+        pub fn incidents(&self) -> impl Iterator<Item = (usize, &Incident)> {
+            self.incidents.iter().enumerate().map(|(index, incident)| (index + 1, incident))
+        }
+
+        pub fn get_loop_incident(&self, loop_id: &str) -> Option<usize> {
+            self.loop_incident.get(loop_id).cloned()
+        }
+
+        pub fn get_router_incident(&self, router: &str) -> Option<usize> {
+            self.router_incident.get(router).cloned()
+        }
+
+        /// incidents of more than one loop
+        pub fn num_multi_loop(&self) -> usize {
+            self.incidents.iter().filter(|incident| incident.loops.len() > 1).count()
+        }
+
+        /// incidents with at least one persistent loop
+        pub fn num_persistent(&self, persistent_loops: &HashSet<String>) -> usize {
+            self.incidents.iter()
+                .filter(|incident| incident.loops.iter().any(|loop_id| persistent_loops.contains(loop_id)))
+                .count()
+        }
+
+        /// one line per loop and router with its incident and community, and the ASNs of the routers,
+        /// the distinct shadowed nets and the persistent loops of the incident
+        pub fn write_incidents_csv<T>(&self, output_path: &Path, routers_to_asn: &MapOrigin, loop_destinations: &MapVecT<T>,
+                                      persistent_loops: &HashSet<String>) -> Result<(), YarrpError>
+            where T: Eq + Hash {
+            let mut writer = atomic_csv_writer(output_path)?;
+
+            for (incident_id, incident) in self.incidents() {
+                let mut asn: HashSet<String> = HashSet::new();
+                for router in &incident.routers {
+                    if let Some(origin) = routers_to_asn.get(router) {
+                        asn.extend(origin.asns());
+                    }
+                }
+
+                let mut shadowed: HashSet<&T> = HashSet::new();
+                for loop_id in &incident.loops {
+                    if let Some(destinations) = loop_destinations.get(loop_id) {
+                        shadowed.extend(destinations);
+                    }
+                }
+                let persistent = incident.loops.iter().filter(|loop_id| persistent_loops.contains(*loop_id)).count() as u64;
+
+                let members = incident.loops.iter().map(|loop_id| (loop_id, "loop", self.loop_community[loop_id]))
+                    .chain(incident.routers.iter().map(|router| (router, "router", self.router_community[router])));
+                for (member, member_type, community) in members {
+                    writer.serialize(IncidentEntry {
+                        incident: incident_id as u64,
+                        member: member.clone(),
+                        member_type: member_type.to_string(),
+                        community: community as u64,
+                        loops: incident.loops.len() as u64,
+                        routers: incident.routers.len() as u64,
+                        asn: asn.clone(),
+                        shadowed: shadowed.len() as u64,
+                        persistent_loops: persistent,
+                    })?;
+                }
+            }
+            commit_csv(writer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use crate::analytics::LoopIncidents;
+    use crate::structs::{ASNOrigin, MapSetString};
+    use crate::helpers::test_helper::TempFixture;
+
+    fn set(values: &[&str]) -> HashSet<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn incidents_and_communities() {
+        let mut loop_members: MapSetString = HashMap::new();
+        // two triangles of loops around the border routers .1 and .4, bridged by loop c
+        loop_members.insert("a".to_string(), set(&["192.0.2.1", "192.0.2.2"]));
+        loop_members.insert("b".to_string(), set(&["192.0.2.1", "192.0.2.3"]));
+        loop_members.insert("c".to_string(), set(&["192.0.2.1", "192.0.2.4"]));
+        loop_members.insert("d".to_string(), set(&["192.0.2.4", "192.0.2.5"]));
+        loop_members.insert("e".to_string(), set(&["192.0.2.4", "192.0.2.6"]));
+        loop_members.insert("f".to_string(), set(&["198.51.100.1", "198.51.100.2"]));
+
+        let incidents = LoopIncidents::new(&loop_members);
+        assert_eq!(incidents.len(), 2);
+        assert_eq!(incidents.get_loop_incident("a"), Some(1));
+        assert_eq!(incidents.get_loop_incident("e"), Some(1));
+        assert_eq!(incidents.get_router_incident("198.51.100.2"), Some(2));
+        assert_eq!(incidents.get_loop_incident("g"), None);
+        assert_eq!(incidents.num_multi_loop(), 1);
+        assert_eq!(incidents.num_persistent(&set(&["f"])), 1);
+        assert_eq!(incidents.num_communities(), 3, "The incident of loops a to e splits at the bridging loop c");
+
+        let mut routers_to_asn = HashMap::new();
+        routers_to_asn.insert("198.51.100.1".to_string(), ASNOrigin::Single("64496".to_string()));
+        routers_to_asn.insert("198.51.100.2".to_string(), ASNOrigin::Moas(vec![ASNOrigin::Single("64496".to_string()), ASNOrigin::Single("64497".to_string())]));
+        let mut loop_destinations: HashMap<String, Vec<u32>> = HashMap::new();
+        loop_destinations.insert("f".to_string(), vec![1, 2, 3]);
+
+        let output = TempFixture::new("incidents.csv");
+        incidents.write_incidents_csv(output.path(), &routers_to_asn, &loop_destinations, &set(&["f"])).ok().unwrap();
+        let content = fs::read_to_string(output.path()).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 1 + 11 + 3);
+        assert_eq!(lines[0], "incident,member,member_type,community,loops,routers,asn,shadowed,persistent_loops");
+        assert_eq!(lines[12], "2,f,loop,3,1,2,64496;64497,3,1");
+    }
+}
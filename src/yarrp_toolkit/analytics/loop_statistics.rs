@@ -3,8 +3,8 @@ pub mod loop_statistics {
     use std::path::PathBuf;
     use log::{info, warn, error, trace};
 
-    use crate::structs::Route;
-    use crate::structs::YarrpError;
+    use crate::structs::{Route, SimpleLoopOutput};
+    use crate::structs::{YarrpError, atomic_csv_writer, commit_csv};
     use csv::StringRecord;
     use std::process::exit;
     use itertools::sorted;
@@ -12,23 +12,40 @@ pub mod loop_statistics {
     use std::hash::Hash;
     use crate::traits::IpAddrExt;
 
+    /// resolution of the credibility histogram, credibility values are binned per mille
+    pub const CREDIBILITY_BINS: u16 = 1000;
+    /// credibility values are summed up as integers in millionths so merging stays exact
+    const CREDIBILITY_SUM_SCALE: f64 = 1_000_000.0;
+
+    /// All state is kept as counts, sums and histograms, so statistics of several runs or projects
+    /// can be merged and give the same result as a single run over all inputs.
     pub struct LoopStatistics {
         pub only_full_routes: bool,
         pub storage_path: PathBuf,
         pub number_of_routes: u64,
         pub number_of_loops: u64,
         pub loop_size_map: HashMap<u8, u64>,
-        pub average_loop_length: f64,
         pub number_of_load_balancers: u64,
         pub number_of_spammers: u64,
         pub number_of_full_loops: u64,
         pub number_of_imperiled: u64,
-        pub average_credibility: f64,
-        pub credibility_quantils: (u32, u32, u32, u32),
+        pub credibility_sum: u64,
+        pub credibility_histogram: HashMap<u16, u64>,
     }
 
     impl LoopStatistics {
         pub fn new(only_full_routes: bool, storage_path: PathBuf) -> LoopStatistics {
+            match LoopStatistics::from_file(only_full_routes, storage_path) {
+                Ok(loop_statistics) => loop_statistics,
+                Err(_) => {
+                    error!("Error while reading or parsing csv file!");
+                    exit(1);
+                }
+            }
+        }
+
+        /// statistics continuing an existing stats file at storage_path, empty if there is none
+        pub fn from_file(only_full_routes: bool, storage_path: PathBuf) -> Result<LoopStatistics, YarrpError> {
 
             // check if storage file exists
             let path = &storage_path.to_path_buf();
@@ -39,21 +56,17 @@ pub mod loop_statistics {
                 number_of_routes: 0,
                 number_of_loops: 0,
                 loop_size_map: HashMap::new(),
-                average_loop_length: 0.0,
                 number_of_load_balancers: 0,
                 number_of_spammers: 0,
                 number_of_full_loops: 0,
                 number_of_imperiled: 0,
-                average_credibility: 0.0,
-                credibility_quantils: (0, 0, 0, 0),
+                credibility_sum: 0,
+                credibility_histogram: HashMap::new(),
             };
 
             if path.exists() {
                 info!("CSV File exists, attempting to read it...");
-                if let Err(_) = loop_statistics.read_csv(&path) {
-                    error!("Error while reading or parsing csv file!");
-                    exit(1);
-                }
+                loop_statistics.read_csv(&path)?;
             } else {
                 if let Some(storage_path_str) = path.to_str() {
                     info!("No stats file found at {}.", storage_path_str);
@@ -63,15 +76,52 @@ pub mod loop_statistics {
                 }
             }
 
-            return loop_statistics;
+            Ok(loop_statistics)
+        }
+
+        /// reads only the only_full_routes flag of an existing stats file, None if the file or key is missing
+        pub fn read_only_full_routes(stats_file: &PathBuf) -> Result<Option<bool>, YarrpError> {
+            if !stats_file.exists() {
+                return Ok(None);
+            }
+
+            let mut csv_reader = csv::ReaderBuilder::new().has_headers(false).from_path(stats_file)?;
+            for record in csv_reader.records() {
+                let record = record?;
+                if let (Some("only_full_routes"), Some(value)) = (record.get(0), record.get(1)) {
+                    return Ok(Some(value == "1"));
+                }
+            }
+            Ok(None)
         }
 
         fn read_csv(&mut self, stats_file: &PathBuf) -> Result<(), YarrpError> {
             let mut csv_reader = csv::Reader::from_path(stats_file)?;
             trace!("csv file has headers: {}", csv_reader.has_headers());
+
+            let mut legacy_average_credibility = None;
+            let mut has_credibility_sum = false;
+
             for record in csv_reader.records() {
                 let record = record?;
-                self.parse_record(&record)?;
+                match record.get(0) {
+                    Some("average_credibility") => {
+                        if let Some(value) = record.get(1) {
+                            legacy_average_credibility = Some(value.parse::<f64>()?);
+                        }
+                    }
+                    Some("credibility_sum") => {
+                        has_credibility_sum = true;
+                        self.parse_record(&record)?;
+                    }
+                    _ => self.parse_record(&record)?,
+                }
+            }
+
+            // files written before the sums were stored only contain the average
+            if let (false, Some(average)) = (has_credibility_sum, legacy_average_credibility) {
+                warn!("Stats file only contains an average credibility, histogram is not available!");
+                self.credibility_sum = (average * self.number_of_routes as f64 * CREDIBILITY_SUM_SCALE).round() as u64;
             }
             Ok(())
         }
@@ -80,18 +130,15 @@ pub mod loop_statistics {
             // grab key and value
             if let (Some(key), Some(value)) = (record.get(0), record.get(1)) {
                 if key.starts_with("loop_length_") {
-                    // grab loop length
-                    let split = key.split("_").collect::<Vec<&str>>();
-                    let loop_length;
-                    if let Some(temp_loop_length) = split.get(split.len() - 1) {
-                        loop_length = temp_loop_length;
-                    } else {
-                        return Err(YarrpError::CouldNotReadError);
-                    }
-                    let loop_length: u8 = loop_length.parse()?;
+                    let loop_length: u8 = LoopStatistics::parse_key_suffix(key)?;
                     let value: u64 = value.parse()?;
 
                     self.loop_size_map.insert(loop_length.clone(), value);
+                } else if key.starts_with("credibility_permille_") {
+                    let bin: u16 = LoopStatistics::parse_key_suffix(key)?;
+                    let value: u64 = value.parse()?;
+
+                    self.credibility_histogram.insert(bin, value);
                 } else {
                     match key {
                         "only_full_routes" => {
@@ -107,7 +154,9 @@ pub mod loop_statistics {
                         "spammers" => self.number_of_spammers = value.parse()?,
                         "full_loops" => self.number_of_full_loops = value.parse()?,
                         "imperiled" => self.number_of_imperiled = value.parse()?,
-                        "average_credibility" => self.average_credibility = value.parse()?,
+                        "credibility_sum" => self.credibility_sum = value.parse()?,
+                        // derived values, recomputed from sums and histograms
+                        "average_loop_length" | "average_credibility" => {}
                         _ => { warn!("Ignoring unknown Option '{}' from record!", key); }
                     }
                 }
@@ -120,8 +169,19 @@ pub mod loop_statistics {
             }
         }
 
+        /// parses the number after the last underscore of keys like loop_length_3
+        fn parse_key_suffix<N: std::str::FromStr>(key: &str) -> Result<N, YarrpError> {
+            if let Some(suffix) = key.rsplit("_").next() {
+                if let Ok(value) = suffix.parse() {
+                    return Ok(value);
+                }
+                return Err(YarrpError::CouldNotParseError);
+            }
+            Err(YarrpError::CouldNotReadError)
+        }
+
         pub fn write_csv(&self) -> Result<(), YarrpError> {
-            let mut csv_writer = csv::Writer::from_path(&self.storage_path)?;
+            let mut csv_writer = atomic_csv_writer(&self.storage_path)?;
 
             if self.only_full_routes {
                 csv_writer.write_record(&["only_full_routes", "1"])?;
@@ -135,7 +195,9 @@ pub mod loop_statistics {
             let _ = csv_writer.write_record(&["spammers", &self.number_of_spammers.to_string()])?;
             let _ = csv_writer.write_record(&["full_loops", &self.number_of_full_loops.to_string()])?;
             let _ = csv_writer.write_record(&["imperiled", &self.number_of_imperiled.to_string()])?;
-            let _ = csv_writer.write_record(&["average_credibility", &self.average_credibility.to_string()])?;
+            let _ = csv_writer.write_record(&["average_loop_length", &self.average_loop_length().to_string()])?;
+            let _ = csv_writer.write_record(&["average_credibility", &self.average_credibility().to_string()])?;
+            let _ = csv_writer.write_record(&["credibility_sum", &self.credibility_sum.to_string()])?;
 
             let keys = &self.loop_size_map.keys().collect::<Vec<&u8>>();
             let keys = sorted(keys);
@@ -146,17 +208,21 @@ pub mod loop_statistics {
                     let _ = csv_writer.write_record(&[&csv_key, &value.to_string()]);
                 }
             }
-            Ok(())
+
+            for key in sorted(self.credibility_histogram.keys()) {
+                if let Some(value) = self.credibility_histogram.get(key) {
+                    let csv_key = format!("credibility_permille_{}", key);
+                    let _ = csv_writer.write_record(&[&csv_key, &value.to_string()]);
+                }
+            }
+            commit_csv(csv_writer)
         }
 
         pub fn handle_route<T: Display + Copy + Clone + Eq + Hash + IpAddrExt>(&mut self, route: &Route<T>) {
-            let counter = self.number_of_routes as f64;
-            let loop_counter = self.number_of_loops as f64;
-
-            self.add_to_credibility(route.credibility, counter);
+            self.add_to_credibility(route.credibility);
 
             if (self.only_full_routes && route.has_full_loop) || (!self.only_full_routes && route.is_looping) {
-                self.add_to_loops(route.loop_len(), loop_counter);
+                self.add_to_loops(route.loop_len());
                 self.number_of_loops += 1;
             }
 
@@ -179,22 +245,56 @@ pub mod loop_statistics {
             self.number_of_routes += 1;
         }
 
-        fn add_to_credibility(&mut self, credibility: f64, counter: f64) {
-            self.average_credibility = (self.average_credibility * counter + credibility) / (counter + 1.0);
-            if credibility < 0.25 {
-                self.credibility_quantils.0 += 1;
-            } else if credibility < 0.5 {
-                self.credibility_quantils.1 += 1;
-            } else if credibility < 0.75 {
-                self.credibility_quantils.2 += 1;
-            } else {
-                self.credibility_quantils.3 += 1;
+        /// adds the counters, sums and histograms of other to self
+        pub fn merge(&mut self, other: &LoopStatistics) -> Result<(), YarrpError> {
+            if self.only_full_routes != other.only_full_routes {
+                error!("Cannot merge statistics that differ on only_full_routes!");
+                return Err(YarrpError::NotCompatibleError);
+            }
+
+            self.number_of_routes += other.number_of_routes;
+            self.number_of_loops += other.number_of_loops;
+            self.number_of_load_balancers += other.number_of_load_balancers;
+            self.number_of_spammers += other.number_of_spammers;
+            self.number_of_full_loops += other.number_of_full_loops;
+            self.number_of_imperiled += other.number_of_imperiled;
+            self.credibility_sum += other.credibility_sum;
+
+            for (loop_len, count) in &other.loop_size_map {
+                *self.loop_size_map.entry(*loop_len).or_insert(0) += count;
+            }
+
+            for (bin, count) in &other.credibility_histogram {
+                *self.credibility_histogram.entry(*bin).or_insert(0) += count;
+            }
+            Ok(())
+        }
+
+        /// replaces the loop counters by the shadowed nets of the given loops.csv entries,
+        /// each shadowed net is a looping route of the entry's loop length
+        pub fn rebuild_loop_counters<'a, I: IntoIterator<Item=&'a SimpleLoopOutput>>(&mut self, loop_information: I) {
+            self.loop_size_map.clear();
+            self.number_of_loops = 0;
+            for info in loop_information {
+                *self.loop_size_map.entry(info.loop_len).or_insert(0) += info.shadowed_nets;
+                self.number_of_loops += info.shadowed_nets;
             }
         }
 
-        fn add_to_loops(&mut self, loop_len: u8, counter: f64) {
-            self.average_loop_length = (self.average_loop_length * counter + loop_len as f64) / (counter + 1.0);
+        fn add_to_credibility(&mut self, credibility: f64) {
+            self.credibility_sum += (credibility * CREDIBILITY_SUM_SCALE).round() as u64;
+            *self.credibility_histogram.entry(LoopStatistics::credibility_bin(credibility)).or_insert(0) += 1;
+        }
+
+        fn credibility_bin(credibility: f64) -> u16 {
+            let bin = (credibility * CREDIBILITY_BINS as f64).floor();
+            if bin < 0.0 {
+                return 0;
+            }
+            (bin as u16).min(CREDIBILITY_BINS)
+        }
 
+        fn add_to_loops(&mut self, loop_len: u8) {
             match self.loop_size_map.get_mut(&loop_len) {
                 None => {
                     self.loop_size_map.insert(loop_len, 1);
@@ -208,5 +308,156 @@ pub mod loop_statistics {
         pub fn get_loop_percentage(&self) -> f64 {
             return self.number_of_loops as f64 / self.number_of_routes as f64;
         }
+
+        pub fn average_loop_length(&self) -> f64 {
+            let mut loops = 0;
+            let mut length_sum = 0;
+            for (loop_len, count) in &self.loop_size_map {
+                loops += count;
+                length_sum += *loop_len as u64 * count;
+            }
+
+            if loops == 0 {
+                return 0.0;
+            }
+            length_sum as f64 / loops as f64
+        }
+
+        pub fn average_credibility(&self) -> f64 {
+            if self.number_of_routes == 0 {
+                return 0.0;
+            }
+            self.credibility_sum as f64 / CREDIBILITY_SUM_SCALE / self.number_of_routes as f64
+        }
+
+        /// number of routes with a credibility below 25%, 50%, 75% and the rest
+        pub fn credibility_quantils(&self) -> (u64, u64, u64, u64) {
+            let mut quantils = (0, 0, 0, 0);
+            let quarter = CREDIBILITY_BINS / 4;
+            for (bin, count) in &self.credibility_histogram {
+                if *bin < quarter {
+                    quantils.0 += count;
+                } else if *bin < 2 * quarter {
+                    quantils.1 += count;
+                } else if *bin < 3 * quarter {
+                    quantils.2 += count;
+                } else {
+                    quantils.3 += count;
+                }
+            }
+            quantils
+        }
+
+        /// returns the credibility at the given quantile (0.0 - 1.0), with a resolution of one per mille
+        pub fn credibility_quantile(&self, quantile: f64) -> f64 {
+            let bin = LoopStatistics::histogram_quantile(&self.credibility_histogram, quantile).unwrap_or(0);
+            bin as f64 / CREDIBILITY_BINS as f64
+        }
+
+        /// returns the loop length at the given quantile (0.0 - 1.0)
+        pub fn loop_length_quantile(&self, quantile: f64) -> u8 {
+            LoopStatistics::histogram_quantile(&self.loop_size_map, quantile).unwrap_or(0)
+        }
+
+        fn histogram_quantile<K: Ord + Copy + Hash>(histogram: &HashMap<K, u64>, quantile: f64) -> Option<K> {
+            let total: u64 = histogram.values().sum();
+            if total == 0 {
+                return None;
+            }
+
+            let rank = ((quantile.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+            let mut seen = 0;
+            for key in sorted(histogram.keys()) {
+                seen += histogram[key];
+                if seen >= rank {
+                    return Some(*key);
+                }
+            }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::Ipv4Addr;
+    use std::path::PathBuf;
+    use crate::analytics::LoopStatistics;
+    use crate::structs::{Route, YarrpLine};
+    use crate::helpers::test_helper::{init, create_v4_yarrp_line_vec, EMPTY_STRING, MIN_TTL, MAX_TTL};
+    use crate::traits::IpAddrExt;
+
+    /// creates a route looping over loop_len routers from ttl 8 on, the first missing hops lower the credibility
+    fn create_looping_route_map(loop_len: u8, missing_hops: u8) -> HashMap<u8, Vec<YarrpLine<Ipv4Addr>>> {
+        let mut test_map = HashMap::new();
+        let r_type = Ipv4Addr::time_exceeded_type();
+
+        for i in (MIN_TTL + missing_hops)..(MAX_TTL + 1) {
+            let mut hop_id = i;
+            if i >= 8 && loop_len > 0 {
+                hop_id = 8 + (i - 8) % loop_len;
+            }
+            test_map.insert(i, create_v4_yarrp_line_vec(i, hop_id, r_type, 0, &EMPTY_STRING));
+        }
+        test_map
+    }
+
+    fn create_statistics(routes: &[(u8, u8)]) -> LoopStatistics {
+        let mut statistics = LoopStatistics::new(true, PathBuf::from("/nonexistent/stats.csv"));
+        for (loop_len, missing_hops) in routes {
+            let route_map = create_looping_route_map(*loop_len, *missing_hops);
+            let route = Route::new(&route_map, MIN_TTL, MAX_TTL);
+            statistics.handle_route(&route);
+        }
+        statistics
+    }
+
+    #[test]
+    fn merged_statistics_equal_single_run() {
+        init();
+
+        let inputs = vec![(2, 0), (3, 1), (2, 4), (4, 2), (3, 0), (5, 3), (2, 2)];
+        let single = create_statistics(&inputs);
+
+        let mut merged = create_statistics(&inputs[0..2]);
+        merged.merge(&create_statistics(&inputs[2..5])).ok().unwrap();
+        merged.merge(&create_statistics(&inputs[5..])).ok().unwrap();
+
+        assert_eq!(merged.number_of_routes, single.number_of_routes);
+        assert_eq!(merged.number_of_loops, single.number_of_loops);
+        assert_eq!(merged.number_of_full_loops, single.number_of_full_loops);
+        assert_eq!(merged.loop_size_map, single.loop_size_map);
+        assert_eq!(merged.credibility_sum, single.credibility_sum);
+        assert_eq!(merged.credibility_histogram, single.credibility_histogram);
+        assert_eq!(merged.average_loop_length(), single.average_loop_length());
+        assert_eq!(merged.average_credibility(), single.average_credibility());
+        assert_eq!(merged.credibility_quantils(), single.credibility_quantils());
+        assert_eq!(merged.credibility_quantile(0.5), single.credibility_quantile(0.5));
+        assert_eq!(merged.loop_length_quantile(0.9), single.loop_length_quantile(0.9));
+    }
+
+    #[test]
+    fn averages_and_quantiles_from_histograms() {
+        init();
+
+        let statistics = create_statistics(&[(2, 0), (2, 0), (4, 0), (4, 8)]);
+
+        assert_eq!(statistics.number_of_loops, 4);
+        assert_eq!(statistics.average_loop_length(), 3.0);
+        assert_eq!(statistics.loop_length_quantile(0.5), 2);
+        assert_eq!(statistics.loop_length_quantile(1.0), 4);
+        assert_eq!(statistics.average_credibility(), 3.5 / 4.0);
+        assert_eq!(statistics.credibility_quantile(0.25), 0.5);
+        assert_eq!(statistics.credibility_quantils(), (0, 0, 1, 3));
+    }
+
+    #[test]
+    fn merge_refuses_different_only_full_routes() {
+        init();
+
+        let mut statistics = create_statistics(&[(2, 0)]);
+        let other = LoopStatistics::new(false, PathBuf::from("/nonexistent/stats.csv"));
+        assert!(statistics.merge(&other).is_err());
     }
-}
\ No newline at end of file
+}
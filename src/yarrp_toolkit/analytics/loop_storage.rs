@@ -1,6 +1,7 @@
 pub mod loop_storage {
-    use crate::structs::{Route, YarrpError, SimpleLoopOutput, ShadowedPreceding, MapSetString};
-    use crate::structs::CountingFile;
+    use crate::structs::{Route, YarrpError, SimpleLoopOutput, ShadowedPreceding, MapSetString, stable_key, stable_sorted};
+    use crate::structs::{CountingFile, DetailFormat, OutputPolicy, detect_detail_format, read_binary_details};
+    use crate::structs::{AtomicFile, atomic_csv_writer, commit_csv};
     use crate::read_lines;
     use std::collections::{HashSet, HashMap};
     use log::{error, info, trace, warn};
@@ -9,7 +10,6 @@ pub mod loop_storage {
     use itertools::sorted;
     use std::process::exit;
     use std::path::{Path, PathBuf};
-    use std::fs::{OpenOptions, File};
     use std::io::Write;
     use itertools::Itertools;
     use std::hash::Hash;
@@ -24,6 +24,8 @@ pub mod loop_storage {
     pub const IMPERILED: &str = "imperiled";
     pub const LOOPS_CSV: &str = "loops.csv";
     pub const SHADOWED_PRECEDING_INFO: &str = "shadowed_preceding.csv";
+    pub const EVIDENCE: &str = "evidence";
+    pub const PROCESSED_FILES: &str = "processed_files.lst";
 
     pub enum LoopStorageError {
         NothingToHashError,
@@ -46,7 +48,8 @@ pub mod loop_storage {
         pub(crate) loop_information: HashMap<(String, String), SimpleLoopOutput>,
         pub(crate) only_full_loops: bool,
         pub(crate) storage_path: String,
-        pub(crate) shadowed_storage: Option<Writer<File>>
+        pub(crate) shadowed_storage: Option<Writer<AtomicFile>>,
+        pub detail_format: DetailFormat,
     }
 
     impl From<std::fmt::Error> for LoopStorageError {
@@ -69,7 +72,8 @@ pub mod loop_storage {
                 loop_information: Default::default(),
                 only_full_loops,
                 storage_path,
-                shadowed_storage: None
+                shadowed_storage: None,
+                detail_format: DetailFormat::Text,
             };
 
             if let Err(_) = storage.read_loop_info_if_available(){
@@ -103,10 +107,7 @@ pub mod loop_storage {
                 let writer_path = self.get_storage_file(SHADOWED_PRECEDING_INFO)?;
                 let write_header = ! writer_path.exists();
 
-                let file = OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(writer_path)?;
+                let file = AtomicFile::append(&writer_path)?;
 
                 let writer = WriterBuilder::new()
                     .has_headers(write_header)
@@ -117,6 +118,14 @@ pub mod loop_storage {
             Ok(())
         }
 
+        /// renames the shadowed preceding file written so far into place
+        pub(crate) fn commit_shadowed_preceding(&mut self) -> Result<(), YarrpError> {
+            if let Some(writer) = self.shadowed_storage.take() {
+                commit_csv(writer)?;
+            }
+            Ok(())
+        }
+
         pub fn add_route_information(&mut self, route: &Route<T>) -> Result<(), YarrpError> {
             // if route is not looping, or we are filtering on full loops only return
             if !route.is_looping || (self.only_full_loops && !route.has_full_loop) {
@@ -182,7 +191,7 @@ pub mod loop_storage {
                 let sub_path = self.get_storage_sub_dir("loops")?;
                 let sub_path = sub_path.join(format!("{}.dest", identifier));
 
-                if let Some(counting_file) = CountingFile::new(&sub_path) {
+                if let Some(counting_file) = CountingFile::new_details(&sub_path, self.detail_format, OutputPolicy::Append) {
                     self.loop_destination_files.insert(identifier.clone(), counting_file);
                 } else {
                     exit(1);
@@ -214,6 +223,14 @@ pub mod loop_storage {
 
         // read and update the statistics file in the loop output dir
         pub fn update_statistics(&mut self) {
+            // destinations are appended in place, binary files get their count before the index points at them
+            for counting_file in self.loop_destination_files.values_mut() {
+                if counting_file.update_binary_header().is_err() {
+                    error!("Could not update loop destination file");
+                    exit(1);
+                }
+            }
+
             if let Err(_) = self.update_identifiers() {
                 error!("Could not update identifiers");
                 exit(1);
@@ -227,6 +244,11 @@ pub mod loop_storage {
                 error!("Could not write loop information file!");
                 exit(3);
             }
+
+            if let Err(_) = self.commit_shadowed_preceding() {
+                error!("Could not write shadowed preceding file!");
+                exit(3);
+            }
         }
 
         fn update_identifiers(&self) -> Result<(), YarrpError> {
@@ -239,13 +261,15 @@ pub mod loop_storage {
             let mut total_identifiers = previous_identifiers.len();
 
             // Only add new loop identifiers, ignore all already found ones
-            let mut write_file = OpenOptions::new().append(true).create(true).open(&identifiers_file)?;
+            // new identifiers are appended ordered by loop id, with their routers ordered by address
+            let mut write_file = AtomicFile::append(&identifiers_file)?;
             info!("Writing new set of loop identifiers!");
 
-            for (identifier, routers) in &self.loop_members {
+            for identifier in sorted(self.loop_members.keys()) {
+                let routers = &self.loop_members[identifier];
                 let mut routers_string = String::new();
                 if !previous_identifiers.contains_key(identifier) {
-                    for hop in routers {
+                    for hop in sorted(routers) {
                         routers_string.push_str(&hop.to_string());
                         routers_string.push_str(";");
                     }
@@ -257,6 +281,7 @@ pub mod loop_storage {
                     total_identifiers += 1;
                 }
             }
+            write_file.commit()?;
 
             info!("Total identifiers after merge: {}", total_identifiers);
             Ok(())
@@ -298,6 +323,10 @@ pub mod loop_storage {
 
         /// reads a detail file (e.g. loops destination or imperiled) and returns a T (Ipv4Addr / Ipv6Addr) vector
         pub fn read_details_file_as_t(path: &PathBuf) -> Result<Vec<T>, YarrpError> {
+            if detect_detail_format(path)? == DetailFormat::Binary {
+                return read_binary_details(path);
+            }
+
             let mut destinations: Vec<T> = Vec::new();
 
             let lines = read_lines(path)?;
@@ -316,6 +345,10 @@ pub mod loop_storage {
 
         /// reads a detail file (e.g. loops destination or imperiled) and returns a T (Ipv4Addr / Ipv6Addr) vector
         pub fn read_details_file_as_t_ret_set(path: &PathBuf) -> Result<HashSet<T>, YarrpError> {
+            if detect_detail_format(path)? == DetailFormat::Binary {
+                return Ok(read_binary_details(path)?.into_iter().collect());
+            }
+
             let mut destinations: HashSet<T> = HashSet::new();
 
             let lines = read_lines(path)?;
@@ -333,6 +366,11 @@ pub mod loop_storage {
 
         /// reads a detail file (e.g. loops destination or imperiled) and returns a string vector
         pub fn read_details_file_as_string(path: &PathBuf) -> Result<Vec<String>, YarrpError> {
+            if detect_detail_format(path)? == DetailFormat::Binary {
+                let addresses: Vec<T> = read_binary_details(path)?;
+                return Ok(addresses.iter().map(|address| address.to_string()).collect());
+            }
+
             let mut destinations: Vec<String> = Vec::new();
 
             let lines = read_lines(path)?;
@@ -344,11 +382,13 @@ pub mod loop_storage {
             Ok(destinations)
         }
 
+        /// writes an id file ordered by key, values of a line are ordered as well (see stable_key)
         pub fn write_id_file(path: &PathBuf, hashmap: &HashMap<String, HashSet<String>>) -> Result<(), YarrpError> {
-            let mut write_file = File::create(path.clone())?;
-            for (identifier, values) in hashmap {
+            let mut write_file = AtomicFile::create(path)?;
+            for identifier in stable_sorted(hashmap.keys()) {
+                let values = &hashmap[identifier];
                 let mut identifiers_string = String::new();
-                for value in values {
+                for value in stable_sorted(values) {
                     identifiers_string.push_str(&value);
                     identifiers_string.push_str(";");
                 }
@@ -360,7 +400,7 @@ pub mod loop_storage {
                 }
             }
 
-            Ok(())
+            write_file.commit()
         }
 
         /// Merges to_merge into base, modifying base!
@@ -477,14 +517,18 @@ pub mod loop_storage {
             return Ok(loop_info);
         }
 
-        fn store_loop_info(&self) -> Result<(), YarrpError> {
+        pub(crate) fn store_loop_info(&self) -> Result<(), YarrpError> {
             let path = self.get_storage_file(LOOPS_CSV)?;
-            let mut csv_writer = csv::Writer::from_path(path)?;
+            let mut csv_writer = atomic_csv_writer(&path)?;
 
-            for (_record_key, record) in &self.loop_information {
+            // ordered by loop id and preceding router
+            let mut records: Vec<&SimpleLoopOutput> = self.loop_information.values().collect();
+            records.sort_by(|a, b| (&a.loop_id, stable_key(&a.preceding_router)).cmp(&(&b.loop_id, stable_key(&b.preceding_router))));
+
+            for record in records {
                 csv_writer.serialize(record)?;
             }
-            Ok(())
+            commit_csv(csv_writer)
         }
     }
 }
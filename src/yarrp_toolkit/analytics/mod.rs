@@ -2,12 +2,38 @@ mod loop_analysis;
 mod loop_statistics;
 mod loop_storage;
 mod loop_imperiled;
+mod loop_amplification;
+mod loop_incidents;
 mod asn_attribution;
+mod as2org;
+mod as_relationships;
+mod prefix_coverage;
+mod asn_coverage;
+mod rpki;
+mod geo_attribution;
+mod ptr_names;
+mod operator_report;
+mod project;
+mod loop_evidence;
+mod project_watcher;
 
 pub use loop_analysis::loop_analysis::LoopAnalysis;
 pub use loop_statistics::loop_statistics::LoopStatistics;
 pub use loop_storage::loop_storage::{LoopStorage, LoopStorageError};
 pub use loop_imperiled::loop_imperiled::{LoopImperiled};
+pub use loop_incidents::loop_incidents::{LoopIncidents, Incident};
+pub use loop_amplification::loop_amplification::{LoopAmplification, amplification, impact_score, INITIAL_TTLS};
 pub use asn_attribution::asn_attribution::ASNAttribution;
+pub use prefix_coverage::prefix_coverage::PrefixCoverage;
+pub use asn_coverage::asn_coverage::ASNCoverage;
+pub use rpki::rpki::{RoaTable, RpkiState, RPKI_STATES};
+pub use geo_attribution::geo_attribution::GeoAttribution;
+pub use ptr_names::ptr_names::{PtrNames, registered_domain};
+pub use operator_report::operator_report::{OperatorReports, render_report_markdown};
+pub use as2org::as2org::AS2Org;
+pub use as_relationships::as_relationships::{ASRelationships, ASRelationship, LoopASRelationship, LOOP_AS_RELATIONSHIPS};
+pub use loop_evidence::loop_evidence::LoopEvidence;
+pub use project_watcher::project_watcher::ProjectWatcher;
+pub use project::project::{Project, ProjectLoop, ProjectRouter, ProjectLoopIter, ProjectRouterIter, ProjectOutput, prepare_project_output, STAGING_DIR};
 
-pub use loop_storage::loop_storage::{ROUTERS, IDENTIFIERS, IMPERILED, LOOPS, STATS, SHADOWED_PRECEDING_INFO};
\ No newline at end of file
+pub use loop_storage::loop_storage::{ROUTERS, IDENTIFIERS, IMPERILED, LOOPS, LOOPS_CSV, STATS, SHADOWED_PRECEDING_INFO, EVIDENCE, PROCESSED_FILES};
\ No newline at end of file
@@ -0,0 +1,307 @@
+pub mod operator_report {
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+    use std::fmt::{Display, Write};
+    use std::hash::Hash;
+    use std::path::Path;
+    use std::str::FromStr;
+    use itertools::sorted;
+    use log::{info, warn};
+
+    use crate::analytics::{ASNAttribution, Project};
+    use crate::structs::{ASNTree, YarrpError, MapOrigin, ReportResult, ReportLoopResult, ReportRouterResult, ReportPrecedingResult, ReportPrefixResult, stable_cmp, stable_sorted, parse_asn};
+    use crate::traits::IpAddrExt;
+
+    /// Builds one notification report per ASN of a project with the loops involving the AS.
+    /// Loop sections are built once and shared by the reports of all ASes of the loop.
+    pub struct OperatorReports {
+        num_examples: usize,
+        persistent_loops: Option<HashSet<String>>,
+        abuse_contacts: HashMap<String, BTreeSet<String>>,
+        loops: HashMap<String, ReportLoopResult>,
+    }
+
+    impl OperatorReports {
+        pub fn new(num_examples: usize) -> OperatorReports {
+            OperatorReports {
+                num_examples,
+                persistent_loops: None,
+                abuse_contacts: HashMap::new(),
+                loops: HashMap::new(),
+            }
+        }
+
+        /// without persistent loops the persistence of all loops is unknown
+        pub fn set_persistent_loops(&mut self, persistent_loops: HashSet<String>) {
+            self.persistent_loops = Some(persistent_loops);
+        }
+
+        /// reads asn,contact lines (e.g. AS64496,abuse@example.net), further columns, a header and lines starting with # are ignored
+        pub fn load_abuse_contacts(&mut self, path: &Path) -> Result<(), YarrpError> {
+            info!("Loading abuse contacts");
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .comment(Some(b'#'))
+                .from_path(path)?;
+
+            for record in reader.records() {
+                let record = record?;
+                let asn = record.get(0).and_then(parse_asn).map(|asn| asn.to_string());
+                let contact = record.get(1).map(|contact| contact.trim()).filter(|contact| !contact.is_empty());
+
+                match (asn, contact) {
+                    (Some(asn), Some(contact)) => {
+                        self.abuse_contacts.entry(asn).or_default().insert(contact.to_string());
+                    }
+                    _ => warn!("Skipping abuse contact {:?}", record),
+                }
+            }
+
+            info!("Loaded abuse contacts of {} ASN", self.abuse_contacts.len());
+            Ok(())
+        }
+
+        /// reports of all ASNs with routers in loops, restricted to the given ASNs if there are any, ordered by ASN
+        pub fn build<T>(&mut self, project: &Project<T>, attribution: &mut ASNAttribution, asns: &HashSet<String>) -> Result<Vec<ReportResult>, YarrpError>
+            where T: Display + FromStr + Ord + Copy + Clone + Hash + IpAddrExt
+        {
+            let (asn_to_routers, routers_to_asn) = attribution.build_routers_to_asn(project.router_loops()?)?;
+            let (asn_to_loops, _loops_to_asn) = attribution.build_loops_to_asn(project.loop_members()?, &routers_to_asn)?;
+
+            let mut reports = Vec::new();
+            for asn in stable_sorted(asn_to_loops.keys()) {
+                if !asns.is_empty() && !asns.contains(asn) {
+                    continue;
+                }
+
+                let members = &asn_to_loops[asn];
+                let loop_ids: HashSet<&String> = members.single.iter().chain(&members.moas).chain(&members.as_set).collect();
+                let num_routers = asn_to_routers.get(asn)
+                    .map(|routers| routers.single.len() + routers.moas.len() + routers.as_set.len())
+                    .unwrap_or(0);
+
+                let mut loops = Vec::new();
+                for loop_id in stable_sorted(loop_ids) {
+                    if !self.loops.contains_key(loop_id) {
+                        let loop_result = self.build_loop(project, attribution, &routers_to_asn, loop_id)?;
+                        self.loops.insert(loop_id.clone(), loop_result);
+                    }
+                    loops.push(self.loops[loop_id].clone());
+                }
+
+                reports.push(ReportResult {
+                    asn: asn.clone(),
+                    abuse_contacts: self.abuse_contacts.get(asn).map(|contacts| contacts.iter().cloned().collect()).unwrap_or_default(),
+                    routers: num_routers as u64,
+                    shadowed_destinations: loops.iter().map(|loop_result| loop_result.shadowed_destinations).sum(),
+                    loops,
+                });
+            }
+            info!("Built reports for {} ASN", reports.len());
+            Ok(reports)
+        }
+
+        fn build_loop<T>(&self, project: &Project<T>, attribution: &ASNAttribution, routers_to_asn: &MapOrigin, loop_id: &str) -> Result<ReportLoopResult, YarrpError>
+            where T: Display + FromStr + Ord + Copy + Clone + Hash + IpAddrExt
+        {
+            let project_loop = match project.get_loop(loop_id)? {
+                Some(project_loop) => project_loop,
+                None => return Err(YarrpError::NotFoundError)
+            };
+            let asn_list = |router: &str| routers_to_asn.get(router).map(|origin| origin.asns()).unwrap_or_default();
+
+            // ttls a member answered within the loop segment of the sample traces
+            let mut member_ttls: HashMap<T, BTreeSet<u8>> = HashMap::new();
+            let evidence = project.loop_evidence(loop_id)?;
+            for trace in &evidence {
+                for line in &trace.lines {
+                    if trace.in_loop(line.sent_ttl) && project_loop.members.contains(&line.hop) {
+                        member_ttls.entry(line.hop).or_default().insert(line.sent_ttl);
+                    }
+                }
+            }
+
+            // ordered by first ttl within the loop, members without evidence by address
+            let mut members: Vec<&T> = project_loop.members.iter().collect();
+            members.sort_by_key(|member| (member_ttls.get(member).and_then(|ttls| ttls.iter().next().cloned()).unwrap_or(u8::MAX), **member));
+            let members = members.into_iter().map(|member| {
+                let router = member.to_string();
+                ReportRouterResult {
+                    asn_list: asn_list(&router),
+                    origin_type: routers_to_asn.get(&router).and_then(|origin| origin.kind()).map(|kind| kind.to_string()),
+                    ttls: member_ttls.get(member).map(|ttls| ttls.iter().cloned().collect()).unwrap_or_default(),
+                    router,
+                }
+            }).collect();
+
+            let mut info = project_loop.info.clone();
+            info.sort_by(|a, b| a.preceding_router_ttl.cmp(&b.preceding_router_ttl).then_with(|| stable_cmp(&a.preceding_router, &b.preceding_router)));
+            let preceding_routers = info.iter().map(|info| {
+                let preceding_asn = match info.preceding_router.parse() {
+                    Ok(address) => attribution.get_origin_for_ip(&address).asns(),
+                    Err(_) => Vec::new()
+                };
+                ReportPrecedingResult {
+                    router: info.preceding_router.clone(),
+                    asn_list: preceding_asn,
+                    ttl: info.preceding_router_ttl,
+                    loop_start_ttl: info.loop_start_ttl,
+                    loop_stop_ttl: info.loop_stop_ttl,
+                    shadowed_nets: info.shadowed_nets,
+                }
+            }).collect();
+
+            let mut prefixes: BTreeMap<_, (String, u64)> = BTreeMap::new();
+            let mut unannounced = 0;
+            for destination in &project_loop.destinations {
+                match attribution.get_announced_node(&destination.to_ipaddr()?) {
+                    Some(node) => prefixes.entry(*node.get_network()).or_insert_with(|| (node.get_origin().to_string(), 0)).1 += 1,
+                    None => unannounced += 1,
+                }
+            }
+
+            Ok(ReportLoopResult {
+                loop_id: loop_id.to_string(),
+                persistent: self.persistent_loops.as_ref().map(|persistent_loops| persistent_loops.contains(loop_id)),
+                members,
+                evidence_traces: evidence.len() as u64,
+                preceding_routers,
+                shadowed_destinations: project_loop.destinations.len() as u64,
+                unannounced_destinations: unannounced,
+                shadowed_prefixes: prefixes.into_iter().map(|(prefix, (origin, shadowed))| ReportPrefixResult {
+                    prefix: prefix.to_string(),
+                    origin,
+                    shadowed_destinations: shadowed,
+                }).collect(),
+                example_destinations: sorted(&project_loop.destinations).take(self.num_examples).map(|destination| destination.to_string()).collect(),
+            })
+        }
+    }
+
+    /// notification document of one ASN
+    pub fn render_report_markdown(report: &ReportResult) -> String {
+        let mut output = String::new();
+        let _ = writeln!(output, "# Routing loops in AS{}", report.asn);
+        let _ = writeln!(output);
+        if !report.abuse_contacts.is_empty() {
+            let _ = writeln!(output, "Abuse contact: {}", report.abuse_contacts.join(", "));
+            let _ = writeln!(output);
+        }
+        let _ = writeln!(output, "{} loops with {} routers of AS{}, shadowing {} destinations.",
+                         report.loops.len(), report.routers, report.asn, report.shadowed_destinations);
+
+        for loop_result in &report.loops {
+            let persistence = match loop_result.persistent {
+                Some(true) => "yes",
+                Some(false) => "no",
+                None => "unknown"
+            };
+
+            let _ = writeln!(output);
+            let _ = writeln!(output, "## Loop {}", loop_result.loop_id);
+            let _ = writeln!(output);
+            let _ = writeln!(output, "* Persistent: {}", persistence);
+            let _ = writeln!(output, "* Shadowed destinations: {} in {} announced prefixes, {} outside announced prefixes",
+                             loop_result.shadowed_destinations, loop_result.shadowed_prefixes.len(), loop_result.unannounced_destinations);
+
+            let _ = writeln!(output);
+            let _ = writeln!(output, "### Member routers");
+            let _ = writeln!(output);
+            let _ = writeln!(output, "| Router | ASN | TTL |");
+            let _ = writeln!(output, "|---|---|---|");
+            for member in &loop_result.members {
+                let ttls: Vec<String> = member.ttls.iter().map(|ttl| ttl.to_string()).collect();
+                let _ = writeln!(output, "| {} | {} | {} |", member.router, member.asn_list.join(", "), ttls.join(", "));
+            }
+            if loop_result.evidence_traces == 0 {
+                let _ = writeln!(output);
+                let _ = writeln!(output, "No evidence traces are stored for this loop, so the TTLs are unknown. Run loops with `--evidence` to keep them.");
+            }
+
+            if !loop_result.preceding_routers.is_empty() {
+                let _ = writeln!(output);
+                let _ = writeln!(output, "### Preceding routers");
+                let _ = writeln!(output);
+                let _ = writeln!(output, "| Router | ASN | TTL | Loop TTL | Shadowed destinations |");
+                let _ = writeln!(output, "|---|---|---|---|---|");
+                for preceding in &loop_result.preceding_routers {
+                    let _ = writeln!(output, "| {} | {} | {} | {}-{} | {} |", preceding.router, preceding.asn_list.join(", "), preceding.ttl,
+                                     preceding.loop_start_ttl, preceding.loop_stop_ttl, preceding.shadowed_nets);
+                }
+            }
+
+            if !loop_result.shadowed_prefixes.is_empty() {
+                let _ = writeln!(output);
+                let _ = writeln!(output, "### Shadowed prefixes");
+                let _ = writeln!(output);
+                let _ = writeln!(output, "| Prefix | Origin | Shadowed destinations |");
+                let _ = writeln!(output, "|---|---|---|");
+                for prefix in &loop_result.shadowed_prefixes {
+                    let _ = writeln!(output, "| {} | {} | {} |", prefix.prefix, prefix.origin, prefix.shadowed_destinations);
+                }
+            }
+
+            if !loop_result.example_destinations.is_empty() {
+                let _ = writeln!(output);
+                let _ = writeln!(output, "### Example shadowed destinations");
+                let _ = writeln!(output);
+                for destination in &loop_result.example_destinations {
+                    let _ = writeln!(output, "* {}", destination);
+                }
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::net::Ipv4Addr;
+    use crate::analytics::{ASNAttribution, OperatorReports, Project, render_report_markdown};
+    use crate::helpers::test_helper::{TempFixture, ProjectFixture};
+
+    #[test]
+    fn reports_per_asn() {
+        let project_path = ProjectFixture::empty("report_project")
+            .add_loop("deadbeef", &["192.0.2.8", "198.51.100.9"])
+            .destinations("deadbeef", &["10.0.0.1", "10.0.0.2", "203.0.113.1"])
+            .loop_info("deadbeef,192.0.2.7,3,2,8,9,7")
+            .build();
+
+        let routeviews = TempFixture::file("report.pfx2as", "192.0.2.0\t24\t64496\n198.51.100.0\t24\t64497\n10.0.0.0\t8\t64496\n");
+        let mut attribution = ASNAttribution::new("0.0.0.0/0");
+        attribution.load_routeviews_bgp(routeviews.to_str()).ok().unwrap();
+
+        let contacts = TempFixture::file("report_contacts.csv", "asn,contact\nAS64496,abuse@example.net\n");
+
+        let project: Project<Ipv4Addr> = Project::open(project_path.path()).ok().unwrap();
+        let mut reports = OperatorReports::new(2);
+        reports.load_abuse_contacts(contacts.path()).ok().unwrap();
+        reports.set_persistent_loops(vec!["deadbeef".to_string()].into_iter().collect());
+        let results = reports.build(&project, &mut attribution, &HashSet::new()).ok().unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].asn, "64496");
+        assert_eq!(results[0].abuse_contacts, vec!["abuse@example.net".to_string()]);
+        assert_eq!(results[0].routers, 1);
+        assert_eq!(results[1].asn, "64497");
+        assert!(results[1].abuse_contacts.is_empty());
+
+        let loop_result = &results[0].loops[0];
+        assert_eq!(loop_result.persistent, Some(true));
+        assert_eq!(loop_result.evidence_traces, 0);
+        assert_eq!(loop_result.shadowed_destinations, 3);
+        assert_eq!(loop_result.unannounced_destinations, 1);
+        assert_eq!(loop_result.shadowed_prefixes.len(), 1);
+        assert_eq!(loop_result.shadowed_prefixes[0].shadowed_destinations, 2);
+        assert_eq!(loop_result.example_destinations, vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()]);
+        assert_eq!(loop_result.preceding_routers[0].asn_list, vec!["64496".to_string()]);
+
+        let markdown = render_report_markdown(&results[0]);
+        assert!(markdown.starts_with("# Routing loops in AS64496\n"));
+        assert!(markdown.contains("| 198.51.100.9 | 64497 |  |"));
+        assert!(markdown.contains("No evidence traces are stored for this loop"), "The empty TTL column is explained");
+        assert!(markdown.contains("| 192.0.2.7 | 64496 | 7 | 8-9 | 3 |"));
+    }
+}
@@ -0,0 +1,197 @@
+pub mod prefix_coverage {
+    use std::collections::{HashMap, HashSet};
+    use std::net::IpAddr;
+    use std::path::Path;
+    use ipnet::IpNet;
+    use log::info;
+
+    use crate::analytics::ASNAttribution;
+    use crate::structs::{ASNTree, ASNOrigin, YarrpError, PrefixCoverageEntry, LoopCoverageEntry, atomic_csv_writer, commit_csv};
+
+    struct PrefixCounts {
+        origin: ASNOrigin,
+        more_specifics: u64,
+        probed: HashSet<IpAddr>,
+        shadowed: HashSet<IpAddr>,
+        loops: HashSet<String>,
+    }
+
+    #[derive(Default)]
+    struct LoopCoverage {
+        shadowed: HashSet<IpAddr>,
+        unannounced: u64,
+        aggregate_gap: u64,
+        covering: HashSet<IpNet>,
+    }
+
+    /// Distinct probed and shadowed targets per announced prefix, attributed to the most specific announced prefix.
+    /// A target whose most specific prefix has announced more specifics lies in the unannounced
+    /// space of a covering aggregate.
+    #[derive(Default)]
+    pub struct PrefixCoverage {
+        prefixes: HashMap<IpNet, PrefixCounts>,
+        loops: HashMap<String, LoopCoverage>,
+        unannounced_probed: HashSet<IpAddr>,
+    }
+
+    impl PrefixCoverage {
+        pub fn new() -> PrefixCoverage {
+            PrefixCoverage::default()
+        }
+
+        fn prefix_counts(&mut self, attribution: &ASNAttribution, address: &IpAddr) -> Option<(IpNet, &mut PrefixCounts)> {
+            let node = attribution.get_announced_node(address)?;
+            let prefix = *node.get_network();
+            let counts = self.prefixes.entry(prefix).or_insert_with(|| PrefixCounts {
+                origin: node.get_origin().clone(),
+                more_specifics: node.get_num_children(),
+                probed: HashSet::new(),
+                shadowed: HashSet::new(),
+                loops: HashSet::new(),
+            });
+            Some((prefix, counts))
+        }
+
+        pub fn add_probed(&mut self, attribution: &ASNAttribution, address: &IpAddr) {
+            match self.prefix_counts(attribution, address) {
+                Some((_prefix, counts)) => counts.probed.insert(*address),
+                None => self.unannounced_probed.insert(*address),
+            };
+        }
+
+        /// a destination shadowed by several loops counts once per prefix and once for each loop
+        pub fn add_shadowed(&mut self, attribution: &ASNAttribution, loop_id: &str, address: &IpAddr) {
+            let loop_coverage = self.loops.entry(loop_id.to_string()).or_default();
+            if !loop_coverage.shadowed.insert(*address) {
+                return;
+            }
+
+            let mut gap_prefix = None;
+            let mut announced = false;
+            if let Some((prefix, counts)) = self.prefix_counts(attribution, address) {
+                counts.shadowed.insert(*address);
+                counts.loops.insert(loop_id.to_string());
+                announced = true;
+                if counts.more_specifics > 0 {
+                    gap_prefix = Some(prefix);
+                }
+            }
+
+            let loop_coverage = self.loops.entry(loop_id.to_string()).or_default();
+            if !announced {
+                loop_coverage.unannounced += 1;
+            }
+            if let Some(prefix) = gap_prefix {
+                loop_coverage.aggregate_gap += 1;
+                loop_coverage.covering.insert(prefix);
+            }
+        }
+
+        /// number of loops with shadowed nets in the unannounced space of a covering prefix
+        pub fn num_aggregate_gap_loops(&self) -> usize {
+            self.loops.values().filter(|coverage| coverage.aggregate_gap > 0).count()
+        }
+
+        pub fn num_prefixes(&self) -> usize {
+            self.prefixes.len()
+        }
+
+        pub fn num_unannounced_probed(&self) -> u64 {
+            self.unannounced_probed.len() as u64
+        }
+
+        /// one line per announced prefix with probed or shadowed targets, coverage is the share of probed targets
+        /// that are shadowed, so shadowed destinations missing from the target list do not count
+        pub fn write_prefix_csv(&self, output_path: &Path) -> Result<(), YarrpError> {
+            info!("Writing prefix coverage for {} prefixes", self.prefixes.len());
+            let mut writer = atomic_csv_writer(output_path)?;
+
+            let mut prefixes: Vec<&IpNet> = self.prefixes.keys().collect();
+            prefixes.sort();
+            for prefix in prefixes {
+                let counts = &self.prefixes[prefix];
+                let shadowed_probed = counts.shadowed.intersection(&counts.probed).count() as u64;
+                let coverage = match counts.probed.len() {
+                    0 => 0.0,
+                    probed => shadowed_probed as f64 / probed as f64
+                };
+
+                writer.serialize(PrefixCoverageEntry {
+                    prefix: *prefix,
+                    origin: counts.origin.to_string(),
+                    more_specifics: counts.more_specifics,
+                    probed: counts.probed.len() as u64,
+                    shadowed: counts.shadowed.len() as u64,
+                    shadowed_probed,
+                    loops: counts.loops.len() as u64,
+                    coverage,
+                })?;
+            }
+            commit_csv(writer)
+        }
+
+        pub fn write_loop_csv(&self, output_path: &Path) -> Result<(), YarrpError> {
+            let mut writer = atomic_csv_writer(output_path)?;
+
+            let mut loop_ids: Vec<&String> = self.loops.keys().collect();
+            loop_ids.sort();
+            for loop_id in loop_ids {
+                let coverage = &self.loops[loop_id];
+                let mut covering: Vec<&IpNet> = coverage.covering.iter().collect();
+                covering.sort();
+
+                writer.serialize(LoopCoverageEntry {
+                    loop_id: loop_id.clone(),
+                    shadowed: coverage.shadowed.len() as u64,
+                    unannounced: coverage.unannounced,
+                    aggregate_gap: coverage.aggregate_gap,
+                    in_aggregate_gap: coverage.aggregate_gap > 0,
+                    covering_prefixes: covering.iter().map(|prefix| prefix.to_string()).collect(),
+                })?;
+            }
+            commit_csv(writer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use crate::analytics::{ASNAttribution, PrefixCoverage};
+    use crate::helpers::test_helper::TempFixture;
+
+    #[test]
+    fn shadowed_in_aggregate_gap() {
+        let path = TempFixture::file("coverage.pfx2as", "10.0.0.0\t16\t64496\n10.0.1.0\t24\t64497\n");
+        let mut attribution = ASNAttribution::new("0.0.0.0/0");
+        attribution.load_routeviews_bgp(path.to_str()).ok().unwrap();
+
+        let mut coverage = PrefixCoverage::new();
+        for address in &["10.0.1.1", "10.0.1.2", "10.0.1.2", "10.0.2.1", "192.0.2.1", "192.0.2.1"] {
+            coverage.add_probed(&attribution, &IpAddr::from_str(address).unwrap());
+        }
+        coverage.add_shadowed(&attribution, "more_specific", &IpAddr::from_str("10.0.1.1").unwrap());
+        coverage.add_shadowed(&attribution, "more_specific", &IpAddr::from_str("10.0.1.1").unwrap());
+        coverage.add_shadowed(&attribution, "other", &IpAddr::from_str("10.0.1.1").unwrap());
+        coverage.add_shadowed(&attribution, "other", &IpAddr::from_str("10.0.1.3").unwrap());
+        coverage.add_shadowed(&attribution, "aggregate", &IpAddr::from_str("10.0.2.1").unwrap());
+
+        assert_eq!(coverage.num_prefixes(), 2);
+        assert_eq!(coverage.num_unannounced_probed(), 1);
+        assert_eq!(coverage.num_aggregate_gap_loops(), 1);
+
+        let output = TempFixture::new("coverage.csv");
+        coverage.write_prefix_csv(output.path()).ok().unwrap();
+        let content = fs::read_to_string(output.path()).unwrap();
+        assert!(content.contains("10.0.0.0/16,64496,1,1,1,1,1,1.0"));
+        assert!(content.contains("10.0.1.0/24,64497,0,2,2,1,2,0.5"), "Shadowed targets missing from the target list do not count as coverage");
+
+        coverage.write_loop_csv(output.path()).ok().unwrap();
+        let content = fs::read_to_string(output.path()).unwrap();
+        assert!(content.contains("aggregate,1,0,1,true,10.0.0.0/16"));
+        assert!(content.contains("more_specific,1,0,0,false,"), "Repeated destinations of a loop count once");
+        assert!(content.contains("other,2,0,0,false,"));
+    }
+}
@@ -0,0 +1,472 @@
+pub mod project {
+    use std::cell::OnceCell;
+    use std::collections::{HashMap, HashSet};
+    use std::fmt::Display;
+    use std::fs::{self, File};
+    use std::hash::Hash;
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+    use log::{error, info, trace, warn};
+    use itertools::sorted;
+
+    use crate::analytics::{LoopStorage, LoopStatistics};
+    use crate::analytics::{ROUTERS, IDENTIFIERS, IMPERILED, LOOPS, LOOPS_CSV, STATS, SHADOWED_PRECEDING_INFO, EVIDENCE, PROCESSED_FILES};
+    use crate::structs::{YarrpError, SimpleLoopOutput, ShadowedPreceding, OutputPolicy, output_exists};
+    use crate::structs::{EvidenceTrace, read_evidence_file};
+    use crate::traits::IpAddrExt;
+
+    /// files and directories a project consists of
+    pub const PROJECT_FILES: [&str; 6] = [IDENTIFIERS, ROUTERS, STATS, LOOPS_CSV, SHADOWED_PRECEDING_INFO, PROCESSED_FILES];
+    pub const PROJECT_DIRS: [&str; 3] = [LOOPS, IMPERILED, EVIDENCE];
+
+    /// directory within the project directory an overwriting run writes its new project to
+    pub const STAGING_DIR: &str = ".overwrite";
+
+    /// Where a run writes its project. Overwriting an existing project writes the new project into
+    /// STAGING_DIR, its files replace the existing project files on commit, so a failed run keeps the old project.
+    /// A staged project dropped without commit is removed, one left behind by an exiting run is removed by the next run.
+    pub struct ProjectOutput {
+        path: PathBuf,
+        staging: Option<PathBuf>,
+    }
+
+    impl ProjectOutput {
+        /// the project is written to path directly
+        pub fn in_place(path: &Path) -> ProjectOutput {
+            ProjectOutput {
+                path: path.to_path_buf(),
+                staging: None,
+            }
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// directory to write the project to until commit
+        pub fn write_path(&self) -> &Path {
+            self.staging.as_deref().unwrap_or(&self.path)
+        }
+
+        pub fn is_staged(&self) -> bool {
+            self.staging.is_some()
+        }
+
+        /// replaces the project files and directories of path by the staged ones,
+        /// afterwards the project is written in place
+        pub fn commit(&mut self) -> Result<(), YarrpError> {
+            let staging = match self.staging.take() {
+                Some(staging) => staging,
+                None => return Ok(())
+            };
+
+            info!("Replacing the project files in {}", self.path.to_str().unwrap_or(""));
+            for name in PROJECT_FILES.iter().chain(PROJECT_DIRS.iter()) {
+                let existing = self.path.join(name);
+                if existing.is_dir() {
+                    fs::remove_dir_all(&existing)?;
+                } else if existing.exists() {
+                    fs::remove_file(&existing)?;
+                }
+
+                let staged = staging.join(name);
+                if staged.exists() {
+                    fs::rename(&staged, &existing)?;
+                }
+            }
+            fs::remove_dir_all(&staging)?;
+            Ok(())
+        }
+    }
+
+    impl Drop for ProjectOutput {
+        fn drop(&mut self) {
+            if let Some(staging) = &self.staging {
+                let _ = fs::remove_dir_all(staging);
+            }
+        }
+    }
+
+    /// Prepares path to be written as a project according to the output policy.
+    /// An existing project is refused, staged for replacement on overwrite or kept on append.
+    pub fn prepare_project_output(path: &Path, policy: OutputPolicy) -> Result<ProjectOutput, YarrpError> {
+        let mut output = ProjectOutput::in_place(path);
+        let existing = PROJECT_FILES.iter().chain(PROJECT_DIRS.iter())
+            .map(|name| path.join(name))
+            .any(|item| output_exists(&item));
+        if !existing {
+            return Ok(output);
+        }
+
+        match policy {
+            OutputPolicy::Refuse => {
+                error!("{} already holds a project, use --overwrite or --append!", path.to_str().unwrap_or(""));
+                Err(YarrpError::OutputExistsError)
+            }
+            OutputPolicy::Overwrite => {
+                // a staging directory left behind by a failed run is started over
+                let staging = path.join(STAGING_DIR);
+                if staging.exists() {
+                    fs::remove_dir_all(&staging)?;
+                }
+                fs::create_dir_all(&staging)?;
+                info!("Writing the new project to {} until it is complete", staging.to_str().unwrap_or(""));
+                output.staging = Some(staging);
+                Ok(output)
+            }
+            OutputPolicy::Append => Ok(output),
+        }
+    }
+
+    /// A loop of a project with its members, loops.csv entries (one per preceding router) and shadowed destinations
+    pub struct ProjectLoop<T> {
+        pub loop_id: String,
+        pub members: HashSet<T>,
+        pub info: Vec<SimpleLoopOutput>,
+        pub destinations: Vec<T>,
+    }
+
+    /// A router of a project with the loops it is part of and the destinations it imperils
+    pub struct ProjectRouter<T> {
+        pub router: T,
+        pub loops: HashSet<String>,
+        pub imperiled: HashSet<T>,
+    }
+
+    /// Read-only view on a project directory created by the loops mode.
+    /// Id files and loops.csv are read on first use, detail files whenever a loop or router is requested.
+    pub struct Project<T> {
+        path: PathBuf,
+        loop_members: OnceCell<HashMap<String, HashSet<T>>>,
+        router_loops: OnceCell<HashMap<T, HashSet<String>>>,
+        loop_info: OnceCell<HashMap<String, Vec<SimpleLoopOutput>>>,
+    }
+
+    impl<T: Display + FromStr + Ord + Copy + Clone + Hash + IpAddrExt> Project<T> {
+        pub fn open(path: &Path) -> Result<Project<T>, YarrpError> {
+            if !path.exists() || !path.is_dir() {
+                error!("Project path {} does not exist or is not a directory!", path.to_str().unwrap_or(""));
+                return Err(YarrpError::NotFoundError);
+            }
+
+            Ok(Project {
+                path: path.to_path_buf(),
+                loop_members: OnceCell::new(),
+                router_loops: OnceCell::new(),
+                loop_info: OnceCell::new(),
+            })
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        pub fn file(&self, file: &str) -> PathBuf {
+            self.path.join(file)
+        }
+
+        pub fn loop_destinations_path(&self, loop_id: &str) -> PathBuf {
+            self.path.join(LOOPS).join(format!("{}.dest", loop_id))
+        }
+
+        pub fn router_imperiled_path(&self, router: &T) -> PathBuf {
+            self.path.join(IMPERILED).join(format!("{}.imp", router))
+        }
+
+        pub fn loop_evidence_path(&self, loop_id: &str) -> PathBuf {
+            self.path.join(EVIDENCE).join(format!("{}.trace", loop_id))
+        }
+
+        /// only_full_routes setting of the stats file, None if there is none
+        pub fn only_full_routes(&self) -> Result<Option<bool>, YarrpError> {
+            LoopStatistics::read_only_full_routes(&self.file(STATS))
+        }
+
+        /// statistics of the stats file, fails if it can not be parsed
+        pub fn stats(&self) -> Result<LoopStatistics, YarrpError> {
+            let only_full_routes = self.only_full_routes()?.unwrap_or(true);
+            LoopStatistics::from_file(only_full_routes, self.file(STATS))
+        }
+
+        /// loop id -> loop members
+        pub fn loop_members(&self) -> Result<&HashMap<String, HashSet<T>>, YarrpError> {
+            if self.loop_members.get().is_none() {
+                info!("Reading loop identifiers from file");
+                let raw = LoopStorage::<T>::read_id_file(&self.file(IDENTIFIERS))?;
+                let mut loop_members = HashMap::new();
+                for (loop_id, members) in raw {
+                    loop_members.insert(loop_id, Project::<T>::parse_set(&members)?);
+                }
+                let _ = self.loop_members.set(loop_members);
+            }
+            Ok(self.loop_members.get().unwrap())
+        }
+
+        /// router -> loop ids
+        pub fn router_loops(&self) -> Result<&HashMap<T, HashSet<String>>, YarrpError> {
+            if self.router_loops.get().is_none() {
+                info!("Reading router associations from file");
+                let raw = LoopStorage::<T>::read_id_file(&self.file(ROUTERS))?;
+                let mut router_loops = HashMap::new();
+                for (router, loops) in raw {
+                    router_loops.insert(Project::<T>::parse_address(&router)?, loops);
+                }
+                let _ = self.router_loops.set(router_loops);
+            }
+            Ok(self.router_loops.get().unwrap())
+        }
+
+        /// loop id -> loops.csv entries of this loop
+        pub fn loop_info(&self) -> Result<&HashMap<String, Vec<SimpleLoopOutput>>, YarrpError> {
+            if self.loop_info.get().is_none() {
+                let mut loop_info: HashMap<String, Vec<SimpleLoopOutput>> = HashMap::new();
+                // postloopstats replaces loops.csv with its own summary, which holds no loop information
+                match LoopStorage::<T>::read_loop_info(&self.file(LOOPS_CSV)) {
+                    Ok(records) => {
+                        for ((loop_id, _preceding), info) in records {
+                            loop_info.entry(loop_id).or_default().push(info);
+                        }
+                    }
+                    Err(_) => warn!("{} does not contain loop information, ignoring it!", LOOPS_CSV)
+                }
+                let _ = self.loop_info.set(loop_info);
+            }
+            Ok(self.loop_info.get().unwrap())
+        }
+
+        /// shadowed destinations of a loop, empty if the loop has no destination file
+        pub fn loop_destinations(&self, loop_id: &str) -> Result<Vec<T>, YarrpError> {
+            let path = self.loop_destinations_path(loop_id);
+            if !path.exists() {
+                trace!("No destination file for loop {}", loop_id);
+                return Ok(Vec::new());
+            }
+            LoopStorage::<T>::read_details_file_as_t(&path)
+        }
+
+        /// sample traces stored for a loop by loops --evidence, empty if there are none
+        pub fn loop_evidence(&self, loop_id: &str) -> Result<Vec<EvidenceTrace<T>>, YarrpError> {
+            let path = self.loop_evidence_path(loop_id);
+            if !path.exists() {
+                trace!("No evidence file for loop {}", loop_id);
+                return Ok(Vec::new());
+            }
+            read_evidence_file(&path)
+        }
+
+        /// destinations imperiled by a router, empty if the router has no imperiled file
+        pub fn router_imperiled(&self, router: &T) -> Result<HashSet<T>, YarrpError> {
+            let path = self.router_imperiled_path(router);
+            if !path.exists() {
+                return Ok(HashSet::new());
+            }
+            LoopStorage::<T>::read_details_file_as_t_ret_set(&path)
+        }
+
+        pub fn get_loop(&self, loop_id: &str) -> Result<Option<ProjectLoop<T>>, YarrpError> {
+            let members;
+            if let Some(value) = self.loop_members()?.get(loop_id) {
+                members = value.clone();
+            } else {
+                return Ok(None);
+            }
+
+            let info = self.loop_info()?.get(loop_id).cloned().unwrap_or_default();
+            Ok(Some(ProjectLoop {
+                loop_id: loop_id.to_string(),
+                members,
+                info,
+                destinations: self.loop_destinations(loop_id)?,
+            }))
+        }
+
+        pub fn get_router(&self, router: &T) -> Result<Option<ProjectRouter<T>>, YarrpError> {
+            let loops;
+            if let Some(value) = self.router_loops()?.get(router) {
+                loops = value.clone();
+            } else {
+                return Ok(None);
+            }
+
+            Ok(Some(ProjectRouter {
+                router: *router,
+                loops,
+                imperiled: self.router_imperiled(router)?,
+            }))
+        }
+
+        /// iterates over all loops ordered by loop id, destinations are read per loop
+        pub fn loops(&self) -> Result<ProjectLoopIter<'_, T>, YarrpError> {
+            let loop_ids = sorted(self.loop_members()?.keys().cloned()).collect();
+            Ok(ProjectLoopIter { project: self, loop_ids, index: 0 })
+        }
+
+        /// iterates over all routers ordered by address, imperiled destinations are read per router
+        pub fn routers(&self) -> Result<ProjectRouterIter<'_, T>, YarrpError> {
+            let routers = sorted(self.router_loops()?.keys().cloned()).collect();
+            Ok(ProjectRouterIter { project: self, routers, index: 0 })
+        }
+
+        /// iterates over the records of shadowed_preceding.csv, empty if the file does not exist
+        pub fn shadowed_preceding(&self) -> Result<Box<dyn Iterator<Item=Result<ShadowedPreceding, YarrpError>>>, YarrpError> {
+            let path = self.file(SHADOWED_PRECEDING_INFO);
+            if !path.exists() {
+                return Ok(Box::new(std::iter::empty()));
+            }
+
+            let reader: csv::Reader<File> = csv::Reader::from_path(path)?;
+            Ok(Box::new(reader.into_deserialize().map(|record| record.map_err(YarrpError::from))))
+        }
+
+        fn parse_address(value: &str) -> Result<T, YarrpError> {
+            if let Ok(address) = T::from_str(value) {
+                Ok(address)
+            } else {
+                error!("Could not parse address {}", value);
+                Err(YarrpError::CouldNotParseError)
+            }
+        }
+
+        fn parse_set(values: &HashSet<String>) -> Result<HashSet<T>, YarrpError> {
+            let mut set = HashSet::new();
+            for value in values {
+                set.insert(Project::<T>::parse_address(value)?);
+            }
+            Ok(set)
+        }
+    }
+
+    pub struct ProjectLoopIter<'a, T> {
+        project: &'a Project<T>,
+        loop_ids: Vec<String>,
+        index: usize,
+    }
+
+    impl<'a, T: Display + FromStr + Ord + Copy + Clone + Hash + IpAddrExt> Iterator for ProjectLoopIter<'a, T> {
+        type Item = Result<ProjectLoop<T>, YarrpError>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let loop_id = self.loop_ids.get(self.index)?;
+            self.index += 1;
+
+            match self.project.get_loop(loop_id) {
+                Ok(Some(project_loop)) => Some(Ok(project_loop)),
+                Ok(None) => Some(Err(YarrpError::NotFoundError)),
+                Err(error) => Some(Err(error)),
+            }
+        }
+    }
+
+    pub struct ProjectRouterIter<'a, T> {
+        project: &'a Project<T>,
+        routers: Vec<T>,
+        index: usize,
+    }
+
+    impl<'a, T: Display + FromStr + Ord + Copy + Clone + Hash + IpAddrExt> Iterator for ProjectRouterIter<'a, T> {
+        type Item = Result<ProjectRouter<T>, YarrpError>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let router = self.routers.get(self.index)?;
+            self.index += 1;
+
+            match self.project.get_router(router) {
+                Ok(Some(project_router)) => Some(Ok(project_router)),
+                Ok(None) => Some(Err(YarrpError::NotFoundError)),
+                Err(error) => Some(Err(error)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::Ipv4Addr;
+    use crate::analytics::{Project, IDENTIFIERS, LOOPS, STAGING_DIR, prepare_project_output};
+    use crate::structs::OutputPolicy;
+    use crate::helpers::test_helper::{init, get_ipv4_hop, TempFixture, ProjectFixture};
+
+    fn create_project(name: &str) -> TempFixture {
+        let project = ProjectFixture::new(name).build();
+        project.write("imperiled/192.0.2.9.imp", "198.51.100.3\n");
+        project.write("shadowed_preceding.csv", "shadowed_net,preceding_router,preceding_ttl,loop_id\n198.51.100.1,192.0.2.7,7,aa\n198.51.100.2,192.0.2.7,7,aa\n");
+        project.write("stats.csv", "only_full_routes,0\nroutes,10\nloops,2\nloop_length_2,2\n");
+        project
+    }
+
+    #[test]
+    fn read_loops_and_routers() {
+        init();
+        let path = create_project("project_read");
+        let project = Project::<Ipv4Addr>::open(path.path()).ok().unwrap();
+
+        let loops: Vec<_> = project.loops().ok().unwrap().map(|item| item.ok().unwrap()).collect();
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].loop_id, "aa");
+        assert_eq!(loops[0].destinations.len(), 2);
+        assert_eq!(loops[0].info.len(), 1);
+        assert!(loops[0].members.contains(&get_ipv4_hop(8)));
+        assert!(loops[1].destinations.is_empty(), "Loop without destination file should be empty");
+
+        let routers: Vec<_> = project.routers().ok().unwrap().map(|item| item.ok().unwrap()).collect();
+        assert_eq!(routers.len(), 3);
+        assert_eq!(routers[0].router, get_ipv4_hop(8));
+        assert_eq!(routers[1].loops.len(), 2);
+        assert_eq!(routers[1].imperiled.len(), 1);
+
+        assert_eq!(project.shadowed_preceding().ok().unwrap().count(), 2);
+        assert_eq!(project.only_full_routes().ok().unwrap(), Some(false));
+        assert_eq!(project.stats().ok().unwrap().number_of_routes, 10);
+    }
+
+    #[test]
+    fn unparsable_stats_are_an_error() {
+        init();
+        let path = create_project("project_stats");
+        path.write("stats.csv", "only_full_routes,0\nroutes,many\n");
+        let project = Project::<Ipv4Addr>::open(path.path()).ok().unwrap();
+        assert!(project.stats().is_err());
+    }
+
+    #[test]
+    fn open_refuses_missing_project() {
+        init();
+        let path = TempFixture::new("nonexistent_project");
+        assert!(Project::<Ipv4Addr>::open(path.path()).is_err());
+    }
+
+    #[test]
+    fn overwrite_replaces_project_on_commit() {
+        init();
+        let project_path = ProjectFixture::empty("project_overwrite")
+            .add_loop("aa", &["192.0.2.8", "192.0.2.9"])
+            .destinations("aa", &["198.51.100.1"])
+            .build();
+        project_path.write("routers.csv", "router\n");
+        assert!(prepare_project_output(project_path.path(), OutputPolicy::Refuse).is_err());
+
+        {
+            let output = prepare_project_output(project_path.path(), OutputPolicy::Overwrite).ok().unwrap();
+            assert_eq!(output.write_path(), project_path.join(STAGING_DIR));
+            fs::write(output.write_path().join(IDENTIFIERS), "unfinished\n").unwrap();
+        }
+        assert_eq!(fs::read_to_string(project_path.join(IDENTIFIERS)).unwrap(), "aa=192.0.2.8;192.0.2.9;\n", "A failed run keeps the project");
+        assert!(!project_path.join(STAGING_DIR).exists(), "The unfinished project is removed");
+
+        project_path.write(format!("{}/{}", STAGING_DIR, IDENTIFIERS), "left behind\n");
+        let mut output = prepare_project_output(project_path.path(), OutputPolicy::Overwrite).ok().unwrap();
+        assert!(!output.write_path().join(IDENTIFIERS).exists(), "Staging starts over");
+        fs::write(output.write_path().join(IDENTIFIERS), "bb=192.0.2.9;192.0.2.10;\n").unwrap();
+        output.commit().ok().unwrap();
+
+        assert_eq!(output.write_path(), project_path.path());
+        assert_eq!(fs::read_to_string(project_path.join(IDENTIFIERS)).unwrap(), "bb=192.0.2.9;192.0.2.10;\n");
+        assert!(!project_path.join(LOOPS).exists(), "Project directories of the old project are removed");
+        assert!(!project_path.join(STAGING_DIR).exists());
+        assert!(project_path.join("routers.csv").exists(), "Files other than project files are kept");
+
+        let output = prepare_project_output(project_path.path(), OutputPolicy::Append).ok().unwrap();
+        assert_eq!(output.write_path(), project_path.path());
+    }
+}
@@ -0,0 +1,183 @@
+pub mod project_watcher {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+    use log::{info, trace};
+
+    use crate::analytics::PROCESSED_FILES;
+    use crate::modes::ModeTrait;
+    use crate::structs::{AtomicFile, YarrpError};
+    use crate::{read_input_file, read_lines};
+
+    /// Watches a directory for new yarrp output files and ingests every completed file into a project.
+    /// Ingested files are recorded in the project, so a restarted watcher does not pass a file twice.
+    pub struct ProjectWatcher {
+        watch_dir: PathBuf,
+        processed_path: PathBuf,
+        settle_time: Duration,
+        processed: HashSet<String>,
+        file_number: u64,
+    }
+
+    impl ProjectWatcher {
+        /// files count as completed once they were not modified for settle_time
+        pub fn new(watch_dir: &Path, project_path: &Path, settle_time: Duration) -> Result<ProjectWatcher, YarrpError> {
+            if !watch_dir.is_dir() {
+                return Err(YarrpError::NotFoundError);
+            }
+
+            let processed_path = project_path.join(PROCESSED_FILES);
+            let mut processed = HashSet::new();
+            if processed_path.exists() {
+                for line in read_lines(&processed_path)? {
+                    processed.insert(line?);
+                }
+                info!("Project already holds {} processed files", processed.len());
+            }
+
+            Ok(ProjectWatcher {
+                watch_dir: watch_dir.to_path_buf(),
+                processed_path,
+                settle_time,
+                file_number: processed.len() as u64,
+                processed,
+            })
+        }
+
+        /// continues with the project moved to project_path, e.g. once a staged project replaced the existing one
+        pub fn move_project(&mut self, project_path: &Path) {
+            self.processed_path = project_path.join(PROCESSED_FILES);
+        }
+
+        pub fn is_processed(&self, file_name: &str) -> bool {
+            self.processed.contains(file_name)
+        }
+
+        fn is_complete(&self, path: &Path) -> Result<bool, YarrpError> {
+            let modified = path.metadata()?.modified()?;
+            let age = SystemTime::now().duration_since(modified).unwrap_or(Duration::from_secs(0));
+            Ok(age >= self.settle_time)
+        }
+
+        /// completed files of the watch directory that were not ingested yet, ordered by name.
+        /// Hidden and temporary (.tmp) files are ignored.
+        pub fn pending_files(&self) -> Result<Vec<PathBuf>, YarrpError> {
+            let mut pending = Vec::new();
+            for entry in fs::read_dir(&self.watch_dir)? {
+                let path = entry?.path();
+                let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                    Some(value) => value,
+                    None => continue,
+                };
+
+                if !path.is_file() || file_name.starts_with('.') || file_name.ends_with(".tmp") || self.is_processed(file_name) {
+                    continue;
+                }
+
+                if !self.is_complete(&path)? {
+                    trace!("{} is still being written", file_name);
+                    continue;
+                }
+                pending.push(path);
+            }
+            pending.sort();
+            Ok(pending)
+        }
+
+        /// feeds a file through a freshly created mode, which writes the project when it is closed and dropped,
+        /// and records the file as processed afterwards
+        pub fn ingest<F>(&mut self, path: &Path, create_mode: &mut F) -> Result<(), YarrpError>
+            where F: FnMut() -> Box<dyn ModeTrait> {
+            let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("").to_string();
+            let path_str = path.to_str().unwrap_or("");
+            info!("Ingesting {}", path_str);
+
+            let mut mode_item = create_mode();
+            read_input_file(mode_item.as_mut(), path_str, self.file_number, || {})?;
+            mode_item.do_calculations();
+            mode_item.close();
+            drop(mode_item);
+
+            let mut processed_file = AtomicFile::append(&self.processed_path)?;
+            writeln!(processed_file, "{}", file_name)?;
+            processed_file.commit()?;
+
+            self.processed.insert(file_name);
+            self.file_number += 1;
+            Ok(())
+        }
+
+        /// ingests all pending files, returns how many were ingested
+        pub fn poll_once<F>(&mut self, mut create_mode: F) -> Result<u64, YarrpError>
+            where F: FnMut() -> Box<dyn ModeTrait> {
+            let mut ingested = 0;
+            for path in self.pending_files()? {
+                self.ingest(&path, &mut create_mode)?;
+                ingested += 1;
+            }
+            Ok(ingested)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::Ipv4Addr;
+    use std::path::Path;
+    use std::time::Duration;
+    use crate::analytics::{LoopAnalysis, LoopStatistics, ProjectWatcher, STATS, PROCESSED_FILES};
+    use crate::modes::{LoopsMode, ModeTrait};
+    use crate::structs::DetailFormat;
+    use crate::helpers::test_helper::{init, MIN_TTL, MAX_TTL, TempFixture};
+
+    fn write_scan(path: &Path, first_destination: u8) {
+        let mut content = String::new();
+        for destination in first_destination..first_destination + 3 {
+            for ttl in MIN_TTL..=MAX_TTL {
+                let hop = if ttl < 10 { format!("192.0.2.{}", ttl) } else { format!("10.0.0.{}", 1 + ttl % 2) };
+                content.push_str(&format!("198.51.100.{} 1 1 11 0 {} {} 590 0 36 84 63 0 0 27\n", destination, ttl, hop));
+            }
+        }
+        fs::write(path, content).unwrap();
+    }
+
+    fn create_mode(project_path: &Path) -> Box<dyn ModeTrait> {
+        let analysis = LoopAnalysis::<Ipv4Addr>::new(false, project_path.to_str().unwrap().to_string(), "", "", DetailFormat::Text, 0);
+        Box::new(LoopsMode::from_analysis(analysis, MIN_TTL, MAX_TTL))
+    }
+
+    #[test]
+    fn poll_ingests_each_file_once() {
+        init();
+
+        let watch_dir = TempFixture::dir("watch_in");
+        let project = TempFixture::dir("watch_project");
+        let project_path = project.path();
+
+        write_scan(&watch_dir.join("scan_01.yrp"), 1);
+        write_scan(&watch_dir.join("scan_02.yrp"), 10);
+        fs::write(watch_dir.join("scan_03.yrp.tmp"), "").unwrap();
+
+        let mut watcher = ProjectWatcher::new(watch_dir.path(), project_path, Duration::from_secs(0)).ok().unwrap();
+        assert_eq!(watcher.poll_once(|| create_mode(project_path)).ok().unwrap(), 2);
+        assert_eq!(watcher.poll_once(|| create_mode(project_path)).ok().unwrap(), 0, "Files must not be ingested twice");
+
+        let stats = LoopStatistics::new(false, project_path.join(STATS));
+        assert_eq!(stats.number_of_routes, 6);
+        assert_eq!(stats.number_of_loops, 6);
+
+        // a restarted watcher continues with the files recorded in the project
+        write_scan(&watch_dir.join("scan_03.yrp"), 20);
+        let mut watcher = ProjectWatcher::new(watch_dir.path(), project_path, Duration::from_secs(0)).ok().unwrap();
+        assert!(watcher.is_processed("scan_01.yrp"));
+        assert_eq!(watcher.poll_once(|| create_mode(project_path)).ok().unwrap(), 1);
+
+        let stats = LoopStatistics::new(false, project_path.join(STATS));
+        assert_eq!(stats.number_of_routes, 9);
+        assert_eq!(fs::read_to_string(project_path.join(PROCESSED_FILES)).unwrap(), "scan_01.yrp\nscan_02.yrp\nscan_03.yrp\n");
+        assert_eq!(fs::read_to_string(project_path.join("loops.csv")).unwrap().lines().count(), 2, "One loop with one preceding router expected");
+    }
+}
@@ -0,0 +1,157 @@
+pub mod ptr_names {
+    use std::collections::{HashMap, HashSet};
+    use std::fmt::Display;
+    use std::net::IpAddr;
+    use std::path::Path;
+    use std::str::FromStr;
+    use log::{info, warn};
+    use serde_json::Value;
+
+    use crate::read_lines;
+    use crate::structs::YarrpError;
+
+    /// second level labels under which country code TLDs register domains, e.g. example.co.uk
+    const SECOND_LEVEL_LABELS: [&str; 10] = ["ac", "co", "com", "edu", "gov", "ne", "net", "or", "org", "go"];
+
+    /// Reverse DNS names of router addresses, read from an offline PTR dump
+    pub struct PtrNames {
+        names: HashMap<IpAddr, String>,
+    }
+
+    /// registered domain of a hostname, the last two labels or three below a second level label of a country code TLD
+    pub fn registered_domain(hostname: &str) -> Option<String> {
+        let hostname = hostname.trim_end_matches('.').to_lowercase();
+        let labels: Vec<&str> = hostname.split('.').filter(|label| !label.is_empty()).collect();
+        if labels.len() < 2 {
+            return None;
+        }
+
+        let top_level = labels[labels.len() - 1];
+        let second_level = labels[labels.len() - 2];
+        let num_labels = match top_level.len() == 2 && SECOND_LEVEL_LABELS.contains(&second_level) && labels.len() > 2 {
+            true => 3,
+            false => 2
+        };
+        Some(labels[labels.len() - num_labels..].join("."))
+    }
+
+    impl PtrNames {
+        /// reads the names of the wanted addresses from a Rapid7 style rDNS dump (one JSON object with name and value per line)
+        /// or from lines of address and name, separated by a comma or whitespace. Later names of an address replace earlier ones.
+        pub fn load(path: &Path, wanted: &HashSet<IpAddr>) -> Result<PtrNames, YarrpError> {
+            info!("Loading PTR names for {} addresses", wanted.len());
+            let mut names = HashMap::new();
+
+            for line in read_lines(path)? {
+                let line = line?;
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                let entry = match line.starts_with('{') {
+                    true => PtrNames::parse_json_line(line),
+                    false => PtrNames::parse_text_line(line),
+                };
+
+                match entry {
+                    Some((address, name)) => {
+                        if wanted.contains(&address) {
+                            names.insert(address, name.trim_end_matches('.').to_string());
+                        }
+                    }
+                    None => warn!("Skipping PTR line {}", line),
+                }
+            }
+
+            info!("Loaded PTR names of {} addresses", names.len());
+            Ok(PtrNames { names })
+        }
+
+        fn parse_json_line(line: &str) -> Option<(IpAddr, String)> {
+            let json: Value = serde_json::from_str(line).ok()?;
+            if let Some(record_type) = json.get("type").and_then(|record_type| record_type.as_str()) {
+                if record_type != "ptr" {
+                    return None;
+                }
+            }
+            let address = IpAddr::from_str(json.get("name")?.as_str()?).ok()?;
+            let name = json.get("value")?.as_str()?;
+            Some((address, name.to_string()))
+        }
+
+        fn parse_text_line(line: &str) -> Option<(IpAddr, String)> {
+            let mut fields = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|field| !field.is_empty());
+            let address = IpAddr::from_str(fields.next()?).ok()?;
+            let name = fields.next()?;
+            Some((address, name.to_string()))
+        }
+
+        pub fn len(&self) -> usize {
+            self.names.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.names.is_empty()
+        }
+
+        pub fn get_hostname(&self, address: &str) -> Option<&str> {
+            let address = IpAddr::from_str(address).ok()?;
+            self.names.get(&address).map(|name| name.as_str())
+        }
+
+        /// registered domain shared by all named members, requires at least two named members.
+        /// Members without a PTR name are ignored, they neither share nor break the domain of the others.
+        pub fn shared_domain<'a, T, I>(&self, members: I) -> Option<String>
+            where T: 'a + Display, I: IntoIterator<Item = &'a T> {
+            let domains: Vec<Option<String>> = members.into_iter()
+                .filter_map(|member| self.get_hostname(&member.to_string()))
+                .map(registered_domain)
+                .collect();
+
+            if domains.len() < 2 {
+                return None;
+            }
+            let first = domains[0].clone()?;
+            match domains.iter().all(|domain| domain.as_ref() == Some(&first)) {
+                true => Some(first),
+                false => None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use crate::analytics::{PtrNames, registered_domain};
+    use crate::helpers::test_helper::TempFixture;
+
+    #[test]
+    fn hostnames_and_shared_domains() {
+        assert_eq!(registered_domain("ae-1.r01.vie01.example.net."), Some("example.net".to_string()));
+        assert_eq!(registered_domain("xe-0-0-1.core.example.co.uk"), Some("example.co.uk".to_string()));
+        assert_eq!(registered_domain("localhost"), None);
+
+        let path = TempFixture::file("ptr.txt", "{\"timestamp\":\"1622505600\",\"name\":\"192.0.2.1\",\"type\":\"ptr\",\"value\":\"ae-1.r01.vie01.example.net\"}\n\
+            {\"timestamp\":\"1622505600\",\"name\":\"192.0.2.9\",\"type\":\"ptr\",\"value\":\"unwanted.example.org\"}\n\
+            192.0.2.2,ae-2.r02.vie01.example.net.\n\
+            192.0.2.3 gw.example.org\n");
+
+        let wanted: HashSet<IpAddr> = ["192.0.2.1", "192.0.2.2", "192.0.2.3", "192.0.2.4"].iter()
+            .map(|address| IpAddr::from_str(address).unwrap()).collect();
+        let names = PtrNames::load(path.path(), &wanted).ok().unwrap();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names.get_hostname("192.0.2.2"), Some("ae-2.r02.vie01.example.net"));
+        assert_eq!(names.get_hostname("192.0.2.9"), None);
+
+        let members: Vec<String> = vec!["192.0.2.1".to_string(), "192.0.2.2".to_string(), "192.0.2.4".to_string()];
+        assert_eq!(names.shared_domain(&members), Some("example.net".to_string()), "The unnamed member .4 is ignored");
+        let members: Vec<String> = vec!["192.0.2.1".to_string(), "192.0.2.3".to_string()];
+        assert_eq!(names.shared_domain(&members), None);
+        let members: Vec<String> = vec!["192.0.2.1".to_string(), "192.0.2.4".to_string()];
+        assert_eq!(names.shared_domain(&members), None, "A single named member shares no domain");
+    }
+}
@@ -0,0 +1,222 @@
+pub mod rpki {
+    use std::collections::HashMap;
+    use std::fmt::{self, Display, Formatter};
+    use std::fs;
+    use std::path::Path;
+    use ipnet::IpNet;
+    use log::{error, info, warn};
+    use serde_json::Value;
+
+    use crate::structs::{ASNOrigin, YarrpError, parse_asn};
+
+    /// Route origin validation state of RFC 6811, NotFound is reported as unknown
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum RpkiState {
+        Valid,
+        Invalid,
+        NotFound,
+    }
+
+    pub const RPKI_STATES: [RpkiState; 3] = [RpkiState::Valid, RpkiState::Invalid, RpkiState::NotFound];
+
+    impl Display for RpkiState {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            let state = match self {
+                RpkiState::Valid => "valid",
+                RpkiState::Invalid => "invalid",
+                RpkiState::NotFound => "unknown",
+            };
+            write!(f, "{}", state)
+        }
+    }
+
+    /// Validated ROA payloads of a validator export, indexed by prefix
+    pub struct RoaTable {
+        roas: HashMap<IpNet, Vec<(u32, u8)>>,
+    }
+
+    impl RoaTable {
+        /// reads the CSV (ASN,IP Prefix,Max Length,...) or JSON ({"roas": [{"asn", "prefix", "maxLength"}]})
+        /// export of validators like routinator, the format is taken from the first character
+        pub fn load(path: &Path) -> Result<RoaTable, YarrpError> {
+            info!("Loading ROAs");
+            let content = fs::read_to_string(path)?;
+
+            let mut table = RoaTable { roas: HashMap::new() };
+            if content.trim_start().starts_with('{') {
+                table.load_json(&content)?;
+            } else {
+                table.load_csv(&content)?;
+            }
+
+            info!("Loaded ROAs for {} prefixes", table.roas.len());
+            Ok(table)
+        }
+
+        fn load_json(&mut self, content: &str) -> Result<(), YarrpError> {
+            let json: Value = match serde_json::from_str(content) {
+                Ok(json) => json,
+                Err(_) => {
+                    error!("Could not parse ROA json!");
+                    return Err(YarrpError::CouldNotParseError);
+                }
+            };
+
+            let roas = match json.get("roas").and_then(|roas| roas.as_array()) {
+                Some(roas) => roas,
+                None => {
+                    error!("ROA json has no roas list!");
+                    return Err(YarrpError::CouldNotParseError);
+                }
+            };
+
+            for roa in roas {
+                let asn = match roa.get("asn") {
+                    Some(Value::String(asn)) => parse_asn(asn),
+                    Some(Value::Number(asn)) => asn.as_u64().map(|asn| asn as u32),
+                    _ => None,
+                };
+                let prefix = roa.get("prefix").and_then(|prefix| prefix.as_str());
+                let max_length = roa.get("maxLength").and_then(|max_length| max_length.as_u64());
+
+                match (asn, prefix) {
+                    (Some(asn), Some(prefix)) => self.add(asn, prefix, max_length.map(|max_length| max_length as u8)),
+                    _ => warn!("Skipping ROA {}", roa),
+                }
+            }
+            Ok(())
+        }
+
+        fn load_csv(&mut self, content: &str) -> Result<(), YarrpError> {
+            let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(content.as_bytes());
+            let headers: Vec<String> = reader.headers()?.iter()
+                .map(|header| header.trim().to_lowercase().replace(' ', ""))
+                .collect();
+
+            let column = |names: &[&str]| headers.iter().position(|header| names.contains(&header.as_str()));
+            let (asn_column, prefix_column) = match (column(&["asn"]), column(&["ipprefix", "prefix"])) {
+                (Some(asn_column), Some(prefix_column)) => (asn_column, prefix_column),
+                _ => {
+                    error!("ROA csv needs ASN and IP Prefix columns!");
+                    return Err(YarrpError::CouldNotParseError);
+                }
+            };
+            let max_length_column = column(&["maxlength"]);
+
+            for record in reader.records() {
+                let record = record?;
+                let asn = record.get(asn_column).and_then(parse_asn);
+                let prefix = record.get(prefix_column);
+                let max_length = max_length_column.and_then(|max_length| record.get(max_length)).and_then(|max_length| max_length.trim().parse().ok());
+
+                match (asn, prefix) {
+                    (Some(asn), Some(prefix)) => self.add(asn, prefix, max_length),
+                    _ => warn!("Skipping ROA {:?}", record),
+                }
+            }
+            Ok(())
+        }
+
+        fn add(&mut self, asn: u32, prefix: &str, max_length: Option<u8>) {
+            let prefix: IpNet = match prefix.trim().parse() {
+                Ok(prefix) => prefix,
+                Err(_) => {
+                    warn!("Skipping ROA with prefix {}", prefix);
+                    return;
+                }
+            };
+            let prefix = prefix.trunc();
+            let max_length = max_length.unwrap_or(prefix.prefix_len());
+            self.roas.entry(prefix).or_default().push((asn, max_length));
+        }
+
+        pub fn len(&self) -> usize {
+            self.roas.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.roas.is_empty()
+        }
+
+        /// validates a route from one origin AS, AS 0 ROAs cover but never match
+        fn validate_asn(&self, network: &IpNet, asn: Option<u32>) -> RpkiState {
+            let mut covered = false;
+            let mut covering = Some(*network);
+            while let Some(prefix) = covering {
+                covering = prefix.supernet();
+                if let Some(roas) = self.roas.get(&prefix) {
+                    covered = true;
+                    for (roa_asn, max_length) in roas {
+                        if Some(*roa_asn) == asn && *roa_asn != 0 && network.prefix_len() <= *max_length {
+                            return RpkiState::Valid;
+                        }
+                    }
+                }
+            }
+
+            match covered {
+                true => RpkiState::Invalid,
+                false => RpkiState::NotFound
+            }
+        }
+
+        /// origin validation of an announced prefix. Routes ending in an AS set never match a ROA,
+        /// a MOAS prefix is valid if one of its origins is.
+        pub fn validate(&self, network: &IpNet, origin: &ASNOrigin) -> RpkiState {
+            let network = network.trunc();
+            match origin {
+                ASNOrigin::Unknown => RpkiState::NotFound,
+                ASNOrigin::Single(asn) => self.validate_asn(&network, parse_asn(asn)),
+                ASNOrigin::AsSet(_) => self.validate_asn(&network, None),
+                ASNOrigin::Moas(origins) => {
+                    let states: Vec<RpkiState> = origins.iter().map(|origin| self.validate(&network, origin)).collect();
+                    if states.contains(&RpkiState::Valid) {
+                        RpkiState::Valid
+                    } else if states.contains(&RpkiState::Invalid) {
+                        RpkiState::Invalid
+                    } else {
+                        RpkiState::NotFound
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use ipnet::IpNet;
+    use crate::analytics::{RoaTable, RpkiState};
+    use crate::structs::ASNOrigin;
+    use crate::helpers::test_helper::TempFixture;
+
+    fn validate(table: &RoaTable, network: &str, origin: &str) -> RpkiState {
+        table.validate(&IpNet::from_str(network).unwrap(), &ASNOrigin::parse(origin))
+    }
+
+    #[test]
+    fn origin_validation() {
+        let csv_roas = TempFixture::file("roas.csv", "ASN,IP Prefix,Max Length,Trust Anchor\n\
+            AS64496,192.0.2.0/24,24,ripe\n\
+            AS64497,198.51.100.0/22,24,arin\n\
+            AS0,203.0.113.0/24,24,apnic\n");
+        let json_roas = TempFixture::file("roas.json", r#"{"roas": [
+            {"asn": "AS64496", "prefix": "192.0.2.0/24", "maxLength": 24, "ta": "ripe"},
+            {"asn": 64497, "prefix": "198.51.100.0/22", "maxLength": 24, "ta": "arin"},
+            {"asn": "AS0", "prefix": "203.0.113.0/24", "maxLength": 24, "ta": "apnic"}]}"#);
+
+        for roas in &[&csv_roas, &json_roas] {
+            let table = RoaTable::load(roas.path()).ok().unwrap();
+            assert_eq!(table.len(), 3);
+            assert_eq!(validate(&table, "192.0.2.0/24", "64496"), RpkiState::Valid);
+            assert_eq!(validate(&table, "192.0.2.0/24", "64499"), RpkiState::Invalid);
+            assert_eq!(validate(&table, "198.51.101.0/24", "64497"), RpkiState::Valid);
+            assert_eq!(validate(&table, "198.51.101.0/25", "64497"), RpkiState::Invalid, "Longer than max length");
+            assert_eq!(validate(&table, "203.0.113.0/24", "0"), RpkiState::Invalid, "AS0 ROAs never match");
+            assert_eq!(validate(&table, "10.0.0.0/8", "64496"), RpkiState::NotFound);
+            assert_eq!(validate(&table, "192.0.2.0/24", "64496,64497"), RpkiState::Invalid, "AS sets never match");
+            assert_eq!(validate(&table, "192.0.2.0/24", "64499_64496"), RpkiState::Valid);
+        }
+    }
+}
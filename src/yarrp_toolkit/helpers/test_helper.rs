@@ -1,7 +1,12 @@
 use log::{trace, LevelFilter};
 use std::net::{Ipv6Addr, Ipv4Addr};
 use crate::structs::YarrpLine;
+use crate::analytics::{IDENTIFIERS, ROUTERS, LOOPS, LOOPS_CSV};
 use std::str::FromStr;
+use std::env::temp_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub const DESTINATION_STRING_V6: &str = "2001:db8::1000";
 pub const DESTINATION_STRING_V4: &str = "192.0.2.254";
@@ -9,6 +14,154 @@ pub const EMPTY_STRING: String = String::new();
 pub const MIN_TTL: u8 = 3;
 pub const MAX_TTL: u8 = 18;
 
+static FIXTURE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A file or directory in the temp directory, unique per process and fixture,
+/// removed when dropped, also if the test panics before its end
+pub struct TempFixture {
+    path: PathBuf,
+}
+
+impl TempFixture {
+    /// reserves a unique path ending with name, nothing is created
+    pub fn new(name: &str) -> TempFixture {
+        let number = FIXTURE_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let path = temp_dir().join(format!("yarrp_toolkit_{}_{}_{}", std::process::id(), number, name));
+        let fixture = TempFixture { path };
+        fixture.remove();
+        fixture
+    }
+
+    /// a file with the given content
+    pub fn file<C: AsRef<[u8]>>(name: &str, content: C) -> TempFixture {
+        let fixture = TempFixture::new(name);
+        fs::write(&fixture.path, content).unwrap();
+        fixture
+    }
+
+    /// an empty directory, e.g. for a fixture project
+    pub fn dir(name: &str) -> TempFixture {
+        let fixture = TempFixture::new(name);
+        fs::create_dir_all(&fixture.path).unwrap();
+        fixture
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn to_str(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, name: P) -> PathBuf {
+        self.path.join(name)
+    }
+
+    /// writes a file below a directory fixture, creating missing parent directories
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, name: P, content: C) -> PathBuf {
+        let path = self.path.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn remove(&self) {
+        if self.path.is_dir() {
+            let _ = fs::remove_dir_all(&self.path);
+        } else {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Drop for TempFixture {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+pub const LOOPS_CSV_HEADER: &str = "loop_id,preceding_router,shadowed_nets,loop_len,loop_start_ttl,loop_stop_ttl,preceding_router_ttl\n";
+
+/// Builds a fixture project of identifiers.id, routers.id, loops/*.dest and loops.csv,
+/// further project files are added to the built TempFixture with write
+pub struct ProjectFixture {
+    name: String,
+    loops: Vec<(String, Vec<String>)>,
+    destinations: Vec<(String, Vec<String>)>,
+    loop_info: Vec<String>,
+}
+
+impl ProjectFixture {
+    /// loops aa and bb share router 192.0.2.9, aa shadows two destinations behind the preceding router 192.0.2.7
+    pub fn new(name: &str) -> ProjectFixture {
+        ProjectFixture::empty(name)
+            .add_loop("aa", &["192.0.2.8", "192.0.2.9"])
+            .add_loop("bb", &["192.0.2.9", "192.0.2.10"])
+            .destinations("aa", &["198.51.100.1", "198.51.100.2"])
+            .loop_info("aa,192.0.2.7,2,2,8,9,7")
+    }
+
+    /// a project without loops, loops.csv only holds its header row
+    pub fn empty(name: &str) -> ProjectFixture {
+        ProjectFixture {
+            name: name.to_string(),
+            loops: Vec::new(),
+            destinations: Vec::new(),
+            loop_info: Vec::new(),
+        }
+    }
+
+    pub fn add_loop(mut self, loop_id: &str, members: &[&str]) -> ProjectFixture {
+        self.loops.push((loop_id.to_string(), members.iter().map(|member| member.to_string()).collect()));
+        self
+    }
+
+    /// replaces the shadowed destinations of a loop
+    pub fn destinations<S: AsRef<str>>(mut self, loop_id: &str, destinations: &[S]) -> ProjectFixture {
+        self.destinations.retain(|(existing, _)| existing != loop_id);
+        self.destinations.push((loop_id.to_string(), destinations.iter().map(|destination| destination.as_ref().to_string()).collect()));
+        self
+    }
+
+    /// adds a loops.csv row without line break
+    pub fn loop_info(mut self, row: &str) -> ProjectFixture {
+        self.loop_info.push(row.to_string());
+        self
+    }
+
+    /// routers.id lists the routers in the order of their first loop
+    pub fn build(self) -> TempFixture {
+        let fixture = TempFixture::dir(&self.name);
+
+        let mut identifiers = String::new();
+        let mut routers: Vec<(&String, Vec<&String>)> = Vec::new();
+        for (loop_id, members) in &self.loops {
+            identifiers.push_str(&format!("{}={};\n", loop_id, members.join(";")));
+            for member in members {
+                if let Some((_, loop_ids)) = routers.iter_mut().find(|(router, _)| *router == member) {
+                    loop_ids.push(loop_id);
+                } else {
+                    routers.push((member, vec![loop_id]));
+                }
+            }
+        }
+        fixture.write(IDENTIFIERS, identifiers);
+        fixture.write(ROUTERS, routers.iter()
+            .map(|(router, loop_ids)| format!("{}={};\n", router, loop_ids.iter().map(|loop_id| format!("{};", loop_id)).collect::<String>()))
+            .collect::<String>());
+
+        for (loop_id, destinations) in &self.destinations {
+            fixture.write(format!("{}/{}.dest", LOOPS, loop_id), destinations.iter().map(|destination| format!("{}\n", destination)).collect::<String>());
+        }
+
+        fixture.write(LOOPS_CSV, self.loop_info.iter().fold(LOOPS_CSV_HEADER.to_string(), |rows, row| format!("{}{}\n", rows, row)));
+        fixture
+    }
+}
+
 pub fn init() {
     let _ = env_logger::builder().is_test(true).filter_level(LevelFilter::Trace).try_init();
 }
@@ -27,7 +27,9 @@ use modes::{
     P50TargetMode,
     P50Analysis
 };
-use crate::modes::{ModeTrait, ModeEnum, PostLoopStatsMode, MergeIdMode, ASNMode};
+use crate::modes::{ModeTrait, ModeEnum, PostLoopStatsMode, MergeIdMode, ASNMode, CheckMode, QueryMode, ConvertMode, EvidenceMode, WatchMode, ASNDatabaseMode, CoverageMode, ReportMode};
+use crate::structs::YarrpError;
+use crate::traits::IpAddrExt;
 use clap::ArgMatches;
 use std::fs;
 use std::net::{Ipv6Addr, Ipv4Addr};
@@ -40,6 +42,29 @@ pub fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
     Ok(io::BufReader::new(file).lines())
 }
 
+/// Feeds all lines of a yarrp output file into the mode and rotates it afterwards.
+/// on_line is called after every line, e.g. to advance a progress bar.
+pub fn read_input_file<F: FnMut()>(mode_item: &mut dyn ModeTrait, file_path: &str, file_number: u64, mut on_line: F) -> Result<(), YarrpError> {
+    let lines = read_lines(file_path)?;
+    for line in lines {
+        if let Ok(str_line) = line {
+            if str_line.starts_with('#') {
+                // remove leading '#' and resulting whitespaces
+                let str_line = str_line[1..].trim();
+                mode_item.parse_comment_line(&str_line);
+            } else {
+                mode_item.parse_string_line(&str_line);
+            }
+            on_line();
+        } else {
+            error!("Could not read line?");
+        }
+    }
+
+    mode_item.do_file_rotate(file_number, file_path);
+    Ok(())
+}
+
 pub fn create_dir_if_not_existing(path: &str) -> bool {
     let output_path = Path::new(&path);
     if output_path.exists() && !output_path.is_dir() {
@@ -75,11 +100,19 @@ pub fn get_correct_mode_v4(subcommand: ModeEnum, matches: ArgMatches) -> Box<dyn
         ModeEnum::Merge => Box::new(MergeMode::new(matches)),
         ModeEnum::Imperiled => Box::new(ImperiledMode::new(matches)),
         ModeEnum::PostLoopStats => Box::new(PostLoopStatsMode::<Ipv4Addr>::new(matches)),
-        ModeEnum::MergeId => Box::new(MergeIdMode::new(matches)),
+        ModeEnum::MergeId => Box::new(MergeIdMode::<Ipv4Addr>::new(matches)),
         ModeEnum::Scatter => Box::new(ScatterMode::<Ipv4Addr>::new(matches)),
         ModeEnum::P50Target => Box::new(P50TargetMode::<Ipv4Addr>::new(matches)),
         ModeEnum::P50Analysis => Box::new(P50Analysis::<Ipv4Addr>::new(matches)),
-        ModeEnum::ASN => Box::new(ASNMode::new(matches, true))
+        ModeEnum::ASN => Box::new(ASNMode::new(matches, true)),
+        ModeEnum::Check => Box::new(CheckMode::<Ipv4Addr>::new(matches)),
+        ModeEnum::Query => Box::new(QueryMode::<Ipv4Addr>::new(matches)),
+        ModeEnum::Convert => Box::new(ConvertMode::<Ipv4Addr>::new(matches)),
+        ModeEnum::Evidence => Box::new(EvidenceMode::<Ipv4Addr>::new(matches)),
+        ModeEnum::Watch => Box::new(WatchMode::<Ipv4Addr>::new(matches)),
+        ModeEnum::ASNDatabase => Box::new(ASNDatabaseMode::new(matches, &Ipv4Addr::root_net())),
+        ModeEnum::Coverage => Box::new(CoverageMode::<Ipv4Addr>::new(matches)),
+        ModeEnum::Report => Box::new(ReportMode::<Ipv4Addr>::new(matches))
     }
 }
 
@@ -95,10 +128,18 @@ pub fn get_correct_mode_v6(subcommand: ModeEnum, matches: ArgMatches) -> Box<dyn
         ModeEnum::Merge => Box::new(MergeMode::new(matches)),
         ModeEnum::Imperiled => Box::new(ImperiledMode::new(matches)),
         ModeEnum::PostLoopStats => Box::new(PostLoopStatsMode::<Ipv6Addr>::new(matches)),
-        ModeEnum::MergeId => Box::new(MergeIdMode::new(matches)),
+        ModeEnum::MergeId => Box::new(MergeIdMode::<Ipv6Addr>::new(matches)),
         ModeEnum::Scatter => Box::new(ScatterMode::<Ipv6Addr>::new(matches)),
         ModeEnum::P50Target => Box::new(P50TargetMode::<Ipv6Addr>::new(matches)),
         ModeEnum::P50Analysis => Box::new(P50Analysis::<Ipv6Addr>::new(matches)),
-        ModeEnum::ASN => Box::new(ASNMode::new(matches, false))
+        ModeEnum::ASN => Box::new(ASNMode::new(matches, false)),
+        ModeEnum::Check => Box::new(CheckMode::<Ipv6Addr>::new(matches)),
+        ModeEnum::Query => Box::new(QueryMode::<Ipv6Addr>::new(matches)),
+        ModeEnum::Convert => Box::new(ConvertMode::<Ipv6Addr>::new(matches)),
+        ModeEnum::Evidence => Box::new(EvidenceMode::<Ipv6Addr>::new(matches)),
+        ModeEnum::Watch => Box::new(WatchMode::<Ipv6Addr>::new(matches)),
+        ModeEnum::ASNDatabase => Box::new(ASNDatabaseMode::new(matches, &Ipv6Addr::root_net())),
+        ModeEnum::Coverage => Box::new(CoverageMode::<Ipv6Addr>::new(matches)),
+        ModeEnum::Report => Box::new(ReportMode::<Ipv6Addr>::new(matches))
     }
 }
@@ -0,0 +1,101 @@
+pub mod asn_db_mode {
+    use clap::ArgMatches;
+    use std::process::exit;
+    use std::path::Path;
+    use log::{error, info};
+
+    use crate::modes::{ModeEnum, ModeTrait, load_output_policy};
+    use crate::analytics::ASNAttribution;
+
+    /// Compiles a routeviews file or RIB dump into the binary database used by --asn_db
+    pub struct ASNDatabaseMode {
+        mode: ModeEnum,
+        source_path: String,
+        is_rib: bool,
+        output_path: String,
+        root_net: String,
+        written: bool,
+    }
+
+    impl ASNDatabaseMode {
+        pub fn new(matches: ArgMatches, root_net: &str) -> ASNDatabaseMode {
+            let mode = ModeEnum::ASNDatabase;
+            let mode_string = mode.to_string().to_lowercase();
+            let sub_matches = matches.subcommand_matches(mode_string).unwrap();
+
+            let is_rib = sub_matches.is_present("rib");
+            let source_path;
+            if let Some(path) = sub_matches.value_of("rib").or_else(|| sub_matches.value_of("routeviews")) {
+                source_path = path.to_string();
+            } else {
+                error!("Could not parse routeviews or rib!");
+                exit(1);
+            }
+
+            let output_path;
+            if let Some(path) = sub_matches.value_of("output") {
+                output_path = path.to_string();
+            } else {
+                error!("Could not parse output!");
+                exit(1);
+            }
+
+            // a database is always written as a whole
+            if load_output_policy(&matches, false).check(Path::new(&output_path)).is_err() {
+                exit(1);
+            }
+
+            ASNDatabaseMode {
+                mode,
+                source_path,
+                is_rib,
+                output_path,
+                root_net: root_net.to_string(),
+                written: false,
+            }
+        }
+    }
+
+    impl ModeTrait for ASNDatabaseMode {
+        fn get_mode(&self) -> ModeEnum {
+            self.mode
+        }
+
+        fn no_input_capable(&self) -> bool {
+            true
+        }
+
+        fn parse_comment_line(&mut self, _input: &str) {}
+
+        fn parse_string_line(&mut self, _input: &str) {}
+
+        fn do_file_rotate(&mut self, _file_number: u64, _file_name: &str) {}
+
+        fn do_calculations(&mut self) {
+            let mut asn_attribution = ASNAttribution::new(&self.root_net);
+            let loaded = match self.is_rib {
+                true => asn_attribution.load_mrt_rib(&self.source_path),
+                false => asn_attribution.load_routeviews_bgp(&self.source_path)
+            };
+
+            if loaded.is_err() {
+                error!("Could not load {}!", self.source_path);
+                exit(5);
+            }
+
+            if asn_attribution.write_asn_database(&self.output_path, &self.source_path).is_err() {
+                error!("Could not write ASN database!");
+                exit(5);
+            }
+            self.written = true;
+        }
+
+        fn print_output(&self) {
+            if self.written {
+                info!("Compiled {} into {}", self.source_path, self.output_path);
+            }
+        }
+
+        fn close(&mut self) {}
+    }
+}
@@ -4,18 +4,23 @@ pub mod asn_mode {
     use std::process::exit;
     use log::{error, warn, info};
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-    use std::collections::{HashMap};
+    use std::collections::{HashMap, HashSet};
+    use std::path::Path;
 
     use crate::traits::IpAddrExt;
-    use crate::modes::{ModeEnum, ModeTrait};
-    use crate::structs::{YarrpError, ASNIPAttribution};
-    use crate::analytics::{ASNAttribution};
+    use crate::modes::{ModeEnum, ModeTrait, load_output_policy, load_asn_attribution};
+    use crate::structs::{YarrpError, ASNIPAttribution, ORGIPAttribution, stable_sorted, atomic_csv_writer, commit_csv};
+    use crate::analytics::{ASNAttribution, ASNCoverage};
 
     pub struct ASNMode {
         mode: ModeEnum,
         output_path: String,
         asn_attribution: ASNAttribution,
-        asn_dict: HashMap<String, u64>
+        asn_dict: HashMap<String, u64>,
+        asn_coverage: ASNCoverage,
+        all_asn: bool,
+        org_output_path: Option<String>,
+        org_dict: HashMap<String, u64>
     }
 
     impl ASNMode {
@@ -24,14 +29,6 @@ pub mod asn_mode {
             let mode_string = mode.to_string().to_lowercase();
             let sub_matches = matches.subcommand_matches(mode_string).unwrap();
 
-            let routeviews_path;
-            if let Some(path) = sub_matches.value_of("routeviews") {
-                routeviews_path = path.to_string();
-            } else {
-                error!("Could not parse routeviews!");
-                exit(1);
-            }
-
             let output_path;
             if let Some(path) = sub_matches.value_of("output") {
                 output_path = path.to_string();
@@ -40,36 +37,89 @@ pub mod asn_mode {
                 exit(1);
             }
 
+            // csv output can not be appended to
+            let output_policy = load_output_policy(&matches, false);
+            if output_policy.check(Path::new(&output_path)).is_err() {
+                exit(1);
+            }
+
+            let org_output_path = sub_matches.value_of("org_output").map(|path| path.to_string());
+            if let Some(path) = &org_output_path {
+                if output_policy.check(Path::new(path)).is_err() {
+                    exit(1);
+                }
+            }
+
             let net_str = match v4 {
                 true => Ipv4Addr::root_net(),
                 false => Ipv6Addr::root_net()
             };
-            let mut asn_attribution = ASNAttribution::new(&net_str);
-            if let Err(_) = asn_attribution.load_routeviews_bgp(&routeviews_path) {
+            let asn_attribution;
+            if let Ok(value) = load_asn_attribution(sub_matches, &net_str) {
+                asn_attribution = value;
+            } else {
                 error!("Could not load asn file!");
                 exit(5);
             }
 
+            let asn_coverage = ASNCoverage::new(&asn_attribution);
+
             ASNMode {
                 mode,
                 output_path,
                 asn_attribution,
-                asn_dict: HashMap::new()
+                asn_dict: HashMap::new(),
+                asn_coverage,
+                all_asn: sub_matches.is_present("all_asn"),
+                org_output_path,
+                org_dict: HashMap::new()
             }
         }
 
         fn write_asn_csv(&self) -> Result<(), YarrpError> {
-            let mut writer = csv::Writer::from_path(&self.output_path)?;
+            let mut writer = atomic_csv_writer(Path::new(&self.output_path))?;
+
+            // announcing ASNs without input addresses only on request
+            let mut asns: HashSet<&String> = self.asn_dict.keys().collect();
+            if self.all_asn {
+                asns.extend(self.asn_coverage.announcing_asns());
+            }
+
+            for asn in stable_sorted(asns) {
+                let output_obj = ASNIPAttribution{
+                    asn: asn.clone(),
+                    num_ips: *self.asn_dict.get(asn).unwrap_or(&0),
+                    announced_prefixes: self.asn_coverage.get_announced_prefixes(asn),
+                    announced_blocks: self.asn_coverage.get_announced_blocks(asn),
+                    touched_prefixes: self.asn_coverage.get_touched_prefixes(asn),
+                    covered_blocks: self.asn_coverage.get_covered_blocks(asn),
+                    block_coverage: self.asn_coverage.get_block_coverage(asn),
+                };
+                if let Err(_) = writer.serialize(&output_obj) {
+                    error!("Could not serialize output object!");
+                    exit(5);
+                }
+            }
+
+            commit_csv(writer)
+        }
+
+        fn write_org_csv(&self, output_path: &str) -> Result<(), YarrpError> {
+            let as2org = match self.asn_attribution.get_as2org() {
+                Some(as2org) => as2org,
+                None => return Err(YarrpError::NotFoundError)
+            };
+            let mut writer = atomic_csv_writer(Path::new(output_path))?;
 
-            for (asn, numbers) in &self.asn_dict {
-                let output_obj = ASNIPAttribution{ asn: asn.clone(), num_ips: numbers.clone() };
+            for org in stable_sorted(self.org_dict.keys()) {
+                let output_obj = ORGIPAttribution{ org: org.clone(), name: as2org.get_org_name(org).to_string(), num_ips: self.org_dict[org] };
                 if let Err(_) = writer.serialize(&output_obj) {
                     error!("Could not serialize output object!");
                     exit(5);
                 }
             }
 
-            Ok(())
+            commit_csv(writer)
         }
     }
 
@@ -89,6 +139,7 @@ pub mod asn_mode {
         fn parse_string_line(&mut self, input: &str) {
             // parse each line and assign ASN
             if let Ok(addr) = IpAddr::from_str(input) {
+                self.asn_coverage.add_address(&self.asn_attribution, &addr);
                 if let Some(asn_vec) = self.asn_attribution.get_asn_for_ip(&addr) {
                     for asn in asn_vec {
                         if ! self.asn_dict.contains_key(&asn){
@@ -102,6 +153,14 @@ pub mod asn_mode {
                         }
                     }
                 }
+
+                // ASNs of the same organisation count the address once
+                if let Some(as2org) = self.asn_attribution.get_as2org() {
+                    let org_origin = as2org.org_origin(&self.asn_attribution.get_origin_for_ip(&addr));
+                    for org in org_origin.asns() {
+                        *self.org_dict.entry(org).or_insert(0) += 1;
+                    }
+                }
             } else {
                 warn!("Could not parse IP address from str {}!", input);
             }
@@ -114,10 +173,19 @@ pub mod asn_mode {
             if let Err(_) = self.write_asn_csv() {
                 error!("Could not write asn csv file!");
             }
+
+            if let Some(path) = &self.org_output_path {
+                if let Err(_) = self.write_org_csv(path) {
+                    error!("Could not write org csv file!");
+                }
+            }
         }
 
         fn print_output(&self) {
             info!("Loaded {} ASN!", self.asn_dict.len());
+            if self.org_output_path.is_some() {
+                info!("Loaded {} organisations!", self.org_dict.len());
+            }
         }
 
         fn close(&mut self) {}
@@ -0,0 +1,643 @@
+pub mod check_mode {
+    use clap::ArgMatches;
+    use std::process::exit;
+    use log::{error, info, warn};
+    use std::collections::{HashMap, HashSet};
+    use std::fmt::Display;
+    use std::hash::Hash;
+    use std::str::FromStr;
+    use std::path::PathBuf;
+    use std::marker::PhantomData;
+
+    use crate::modes::{ModeTrait, ModeEnum, load_path_param};
+    use crate::structs::{YarrpError, MapSetString, SimpleLoopOutput, ShadowedPreceding, stable_key, atomic_csv_writer, commit_csv};
+    use crate::traits::IpAddrExt;
+    use crate::analytics::{LoopStorage, LoopStatistics};
+    use crate::analytics::{ROUTERS, IDENTIFIERS, IMPERILED, LOOPS, LOOPS_CSV, STATS, SHADOWED_PRECEDING_INFO};
+
+    /// Cross validates the files of a project.
+    /// identifiers.id, loops/*.dest, imperiled/*.imp and shadowed_preceding.csv are treated as primary,
+    /// routers.id, loops.csv and the loop counters in stats.csv are derived from them and can be repaired
+    /// as long as no primary file is missing or unreadable.
+    pub struct CheckMode<T> {
+        pub mode: ModeEnum,
+        project_path: PathBuf,
+        repair: bool,
+        loop_members: MapSetString,
+        router_loops: MapSetString,
+        destination_counts: HashMap<String, u64>,
+        imperiled_destinations: HashSet<String>,
+        loop_info: HashMap<(String, String), SimpleLoopOutput>,
+        shadowed_counts: HashMap<(String, String), (u8, u64)>,
+        issues: Vec<String>,
+        unusable_primary: Vec<String>,
+        repaired: Vec<String>,
+        ip_type: PhantomData<T>,
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> CheckMode<T> {
+        pub fn new(matches: ArgMatches) -> CheckMode<T> {
+            let mode = ModeEnum::Check;
+            let mode_string = mode.to_string().to_lowercase();
+            let sub_matches = matches.subcommand_matches(mode_string).unwrap();
+
+            let project_path = load_path_param(sub_matches, "project_path");
+            if !project_path.exists() || !project_path.is_dir() {
+                error!("Project path does not exist or is not a directory!");
+                exit(1);
+            }
+
+            let repair = sub_matches.occurrences_of("repair") > 0;
+            CheckMode::from_project(project_path, repair)
+        }
+
+        pub fn from_project(project_path: PathBuf, repair: bool) -> CheckMode<T> {
+            CheckMode {
+                mode: ModeEnum::Check,
+                project_path,
+                repair,
+                loop_members: HashMap::new(),
+                router_loops: HashMap::new(),
+                destination_counts: HashMap::new(),
+                imperiled_destinations: HashSet::new(),
+                loop_info: HashMap::new(),
+                shadowed_counts: HashMap::new(),
+                issues: Vec::new(),
+                unusable_primary: Vec::new(),
+                repaired: Vec::new(),
+                ip_type: PhantomData,
+            }
+        }
+
+        pub fn issues(&self) -> &Vec<String> {
+            &self.issues
+        }
+
+        pub fn repaired(&self) -> &Vec<String> {
+            &self.repaired
+        }
+
+        fn add_issue(&mut self, issue: String) {
+            warn!("{}", issue);
+            self.issues.push(issue);
+        }
+
+        /// a missing or unreadable primary file is an issue that cannot be repaired
+        fn add_unusable_primary(&mut self, file_name: String, reason: &str) {
+            self.add_issue(format!("{} is {}", file_name, reason));
+            self.unusable_primary.push(file_name);
+        }
+
+        fn check_identifiers(&mut self) -> Result<(), YarrpError> {
+            info!("Checking {}", IDENTIFIERS);
+            let path = self.project_path.join(IDENTIFIERS);
+            if !path.exists() {
+                self.add_unusable_primary(IDENTIFIERS.to_string(), "missing");
+                return Ok(());
+            }
+
+            // lines that cannot be read are skipped by read_id_file
+            if std::fs::read_to_string(&path).is_err() {
+                self.add_unusable_primary(IDENTIFIERS.to_string(), "unreadable");
+                return Ok(());
+            }
+
+            self.loop_members = LoopStorage::<T>::read_id_file(&path)?;
+
+            let mut issues = Vec::new();
+            for (loop_id, members) in &self.loop_members {
+                let mut parsed_members = HashSet::new();
+                for member in members {
+                    if let Ok(router) = T::from_str(member) {
+                        parsed_members.insert(router);
+                    } else {
+                        issues.push(format!("Loop {} in {} has unparsable member {}", loop_id, IDENTIFIERS, member));
+                    }
+                }
+
+                if parsed_members.len() != members.len() {
+                    continue;
+                }
+
+                if let Ok(expected_id) = LoopStorage::create_loop_identifier(&parsed_members) {
+                    if &expected_id != loop_id {
+                        issues.push(format!("Loop {} in {} does not match the hash of its members ({})", loop_id, IDENTIFIERS, expected_id));
+                    }
+                } else {
+                    issues.push(format!("Loop {} in {} has no members", loop_id, IDENTIFIERS));
+                }
+            }
+
+            issues.sort();
+            for issue in issues {
+                self.add_issue(issue);
+            }
+            Ok(())
+        }
+
+        fn check_routers(&mut self) -> Result<(), YarrpError> {
+            info!("Checking {}", ROUTERS);
+            let path = self.project_path.join(ROUTERS);
+            if !path.exists() {
+                self.add_issue(format!("{} is missing", ROUTERS));
+                return Ok(());
+            }
+
+            self.router_loops = LoopStorage::<T>::read_id_file(&path)?;
+            let expected_routers = CheckMode::<T>::build_router_loops(&self.loop_members);
+
+            let mut issues = Vec::new();
+            for (router, loops) in &self.router_loops {
+                for loop_id in loops {
+                    if !self.loop_members.contains_key(loop_id) {
+                        issues.push(format!("Router {} in {} references unknown loop {}", router, ROUTERS, loop_id));
+                    } else if !expected_routers.get(router).is_some_and(|set| set.contains(loop_id)) {
+                        issues.push(format!("Router {} in {} references loop {} it is not a member of", router, ROUTERS, loop_id));
+                    }
+                }
+            }
+
+            for (router, loops) in &expected_routers {
+                for loop_id in loops {
+                    if !self.router_loops.get(router).is_some_and(|set| set.contains(loop_id)) {
+                        issues.push(format!("Router {} of loop {} is missing in {}", router, loop_id, ROUTERS));
+                    }
+                }
+            }
+
+            issues.sort();
+            for issue in issues {
+                self.add_issue(issue);
+            }
+            Ok(())
+        }
+
+        fn check_loop_destinations(&mut self) -> Result<(), YarrpError> {
+            info!("Checking {} directory", LOOPS);
+            let loops_dir = self.project_path.join(LOOPS);
+            let mut issues = Vec::new();
+
+            for (file_stem, path) in CheckMode::<T>::list_details_files(&loops_dir, "dest")? {
+                if !self.loop_members.contains_key(&file_stem) {
+                    issues.push(format!("{}/{}.dest has no entry in {}", LOOPS, file_stem, IDENTIFIERS));
+                }
+
+                let (count, unparsable) = CheckMode::<T>::count_details_file(&path)?;
+                if unparsable > 0 {
+                    issues.push(format!("{}/{}.dest contains {} unparsable lines", LOOPS, file_stem, unparsable));
+                }
+                self.destination_counts.insert(file_stem, count);
+            }
+
+            let mut missing = Vec::new();
+            for loop_id in self.loop_members.keys() {
+                if !self.destination_counts.contains_key(loop_id) {
+                    issues.push(format!("Loop {} in {} has no {}/{}.dest file", loop_id, IDENTIFIERS, LOOPS, loop_id));
+                    missing.push(format!("{}/{}.dest", LOOPS, loop_id));
+                }
+            }
+
+            issues.sort();
+            for issue in issues {
+                self.add_issue(issue);
+            }
+            missing.sort();
+            self.unusable_primary.extend(missing);
+            Ok(())
+        }
+
+        fn check_imperiled(&mut self) -> Result<(), YarrpError> {
+            info!("Checking {} directory", IMPERILED);
+            let imperiled_dir = self.project_path.join(IMPERILED);
+            let mut issues = Vec::new();
+
+            for (file_stem, path) in CheckMode::<T>::list_details_files(&imperiled_dir, "imp")? {
+                if T::from_str(&file_stem).is_err() {
+                    issues.push(format!("{}/{}.imp is not named after a router address", IMPERILED, file_stem));
+                }
+
+                for line in LoopStorage::<T>::read_details_file_as_string(&path)? {
+                    if T::from_str(&line).is_err() {
+                        issues.push(format!("{}/{}.imp contains unparsable line {}", IMPERILED, file_stem, line));
+                    } else {
+                        self.imperiled_destinations.insert(line);
+                    }
+                }
+            }
+
+            issues.sort();
+            for issue in issues {
+                self.add_issue(issue);
+            }
+            Ok(())
+        }
+
+        fn check_shadowed_preceding(&mut self) -> Result<(), YarrpError> {
+            info!("Checking {}", SHADOWED_PRECEDING_INFO);
+            let path = self.project_path.join(SHADOWED_PRECEDING_INFO);
+            if !path.exists() {
+                self.add_unusable_primary(SHADOWED_PRECEDING_INFO.to_string(), "missing");
+                return Ok(());
+            }
+
+            let mut issues = Vec::new();
+            let mut per_loop: HashMap<String, u64> = HashMap::new();
+            let mut reader = csv::Reader::from_path(path)?;
+
+            for result in reader.deserialize() {
+                let record: ShadowedPreceding;
+                if let Ok(value) = result {
+                    record = value;
+                } else {
+                    issues.push(format!("{} contains an unparsable row", SHADOWED_PRECEDING_INFO));
+                    continue;
+                }
+
+                if !self.loop_members.contains_key(&record.loop_id) {
+                    issues.push(format!("{} references unknown loop {}", SHADOWED_PRECEDING_INFO, record.loop_id));
+                }
+
+                *per_loop.entry(record.loop_id.clone()).or_insert(0) += 1;
+                let key = (record.loop_id, record.preceding_router);
+                let entry = self.shadowed_counts.entry(key).or_insert((record.preceding_ttl, 0));
+                entry.1 += 1;
+            }
+
+            for (loop_id, count) in &self.destination_counts {
+                let shadowed = per_loop.get(loop_id).cloned().unwrap_or(0);
+                if shadowed != *count {
+                    issues.push(format!("Loop {} has {} destinations in {} but {} rows in {}", loop_id, count, LOOPS, shadowed, SHADOWED_PRECEDING_INFO));
+                }
+            }
+
+            issues.sort();
+            for issue in issues {
+                self.add_issue(issue);
+            }
+            Ok(())
+        }
+
+        fn check_loop_info(&mut self) -> Result<(), YarrpError> {
+            info!("Checking {}", LOOPS_CSV);
+            let path = self.project_path.join(LOOPS_CSV);
+            if !path.exists() {
+                self.add_issue(format!("{} is missing", LOOPS_CSV));
+                return Ok(());
+            }
+
+            match LoopStorage::<T>::read_loop_info(&path) {
+                Ok(info) => self.loop_info = info,
+                Err(_) => {
+                    self.add_issue(format!("{} could not be parsed as loop information", LOOPS_CSV));
+                    return Ok(());
+                }
+            }
+
+            let mut issues = Vec::new();
+            let mut per_loop: HashMap<String, u64> = HashMap::new();
+
+            for (key, info) in &self.loop_info {
+                if !self.loop_members.contains_key(&key.0) {
+                    issues.push(format!("{} references unknown loop {}", LOOPS_CSV, key.0));
+                }
+
+                *per_loop.entry(key.0.clone()).or_insert(0) += info.shadowed_nets;
+
+                let shadowed = self.shadowed_counts.get(key).map_or(0, |value| value.1);
+                if shadowed != info.shadowed_nets {
+                    issues.push(format!("Loop {} with preceding router {} counts {} shadowed nets in {} but has {} rows in {}",
+                                        key.0, key.1, info.shadowed_nets, LOOPS_CSV, shadowed, SHADOWED_PRECEDING_INFO));
+                }
+            }
+
+            for (loop_id, count) in &self.destination_counts {
+                let shadowed = per_loop.get(loop_id).cloned().unwrap_or(0);
+                if shadowed != *count {
+                    issues.push(format!("Loop {} has {} destinations in {} but counts {} in {}", loop_id, count, LOOPS, shadowed, LOOPS_CSV));
+                }
+            }
+
+            issues.sort();
+            for issue in issues {
+                self.add_issue(issue);
+            }
+            Ok(())
+        }
+
+        fn check_stats(&mut self) -> Result<(), YarrpError> {
+            info!("Checking {}", STATS);
+            let path = self.project_path.join(STATS);
+            let only_full_routes;
+            if let Some(value) = LoopStatistics::read_only_full_routes(&path)? {
+                only_full_routes = value;
+            } else {
+                self.add_issue(format!("{} is missing or has no only_full_routes entry", STATS));
+                return Ok(());
+            }
+
+            let stats = LoopStatistics::new(only_full_routes, path);
+            let total_destinations: u64 = self.destination_counts.values().sum();
+            let loop_lengths: u64 = stats.loop_size_map.values().sum();
+
+            if stats.number_of_loops != total_destinations {
+                self.add_issue(format!("{} counts {} loops but {} holds {} destinations", STATS, stats.number_of_loops, LOOPS, total_destinations));
+            }
+
+            if loop_lengths != stats.number_of_loops {
+                self.add_issue(format!("{} loop length counts sum up to {} instead of {}", STATS, loop_lengths, stats.number_of_loops));
+            }
+
+            let imperiled = self.imperiled_destinations.len() as u64;
+            if imperiled > 0 && stats.number_of_imperiled != imperiled {
+                self.add_issue(format!("{} counts {} imperiled routes but {} holds {} distinct destinations", STATS, stats.number_of_imperiled, IMPERILED, imperiled));
+            }
+            Ok(())
+        }
+
+        /// rebuilds routers.id, loops.csv and the loop counters of stats.csv from the primary files,
+        /// refused if one of them is missing or unreadable as the derived files would lose their entries
+        pub fn repair_project(&mut self) -> Result<(), YarrpError> {
+            if !self.unusable_primary.is_empty() {
+                error!("Cannot repair with missing or unreadable primary files {}!", self.unusable_primary.join(", "));
+                return Err(YarrpError::NotFoundError);
+            }
+
+            let routers = CheckMode::<T>::build_router_loops(&self.loop_members);
+            LoopStorage::<T>::write_id_file(&self.project_path.join(ROUTERS), &routers)?;
+            self.repaired.push(format!("Rebuilt {} with {} routers", ROUTERS, routers.len()));
+
+            let mut loop_info = HashMap::new();
+            for (key, (preceding_ttl, shadowed_nets)) in &self.shadowed_counts {
+                let members;
+                if let Some(value) = self.loop_members.get(&key.0) {
+                    members = value;
+                } else {
+                    continue;
+                }
+
+                let mut info;
+                if let Some(existing) = self.loop_info.get(key) {
+                    info = existing.clone();
+                } else {
+                    // reconstruct missing metadata, the loop starts right after the preceding router
+                    let loop_len = members.len() as u8;
+                    let loop_start_ttl = preceding_ttl.saturating_add(1);
+                    info = SimpleLoopOutput {
+                        loop_id: key.0.clone(),
+                        preceding_router: key.1.clone(),
+                        shadowed_nets: 0,
+                        loop_len,
+                        loop_start_ttl,
+                        loop_stop_ttl: loop_start_ttl.saturating_add(loop_len.saturating_sub(1)),
+                        preceding_router_ttl: *preceding_ttl,
+                    };
+                }
+                info.shadowed_nets = *shadowed_nets;
+                loop_info.insert(key.clone(), info);
+            }
+
+            let mut csv_writer = atomic_csv_writer(&self.project_path.join(LOOPS_CSV))?;
+            let mut keys: Vec<&(String, String)> = loop_info.keys().collect();
+            keys.sort_by(|a, b| (&a.0, stable_key(&a.1)).cmp(&(&b.0, stable_key(&b.1))));
+            for key in keys {
+                let record = &loop_info[key];
+                csv_writer.serialize(record)?;
+            }
+            commit_csv(csv_writer)?;
+            self.repaired.push(format!("Rebuilt {} with {} entries", LOOPS_CSV, loop_info.len()));
+
+            let stats_path = self.project_path.join(STATS);
+            if let Some(only_full_routes) = LoopStatistics::read_only_full_routes(&stats_path)? {
+                let mut stats = LoopStatistics::new(only_full_routes, stats_path);
+                stats.rebuild_loop_counters(loop_info.values());
+
+                if !self.imperiled_destinations.is_empty() {
+                    stats.number_of_imperiled = self.imperiled_destinations.len() as u64;
+                }
+
+                stats.write_csv()?;
+                self.repaired.push(format!("Rebuilt loop counters of {}", STATS));
+            } else {
+                warn!("Cannot rebuild {} without the only_full_routes entry!", STATS);
+            }
+
+            Ok(())
+        }
+
+        fn build_router_loops(loop_members: &MapSetString) -> MapSetString {
+            let mut router_loops: MapSetString = HashMap::new();
+            for (loop_id, members) in loop_members {
+                for member in members {
+                    router_loops.entry(member.clone()).or_default().insert(loop_id.clone());
+                }
+            }
+            router_loops
+        }
+
+        /// returns (file stem, path) for all files with the given extension in a project sub directory
+        fn list_details_files(directory: &PathBuf, extension: &str) -> Result<Vec<(String, PathBuf)>, YarrpError> {
+            let mut files = Vec::new();
+            if !directory.exists() {
+                return Ok(files);
+            }
+
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                if path.extension().and_then(|value| value.to_str()) != Some(extension) {
+                    continue;
+                }
+
+                if let Some(file_stem) = path.file_stem().and_then(|value| value.to_str()) {
+                    files.push((file_stem.to_string(), path.clone()));
+                }
+            }
+            Ok(files)
+        }
+
+        /// returns (number of lines, number of unparsable lines)
+        fn count_details_file(path: &PathBuf) -> Result<(u64, u64), YarrpError> {
+            let mut count = 0;
+            let mut unparsable = 0;
+
+            for line in LoopStorage::<T>::read_details_file_as_string(path)? {
+                if T::from_str(&line).is_err() {
+                    unparsable += 1;
+                }
+                count += 1;
+            }
+            Ok((count, unparsable))
+        }
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ModeTrait for CheckMode<T> {
+        fn get_mode(&self) -> ModeEnum {
+            self.mode
+        }
+
+        fn no_input_capable(&self) -> bool {
+            true
+        }
+
+        fn parse_comment_line(&mut self, _input: &str) {
+            // Dummy implementation, just ignore comment lines
+        }
+
+        fn parse_string_line(&mut self, _input: &str) {
+            // No input parsing here!
+        }
+
+        fn do_file_rotate(&mut self, _file_number: u64, _file_name: &str) {
+            // No file rotating here
+        }
+
+        fn do_calculations(&mut self) {
+            if self.check_identifiers().is_err() {
+                error!("Could not read {}!", IDENTIFIERS);
+                exit(1);
+            }
+
+            if self.check_routers().is_err() {
+                error!("Could not read {}!", ROUTERS);
+                exit(1);
+            }
+
+            if self.check_loop_destinations().is_err() {
+                error!("Could not read {} directory!", LOOPS);
+                exit(1);
+            }
+
+            if self.check_imperiled().is_err() {
+                error!("Could not read {} directory!", IMPERILED);
+                exit(1);
+            }
+
+            if self.check_shadowed_preceding().is_err() {
+                error!("Could not read {}!", SHADOWED_PRECEDING_INFO);
+                exit(1);
+            }
+
+            if self.check_loop_info().is_err() {
+                error!("Could not read {}!", LOOPS_CSV);
+                exit(1);
+            }
+
+            if self.check_stats().is_err() {
+                error!("Could not read {}!", STATS);
+                exit(1);
+            }
+
+            if self.repair && !self.issues.is_empty() {
+                info!("Repairing derived files!");
+                if self.repair_project().is_err() {
+                    error!("Could not repair project!");
+                    exit(1);
+                }
+            }
+        }
+
+        fn print_output(&self) {
+            for issue in &self.issues {
+                println!("{}", issue);
+            }
+
+            println!();
+            println!("Loops: {}", self.loop_members.len());
+            println!("Routers: {}", self.router_loops.len());
+            println!("Destination files: {}", self.destination_counts.len());
+            println!("Inconsistencies found: {}", self.issues.len());
+
+            for repaired in &self.repaired {
+                println!("{}", repaired);
+            }
+        }
+
+        fn close(&mut self) {
+            if !self.issues.is_empty() && !self.repair {
+                exit(2);
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::net::Ipv4Addr;
+    use crate::analytics::{LoopStorage, ROUTERS, LOOPS_CSV};
+    use crate::modes::{CheckMode, ModeTrait};
+    use crate::helpers::test_helper::{TempFixture, ProjectFixture, get_ipv4_hop};
+
+    /// a project with one loop and broken derived files, routers.id lacks a member, loops.csv is missing
+    /// and the loop counter of stats.csv is off, returns the project and its loop id
+    fn create_broken_project() -> (TempFixture, String) {
+        let members: HashSet<Ipv4Addr> = vec![get_ipv4_hop(8), get_ipv4_hop(9)].into_iter().collect();
+        let loop_id = LoopStorage::create_loop_identifier(&members).ok().unwrap();
+
+        let project = ProjectFixture::empty("check_project")
+            .add_loop(&loop_id, &["192.0.2.8", "192.0.2.9"])
+            .destinations(&loop_id, &["198.51.100.1", "198.51.100.2"])
+            .build();
+        project.write(ROUTERS, format!("192.0.2.8={};\n", loop_id));
+        fs::remove_file(project.join(LOOPS_CSV)).unwrap();
+        project.write("shadowed_preceding.csv", format!("shadowed_net,preceding_router,preceding_ttl,loop_id\n\
+            198.51.100.1,192.0.2.7,7,{0}\n198.51.100.2,192.0.2.7,7,{0}\n", loop_id));
+        project.write("stats.csv", "only_full_routes,0\nroutes,10\nloops,5\nloop_length_2,5\n");
+        (project, loop_id)
+    }
+
+    #[test]
+    fn check_and_repair_derived_files() {
+        let (project, loop_id) = create_broken_project();
+
+        let mut check: CheckMode<Ipv4Addr> = CheckMode::from_project(project.path().to_path_buf(), false);
+        check.do_calculations();
+        assert_eq!(check.issues().len(), 3, "{:?}", check.issues());
+        assert!(check.issues().contains(&"loops.csv is missing".to_string()));
+        assert!(check.issues().contains(&format!("Router 192.0.2.9 of loop {} is missing in routers.id", loop_id)));
+        assert!(check.repaired().is_empty());
+
+        let mut repair: CheckMode<Ipv4Addr> = CheckMode::from_project(project.path().to_path_buf(), true);
+        repair.do_calculations();
+        assert_eq!(repair.repaired().len(), 3);
+        assert_eq!(fs::read_to_string(project.join("loops.csv")).unwrap(),
+                   format!("loop_id,preceding_router,shadowed_nets,loop_len,loop_start_ttl,loop_stop_ttl,preceding_router_ttl\n\
+                   {},192.0.2.7,2,2,8,9,7\n", loop_id));
+
+        let mut recheck: CheckMode<Ipv4Addr> = CheckMode::from_project(project.path().to_path_buf(), false);
+        recheck.do_calculations();
+        assert!(recheck.issues().is_empty(), "{:?}", recheck.issues());
+    }
+
+    #[test]
+    fn repair_saturates_reconstructed_ttls() {
+        let (project, loop_id) = create_broken_project();
+        project.write("shadowed_preceding.csv", format!("shadowed_net,preceding_router,preceding_ttl,loop_id\n\
+            198.51.100.1,192.0.2.7,255,{0}\n198.51.100.2,192.0.2.7,255,{0}\n", loop_id));
+
+        let mut repair: CheckMode<Ipv4Addr> = CheckMode::from_project(project.path().to_path_buf(), true);
+        repair.do_calculations();
+        assert!(fs::read_to_string(project.join("loops.csv")).unwrap().ends_with(",192.0.2.7,2,2,255,255,255\n"));
+    }
+
+    #[test]
+    fn refuse_repair_without_primary_files() {
+        let (project, _loop_id) = create_broken_project();
+        fs::remove_file(project.join("identifiers.id")).unwrap();
+
+        // a refused --repair exits, so the repair is run on its own
+        let mut repair: CheckMode<Ipv4Addr> = CheckMode::from_project(project.path().to_path_buf(), false);
+        repair.do_calculations();
+        assert!(repair.issues().contains(&"identifiers.id is missing".to_string()));
+        assert!(repair.repair_project().is_err());
+        assert!(repair.repaired().is_empty());
+        assert!(!project.join("loops.csv").exists(), "Derived files must not be rebuilt from an empty project");
+
+        fs::create_dir(project.join("identifiers.id")).unwrap();
+        let mut repair: CheckMode<Ipv4Addr> = CheckMode::from_project(project.path().to_path_buf(), false);
+        repair.do_calculations();
+        assert!(repair.issues().contains(&"identifiers.id is unreadable".to_string()));
+        assert!(repair.repair_project().is_err());
+    }
+}
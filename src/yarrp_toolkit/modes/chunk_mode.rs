@@ -2,14 +2,14 @@ pub mod chunk_mode {
     use std::collections::{HashMap};
     use std::path::PathBuf;
     use std::process::exit;
-    use std::fs::{create_dir_all, remove_file};
+    use std::fs::create_dir_all;
     use std::str::FromStr;
     use clap::ArgMatches;
     use log::{error, info, trace};
     use ipnet::IpNet;
 
-    use crate::modes::{ModeEnum, ModeTrait, load_path_param, parse_param};
-    use crate::structs::{CountingFile, YarrpError};
+    use crate::modes::{ModeEnum, ModeTrait, load_path_param, parse_param, load_output_policy};
+    use crate::structs::{CountingFile, OutputPolicy, YarrpError};
     use crate::read_lines;
     use itertools::Itertools;
 
@@ -19,6 +19,7 @@ pub mod chunk_mode {
         target_prefix: u8,
         ping_prefix: u8,
         output_path: PathBuf,
+        output_policy: OutputPolicy,
         prefixes: HashMap<u8, Vec<IpNet>>,
         output_map: HashMap<u64, u64>,
     }
@@ -36,6 +37,7 @@ pub mod chunk_mode {
             }
 
             let output_path = load_path_param(&sub_matches, "output");
+            let output_policy = load_output_policy(&matches, false);
             if output_path.exists() && output_path.is_file() {
                 error!("Output path is file and exists!");
                 exit(1);
@@ -72,6 +74,7 @@ pub mod chunk_mode {
                 target_prefix,
                 ping_prefix,
                 output_path,
+                output_policy,
                 prefixes: HashMap::new(),
                 output_map: HashMap::new(),
             }
@@ -208,16 +211,13 @@ pub mod chunk_mode {
             let mut output_files = Vec::<CountingFile>::new();
             for index in 0..number_files {
                 let file_path = self.output_path.join(format!("prefix_{:05}.lst", index));
-                if file_path.exists() && file_path.is_file() {
-                    if let Err(_) = remove_file(&file_path) {
-                        error!("Could not delete existing file!");
-                        exit(1);
-                    }
+                if self.output_policy.check(&file_path).is_err() {
+                    exit(1);
                 }
 
                 self.output_map.insert(index, 0);
 
-                if let Some(counting_file) = CountingFile::new(&file_path) {
+                if let Some(counting_file) = CountingFile::new(&file_path, self.output_policy) {
                     output_files.push(counting_file);
                 } else {
                     error!("Could not create file!");
@@ -229,6 +229,13 @@ pub mod chunk_mode {
                 error!("Could not write files!");
                 exit(1);
             }
+
+            for output_file in output_files.iter_mut() {
+                if output_file.commit().is_err() {
+                    error!("Could not write files!");
+                    exit(1);
+                }
+            }
         }
 
         fn print_output(&self) {
@@ -0,0 +1,159 @@
+pub mod convert_mode {
+    use clap::ArgMatches;
+    use std::process::exit;
+    use log::{error, info, trace};
+    use std::fmt::Display;
+    use std::hash::Hash;
+    use std::str::FromStr;
+    use std::path::{Path, PathBuf};
+    use std::io::Write;
+    use std::marker::PhantomData;
+
+    use crate::modes::{ModeTrait, ModeEnum, load_path_param};
+    use crate::structs::{YarrpError, AtomicFile, DetailFormat, detect_detail_format, write_binary_details};
+    use crate::traits::IpAddrExt;
+    use crate::analytics::LoopStorage;
+    use crate::analytics::{IMPERILED, LOOPS};
+
+    /// Rewrites the loops/*.dest and imperiled/*.imp files of a project into the given detail format
+    pub struct ConvertMode<T> {
+        pub mode: ModeEnum,
+        project_path: PathBuf,
+        format: DetailFormat,
+        converted_files: u64,
+        skipped_files: u64,
+        bytes_before: u64,
+        bytes_after: u64,
+        ip_type: PhantomData<T>,
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ConvertMode<T> {
+        pub fn new(matches: ArgMatches) -> ConvertMode<T> {
+            let mode = ModeEnum::Convert;
+            let mode_string = mode.to_string().to_lowercase();
+            let sub_matches = matches.subcommand_matches(mode_string).unwrap();
+
+            let project_path = load_path_param(sub_matches, "project_path");
+            if !project_path.exists() || !project_path.is_dir() {
+                error!("Project path does not exist or is not a directory!");
+                exit(1);
+            }
+
+            let mut format = DetailFormat::Binary;
+            if let Some(value) = sub_matches.value_of("format") {
+                if let Ok(temp_format) = DetailFormat::from_str(value) {
+                    format = temp_format;
+                } else {
+                    error!("Unknown detail format {}, expected text or binary!", value);
+                    exit(1);
+                }
+            }
+
+            ConvertMode {
+                mode,
+                project_path,
+                format,
+                converted_files: 0,
+                skipped_files: 0,
+                bytes_before: 0,
+                bytes_after: 0,
+                ip_type: PhantomData,
+            }
+        }
+
+        fn write_text_details(path: &Path, addresses: &[T]) -> Result<(), YarrpError> {
+            let mut writer = AtomicFile::create(path)?;
+            for address in addresses {
+                writeln!(writer, "{}", address)?;
+            }
+            writer.commit()
+        }
+
+        /// converts a single detail file, the converted file replaces it once it is completely written
+        fn convert_file(&mut self, path: &PathBuf) -> Result<(), YarrpError> {
+            if detect_detail_format(path)? == self.format {
+                trace!("Skipping {}, already stored as {}", path.to_str().unwrap_or(""), self.format);
+                self.skipped_files += 1;
+                return Ok(());
+            }
+
+            let mut addresses = LoopStorage::<T>::read_details_file_as_t(path)?;
+            addresses.sort();
+
+            let bytes_before = path.metadata()?.len();
+            match self.format {
+                DetailFormat::Text => ConvertMode::<T>::write_text_details(path, &addresses)?,
+                DetailFormat::Binary => write_binary_details(path, &addresses)?,
+            }
+
+            self.bytes_before += bytes_before;
+            self.bytes_after += path.metadata()?.len();
+            self.converted_files += 1;
+            Ok(())
+        }
+
+        fn convert_sub_dir(&mut self, sub_folder: &str, extension: &str) -> Result<(), YarrpError> {
+            let directory = self.project_path.join(sub_folder);
+            if !directory.exists() {
+                info!("Project has no {} directory, skipping", sub_folder);
+                return Ok(());
+            }
+
+            info!("Converting {}/*.{} to {}", sub_folder, extension, self.format);
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                if path.extension().and_then(|value| value.to_str()) != Some(extension) {
+                    continue;
+                }
+
+                if let Err(error) = self.convert_file(&path) {
+                    error!("Could not convert {}!", path.to_str().unwrap_or(""));
+                    return Err(error);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ModeTrait for ConvertMode<T> {
+        fn get_mode(&self) -> ModeEnum {
+            self.mode
+        }
+
+        fn no_input_capable(&self) -> bool {
+            true
+        }
+
+        fn parse_comment_line(&mut self, _input: &str) {
+            // Dummy implementation, just ignore comment lines
+        }
+
+        fn parse_string_line(&mut self, _input: &str) {
+            // No input parsing here!
+        }
+
+        fn do_file_rotate(&mut self, _file_number: u64, _file_name: &str) {
+            // No file rotating here
+        }
+
+        fn do_calculations(&mut self) {
+            if self.convert_sub_dir(LOOPS, "dest").is_err() {
+                error!("Could not convert {} directory!", LOOPS);
+                exit(1);
+            }
+
+            if self.convert_sub_dir(IMPERILED, "imp").is_err() {
+                error!("Could not convert {} directory!", IMPERILED);
+                exit(1);
+            }
+        }
+
+        fn print_output(&self) {
+            println!("Converted files: {}", self.converted_files);
+            println!("Files already stored as {}: {}", self.format, self.skipped_files);
+            println!("Size of converted files: {} bytes -> {} bytes", self.bytes_before, self.bytes_after);
+        }
+
+        fn close(&mut self) {}
+    }
+}
@@ -0,0 +1,134 @@
+pub mod coverage_mode {
+    use clap::ArgMatches;
+    use std::process::exit;
+    use std::collections::HashSet;
+    use std::fmt::Display;
+    use std::hash::Hash;
+    use std::net::IpAddr;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use log::{error, info, warn};
+
+    use crate::modes::{ModeTrait, ModeEnum, load_output_policy, load_path_param, load_asn_attribution};
+    use crate::structs::YarrpError;
+    use crate::traits::IpAddrExt;
+    use crate::analytics::{ASNAttribution, PrefixCoverage, Project};
+
+    /// Compares the probed targets of the input target list with the shadowed destinations
+    /// of a project per announced BGP prefix
+    pub struct CoverageMode<T> {
+        pub mode: ModeEnum,
+        project: Project<T>,
+        asn_attribution: ASNAttribution,
+        output_path: PathBuf,
+        loops_output_path: PathBuf,
+        probed: HashSet<IpAddr>,
+        coverage: PrefixCoverage,
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> CoverageMode<T> {
+        pub fn new(matches: ArgMatches) -> CoverageMode<T> {
+            let mode = ModeEnum::Coverage;
+            let mode_string = mode.to_string().to_lowercase();
+            let sub_matches = matches.subcommand_matches(mode_string).unwrap();
+
+            let project_path = load_path_param(sub_matches, "project_path");
+            let project;
+            if let Ok(value) = Project::open(&project_path) {
+                project = value;
+            } else {
+                error!("Could not open project!");
+                exit(1);
+            }
+
+            let output_path = load_path_param(sub_matches, "output");
+            let loops_output_path = load_path_param(sub_matches, "loops_output");
+
+            // csv output can not be appended to
+            let output_policy = load_output_policy(&matches, false);
+            if output_policy.check(&output_path).is_err() || output_policy.check(&loops_output_path).is_err() {
+                exit(1);
+            }
+
+            let asn_attribution;
+            if let Ok(value) = load_asn_attribution(sub_matches, &T::root_net()) {
+                asn_attribution = value;
+            } else {
+                error!("Could not load BGP data for asn attribution!");
+                exit(1);
+            }
+
+            CoverageMode {
+                mode,
+                project,
+                asn_attribution,
+                output_path,
+                loops_output_path,
+                probed: HashSet::new(),
+                coverage: PrefixCoverage::new(),
+            }
+        }
+
+        fn add_shadowed(&mut self) -> Result<(), YarrpError> {
+            let loop_ids: Vec<String> = self.project.loop_members()?.keys().cloned().collect();
+            for loop_id in loop_ids {
+                for destination in self.project.loop_destinations(&loop_id)? {
+                    let address = destination.to_ipaddr()?;
+                    self.coverage.add_shadowed(&self.asn_attribution, &loop_id, &address);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ModeTrait for CoverageMode<T> {
+        fn get_mode(&self) -> ModeEnum {
+            self.mode
+        }
+
+        fn no_input_capable(&self) -> bool {
+            false
+        }
+
+        fn parse_comment_line(&mut self, _input: &str) {}
+
+        fn parse_string_line(&mut self, input: &str) {
+            if let Ok(address) = IpAddr::from_str(input.trim()) {
+                self.probed.insert(address);
+            } else {
+                warn!("Could not parse IP address from str {}!", input);
+            }
+        }
+
+        fn do_file_rotate(&mut self, _file_number: u64, _file_name: &str) {}
+
+        fn do_calculations(&mut self) {
+            info!("Attributing {} probed targets", self.probed.len());
+            for address in &self.probed {
+                self.coverage.add_probed(&self.asn_attribution, address);
+            }
+
+            if self.add_shadowed().is_err() {
+                error!("Could not read shadowed destinations from project!");
+                exit(1);
+            }
+
+            if self.coverage.write_prefix_csv(&self.output_path).is_err() {
+                error!("Could not write prefix coverage csv!");
+            }
+
+            if self.coverage.write_loop_csv(&self.loops_output_path).is_err() {
+                error!("Could not write loop coverage csv!");
+            }
+        }
+
+        fn print_output(&self) {
+            println!("Probed targets: {}", self.probed.len());
+            println!("Probed targets outside announced space: {}", self.coverage.num_unannounced_probed());
+            println!("Announced prefixes with targets: {}", self.coverage.num_prefixes());
+            println!("Loops with shadowed nets in unannounced space of a covering prefix: {}", self.coverage.num_aggregate_gap_loops());
+        }
+
+        fn close(&mut self) {}
+    }
+}
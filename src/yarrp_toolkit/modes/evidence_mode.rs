@@ -0,0 +1,192 @@
+pub mod evidence_mode {
+    use clap::ArgMatches;
+    use std::process::exit;
+    use log::error;
+    use std::fmt::Display;
+    use std::hash::Hash;
+    use std::str::FromStr;
+    use itertools::sorted;
+
+    use crate::modes::{ModeTrait, ModeEnum, load_path_param};
+    use crate::structs::{YarrpError, EvidenceTrace, EvidenceResult};
+    use crate::traits::IpAddrExt;
+    use crate::analytics::Project;
+
+    /// Renders the sample traces stored by loops --evidence for a single loop
+    pub struct EvidenceMode<T> {
+        pub mode: ModeEnum,
+        project: Project<T>,
+        loop_id: String,
+        print_json: bool,
+        members: Vec<T>,
+        traces: Vec<EvidenceTrace<T>>,
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> EvidenceMode<T> {
+        pub fn new(matches: ArgMatches) -> EvidenceMode<T> {
+            let mode_string = ModeEnum::Evidence.to_string().to_lowercase();
+            let sub_matches = matches.subcommand_matches(mode_string).unwrap();
+
+            let project_path = load_path_param(sub_matches, "project_path");
+            let project;
+            if let Ok(value) = Project::open(&project_path) {
+                project = value;
+            } else {
+                error!("Could not open project!");
+                exit(1);
+            }
+
+            let loop_id;
+            if let Some(value) = sub_matches.value_of("loop_id") {
+                loop_id = value.to_string();
+            } else {
+                error!("Could not read loop_id!");
+                exit(1);
+            }
+
+            EvidenceMode::from_project(project, loop_id, sub_matches.occurrences_of("json") > 0)
+        }
+
+        pub fn from_project(project: Project<T>, loop_id: String, print_json: bool) -> EvidenceMode<T> {
+            EvidenceMode {
+                mode: ModeEnum::Evidence,
+                project,
+                loop_id,
+                print_json,
+                members: Vec::new(),
+                traces: Vec::new(),
+            }
+        }
+
+        pub fn traces(&self) -> &[EvidenceTrace<T>] {
+            &self.traces
+        }
+
+        /// loads members and traces of the loop, fails for unknown loops and loops without evidence
+        pub fn load_evidence(&mut self) -> Result<(), YarrpError> {
+            if let Some(members) = self.project.loop_members()?.get(&self.loop_id) {
+                self.members = sorted(members).cloned().collect();
+            } else {
+                error!("Loop {} is not part of the project!", self.loop_id);
+                return Err(YarrpError::NotFoundError);
+            }
+
+            self.traces = self.project.loop_evidence(&self.loop_id)?;
+            if self.traces.is_empty() {
+                error!("No evidence stored for loop {}, run loops with --evidence to keep sample traces!", self.loop_id);
+                return Err(YarrpError::NotFoundError);
+            }
+            Ok(())
+        }
+
+        fn print_human_readable(&self) {
+            let members: Vec<String> = self.members.iter().map(|member| member.to_string()).collect();
+            println!("Loop {} with members {}", self.loop_id, members.join(", "));
+            println!("{} sample traces, hops within the loop are marked with =>", self.traces.len());
+
+            for trace in &self.traces {
+                println!();
+                print!("{}", trace.render_text());
+            }
+        }
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ModeTrait for EvidenceMode<T> {
+        fn get_mode(&self) -> ModeEnum {
+            self.mode
+        }
+
+        fn no_input_capable(&self) -> bool {
+            true
+        }
+
+        fn parse_comment_line(&mut self, _input: &str) {
+            // Dummy implementation, just ignore comment lines
+        }
+
+        fn parse_string_line(&mut self, _input: &str) {
+            // No input parsing here!
+        }
+
+        fn do_file_rotate(&mut self, _file_number: u64, _file_name: &str) {
+            // No file rotating here
+        }
+
+        fn do_calculations(&mut self) {
+            if self.load_evidence().is_err() {
+                exit(1);
+            }
+        }
+
+        fn print_output(&self) {
+            if !self.print_json {
+                self.print_human_readable();
+                return;
+            }
+
+            let result = EvidenceResult {
+                loop_id: self.loop_id.clone(),
+                members: self.members.iter().map(|member| member.to_string()).collect(),
+                traces: self.traces.iter().map(|trace| trace.to_result()).collect(),
+            };
+
+            match serde_json::to_string_pretty(&result) {
+                Ok(output) => println!("{}", output),
+                Err(_) => {
+                    error!("Could not serialize evidence!");
+                    exit(1);
+                }
+            }
+        }
+
+        fn close(&mut self) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use crate::analytics::{Project, EVIDENCE};
+    use crate::modes::EvidenceMode;
+    use crate::helpers::test_helper::{TempFixture, ProjectFixture};
+
+    const LOOP_ID: &str = "deadbeef";
+
+    fn fixture_project(with_evidence: bool) -> TempFixture {
+        let project_path = ProjectFixture::empty("evidence_mode")
+            .add_loop(LOOP_ID, &["192.0.2.8", "192.0.2.9"])
+            .destinations(LOOP_ID, &["198.51.100.1"])
+            .build();
+        if with_evidence {
+            let mut trace = String::from("# trace 198.51.100.1 5 6\n");
+            for (ttl, hop) in &[(4, "192.0.2.7"), (5, "192.0.2.8"), (6, "192.0.2.9"), (7, "192.0.2.8")] {
+                trace.push_str(&format!("198.51.100.1 1 1 11 0 {} {} 590 0 36 84 63 0 0 27\n", ttl, hop));
+            }
+            project_path.write(format!("{}/{}.trace", EVIDENCE, LOOP_ID), trace);
+        }
+        project_path
+    }
+
+    fn evidence_mode(project_path: &TempFixture, loop_id: &str) -> EvidenceMode<Ipv4Addr> {
+        let project = Project::open(project_path.path()).ok().unwrap();
+        EvidenceMode::from_project(project, loop_id.to_string(), false)
+    }
+
+    #[test]
+    fn loads_stored_traces() {
+        let project_path = fixture_project(true);
+        let mut mode = evidence_mode(&project_path, LOOP_ID);
+        assert!(mode.load_evidence().is_ok());
+        assert_eq!(mode.traces().len(), 1);
+
+        let in_loop: Vec<bool> = mode.traces()[0].to_result().hops.iter().map(|hop| hop.in_loop).collect();
+        assert_eq!(in_loop, vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn refuses_unknown_loops_and_missing_evidence() {
+        let project_path = fixture_project(false);
+        assert!(evidence_mode(&project_path, "cafe").load_evidence().is_err(), "Loop is not part of the project");
+        assert!(evidence_mode(&project_path, LOOP_ID).load_evidence().is_err(), "No evidence stored for the loop");
+    }
+}
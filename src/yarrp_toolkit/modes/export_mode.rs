@@ -13,7 +13,7 @@ pub mod export_mode {
     use crate::modes::{ModeEnum, ModeTrait};
     use std::process::exit;
     use std::collections::{HashMap, HashSet};
-    use crate::structs::{YarrpLine, Route, YarrpError};
+    use crate::structs::{YarrpLine, Route, YarrpError, DetailFormat};
     use crate::analytics::{LoopAnalysis, LoopStorage, LoopStorageError};
     use std::net::Ipv6Addr;
 
@@ -138,7 +138,7 @@ pub mod export_mode {
                 elastic_client,
                 meta_info: HashMap::new(),
                 line_count: 0,
-                loop_analysis: LoopAnalysis::new(true, String::new(), "", ""),
+                loop_analysis: LoopAnalysis::new(true, String::new(), "", "", DetailFormat::Text, 0),
                 loop_ids: HashSet::new(),
                 lower_ttl: min_ttl,
                 upper_ttl: max_ttl
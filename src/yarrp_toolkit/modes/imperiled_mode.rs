@@ -1,18 +1,19 @@
 pub mod imperiled_mode {
-    use crate::modes::{ModeTrait, ModeEnum};
+    use crate::modes::{ModeTrait, ModeEnum, load_output_policy};
     use crate::{read_lines, create_dir_if_not_existing};
 
     use std::path::Path;
     use std::collections::HashSet;
-    use std::fs::File;
     use std::process::exit;
     use std::io::Write;
+    use crate::structs::{AtomicFile, OutputPolicy, stable_sorted};
     use log::{error};
     use clap::ArgMatches;
 
     pub struct ImperiledMode {
         pub mode: ModeEnum,
         pub output_directory: String,
+        output_policy: OutputPolicy,
         router_set: HashSet<String>,
         potential_imperiled: HashSet<String>,
         destination_set: HashSet<String>,
@@ -112,6 +113,7 @@ pub mod imperiled_mode {
             ImperiledMode {
                 mode,
                 output_directory,
+                output_policy: load_output_policy(&matches, true),
                 router_set,
                 potential_imperiled: HashSet::new(),
                 destination_set: HashSet::new(),
@@ -199,7 +201,7 @@ pub mod imperiled_mode {
             // build file path for output file
             let output_file = format!("{}{}.dst", self.output_directory, file_stem);
 
-            let output_file_fp = File::create(&output_file);
+            let output_file_fp = AtomicFile::open(Path::new(&output_file), self.output_policy);
             let mut output_file_fp = match output_file_fp {
                 Ok(file) => file,
                 Err(_) => {
@@ -210,7 +212,7 @@ pub mod imperiled_mode {
 
             // write routers involved in loops to file
             let mut written_nets = 0;
-            for net in &self.echo_replied {
+            for net in stable_sorted(&self.echo_replied) {
                 if !self.potential_imperiled.contains(net) {
                     continue;
                 }
@@ -225,6 +227,11 @@ pub mod imperiled_mode {
                 }
             }
 
+            if output_file_fp.commit().is_err() {
+                eprintln!("Could not write the imperiled output file!");
+                exit(1);
+            }
+
             self.print_output();
             println!("{:10} imperiled nets!", written_nets);
 
@@ -1,12 +1,13 @@
 pub mod loops_mode {
 
-    use crate::structs::{YarrpLine};
-    use crate::analytics::{LoopAnalysis};
-    use crate::modes::{ModeEnum, ModeTrait};
+    use crate::structs::{YarrpLine, DetailFormat};
+    use crate::analytics::{LoopAnalysis, ProjectOutput, prepare_project_output};
+    use crate::modes::{ModeEnum, ModeTrait, load_output_policy, parse_param};
     use crate::create_dir_if_not_existing;
 
     use clap::ArgMatches;
     use std::process::exit;
+    use std::path::Path;
     use log::{error};
     use std::fmt::Display;
     use std::hash::Hash;
@@ -19,6 +20,7 @@ pub mod loops_mode {
         pub loop_analysis: LoopAnalysis<T>,
         min_ttl: u8,
         max_ttl: u8,
+        project_output: Option<ProjectOutput>,
     }
 
     impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> LoopsMode<T> {
@@ -46,6 +48,14 @@ pub mod loops_mode {
                 only_full_loops = true;
             }
 
+            // number of complete traces kept per loop, 0 disables evidence storage
+            let evidence_limit = parse_param::<u64>(sub_matches, "evidence", 0);
+
+            let mut detail_format = DetailFormat::Text;
+            if sub_matches.occurrences_of("binary_details") > 0 {
+                detail_format = DetailFormat::Binary;
+            }
+
             if let Some(temp_loop_storage) = sub_matches.value_of("loop_output") {
                 loop_storage_path = temp_loop_storage.to_owned();
                 // check if output directory path exists, if not create it
@@ -58,6 +68,16 @@ pub mod loops_mode {
                 exit(1);
             }
 
+            // appending adds the routes to the existing project, overwriting replaces it once the run is complete
+            let output_policy = load_output_policy(&matches, true);
+            let project_output;
+            if let Ok(value) = prepare_project_output(Path::new(&loop_storage_path), output_policy) {
+                project_output = value;
+            } else {
+                exit(1);
+            }
+            let loop_storage_path = project_output.write_path().to_str().unwrap().to_string();
+
             if let Some(router_input_file) = sub_matches.value_of("imperiled_router_test") {
                 imperiled_router_test_file = router_input_file;
             } else {
@@ -70,12 +90,21 @@ pub mod loops_mode {
                 imperiled_router_blocklist = "";
             }
 
+            let loop_analysis = LoopAnalysis::new(only_full_loops, loop_storage_path, imperiled_router_test_file, imperiled_router_blocklist, detail_format, evidence_limit);
+            let mut loops_mode = LoopsMode::from_analysis(loop_analysis, min_ttl, max_ttl);
+            loops_mode.project_output = Some(project_output);
+            loops_mode
+        }
+
+        /// loops mode working on an already set up analysis, used by the watch mode
+        pub fn from_analysis(loop_analysis: LoopAnalysis<T>, min_ttl: u8, max_ttl: u8) -> LoopsMode<T> {
             LoopsMode {
-                mode,
+                mode: ModeEnum::Loops,
                 line_count: 0,
-                loop_analysis: LoopAnalysis::new(only_full_loops, loop_storage_path, imperiled_router_test_file, imperiled_router_blocklist),
+                loop_analysis,
                 min_ttl,
-                max_ttl
+                max_ttl,
+                project_output: None,
             }
         }
 
@@ -120,7 +149,57 @@ pub mod loops_mode {
         }
 
         fn close(&mut self) {
+            if let Some(project_output) = self.project_output.as_mut() {
+                if project_output.commit().is_err() {
+                    error!("Could not replace the existing project!");
+                    exit(1);
+                }
+            }
             self.clear();
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use crate::analytics::{LoopAnalysis, Project, EVIDENCE, IDENTIFIERS};
+    use crate::modes::{LoopsMode, ModeTrait};
+    use crate::structs::DetailFormat;
+    use crate::helpers::test_helper::{TempFixture, MIN_TTL, MAX_TTL};
+
+    fn run_loops_mode(project_path: &TempFixture, evidence_limit: u64) {
+        let analysis = LoopAnalysis::<Ipv4Addr>::new(false, project_path.to_str().to_string(), "", "", DetailFormat::Text, evidence_limit);
+        let mut mode = LoopsMode::from_analysis(analysis, MIN_TTL, MAX_TTL);
+        for destination in 1..6 {
+            for ttl in MIN_TTL..=MAX_TTL {
+                let hop = match ttl < 10 {
+                    true => format!("192.0.2.{}", ttl),
+                    false => format!("10.0.0.{}", 1 + ttl % 2)
+                };
+                mode.parse_string_line(&format!("198.51.100.{} 1 1 11 0 {} {} 590 0 36 84 63 0 0 27", destination, ttl, hop));
+            }
+        }
+        mode.do_calculations();
+    }
+
+    #[test]
+    fn evidence_limit_keeps_samples() {
+        let project_path = TempFixture::dir("loops_evidence");
+        run_loops_mode(&project_path, 2);
+
+        let project: Project<Ipv4Addr> = Project::open(project_path.path()).ok().unwrap();
+        let loop_members = project.loop_members().ok().unwrap();
+        assert_eq!(loop_members.len(), 1);
+        let loop_id = loop_members.keys().next().unwrap();
+        assert_eq!(project.loop_evidence(loop_id).ok().unwrap().len(), 2, "Five routes through the loop, two samples kept");
+    }
+
+    #[test]
+    fn no_evidence_without_limit() {
+        let project_path = TempFixture::dir("loops_no_evidence");
+        run_loops_mode(&project_path, 0);
+        assert!(project_path.join(IDENTIFIERS).exists());
+        assert!(!project_path.join(EVIDENCE).exists(), "Evidence storage should only be created with --evidence");
+    }
+}
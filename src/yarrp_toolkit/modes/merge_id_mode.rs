@@ -2,26 +2,42 @@ pub mod merge_id_mode {
     use clap::ArgMatches;
     use std::process::exit;
     use log::{error, warn, info, trace};
-    use std::path::{Path, PathBuf};
+    use std::path::Path;
     use std::collections::{HashMap, HashSet};
-    use std::net::Ipv4Addr;
+    use std::fmt::Display;
+    use std::hash::Hash;
+    use std::str::FromStr;
 
-    use crate::modes::{ModeTrait, ModeEnum};
-    use crate::analytics::{LoopStorage, LoopStatistics};
-    use crate::structs::{YarrpError, CountingFile, ShadowedPreceding};
+    use crate::modes::{ModeTrait, ModeEnum, load_output_policy};
+    use crate::analytics::{LoopStorage, LoopStatistics, Project, ProjectOutput, prepare_project_output};
+    use crate::structs::{YarrpError, CountingFile, ShadowedPreceding, SimpleLoopOutput, DetailFormat, OutputPolicy, stable_key};
     use crate::analytics::{ROUTERS, IDENTIFIERS, IMPERILED, LOOPS, STATS, SHADOWED_PRECEDING_INFO};
-    use crate::read_lines;
+    use crate::traits::IpAddrExt;
     use std::borrow::BorrowMut;
 
-    pub struct MergeIdMode {
+    /// Differing loops.csv metadata for the same loop and preceding router
+    pub struct LoopInfoConflict {
+        loop_id: String,
+        preceding_router: String,
+        input_path: String,
+        field: &'static str,
+        existing: u8,
+        conflicting: u8,
+    }
+
+    pub struct MergeIdMode<T> {
         pub mode: ModeEnum,
-        input_paths: Vec<PathBuf>,
+        inputs: Vec<Project<T>>,
         pub output_path: String,
-        storage: LoopStorage<Ipv4Addr>,  // type does not matter here, we just want to use some lower functions
+        only_full_loops: bool,
+        storage: LoopStorage<T>,
+        conflicts: Vec<LoopInfoConflict>,
+        duplicate_shadowed: u64,
+        project_output: Option<ProjectOutput>,
     }
 
-    impl MergeIdMode {
-        pub fn new(matches: ArgMatches) -> MergeIdMode {
+    impl<T: Display + FromStr + Ord + Copy + Clone + Hash + IpAddrExt> MergeIdMode<T> {
+        pub fn new(matches: ArgMatches) -> MergeIdMode<T> {
             let mode = ModeEnum::MergeId;
             let mode_string = mode.to_string().to_lowercase();
             let sub_matches = matches.subcommand_matches(mode_string).unwrap();
@@ -48,57 +64,171 @@ pub mod merge_id_mode {
                 exit(1);
             }
 
-            if !MergeIdMode::check_input_paths(&input_paths) {
-                error!("One or more paths does not exist!");
+            let mut inputs = Vec::new();
+            for path in &input_paths {
+                if let Ok(project) = Project::<T>::open(path) {
+                    inputs.push(project);
+                } else {
+                    error!("One or more paths does not exist!");
+                    exit(1);
+                }
+            }
+
+            // appending merges the inputs into the existing output project, overwriting replaces it once the merge is complete
+            let output_policy = load_output_policy(&matches, true);
+            let project_output;
+            if let Ok(value) = prepare_project_output(Path::new(&output_path), output_policy) {
+                project_output = value;
+            } else {
                 exit(1);
             }
+            let output_path = project_output.write_path().to_str().unwrap().to_string();
+
+            let mut detail_format = DetailFormat::Text;
+            if sub_matches.occurrences_of("binary_details") > 0 {
+                detail_format = DetailFormat::Binary;
+            }
 
-            let storage = LoopStorage::new(true, output_path.to_string());
+            if let Ok(mut merge_id_mode) = MergeIdMode::from_projects(inputs, output_path, detail_format) {
+                merge_id_mode.project_output = Some(project_output);
+                merge_id_mode
+            } else {
+                exit(1);
+            }
+        }
 
-            MergeIdMode {
-                mode,
-                input_paths,
+        /// merges the input projects into the project at output_path, an existing output project is merged as well,
+        /// refuses inputs differing on only_full_routes or of another address family
+        pub fn from_projects(inputs: Vec<Project<T>>, output_path: String, detail_format: DetailFormat) -> Result<MergeIdMode<T>, YarrpError> {
+            let only_full_loops;
+            if let Some(value) = MergeIdMode::<T>::check_only_full_loops(&inputs, Path::new(&output_path)) {
+                only_full_loops = value;
+            } else {
+                error!("Projects differ on only_full_routes, refusing to merge!");
+                return Err(YarrpError::NotCompatibleError);
+            }
+
+            if !MergeIdMode::<T>::check_address_family(&inputs) {
+                error!("One or more projects do not match the selected address family, refusing to merge!");
+                return Err(YarrpError::NotCompatibleError);
+            }
+
+            let mut storage = LoopStorage::new(only_full_loops, output_path.to_string());
+            storage.detail_format = detail_format;
+
+            Ok(MergeIdMode {
+                mode: ModeEnum::MergeId,
+                inputs,
                 output_path,
+                only_full_loops,
                 storage,
-            }
+                conflicts: Vec::new(),
+                duplicate_shadowed: 0,
+                project_output: None,
+            })
         }
 
-        fn check_input_paths(paths: &Vec<PathBuf>) -> bool {
-            let mut return_value = true;
+        pub fn duplicate_shadowed(&self) -> u64 {
+            self.duplicate_shadowed
+        }
+
+        pub fn num_conflicts(&self) -> usize {
+            self.conflicts.len()
+        }
+
+        /// returns the common only_full_routes setting of all inputs and an existing output,
+        /// None if they differ. Projects without a stats file are assumed to be compatible.
+        fn check_only_full_loops(inputs: &[Project<T>], output_path: &Path) -> Option<bool> {
+            let mut only_full_loops = None;
 
-            for path in paths {
-                if !path.exists() || !path.is_dir() {
-                    if let Some(str_path) = path.to_str() {
-                        error!("Input path ({}) is nonexistent or not a directory!", str_path);
+            for path in inputs.iter().map(|project| project.path()).chain(std::iter::once(output_path)) {
+                let stats_file = path.join(STATS);
+                let value = match LoopStatistics::read_only_full_routes(&stats_file) {
+                    Ok(Some(temp_value)) => temp_value,
+                    Ok(None) => {
+                        if stats_file.exists() {
+                            warn!("No only_full_routes setting found in {}", stats_file.to_str().unwrap_or(STATS));
+                        }
+                        continue;
+                    }
+                    Err(_) => {
+                        error!("Could not read {}", stats_file.to_str().unwrap_or(STATS));
+                        return None;
                     }
-                    return_value = false;
+                };
+
+                match only_full_loops {
+                    None => only_full_loops = Some(value),
+                    Some(existing) if existing != value => {
+                        error!("{} has only_full_routes {}, expected {}", stats_file.to_str().unwrap_or(STATS), value, existing);
+                        return None;
+                    }
+                    _ => {}
                 }
             }
 
-            return_value
+            if only_full_loops.is_none() {
+                warn!("No project contains a stats file, assuming only full loops!");
+            }
+
+            Some(only_full_loops.unwrap_or(true))
+        }
+
+        /// checks whether the loop members and routers of all inputs can be parsed as T
+        fn check_address_family(inputs: &[Project<T>]) -> bool {
+            for project in inputs {
+                if project.loop_members().is_err() || project.router_loops().is_err() {
+                    error!("Could not read id files of {}", project.path().to_str().unwrap_or(""));
+                    return false;
+                }
+            }
+            true
         }
 
-        fn merge_identifiers(&self, file: &str) -> Result<Vec<String>, YarrpError> {
-            let mut output_identifiers = HashMap::new();
-            let output_path = self.storage.get_storage_file(file)?;
+        fn merge_loop_identifiers(&self) -> Result<Vec<String>, YarrpError> {
+            let output_path = self.storage.get_storage_file(IDENTIFIERS)?;
+            // identifiers of an existing output project are kept
+            let mut output_identifiers = LoopStorage::<T>::read_id_file(&output_path)?;
 
-            for path in &self.input_paths {
-                let id_file = path.join(file);
-                let temp_id = LoopStorage::<Ipv4Addr>::read_id_file(&id_file)?;
-                LoopStorage::<Ipv4Addr>::merge_id_file_string(&mut output_identifiers, &temp_id)?;
+            for project in &self.inputs {
+                let mut temp_id = HashMap::new();
+                for (loop_id, members) in project.loop_members()? {
+                    temp_id.insert(loop_id.clone(), members.iter().map(|member| member.to_string()).collect());
+                }
+                LoopStorage::<T>::merge_id_file_string(&mut output_identifiers, &temp_id)?;
             }
 
-            LoopStorage::<Ipv4Addr>::write_id_file(&output_path, &output_identifiers)?;
-            let mut keys = Vec::new();
+            LoopStorage::<T>::write_id_file(&output_path, &output_identifiers)?;
+            Ok(output_identifiers.keys().cloned().collect())
+        }
 
-            for item in output_identifiers.keys() {
-                keys.push(item.to_string());
+        fn merge_routers(&self) -> Result<Vec<String>, YarrpError> {
+            let output_path = self.storage.get_storage_file(ROUTERS)?;
+            let mut output_routers = LoopStorage::<T>::read_id_file(&output_path)?;
+
+            for project in &self.inputs {
+                LoopStorage::<T>::merge_id_file(&mut output_routers, project.router_loops()?)?;
             }
 
-            Ok(keys)
+            LoopStorage::<T>::write_id_file(&output_path, &output_routers)?;
+            Ok(output_routers.keys().cloned().collect())
+        }
+
+        fn read_loop_destinations(project: &Project<T>, loop_id: &str) -> Result<Vec<T>, YarrpError> {
+            project.loop_destinations(loop_id)
         }
 
-        fn merge_details(&self, sub_folder: &str, keys: &Vec<String>, extension: &str) -> Result<(), YarrpError> {
+        fn read_router_imperiled(project: &Project<T>, router: &str) -> Result<Vec<T>, YarrpError> {
+            if let Ok(router) = T::from_str(router) {
+                Ok(project.router_imperiled(&router)?.into_iter().collect())
+            } else {
+                error!("Could not parse router {}", router);
+                Err(YarrpError::CouldNotParseError)
+            }
+        }
+
+        fn merge_details(&self, sub_folder: &str, keys: &[String], extension: &str,
+                         read_details: fn(&Project<T>, &str) -> Result<Vec<T>, YarrpError>) -> Result<(), YarrpError> {
             let subdir = self.storage.get_storage_sub_dir(sub_folder)?;
             info!("Merging details for sub dir {} and files with extension {}", sub_folder, extension);
 
@@ -107,39 +237,18 @@ pub mod merge_id_mode {
                 let filename = format!("{}.{}", key, extension);
                 trace!("Selecting file {}", filename);
 
-                let mut key_set = HashSet::new();
-
-                // iterate over existing projects and read the given key
-                for input_path in &self.input_paths {
-                    let file_path = input_path.join(sub_folder).join(&filename);
-                    let file_path_str;
-                    if let Some(string) = file_path.to_str() {
-                        file_path_str = string;
-                    } else {
-                        error!("Could not create string for filepath!");
-                        file_path_str = "";
-                    }
-
-                    trace!("Trying to read from {}", file_path_str);
+                let output_path = subdir.join(&filename);
 
-                    // if the file exists, read the content, collect from all input paths
-                    // and write to new output project
-                    if file_path.exists() && file_path.is_file() {
-                        trace!("File existed, reading from it");
-                        let lines = read_lines(file_path)?;
-                        for line in lines {
-                            let line = line?;
-
-                            key_set.insert(line.to_string());
-                        }
-                    }
+                // only keep the addresses of the current key, inputs are read one file at a time,
+                // addresses of an existing output file are not written again
+                let mut key_set: HashSet<T> = HashSet::new();
+                if output_path.exists() {
+                    key_set = LoopStorage::<T>::read_details_file_as_t_ret_set(&output_path)?;
                 }
 
-                let output_path = subdir.join(filename);
-                if let Some(mut counting_file) = CountingFile::new(&output_path) {
-                    for item in key_set {
-                        counting_file.write_line(&item);
-                    }
+                let mut counting_file;
+                if let Some(temp_counting_file) = CountingFile::new_details(&output_path, self.storage.detail_format, OutputPolicy::Append) {
+                    counting_file = temp_counting_file;
                 } else {
                     if let Some(str_path) = output_path.to_str() {
                         error!("Could not open output file at {}", str_path);
@@ -148,23 +257,42 @@ pub mod merge_id_mode {
                     }
                     exit(1);
                 }
+
+                // iterate over existing projects and read the given key
+                for project in &self.inputs {
+                    trace!("Reading {} from {}", filename, project.path().to_str().unwrap_or(""));
+                    for address in read_details(project, key)? {
+                        if key_set.insert(address) {
+                            counting_file.write_ip_line(&address);
+                        }
+                    }
+                }
             }
             Ok(())
         }
 
-        fn merge_stats(&self) -> Result<(), YarrpError> {
+        /// sums up the route counters of the inputs, the loop and imperiled counters are rebuilt from the
+        /// deduplicated loops.csv and imperiled files so inputs merged before are not counted twice
+        fn merge_stats(&self, routers: &[String]) -> Result<(), YarrpError> {
             let output_path = self.storage.get_storage_file(STATS)?;
-            let mut output_stats = LoopStatistics::new(true, output_path);
+            let mut output_stats = LoopStatistics::new(self.only_full_loops, output_path);
 
-            for path in &self.input_paths {
-                let stats_file = path.join(STATS);
-                let stats_mod = LoopStatistics::new(true, stats_file);
-                output_stats.number_of_routes += stats_mod.number_of_routes;
-                output_stats.number_of_load_balancers += stats_mod.number_of_load_balancers;
-                output_stats.number_of_spammers += stats_mod.number_of_spammers;
-                output_stats.number_of_loops += stats_mod.number_of_loops;
-                output_stats.number_of_full_loops += stats_mod.number_of_full_loops;
-                output_stats.number_of_imperiled += stats_mod.number_of_imperiled;
+            for project in &self.inputs {
+                output_stats.merge(&project.stats()?)?;
+            }
+
+            output_stats.rebuild_loop_counters(self.storage.loop_information.values());
+
+            let subdir = self.storage.get_storage_sub_dir(IMPERILED)?;
+            let mut imperiled = HashSet::new();
+            for router in routers {
+                let path = subdir.join(format!("{}.imp", router));
+                if path.exists() {
+                    imperiled.extend(LoopStorage::<T>::read_details_file_as_t_ret_set(&path)?);
+                }
+            }
+            if !imperiled.is_empty() {
+                output_stats.number_of_imperiled = imperiled.len() as u64;
             }
 
             if let Err(err) = output_stats.write_csv() {
@@ -175,22 +303,61 @@ pub mod merge_id_mode {
             Ok(())
         }
 
+        /// merges loops.csv metadata, shadowed_nets are recounted from the deduplicated shadowed preceding rows
         fn merge_loops_information(&mut self) -> Result<(), YarrpError> {
-            for path in &self.input_paths {
-                let path_info = LoopStorage::<Ipv4Addr>::read_loop_info(path)?;
-                for (loop_id, info) in &path_info {
-                    if ! self.storage.loop_information.contains_key(loop_id) {
-                        self.storage.loop_information.insert(loop_id.clone(), info.clone());
-                    } else if let Some(existing_info) = self.storage.loop_information.get_mut(loop_id) {
-                        existing_info.shadowed_nets += info.shadowed_nets;
+            for project in &self.inputs {
+                let path_str = project.path().to_str().unwrap_or("").to_string();
+
+                // conflicts are reported ordered by loop id and preceding router
+                let mut infos: Vec<&SimpleLoopOutput> = project.loop_info()?.values().flatten().collect();
+                infos.sort_by(|a, b| (&a.loop_id, stable_key(&a.preceding_router)).cmp(&(&b.loop_id, stable_key(&b.preceding_router))));
+
+                for info in infos {
+                    let loop_key = (info.loop_id.clone(), info.preceding_router.clone());
+                    if let Some(existing_info) = self.storage.loop_information.get(&loop_key) {
+                        let fields = [
+                            ("loop_len", existing_info.loop_len, info.loop_len),
+                            ("loop_start_ttl", existing_info.loop_start_ttl, info.loop_start_ttl),
+                            ("loop_stop_ttl", existing_info.loop_stop_ttl, info.loop_stop_ttl),
+                            ("preceding_router_ttl", existing_info.preceding_router_ttl, info.preceding_router_ttl),
+                        ];
+
+                        for (field, existing, conflicting) in fields.iter() {
+                            if existing != conflicting {
+                                self.conflicts.push(LoopInfoConflict {
+                                    loop_id: loop_key.0.clone(),
+                                    preceding_router: loop_key.1.clone(),
+                                    input_path: path_str.clone(),
+                                    field,
+                                    existing: *existing,
+                                    conflicting: *conflicting,
+                                });
+                            }
+                        }
+                    } else {
+                        self.storage.loop_information.insert(loop_key, info.clone());
                     }
                 }
             }
             Ok(())
         }
 
-        fn merge_shadowed_preceding(&mut self) -> Result<(), YarrpError> {
+        fn merge_shadowed_preceding(&mut self) -> Result<HashMap<(String, String), u64>, YarrpError> {
             info!("Attempting to merge shadowed preceding files!");
+            let mut seen_rows = HashSet::new();
+            let mut shadowed_counts = HashMap::new();
+
+            // rows of an existing output project are not written again
+            let output_shadowed = self.storage.get_storage_file(SHADOWED_PRECEDING_INFO)?;
+            if output_shadowed.exists() {
+                let mut reader = csv::Reader::from_path(&output_shadowed)?;
+                for result in reader.deserialize() {
+                    let value: ShadowedPreceding = result?;
+                    MergeIdMode::<T>::count_shadowed(&mut shadowed_counts, &value);
+                    seen_rows.insert(value);
+                }
+            }
+
             self.storage.create_shadowed_preceding_file_if_exists()?;
             let shadowed_writer;
             if let Some(temp_writer) = self.storage.shadowed_storage.borrow_mut() {
@@ -200,30 +367,46 @@ pub mod merge_id_mode {
                 exit(1);
             }
 
-            for path in &self.input_paths {
-                let existing_shadowed = path.join(SHADOWED_PRECEDING_INFO);
-                if !existing_shadowed.exists() {
-                    error!("Shadowed preceding file does not exist at {}", path.to_str().unwrap());
+            for project in &self.inputs {
+                if !project.file(SHADOWED_PRECEDING_INFO).exists() {
+                    error!("Shadowed preceding file does not exist at {}", project.path().to_str().unwrap_or(""));
+                    continue;
                 }
 
-                // open existing preceding file and parse records
-                let mut reader = csv::Reader::from_path(existing_shadowed)?;
-                for result in reader.deserialize() {
+                for result in project.shadowed_preceding()? {
                     if let Ok(value) = result {
-                        let value: ShadowedPreceding = value;
-                        shadowed_writer.serialize(value)?;
+                        if seen_rows.contains(&value) {
+                            self.duplicate_shadowed += 1;
+                            continue;
+                        }
+
+                        shadowed_writer.serialize(&value)?;
+                        MergeIdMode::<T>::count_shadowed(&mut shadowed_counts, &value);
+                        seen_rows.insert(value);
                     } else {
                         warn!("Could not deserialize value!");
                     }
                 }
             }
+            self.storage.commit_shadowed_preceding()?;
 
-            Ok(())
+            Ok(shadowed_counts)
+        }
+
+        fn count_shadowed(counts: &mut HashMap<(String, String), u64>, value: &ShadowedPreceding) {
+            let key = (value.loop_id.clone(), value.preceding_router.clone());
+            *counts.entry(key).or_insert(0) += 1;
         }
 
+        fn update_shadowed_nets(&mut self, shadowed_counts: &HashMap<(String, String), u64>) {
+            for (loop_key, info) in self.storage.loop_information.iter_mut() {
+                let info: &mut SimpleLoopOutput = info;
+                info.shadowed_nets = shadowed_counts.get(loop_key).cloned().unwrap_or(0);
+            }
+        }
     }
 
-    impl ModeTrait for MergeIdMode {
+    impl<T: Display + FromStr + Ord + Copy + Clone + Hash + IpAddrExt> ModeTrait for MergeIdMode<T> {
         fn get_mode(&self) -> ModeEnum {
             self.mode
         }
@@ -251,7 +434,7 @@ pub mod merge_id_mode {
             let loop_identifiers;
             let routers;
 
-            if let Ok(temp_loop_identifiers) = self.merge_identifiers(IDENTIFIERS) {
+            if let Ok(temp_loop_identifiers) = self.merge_loop_identifiers() {
                 loop_identifiers = temp_loop_identifiers;
             } else {
                 error!("Could not merge identifiers!");
@@ -259,36 +442,167 @@ pub mod merge_id_mode {
             }
 
             // merge routers
-            if let Ok(temp_routers) = self.merge_identifiers(ROUTERS) {
+            if let Ok(temp_routers) = self.merge_routers() {
                 routers = temp_routers;
             } else {
                 error!("Could not merge routers!");
                 exit(1);
             }
 
-            if let Err(_e) = self.merge_details(LOOPS, &loop_identifiers, "dest") {
+            // an incomplete merge does not replace an overwritten project
+            let mut complete = true;
+            if let Err(_e) = self.merge_details(LOOPS, &loop_identifiers, "dest", MergeIdMode::<T>::read_loop_destinations) {
                 error!("Could not merge destination details!");
+                complete = false;
             }
 
-            if let Err(_e) = self.merge_details(IMPERILED, &routers, "imp") {
+            if let Err(_e) = self.merge_details(IMPERILED, &routers, "imp", MergeIdMode::<T>::read_router_imperiled) {
                 error!("Could not merge destination details!");
+                complete = false;
+            }
+
+            if let Err(_e) = self.merge_loops_information() {
+                error!("Could not merge loops.csv files!");
+                complete = false;
             }
 
-            if let Err(_e) = self.merge_stats() {
+            match self.merge_shadowed_preceding() {
+                Ok(shadowed_counts) => self.update_shadowed_nets(&shadowed_counts),
+                Err(_e) => {
+                    error!("Could not merge shadowed_preceding.csv files!");
+                    complete = false;
+                }
+            }
+
+            if let Err(_e) = self.storage.store_loop_info() {
+                error!("Could not write loops.csv file!");
+                complete = false;
+            }
+
+            if let Err(_e) = self.merge_stats(&routers) {
                 error!("Could not merge stats.csv files!");
+                complete = false;
             }
 
-            if let Err(_e) = self.merge_loops_information() {
-                error!("Could not merge loops.csv files!");
+            if !complete && self.project_output.as_ref().map(|output| output.is_staged()).unwrap_or(false) {
+                warn!("Keeping the existing project, the incomplete merge in {} is discarded", self.output_path);
+                self.project_output = None;
             }
+        }
+
+        fn print_output(&self) {
+            println!("Skipped duplicate shadowed preceding rows: {}", self.duplicate_shadowed);
+            println!("Conflicting loop information entries: {}", self.conflicts.len());
 
-            if let Err(_e) = self.merge_shadowed_preceding() {
-                error!("Could not merge shadowed_preceding.csv files!");
+            if !self.conflicts.is_empty() {
+                println!("loop_id,preceding_router,input,field,merged_value,input_value");
+            }
+            for conflict in &self.conflicts {
+                println!("{},{},{},{},{},{}", conflict.loop_id, conflict.preceding_router, conflict.input_path,
+                         conflict.field, conflict.existing, conflict.conflicting);
+            }
+        }
+
+        fn close(&mut self) {
+            if let Some(project_output) = self.project_output.as_mut() {
+                if project_output.commit().is_err() {
+                    error!("Could not replace the existing project!");
+                    exit(1);
+                }
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::Ipv4Addr;
+    use std::path::Path;
+    use crate::analytics::Project;
+    use crate::modes::{MergeIdMode, ModeTrait};
+    use crate::structs::DetailFormat;
+    use crate::helpers::test_helper::{TempFixture, ProjectFixture, LOOPS_CSV_HEADER};
+
+    const SHADOWED_HEADER: &str = "shadowed_net,preceding_router,preceding_ttl,loop_id\n";
+
+    fn create_input(name: &str, destinations: &[&str], loop_stop_ttl: u8, only_full_routes: u8) -> TempFixture {
+        let project = ProjectFixture::empty(name)
+            .add_loop("aa", &["192.0.2.8", "192.0.2.9"])
+            .destinations("aa", destinations)
+            .loop_info(&format!("aa,192.0.2.7,{},2,8,{},7", destinations.len(), loop_stop_ttl))
+            .build();
+        project.write("imperiled/192.0.2.9.imp", "203.0.113.1\n");
+        project.write("shadowed_preceding.csv", destinations.iter()
+            .fold(SHADOWED_HEADER.to_string(), |rows, destination| format!("{}{},192.0.2.7,7,aa\n", rows, destination)));
+        project.write("stats.csv", format!("only_full_routes,{}\nroutes,10\nloops,{}\nloop_length_2,{}\n", only_full_routes, destinations.len(), destinations.len()));
+        project
+    }
+
+    fn stats_value(path: &Path, key: &str) -> String {
+        let prefix = format!("{},", key);
+        fs::read_to_string(path).unwrap().lines().find(|line| line.starts_with(&prefix)).unwrap()[prefix.len()..].to_string()
+    }
+
+    fn sorted_lines(path: &Path) -> Vec<String> {
+        let mut lines: Vec<String> = fs::read_to_string(path).unwrap().lines().map(String::from).collect();
+        lines.sort();
+        lines
+    }
+
+    fn merge(inputs: &[&TempFixture], output: &TempFixture) -> MergeIdMode<Ipv4Addr> {
+        let projects = inputs.iter().map(|input| Project::open(input.path()).ok().unwrap()).collect();
+        let mut mode = MergeIdMode::from_projects(projects, output.to_str().to_string(), DetailFormat::Text).ok().unwrap();
+        mode.do_calculations();
+        mode
+    }
+
+    #[test]
+    fn merge_and_append_without_duplicates() {
+        let first = create_input("merge_first", &["198.51.100.1", "198.51.100.2"], 9, 0);
+        let second = create_input("merge_second", &["198.51.100.2", "198.51.100.4"], 10, 0);
+        let output = TempFixture::new("merge_output");
+
+        let mode = merge(&[&first, &second], &output);
+        assert_eq!(mode.duplicate_shadowed(), 1);
+        assert_eq!(mode.num_conflicts(), 1, "loop_stop_ttl differs");
+        assert_eq!(sorted_lines(&output.join("loops/aa.dest")), vec!["198.51.100.1", "198.51.100.2", "198.51.100.4"]);
+        assert_eq!(sorted_lines(&output.join("imperiled/192.0.2.9.imp")), vec!["203.0.113.1"]);
+        assert_eq!(fs::read_to_string(output.join("loops.csv")).unwrap(), format!("{}aa,192.0.2.7,3,2,8,9,7\n", LOOPS_CSV_HEADER));
+        assert_eq!(stats_value(&output.join("stats.csv"), "routes"), "20");
+        assert_eq!(stats_value(&output.join("stats.csv"), "loops"), "3");
+        assert_eq!(stats_value(&output.join("stats.csv"), "loop_length_2"), "3");
+        assert_eq!(stats_value(&output.join("stats.csv"), "imperiled"), "1");
+
+        // appending to the merged project only adds rows it does not hold yet
+        let third = create_input("merge_third", &["198.51.100.1", "198.51.100.5"], 9, 0);
+        let mode = merge(&[&first, &third], &output);
+        assert_eq!(mode.duplicate_shadowed(), 3);
+        assert_eq!(sorted_lines(&output.join("loops/aa.dest")), vec!["198.51.100.1", "198.51.100.2", "198.51.100.4", "198.51.100.5"]);
+        assert_eq!(sorted_lines(&output.join("imperiled/192.0.2.9.imp")), vec!["203.0.113.1"]);
+        assert_eq!(sorted_lines(&output.join("shadowed_preceding.csv")).len(), 1 + 4);
+        assert_eq!(fs::read_to_string(output.join("identifiers.id")).unwrap(), "aa=192.0.2.8;192.0.2.9;\n");
+        assert_eq!(fs::read_to_string(output.join("loops.csv")).unwrap(), format!("{}aa,192.0.2.7,4,2,8,9,7\n", LOOPS_CSV_HEADER));
+
+        // the re-merged first input does not count its loops again
+        assert_eq!(stats_value(&output.join("stats.csv"), "loops"), "4");
+        assert_eq!(stats_value(&output.join("stats.csv"), "loop_length_2"), "4");
+        assert_eq!(stats_value(&output.join("stats.csv"), "imperiled"), "1");
+    }
+
+    #[test]
+    fn refuse_incompatible_projects() {
+        let full = create_input("merge_full", &["198.51.100.1"], 9, 1);
+        let partial = create_input("merge_partial", &["198.51.100.2"], 9, 0);
+        let output = TempFixture::new("merge_refused");
 
-        fn print_output(&self) {}
+        let projects = vec![Project::<Ipv4Addr>::open(full.path()).ok().unwrap(), Project::open(partial.path()).ok().unwrap()];
+        assert!(MergeIdMode::from_projects(projects, output.to_str().to_string(), DetailFormat::Text).is_err());
 
-        fn close(&mut self) {}
+        let v6 = TempFixture::dir("merge_v6");
+        v6.write("identifiers.id", "bb=2001:db8::8;2001:db8::9;\n");
+        v6.write("routers.id", "2001:db8::8=bb;\n2001:db8::9=bb;\n");
+        let projects = vec![Project::<Ipv4Addr>::open(partial.path()).ok().unwrap(), Project::open(v6.path()).ok().unwrap()];
+        assert!(MergeIdMode::from_projects(projects, output.to_str().to_string(), DetailFormat::Text).is_err());
     }
-}
\ No newline at end of file
+}
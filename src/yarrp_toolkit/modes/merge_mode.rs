@@ -1,15 +1,17 @@
 pub mod merge_mode {
     use clap::ArgMatches;
 
-    use crate::modes::{ModeEnum, ModeTrait};
+    use crate::modes::{ModeEnum, ModeTrait, load_output_policy};
     use std::collections::HashSet;
-    use std::fs::File;
+    use std::path::Path;
     use std::process::exit;
     use std::io::Write;
+    use crate::structs::{AtomicFile, OutputPolicy, stable_sorted};
 
     pub struct MergeMode {
         pub mode: ModeEnum,
         pub output_file: String,
+        output_policy: OutputPolicy,
         unique_set: HashSet<String>,
         ignored: u64,
     }
@@ -21,11 +23,18 @@ pub mod merge_mode {
             let sub_matches = matches.subcommand_matches(mode_string).unwrap();
 
             let output_file = sub_matches.value_of("output").unwrap().to_owned();
+
+            // the output holds a unique set, appending could duplicate lines
+            let output_policy = load_output_policy(&matches, false);
+            if output_policy.check(Path::new(&output_file)).is_err() {
+                exit(1);
+            }
             println!("Writing to outputfile {}", output_file);
 
             MergeMode {
                 mode,
                 output_file,
+                output_policy,
                 unique_set: HashSet::new(),
                 ignored: 0,
             }
@@ -64,7 +73,7 @@ pub mod merge_mode {
         fn print_output(&self) {
             println!("Added {}, ignored {}", self.unique_set.len(), self.ignored);
 
-            let output_file = File::create(&self.output_file);
+            let output_file = AtomicFile::open(Path::new(&self.output_file), self.output_policy);
             let mut output_file = match output_file {
                 Ok(file) => file,
                 Err(_) => {
@@ -73,7 +82,7 @@ pub mod merge_mode {
                 }
             };
 
-            for item in &self.unique_set {
+            for item in stable_sorted(&self.unique_set) {
                 let formatted_data = format!("{}\n", item);
                 let formatted_data = formatted_data.as_bytes();
                 if let Err(x) = output_file.write(formatted_data) {
@@ -82,6 +91,11 @@ pub mod merge_mode {
                     exit(1);
                 }
             }
+
+            if output_file.commit().is_err() {
+                eprintln!("Could not write the output file!");
+                exit(1);
+            }
         }
 
         fn close(&mut self) {
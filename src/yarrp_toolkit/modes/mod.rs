@@ -4,7 +4,7 @@ use std::str::FromStr;
 use clap::ArgMatches;
 use std::path::{PathBuf, Path};
 use std::process::exit;
-use log::{error};
+use log::{error, warn};
 
 mod reimagine_mode;
 mod stats_mode;
@@ -20,6 +20,14 @@ mod scatter_mode;
 mod p50_target_mode;
 mod p50_analysis;
 mod asn_mode;
+mod check_mode;
+mod query_mode;
+mod convert_mode;
+mod evidence_mode;
+mod watch_mode;
+mod asn_db_mode;
+mod coverage_mode;
+mod report_mode;
 
 pub use reimagine_mode::reimagine_mode::ReimagineMode;
 pub use stats_mode::stats_mode::StatsMode;
@@ -35,9 +43,18 @@ pub use scatter_mode::scatter_mode::ScatterMode;
 pub use p50_target_mode::p50_target_mode::P50TargetMode;
 pub use p50_analysis::p50_analysis::P50Analysis;
 pub use asn_mode::asn_mode::ASNMode;
+pub use check_mode::check_mode::CheckMode;
+pub use query_mode::query_mode::QueryMode;
+pub use convert_mode::convert_mode::ConvertMode;
+pub use evidence_mode::evidence_mode::EvidenceMode;
+pub use watch_mode::watch_mode::WatchMode;
+pub use asn_db_mode::asn_db_mode::ASNDatabaseMode;
+pub use coverage_mode::coverage_mode::CoverageMode;
+pub use report_mode::report_mode::ReportMode;
 use std::collections::HashSet;
 use ipnet::IpNet;
-use crate::structs::YarrpError;
+use crate::structs::{YarrpError, OutputPolicy};
+use crate::analytics::ASNAttribution;
 use crate::read_lines;
 
 
@@ -56,7 +73,15 @@ pub enum ModeEnum {
     Scatter,
     P50Target,
     P50Analysis,
-    ASN
+    ASN,
+    Check,
+    Query,
+    Convert,
+    Evidence,
+    Watch,
+    ASNDatabase,
+    Coverage,
+    Report
 }
 
 impl FromStr for ModeEnum {
@@ -79,6 +104,14 @@ impl FromStr for ModeEnum {
             "p50targets" => Ok(ModeEnum::P50Target),
             "p50analysis" => Ok(ModeEnum::P50Analysis),
             "asn" => Ok(ModeEnum::ASN),
+            "check" => Ok(ModeEnum::Check),
+            "query" => Ok(ModeEnum::Query),
+            "convert" => Ok(ModeEnum::Convert),
+            "evidence" => Ok(ModeEnum::Evidence),
+            "watch" => Ok(ModeEnum::Watch),
+            "asndb" => Ok(ModeEnum::ASNDatabase),
+            "coverage" => Ok(ModeEnum::Coverage),
+            "report" => Ok(ModeEnum::Report),
             _ => Err(())
         }
     }
@@ -100,7 +133,15 @@ impl Display for ModeEnum {
             ModeEnum::Scatter => "Scatter",
             ModeEnum::P50Target => "P50Targets",
             ModeEnum::P50Analysis => "P50Analysis",
-            ModeEnum::ASN => "ASN"
+            ModeEnum::ASN => "ASN",
+            ModeEnum::Check => "Check",
+            ModeEnum::Query => "Query",
+            ModeEnum::Convert => "Convert",
+            ModeEnum::Evidence => "Evidence",
+            ModeEnum::Watch => "Watch",
+            ModeEnum::ASNDatabase => "ASNDB",
+            ModeEnum::Coverage => "Coverage",
+            ModeEnum::Report => "Report"
         };
 
         write!(f, "{}", mode_enum_string)
@@ -143,6 +184,67 @@ pub fn  load_path_param(args: &ArgMatches, path: &str) -> PathBuf {
     return PathBuf::from(return_path);
 }
 
+/// reads the global output policy, exits if the mode cannot append to its output
+pub fn load_output_policy(args: &ArgMatches, append_supported: bool) -> OutputPolicy {
+    let policy = OutputPolicy::from_matches(args);
+    if policy == OutputPolicy::Append && !append_supported {
+        error!("This mode can not append to existing output, use --overwrite instead!");
+        exit(1);
+    }
+    return policy;
+}
+
+/// builds the ASN attribution from either a CAIDA prefix2as file (--routeviews) or an MRT RIB dump (--rib),
+/// through the compiled database (--asn_db) if it is up to date,
+/// optionally with a CAIDA as2org file (--as2org) for organisation level attribution,
+/// a CAIDA as-rel file (--as_rel) for AS relationships and a validator ROA export (--roas) for origin validation
+pub fn load_asn_attribution(args: &ArgMatches, root_net: &str) -> Result<ASNAttribution, YarrpError> {
+    let mut asn_attribution = ASNAttribution::new(root_net);
+
+    let source_path = match args.value_of("rib").or_else(|| args.value_of("routeviews")) {
+        Some(path) => path,
+        None => {
+            error!("Neither routeviews nor rib given!");
+            return Err(YarrpError::NotFoundError);
+        }
+    };
+
+    // a compiled database is only used if it was built from the same source, otherwise it is rebuilt
+    let database_path = args.value_of("asn_db");
+    let mut loaded = false;
+    if let Some(database_path) = database_path {
+        loaded = asn_attribution.load_asn_database(database_path, source_path)?;
+    }
+
+    if !loaded {
+        if args.is_present("rib") {
+            asn_attribution.load_mrt_rib(source_path)?;
+        } else {
+            asn_attribution.load_routeviews_bgp(source_path)?;
+        }
+
+        if let Some(database_path) = database_path {
+            if asn_attribution.write_asn_database(database_path, source_path).is_err() {
+                warn!("Could not write ASN database {}!", database_path);
+            }
+        }
+    }
+
+    if let Some(path) = args.value_of("as2org") {
+        asn_attribution.load_as2org(path)?;
+    }
+
+    if let Some(path) = args.value_of("as_rel") {
+        asn_attribution.load_as_relationships(path)?;
+    }
+
+    if let Some(path) = args.value_of("roas") {
+        asn_attribution.load_roas(path)?;
+    }
+
+    Ok(asn_attribution)
+}
+
 pub fn parse_param<T>(args: &ArgMatches, param_name: &str, default: T) -> T
     where T: FromStr {
     let param: T;
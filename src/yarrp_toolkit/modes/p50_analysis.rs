@@ -7,13 +7,14 @@ pub mod p50_analysis {
     use std::process::exit;
     use std::path::{PathBuf, Path};
     use std::collections::{HashMap, HashSet};
+    use itertools::sorted;
     use ipnet::IpNet;
 
     use crate::traits::IpAddrExt;
-    use crate::modes::{ModeTrait, ModeEnum, load_path_param, parse_param};
-    use crate::structs::{YarrpError, ZMAPLine, ZMAPClassification, ShadowedAnswer};
+    use crate::modes::{ModeTrait, ModeEnum, load_path_param, parse_param, load_output_policy};
+    use crate::structs::{YarrpError, ZMAPLine, ZMAPClassification, ShadowedAnswer, atomic_csv_writer, commit_csv};
     use crate::read_lines;
-    use crate::analytics::{LoopStorage, LOOPS};
+    use crate::analytics::{LoopStorage, Project};
 
     pub struct P50Analysis<T> {
         pub mode: ModeEnum,
@@ -50,6 +51,11 @@ pub mod p50_analysis {
             let full_scan_path = load_path_param(sub_matches, "full_scan");
             let persistent_loops_path = load_path_param(sub_matches, "persistent_loops");
 
+            // csv output can not be appended to
+            if load_output_policy(&matches, false).check(&output_path).is_err() {
+                exit(1);
+            }
+
             let file_limit = parse_param::<u64>(sub_matches, "file_limit", 0);
             let skip_files = parse_param::<u64>(sub_matches, "skip_files", 0);
 
@@ -251,20 +257,18 @@ pub mod p50_analysis {
             // if so, set the persistent flag to true
             info!("Adding persistence info to found loops");
 
-            let full_scan_storage;
-            if let Some(storage_path) = self.full_scan_path.to_str() {
-                full_scan_storage = LoopStorage::<T>::new(true, storage_path.to_string());
+            let full_scan_project;
+            if let Ok(project) = Project::<T>::open(&self.full_scan_path) {
+                full_scan_project = project;
             } else {
-                error!("Could not grab string for full scan path!");
+                error!("Could not open full scan project!");
                 exit(2);
             }
 
             let mut persistent_nets = 0;
 
             for loop_id in &self.persistent_loops {
-                let sub_file = format!("{}.dest", loop_id);
-                let loop_path = full_scan_storage.get_storage_sub_file(LOOPS, &sub_file)?;
-                let shadowed_nets = LoopStorage::<T>::read_details_file_as_t(&loop_path)?;
+                let shadowed_nets = full_scan_project.loop_destinations(loop_id)?;
 
                 for shadowed_net in shadowed_nets {
                     let mut prefix = shadowed_net.to_network_with_prefix_length(self.prefix_len)?;
@@ -283,9 +287,9 @@ pub mod p50_analysis {
         }
 
         fn write_responses(&self) -> Result<(), YarrpError> {
-            let mut writer = csv::Writer::from_path(&self.output_path)?;
+            let mut writer = atomic_csv_writer(&self.output_path)?;
 
-            for item in &self.original_targets {
+            for item in sorted(&self.original_targets) {
                 let answer;
                 if let Some(value) = self.shadowed_responses.get(item) {
                     answer = value.clone();
@@ -296,7 +300,7 @@ pub mod p50_analysis {
                 writer.serialize(answer)?;
             }
 
-            Ok(())
+            commit_csv(writer)
         }
     }
 
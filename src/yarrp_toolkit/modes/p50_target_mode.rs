@@ -1,24 +1,24 @@
 pub mod p50_target_mode {
     use clap::ArgMatches;
 
-    use crate::modes::{ModeEnum, ModeTrait};
+    use crate::modes::{ModeEnum, ModeTrait, load_output_policy};
     use std::process::exit;
     use log::{info, error};
     use rand::prelude::*;
-    use std::fs::File;
     use std::io::Write;
+    use std::path::Path;
     use std::marker::PhantomData;
     use std::str::FromStr;
     use crate::traits::IpAddrExt;
     use std::hash::Hash;
     use std::fmt::Display;
-    use crate::structs::YarrpError;
+    use crate::structs::{YarrpError, AtomicFile};
     use ipnet::{Ipv4Net, IpAdd};
 
     pub struct P50TargetMode<T> {
         pub mode: ModeEnum,
         pub target_prefix: u8,
-        output_file: File,
+        output_file: Option<AtomicFile>,
         file_number: u64,
         rng: rand_pcg::Lcg128Xsl64,
         ip_type: PhantomData<T>
@@ -47,8 +47,10 @@ pub mod p50_target_mode {
             info!("Storing address list at {}", output_file);
             let rng = rand_pcg::Pcg64::seed_from_u64(seed);
 
+            let output_policy = load_output_policy(&matches, true);
+
             let output_file_pointer;
-            if let Ok(output_file) = File::create(output_file) {
+            if let Ok(output_file) = AtomicFile::open(Path::new(&output_file), output_policy) {
                 output_file_pointer = output_file;
             } else {
                 error!("Could not open file!");
@@ -59,7 +61,7 @@ pub mod p50_target_mode {
                 mode,
                 target_prefix,
                 file_number,
-                output_file: output_file_pointer,
+                output_file: Some(output_file_pointer),
                 rng,
                 ip_type: PhantomData
             }
@@ -95,7 +97,7 @@ pub mod p50_target_mode {
         fn parse_string_line(&mut self, input: &str) {
             if let Ok(target_host) = self.create_target(input) {
                 let formatted = format!("{}\n", target_host);
-                if let Err(_) = self.output_file.write(formatted.as_bytes()) {
+                if let Err(_) = self.output_file.as_mut().unwrap().write(formatted.as_bytes()) {
                     error!("Could not write to output file!");
                     exit(1);
                 }
@@ -112,6 +114,12 @@ pub mod p50_target_mode {
         fn print_output(&self) {}
 
         fn close(&mut self) {
+            if let Some(output_file) = self.output_file.take() {
+                if output_file.commit().is_err() {
+                    error!("Could not write output file!");
+                    exit(1);
+                }
+            }
             self.clear();
         }
     }
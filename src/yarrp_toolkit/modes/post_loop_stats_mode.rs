@@ -4,23 +4,25 @@ pub mod post_loop_stats_mode {
     use log::{error, info, trace, debug};
     use std::collections::{HashMap, HashSet};
     use itertools::{sorted};
-    use std::fs::{File};
     use std::hash::Hash;
     use std::fmt::Display;
     use std::str::FromStr;
     use std::io::Write;
     use std::time::SystemTime;
 
-    use crate::modes::{ModeTrait, ModeEnum};
-    use crate::structs::{YarrpError, MapSetString, MapSetT, MapVecT, LoopDensityOutput, ASNShadowedResults};
+    use crate::modes::{ModeTrait, ModeEnum, load_output_policy, load_asn_attribution, parse_param};
+    use crate::structs::{AtomicFile, atomic_csv_writer, commit_csv};
+    use crate::structs::{YarrpError, MapSetString, MapSetT, MapVecT, LoopDensityOutput, ASNShadowedResults, stable_sorted};
+    use crate::structs::{ASNOriginKind, MapASNMembers, MapOrigin, MapSetOrigin, ShadowedPrecedingCounter, flatten_origins, merge_origins};
     use crate::traits::IpAddrExt;
-    use crate::analytics::{LoopStorage, STATS, LoopStatistics, ASNAttribution};
+    use crate::analytics::{LoopStorage, ASNAttribution, GeoAttribution, PtrNames, LoopAmplification, LoopIncidents, impact_score, registered_domain, Project, LoopASRelationship, LOOP_AS_RELATIONSHIPS, RpkiState, RPKI_STATES};
     use crate::analytics::{ROUTERS, IDENTIFIERS, IMPERILED, LOOPS};
-    use std::path::{Path};
+    use std::path::{Path, PathBuf};
 
     pub struct PostLoopStatsMode<T> {
         pub mode: ModeEnum,
         pub loop_storage: LoopStorage<T>,
+        pub project: Project<T>,
         target_file: String,
         target_number: u64,
         target_take_all: bool,
@@ -29,19 +31,37 @@ pub mod post_loop_stats_mode {
         print_all_output: bool,
         persistent_loops: HashSet<String>,
         persistent_routers: HashSet<String>,
-        pub loop_members: MapSetString,
+        pub loop_members: MapSetT<T>,
         pub loop_destinations: MapVecT<T>,
         // <T> needed for target generation, extract that to an additional module?
         pub unique_loop_lengths: HashMap<u8, u64>,
-        pub router_loops: MapSetString,
-        pub router_shadowed: HashMap<String, u64>,
-        pub router_imperiled: MapSetT<T>,
+        pub router_loops: HashMap<T, HashSet<String>>,
+        pub router_shadowed: HashMap<T, u64>,
+        pub router_imperiled: HashMap<T, HashSet<T>>,
         pub asn_attribution: ASNAttribution,
-        asn_to_routers: MapSetString,
-        routers_to_asn: MapSetString,
-        asn_to_loops: MapSetString,
-        loops_to_asn: MapSetString,
+        asn_to_routers: MapASNMembers,
+        routers_to_asn: MapOrigin,
+        asn_to_loops: MapASNMembers,
+        loops_to_asn: MapSetOrigin,
         shadowed_to_asn_numbers: ASNShadowedResults,
+        org_to_routers: MapASNMembers,
+        routers_to_org: MapOrigin,
+        org_to_loops: MapASNMembers,
+        loops_to_org: MapSetOrigin,
+        shadowed_to_org_numbers: Option<ASNShadowedResults>,
+        shadowed_per_org: HashMap<String, u64>,
+        loops_as_relationship: HashMap<String, LoopASRelationship>,
+        routers_rpki: HashMap<String, RpkiState>,
+        geo_attribution: Option<GeoAttribution>,
+        routers_to_country: HashMap<String, String>,
+        loops_to_country: MapSetString,
+        shadowed_per_country: HashMap<String, u64>,
+        ptr_path: Option<PathBuf>,
+        ptr_names: Option<PtrNames>,
+        loops_shared_domain: HashMap<String, String>,
+        loop_amplification: Option<LoopAmplification>,
+        top_loops: usize,
+        loop_incidents: Option<LoopIncidents>,
         num_imperiled: u64,
         skip_densities: bool
     }
@@ -53,6 +73,7 @@ pub mod post_loop_stats_mode {
             let sub_matches = matches.subcommand_matches(mode_string).unwrap();
 
             let loop_storage;
+            let project;
             let target_file;
             let target_number: u64;
             let target_take_all: bool;
@@ -60,6 +81,12 @@ pub mod post_loop_stats_mode {
 
             if let Some(line) = sub_matches.value_of("project_path") {
                 let project_path = line.to_string();
+                if let Ok(temp_project) = Project::open(Path::new(&project_path)) {
+                    project = temp_project;
+                } else {
+                    error!("Could not open project!");
+                    exit(1);
+                }
                 loop_storage = LoopStorage::new(true, project_path);
             } else {
                 error!("Could not read routers parameter!");
@@ -114,11 +141,12 @@ pub mod post_loop_stats_mode {
                 target_number = 0;
             }
 
-            let routeviews_path;
-            if let Some(path) = sub_matches.value_of("routeviews") {
-                routeviews_path = path.to_string();
-            } else {
-                error!("Could not parse routeviews path!");
+            // postloop_stats.csv marks an earlier run, its csv reports can not be appended to
+            let output_policy = load_output_policy(&matches, false);
+            if output_policy.check(&project.path().join("postloop_stats.csv")).is_err() {
+                exit(1);
+            }
+            if target_file.len() > 0 && output_policy.check(Path::new(&target_file)).is_err() {
                 exit(1);
             }
 
@@ -138,12 +166,35 @@ pub mod post_loop_stats_mode {
                 exit(1);
             }
 
-            let mut asn_attribution = ASNAttribution::new(&T::root_net());
-            if let Err(_) = asn_attribution.load_routeviews_bgp(&routeviews_path) {
+            let asn_attribution;
+            if let Ok(value) = load_asn_attribution(sub_matches, &T::root_net()) {
+                asn_attribution = value;
+            } else {
                 error!("Could not load BGP data for asn attribution!");
                 exit(1);
             }
 
+            let mut geo_attribution = None;
+            if let Some(path) = sub_matches.value_of("geo") {
+                let mut value = GeoAttribution::new(&T::root_net());
+                if value.load(Path::new(path)).is_err() {
+                    error!("Could not load geolocation data!");
+                    exit(1);
+                }
+                geo_attribution = Some(value);
+            }
+
+            // PTR dumps are large, only the names of the project routers are loaded later on
+            let ptr_path = sub_matches.value_of("ptr").map(PathBuf::from);
+            if let Some(path) = &ptr_path {
+                if !path.exists() {
+                    error!("PTR file {} does not exist!", path.display());
+                    exit(1);
+                }
+            }
+
+            let top_loops = parse_param(sub_matches, "top_loops", 20);
+
             let persistent_loops;
             if let Ok(set) = PostLoopStatsMode::<T>::read_persistent_loops(persistent_loops_path) {
                 persistent_loops = set;
@@ -165,12 +216,18 @@ pub mod post_loop_stats_mode {
                 shadowed_asn_is_not_with_loop: 0,
                 shadowed_asn_is_unknown: 0,
                 shadowed_asn_with_single_asn: 0,
-                shadowed_asn_with_multiple_asn: 0
+                shadowed_asn_with_multiple_asn: 0,
+                shadowed_asn_is_moas: 0,
+                shadowed_asn_is_as_set: 0,
+                shadowed_rpki_valid: 0,
+                shadowed_rpki_invalid: 0,
+                shadowed_rpki_unknown: 0
             };
 
             PostLoopStatsMode {
                 mode,
                 loop_storage,
+                project,
                 target_file,
                 target_number,
                 target_take_all,
@@ -191,17 +248,31 @@ pub mod post_loop_stats_mode {
                 asn_to_loops: Default::default(),
                 loops_to_asn: Default::default(),
                 shadowed_to_asn_numbers,
+                org_to_routers: Default::default(),
+                routers_to_org: Default::default(),
+                org_to_loops: Default::default(),
+                loops_to_org: Default::default(),
+                shadowed_to_org_numbers: None,
+                shadowed_per_org: Default::default(),
+                loops_as_relationship: Default::default(),
+                routers_rpki: Default::default(),
+                geo_attribution,
+                routers_to_country: Default::default(),
+                loops_to_country: Default::default(),
+                shadowed_per_country: Default::default(),
+                ptr_path,
+                ptr_names: None,
+                loops_shared_domain: Default::default(),
+                loop_amplification: None,
+                top_loops,
+                loop_incidents: None,
                 num_imperiled: 0,
                 skip_densities
             }
         }
 
         fn read_loop_identifiers(&mut self) -> Result<(), YarrpError> {
-            info!("Reading loop identifiers from file");
-            let loop_file = self.loop_storage.get_storage_file(IDENTIFIERS)?;
-            let loop_identifiers = LoopStorage::<T>::read_id_file(&loop_file)?;
-
-            self.loop_members = loop_identifiers;
+            self.loop_members = self.project.loop_members()?.clone();
             for (_identifier, members) in &self.loop_members {
                 if members.len() >= 255 {
                     error!("Loop length is above 254, input file might be corrupt or contains error!");
@@ -225,10 +296,7 @@ pub mod post_loop_stats_mode {
         }
 
         fn read_router_associations(&mut self) -> Result<(), YarrpError> {
-            info!("Reading router associations from file");
-            let router_file = self.loop_storage.get_storage_file(ROUTERS)?;
-            let routers = LoopStorage::<T>::read_id_file(&router_file)?;
-            self.router_loops = routers;
+            self.router_loops = self.project.router_loops()?.clone();
             Ok(())
         }
 
@@ -237,27 +305,19 @@ pub mod post_loop_stats_mode {
             let mut read_files = 0;
 
             for key in self.loop_members.keys() {
-                let filename = format!("{}.dest", key);
-                let file_path = self.loop_storage.get_storage_sub_file(LOOPS, &filename)?;
+                let file_path = self.project.loop_destinations_path(key);
 
                 if !file_path.exists() {
                     error!("File does not exist!");
                     continue;
                 }
 
-                let file_path_str;
-                if let Some(string) = file_path.to_str() {
-                    file_path_str = string;
-                } else {
-                    file_path_str = "";
-                }
-
-                trace!("Trying to read from details file {}", file_path_str);
+                trace!("Trying to read from details file {}", file_path.to_str().unwrap_or(""));
 
-                if let Ok(lines) = LoopStorage::<T>::read_details_file_as_t(&file_path) {
+                if let Ok(lines) = self.project.loop_destinations(key) {
                     self.loop_destinations.insert(key.clone(), lines);
                 } else {
-                    error!("Could not read file {}", file_path_str);
+                    error!("Could not read file {}", file_path.to_str().unwrap_or(""));
                 }
                 read_files += 1;
             }
@@ -269,33 +329,25 @@ pub mod post_loop_stats_mode {
             let mut read_files = 0;
             let mut total_hashset = HashSet::<T>::new();
 
-            let dir_path = &self.loop_storage.get_storage_file(IMPERILED)?;
-            if !dir_path.exists() {
+            if !self.project.file(IMPERILED).exists() {
                 info!("Imperiled sub directory does not exist, skipping imperiled stats!");
             }
 
             info!("Reading amount of destinations imperiled by a router!");
-            for router in self.router_loops.keys() {
-                let filename = format!("{}.imp", router);
-                let file_path = dir_path.join(&filename);
+            for router in self.project.router_loops()?.keys() {
+                let file_path = self.project.router_imperiled_path(router);
 
                 if !file_path.exists() {
                     continue;
                 }
 
-                let file_path_str;
-                if let Some(string) = file_path.to_str() {
-                    file_path_str = string;
-                } else {
-                    file_path_str = "";
-                }
-                trace!("Trying to read from details file {}", file_path_str);
+                trace!("Trying to read from details file {}", file_path.to_str().unwrap_or(""));
 
-                if let Ok(lines) = LoopStorage::<T>::read_details_file_as_t_ret_set(&file_path) {
+                if let Ok(lines) = self.project.router_imperiled(router) {
                     total_hashset.extend(&lines);
-                    self.router_imperiled.insert(router.clone(), lines);
+                    self.router_imperiled.insert(*router, lines);
                 } else {
-                    error!("Could not read file {}", file_path_str);
+                    error!("Could not read file {}", file_path.to_str().unwrap_or(""));
                 }
 
                 read_files += 1;
@@ -327,7 +379,7 @@ pub mod post_loop_stats_mode {
                         shadowed_ips += shadowed.len() as u64;
                     }
                 }
-                self.router_shadowed.insert(key.clone(), shadowed_ips);
+                self.router_shadowed.insert(*key, shadowed_ips);
             }
         }
 
@@ -345,7 +397,9 @@ pub mod post_loop_stats_mode {
             let mut duplicate_upper = 0;
             let mut not_added_enough = 0;
 
-            for (_loopid, destinations) in &self.loop_destinations {
+            // loops are visited in a stable order as the selected targets depend on the prefixes taken before
+            for loop_id in stable_sorted(self.loop_destinations.keys()) {
+                let destinations = &self.loop_destinations[loop_id];
                 let dest_number = destinations.len() as u64;
 
                 if self.target_take_all {
@@ -443,15 +497,140 @@ pub mod post_loop_stats_mode {
             self.loops_to_asn = l2a;
 
             let asn_path = self.loop_storage.get_storage_sub_file("asn", "shadowed_asn.csv")?;
-            let results = self.asn_attribution.build_shadowed_asn_to_loop(&self.loop_destinations, &self.loops_to_asn, &asn_path)?;
+            let results = self.asn_attribution.build_shadowed_asn_to_loop(&self.loop_destinations, &self.loops_to_asn, &asn_path, self.geo_attribution.as_ref())?;
             self.shadowed_to_asn_numbers = results;
 
+            // AS relationship classification only with as-rel data
+            for (loop_id, origins) in &self.loops_to_asn {
+                if let Some(relationship) = self.asn_attribution.classify_loop(origins) {
+                    self.loops_as_relationship.insert(loop_id.clone(), relationship);
+                }
+            }
+
+            // origin validation of the prefix covering each router only with ROAs
+            if self.asn_attribution.has_roas() {
+                for router in self.router_loops.keys() {
+                    if let Some(state) = self.asn_attribution.get_rpki_state_for_ip(&router.to_ipaddr()?) {
+                        self.routers_rpki.insert(router.to_string(), state);
+                    }
+                }
+            }
+
+            // organisation level attribution only with as2org data
+            if let Some((o2r, r2o)) = self.asn_attribution.build_routers_to_org(&self.routers_to_asn) {
+                self.org_to_routers = o2r;
+                self.routers_to_org = r2o;
+
+                let (o2l, l2o) = self.asn_attribution.build_loops_to_asn(&self.loop_members, &self.routers_to_org)?;
+                self.org_to_loops = o2l;
+                self.loops_to_org = l2o;
+
+                let org_path = self.loop_storage.get_storage_sub_file("asn", "shadowed_org.csv")?;
+                let (results, shadowed_per_org) = self.asn_attribution.build_shadowed_org_to_loop(&self.loop_destinations, &self.loops_to_org, &org_path, self.geo_attribution.as_ref())?;
+                self.shadowed_to_org_numbers = Some(results);
+                self.shadowed_per_org = shadowed_per_org;
+            }
+
+            Ok(())
+        }
+
+        /// country of each router, the countries of each loop's members and the shadowed nets per country
+        fn load_geo_attribution(&mut self) -> Result<(), YarrpError> {
+            let geo_attribution = match &self.geo_attribution {
+                Some(geo_attribution) => geo_attribution,
+                None => return Ok(())
+            };
+
+            for router in self.router_loops.keys() {
+                if let Some(country) = geo_attribution.get_country_for_ip(&router.to_ipaddr()?) {
+                    self.routers_to_country.insert(router.to_string(), country);
+                }
+            }
+
+            for (loop_id, members) in &self.loop_members {
+                let countries = members.iter().filter_map(|member| self.routers_to_country.get(&member.to_string()).cloned()).collect();
+                self.loops_to_country.insert(loop_id.clone(), countries);
+            }
+
+            for shadowed in self.loop_destinations.values() {
+                for shadowed_net in shadowed {
+                    if let Some(country) = geo_attribution.get_country_for_ip(&shadowed_net.to_ipaddr()?) {
+                        *self.shadowed_per_country.entry(country).or_insert(0) += 1;
+                    }
+                }
+            }
+
+            let path = self.loop_storage.get_storage_file("country.csv")?;
+            geo_attribution.write_country_csv(&path, &self.routers_to_country, &self.loops_to_country, &self.shadowed_per_country)
+        }
+
+        /// PTR names of all routers and the registered domain shared by the members of each loop
+        fn load_ptr_names(&mut self) -> Result<(), YarrpError> {
+            let ptr_path = match &self.ptr_path {
+                Some(ptr_path) => ptr_path,
+                None => return Ok(())
+            };
+
+            let mut routers = HashSet::new();
+            for router in self.router_loops.keys() {
+                routers.insert(router.to_ipaddr()?);
+            }
+            let ptr_names = PtrNames::load(ptr_path, &routers)?;
+
+            for (loop_id, members) in &self.loop_members {
+                if let Some(domain) = ptr_names.shared_domain(members) {
+                    self.loops_shared_domain.insert(loop_id.clone(), domain);
+                }
+            }
+
+            self.ptr_names = Some(ptr_names);
+            Ok(())
+        }
+
+        /// amplification of each loop from the loop information of the project, before loops.csv is replaced
+        fn load_loop_amplification(&mut self) -> Result<(), YarrpError> {
+            let loop_amplification = LoopAmplification::new(self.project.loop_info()?);
+            if loop_amplification.is_empty() {
+                return Ok(());
+            }
+
+            let shadowed: HashMap<String, u64> = self.loop_destinations.iter()
+                .map(|(loop_id, destinations)| (loop_id.clone(), destinations.len() as u64))
+                .collect();
+            let path = self.loop_storage.get_storage_file("impact.csv")?;
+            loop_amplification.write_impact_csv(&path, &shadowed, &self.persistent_loops, self.top_loops)?;
+
+            self.loop_amplification = Some(loop_amplification);
             Ok(())
         }
 
+        fn get_hostname(&self, router: &str) -> String {
+            self.ptr_names.as_ref()
+                .and_then(|ptr_names| ptr_names.get_hostname(router))
+                .unwrap_or("")
+                .to_string()
+        }
+
+        /// one line per loop member with its PTR name and registered domain
+        fn write_loop_members_csv(&self) -> Result<(), YarrpError> {
+            let path = self.loop_storage.get_storage_file("loop_members.csv")?;
+            let mut csv_writer = atomic_csv_writer(&path)?;
+            csv_writer.write_record(&["loop", "router", "hostname", "domain"])?;
+
+            for loop_id in stable_sorted(self.loop_members.keys()) {
+                for router in sorted(&self.loop_members[loop_id]) {
+                    let router = router.to_string();
+                    let hostname = self.get_hostname(&router);
+                    let domain = registered_domain(&hostname).unwrap_or_default();
+                    csv_writer.write_record(&[loop_id, &router, &hostname, &domain])?;
+                }
+            }
+            commit_csv(csv_writer)
+        }
+
         fn store_targets_to_file(&mut self) {
-            if let Ok(mut output_file) = File::create(&self.target_file) {
-                for line in &self.target_destinations {
+            if let Ok(mut output_file) = AtomicFile::create(Path::new(&self.target_file)) {
+                for line in sorted(&self.target_destinations) {
                     let format_string = format!("{}\n", line);
                     if let Err(x) = output_file.write(format_string.as_bytes()) {
                         eprintln!("Could not write to output file!");
@@ -459,6 +638,10 @@ pub mod post_loop_stats_mode {
                         exit(1);
                     }
                 }
+                if output_file.commit().is_err() {
+                    error!("Could not write target file!");
+                    exit(1);
+                }
             } else {
                 error!("Could not open or create output file!");
             }
@@ -495,42 +678,44 @@ pub mod post_loop_stats_mode {
         fn create_shadowed_density(&self, prefix_edge: u64, octets: usize) -> Result<(), YarrpError> {
             info!("Creating shadowed density!");
             let csv_output = self.loop_storage.get_storage_file("loop_shadowed_density.csv")?;
-            let mut writer = csv::Writer::from_path(csv_output)?;
+            let mut writer = atomic_csv_writer(&csv_output)?;
 
-            for (loop_id, shadowed_nets) in &self.loop_destinations {
+            for loop_id in stable_sorted(self.loop_destinations.keys()) {
+                let shadowed_nets = &self.loop_destinations[loop_id];
                 let num_nets = shadowed_nets.len() as u64;
                 let same_bits = PostLoopStatsMode::<T>::count_bit_overlap_vec(shadowed_nets, prefix_edge, octets)?;
                 let storage = PostLoopStatsMode::<T>::create_loop_density_output(&self.persistent_loops, loop_id, num_nets, same_bits, prefix_edge)?;
 
                 writer.serialize(storage)?;
             }
-            Ok(())
+            commit_csv(writer)
         }
 
         fn create_imperiled_density(&self, prefix_edge: u64, octets: usize) -> Result<(), YarrpError> {
             info!("Creating imperiled density!");
 
             let csv_output = self.loop_storage.get_storage_file("loop_imperiled_density.csv")?;
-            let mut writer = csv::Writer::from_path(csv_output)?;
+            let mut writer = atomic_csv_writer(&csv_output)?;
 
-            for (loop_id, routers) in &self.loop_members {
+            for loop_id in stable_sorted(self.loop_members.keys()) {
+                let routers = &self.loop_members[loop_id];
                 debug!("Starting for loop {}", loop_id);
                 let start = SystemTime::now();
 
                 // grab largest imperiled packet and clone it
                 let mut max_imp = 0;
-                let mut max_router = "";
+                let mut max_router = None;
                 for router in routers {
                     if let Some(router_imperiled) = self.router_imperiled.get(router) {
                         if router_imperiled.len() > max_imp {
                             max_imp = router_imperiled.len();
-                            max_router = router;
+                            max_router = Some(router);
                         }
                     }
                 }
 
                 let mut loop_imperiled;
-                if max_router.len() > 0 {
+                if let Some(max_router) = max_router {
                     if let Some(router_imperiled) = self.router_imperiled.get(max_router) {
                         loop_imperiled = router_imperiled.clone();
                     } else {
@@ -564,25 +749,27 @@ pub mod post_loop_stats_mode {
                 let dur = write_done.duration_since(dens_done)?.as_millis();
                 debug!("Written to disk for loop {} in {}", loop_id, dur);
             }
-            Ok(())
+            commit_csv(writer)
         }
 
         fn create_imperiled_density_by_router(&self, prefix_edge: u64, octets: usize) -> Result<(), YarrpError> {
             info!("Creating imperiled densities by router!");
             let csv_output = self.loop_storage.get_storage_file("router_imperiled_density.csv")?;
-            let mut writer = csv::Writer::from_path(csv_output)?;
+            let mut writer = atomic_csv_writer(&csv_output)?;
 
-            for (router, imperiled) in &self.router_imperiled {
+            for router in sorted(self.router_imperiled.keys()) {
+                let imperiled = &self.router_imperiled[router];
+                let router = router.to_string();
                 let same_bits = PostLoopStatsMode::<T>::count_bit_overlap_set(imperiled, prefix_edge, octets)?;
                 let num_nets = imperiled.len() as u64;
 
                 let dif = prefix_edge - same_bits;
                 let density = (num_nets as f64) / 2_i32.pow(dif as u32) as f64;
 
-                let persistent = self.persistent_routers.contains(router);
+                let persistent = self.persistent_routers.contains(&router);
 
                 let storage = LoopDensityOutput {
-                    loop_id: router.to_string(),
+                    loop_id: router,
                     address_count: num_nets,
                     same_bits,
                     density,
@@ -590,7 +777,7 @@ pub mod post_loop_stats_mode {
                 };
                 writer.serialize(storage)?;
             }
-            Ok(())
+            commit_csv(writer)
         }
 
         fn create_loop_density_output(persistent_loops: &HashSet<String>, loop_id: &str, num_nets: u64, same_bits: u64, prefix_edge: u64) -> Result<LoopDensityOutput, YarrpError> {
@@ -701,11 +888,10 @@ pub mod post_loop_stats_mode {
         }
 
         fn write_post_loop_stats(&self) -> Result<(), YarrpError> {
-            let input_path = self.loop_storage.get_storage_file(STATS)?;
-            let input_stats = LoopStatistics::new(true, input_path);
+            let input_stats = self.project.stats()?;
 
             let path = self.loop_storage.get_storage_file("postloop_stats.csv")?;
-            let mut csv_writer = csv::Writer::from_path(path)?;
+            let mut csv_writer = atomic_csv_writer(&path)?;
 
             if let Err(_) = csv_writer.write_record(&["key", "value"]) {
                 error!("Could not write header row for postloop_stats.csv!");
@@ -734,13 +920,15 @@ pub mod post_loop_stats_mode {
             let _ = csv_writer.write_record(&["total_router_asn", &router_asn.to_string()])?;
 
             let (total_loop_stats, total_loops) = self.create_total_loops()?;
-            for (loop_len, nr_loops) in &total_loop_stats {
+            for loop_len in sorted(total_loop_stats.keys()) {
+                let nr_loops = &total_loop_stats[loop_len];
                 let key = format!("total_loop_len_{}", loop_len);
                 let _ = csv_writer.write_record(&[&key, &nr_loops.to_string()])?;
             }
             csv_writer.write_record(&["total_loop_len_sum", &total_loops.to_string()])?;
 
-            for (loop_len, nr_loops) in &self.unique_loop_lengths {
+            for loop_len in sorted(self.unique_loop_lengths.keys()) {
+                let nr_loops = &self.unique_loop_lengths[loop_len];
                 let key = format!("unique_loop_len_{}", loop_len);
                 let _ = csv_writer.write_record(&[&key, &nr_loops.to_string()])?;
             }
@@ -750,9 +938,84 @@ pub mod post_loop_stats_mode {
             let _ = csv_writer.write_record(&["shadowed_asn_is_not_with_loop", &self.shadowed_to_asn_numbers.shadowed_asn_is_not_with_loop.to_string()])?;
             let _ = csv_writer.write_record(&["shadowed_asn_with_single_asn", &self.shadowed_to_asn_numbers.shadowed_asn_with_single_asn.to_string()])?;
             let _ = csv_writer.write_record(&["shadowed_asn_with_multiple_asn", &self.shadowed_to_asn_numbers.shadowed_asn_with_multiple_asn.to_string()])?;
+            let _ = csv_writer.write_record(&["shadowed_asn_is_moas", &self.shadowed_to_asn_numbers.shadowed_asn_is_moas.to_string()])?;
+            let _ = csv_writer.write_record(&["shadowed_asn_is_as_set", &self.shadowed_to_asn_numbers.shadowed_asn_is_as_set.to_string()])?;
             let _ = csv_writer.write_record(&["shadowed_asn_is_unknown", &self.shadowed_to_asn_numbers.shadowed_asn_is_unknown.to_string()])?;
 
-            Ok(())
+            if !self.loops_as_relationship.is_empty() {
+                let mut relationship_counts: HashMap<LoopASRelationship, u64> = HashMap::new();
+                for relationship in self.loops_as_relationship.values() {
+                    *relationship_counts.entry(*relationship).or_insert(0) += 1;
+                }
+
+                for relationship in LOOP_AS_RELATIONSHIPS.iter() {
+                    let key = format!("as_rel_loops_{}", relationship);
+                    let _ = csv_writer.write_record(&[&key, &relationship_counts.get(relationship).unwrap_or(&0).to_string()])?;
+                }
+            }
+
+            if self.asn_attribution.has_roas() {
+                let mut rpki_counts: HashMap<RpkiState, u64> = HashMap::new();
+                for state in self.routers_rpki.values() {
+                    *rpki_counts.entry(*state).or_insert(0) += 1;
+                }
+
+                for state in RPKI_STATES.iter() {
+                    let key = format!("router_rpki_{}", state);
+                    let _ = csv_writer.write_record(&[&key, &rpki_counts.get(state).unwrap_or(&0).to_string()])?;
+                }
+                let _ = csv_writer.write_record(&["shadowed_rpki_valid", &self.shadowed_to_asn_numbers.shadowed_rpki_valid.to_string()])?;
+                let _ = csv_writer.write_record(&["shadowed_rpki_invalid", &self.shadowed_to_asn_numbers.shadowed_rpki_invalid.to_string()])?;
+                let _ = csv_writer.write_record(&["shadowed_rpki_unknown", &self.shadowed_to_asn_numbers.shadowed_rpki_unknown.to_string()])?;
+            }
+
+            if let Some(ptr_names) = &self.ptr_names {
+                let _ = csv_writer.write_record(&["ptr_named_routers", &ptr_names.len().to_string()])?;
+                let _ = csv_writer.write_record(&["shared_domain_loops", &self.loops_shared_domain.len().to_string()])?;
+            }
+
+            if let Some(loop_incidents) = &self.loop_incidents {
+                let _ = csv_writer.write_record(&["incidents", &loop_incidents.len().to_string()])?;
+                let _ = csv_writer.write_record(&["multi_loop_incidents", &loop_incidents.num_multi_loop().to_string()])?;
+                let _ = csv_writer.write_record(&["persistent_incidents", &loop_incidents.num_persistent(&self.persistent_loops).to_string()])?;
+                let _ = csv_writer.write_record(&["incident_communities", &loop_incidents.num_communities().to_string()])?;
+            }
+
+            if let Some(org_numbers) = &self.shadowed_to_org_numbers {
+                let (single_org, multi_org, ambiguous_org) = self.count_org_loops();
+                let _ = csv_writer.write_record(&["total_router_org", &self.org_to_routers.len().to_string()])?;
+                let _ = csv_writer.write_record(&["single_org_loops", &single_org.to_string()])?;
+                let _ = csv_writer.write_record(&["multi_org_loops", &multi_org.to_string()])?;
+                let _ = csv_writer.write_record(&["ambiguous_org_loops", &ambiguous_org.to_string()])?;
+                let _ = csv_writer.write_record(&["shadowed_org_is_with_loop", &org_numbers.shadowed_asn_is_with_loop.to_string()])?;
+                let _ = csv_writer.write_record(&["shadowed_org_is_not_with_loop", &org_numbers.shadowed_asn_is_not_with_loop.to_string()])?;
+                let _ = csv_writer.write_record(&["shadowed_org_with_single_org", &org_numbers.shadowed_asn_with_single_asn.to_string()])?;
+                let _ = csv_writer.write_record(&["shadowed_org_with_multiple_org", &org_numbers.shadowed_asn_with_multiple_asn.to_string()])?;
+                let _ = csv_writer.write_record(&["shadowed_org_is_unknown", &org_numbers.shadowed_asn_is_unknown.to_string()])?;
+            }
+
+            commit_csv(csv_writer)
+        }
+
+        /// loops within a single organisation, spanning multiple organisations and
+        /// within one MOAS prefix or AS set of multiple organisations
+        fn count_org_loops(&self) -> (u64, u64, u64) {
+            let mut single_org = 0;
+            let mut multi_org = 0;
+            let mut ambiguous_org = 0;
+
+            for origins in self.loops_to_org.values() {
+                // origins sharing an organisation are one domain, ambiguous if it holds several organisations
+                let domains = merge_origins(origins);
+                match domains.as_slice() {
+                    [] => {}
+                    [domain] if domain.len() == 1 => single_org += 1,
+                    [_domain] => ambiguous_org += 1,
+                    _ => multi_org += 1,
+                }
+            }
+
+            (single_org, multi_org, ambiguous_org)
         }
 
         fn print_loops(len_map: &HashMap<u8, u64>, total: &u64) {
@@ -772,20 +1035,23 @@ pub mod post_loop_stats_mode {
         fn write_routers_csv(&self) -> Result<(), YarrpError> {
             // routers -> (nr loops involved, nr shadowed, nr imperiled)
             let path = self.loop_storage.get_storage_file("routers.csv")?;
-            let mut csv_writer = csv::Writer::from_path(path)?;
-            if let Err(_) = csv_writer.write_record(&["router", "loops", "shadowed", "imperiled", "asn"]) {
+            let mut csv_writer = atomic_csv_writer(&path)?;
+            if let Err(_) = csv_writer.write_record(&["router", "loops", "shadowed", "imperiled", "asn", "origin_type", "rpki_state", "country", "hostname"]) {
                 error!("Could not write header row for routers.csv!");
                 return Err(YarrpError::CouldNotWriteError);
             }
 
-            let mut count_vec: Vec<(&String, &u64)> = self.router_shadowed.iter().collect();
-            count_vec.sort_by(|a, b| b.1.cmp(a.1));
+            // ordered by shadowed destinations, ties by router address
+            let mut count_vec: Vec<(&T, &u64)> = self.router_shadowed.iter().collect();
+            count_vec.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
 
             for (router, shadowed) in count_vec {
+                let router_string = router.to_string();
                 let loops: u8;
                 let shadowed = *shadowed;
                 let imperiled: u64;
                 let asn: u8;
+                let origin_type: String;
 
                 if let Some(value) = self.router_imperiled.get(router) {
                     imperiled = value.len() as u64;
@@ -799,41 +1065,58 @@ pub mod post_loop_stats_mode {
                     loops = 0;
                 }
 
-                if let Some(value) = self.routers_to_asn.get(router) {
-                    asn = value.len() as u8;
+                // MOAS prefixes and AS sets are reported as such instead of as multiple ASes
+                if let Some(value) = self.routers_to_asn.get(&router_string) {
+                    asn = value.attributions().len() as u8;
+                    origin_type = value.kind().map(|kind| kind.to_string()).unwrap_or_else(|| "undefined".to_string());
                 } else {
                     asn = 0;
+                    origin_type = "undefined".to_string();
                 }
 
                 let loops = loops.to_string();
                 let shadowed = shadowed.to_string();
                 let imperiled = imperiled.to_string();
                 let asn = asn.to_string();
+                let rpki_state = match self.routers_rpki.get(&router_string) {
+                    Some(state) => state.to_string(),
+                    None => "undefined".to_string()
+                };
+                let country = match self.routers_to_country.get(&router_string) {
+                    Some(country) => country.clone(),
+                    None => "undefined".to_string()
+                };
+
+                let hostname = self.get_hostname(&router_string);
 
-                if let Err(_e) = csv_writer.write_record(&[router, &loops, &shadowed, &imperiled, &asn]) {
+                if let Err(_e) = csv_writer.write_record(&[&router_string, &loops, &shadowed, &imperiled, &asn, &origin_type, &rpki_state, &country, &hostname]) {
                     error!("Could not write csv line for router {}!", router);
                     return Err(YarrpError::CouldNotWriteError);
                 }
             }
-            Ok(())
+            commit_csv(csv_writer)
         }
 
         fn write_loops_csv(&self) -> Result<(), YarrpError> {
             let path = self.loop_storage.get_storage_file("loops.csv")?;
-            let mut csv_writer = csv::Writer::from_path(path)?;
-            if let Err(_) = csv_writer.write_record(&["loop", "length", "shadowed", "imperiled", "asn"]) {
+            let mut csv_writer = atomic_csv_writer(&path)?;
+            if let Err(_) = csv_writer.write_record(&["loop", "length", "shadowed", "imperiled", "asn", "origins", "moas", "as_set", "as_relationship", "countries", "country", "shared_domain", "amplification_64", "amplification_128", "amplification_255", "impact_score"]) {
                 error!("Could not write header row for loops.csv!");
                 return Err(YarrpError::CouldNotWriteError);
             }
 
+            // ordered by shadowed destinations, ties by loop id
             let mut count_vec: Vec<(&String, &Vec<T>)> = self.loop_destinations.iter().collect();
-            count_vec.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+            count_vec.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
 
             for (loop_id, value) in count_vec {
                 let members: u64;
                 let shadowed = value.len() as u64;
                 let mut imperiled: u64 = 0;
                 let asn: u8;
+                let origins: u8;
+                let mut moas: u8 = 0;
+                let mut as_set: u8 = 0;
 
 
                 if let Some(value) = self.loop_members.get(loop_id) {
@@ -848,24 +1131,56 @@ pub mod post_loop_stats_mode {
                     members = 0;
                 }
 
+                // distinct ASNs and origins of the loop members, origins sharing an ASN count once
                 if let Some(value) = self.loops_to_asn.get(loop_id) {
-                    asn = value.len() as u8;
+                    asn = flatten_origins(value).len() as u8;
+                    origins = merge_origins(value).len() as u8;
+                    for origin in value {
+                        match origin.kind() {
+                            Some(ASNOriginKind::Moas) => moas += 1,
+                            Some(ASNOriginKind::AsSet) => as_set += 1,
+                            _ => {}
+                        }
+                    }
                 } else {
                     asn = 0;
+                    origins = 0;
                 }
 
                 let members = members.to_string();
                 let shadowed = shadowed.to_string();
                 let imperiled = imperiled.to_string();
                 let asn = asn.to_string();
+                let origins = origins.to_string();
+                let moas = moas.to_string();
+                let as_set = as_set.to_string();
+                let as_relationship = match self.loops_as_relationship.get(loop_id) {
+                    Some(relationship) => relationship.to_string(),
+                    None => "undefined".to_string()
+                };
+                // distinct countries of the loop members, separated by ;
+                let (countries, country) = match self.loops_to_country.get(loop_id) {
+                    Some(countries) => (countries.len().to_string(), sorted(countries).map(|country| country.as_str()).collect::<Vec<&str>>().join(";")),
+                    None => ("0".to_string(), "undefined".to_string())
+                };
+
+                let shared_domain = self.loops_shared_domain.get(loop_id).cloned().unwrap_or_default();
+
+                let persistent = self.persistent_loops.contains(loop_id);
+                let amplification = self.loop_amplification.as_ref().and_then(|value| value.get_amplification(loop_id));
+                let (amplification_64, amplification_128, amplification_255, impact_score) = match amplification {
+                    Some(factors) => (format!("{:.2}", factors[0]), format!("{:.2}", factors[1]), format!("{:.2}", factors[2]),
+                                      format!("{:.2}", impact_score(factors[0], value.len() as u64, persistent))),
+                    None => ("undefined".to_string(), "undefined".to_string(), "undefined".to_string(), "undefined".to_string())
+                };
 
-                if let Err(_e) = csv_writer.write_record(&[loop_id, &members, &shadowed, &imperiled, &asn]) {
+                if let Err(_e) = csv_writer.write_record(&[loop_id, &members, &shadowed, &imperiled, &asn, &origins, &moas, &as_set, &as_relationship, &countries, &country, &shared_domain, &amplification_64, &amplification_128, &amplification_255, &impact_score]) {
                     error!("Could not write csv line for loop {}!", loop_id);
                     return Err(YarrpError::CouldNotWriteError);
                 }
             }
 
-            Ok(())
+            commit_csv(csv_writer)
         }
 
         fn write_asn_files(&self) -> Result<(), YarrpError> {
@@ -876,13 +1191,15 @@ pub mod post_loop_stats_mode {
             }
 
             let asn_path = self.loop_storage.get_storage_sub_file("asn", "asn_loops")?;
-            if let Err(_e) = self.asn_attribution.write_item_to_asn_csv(&asn_path, &self.loops_to_asn) {
+            let loops_asn: MapSetString = self.loops_to_asn.iter().map(|(loop_id, origins)| (loop_id.clone(), flatten_origins(origins))).collect();
+            if let Err(_e) = self.asn_attribution.write_item_to_asn_csv(&asn_path, &loops_asn) {
                 error!("Could not write asn_loops");
                 return Err(_e);
             }
 
             let asn_path = self.loop_storage.get_storage_sub_file("asn", "asn_routers")?;
-            if let Err(_e) = self.asn_attribution.write_item_to_asn_csv(&asn_path, &self.routers_to_asn) {
+            let routers_asn: MapSetString = self.routers_to_asn.iter().map(|(router, origin)| (router.clone(), flatten_origins(std::iter::once(origin)))).collect();
+            if let Err(_e) = self.asn_attribution.write_item_to_asn_csv(&asn_path, &routers_asn) {
                 error!("Could not write asn_router");
                 return Err(_e);
             }
@@ -899,31 +1216,75 @@ pub mod post_loop_stats_mode {
                 return Err(_e);
             }
 
+            if self.shadowed_to_org_numbers.is_some() {
+                let org_path = self.loop_storage.get_storage_sub_file("asn", "org.csv")?;
+                if let Err(_e) = self.asn_attribution.write_org_csv(&org_path, &self.org_to_routers, &self.org_to_loops, &self.shadowed_per_org) {
+                    error!("Could not write org.csv");
+                    return Err(_e);
+                }
+
+                let org_path = self.loop_storage.get_storage_sub_file("asn", "loop_org.csv")?;
+                if let Err(_e) = self.asn_attribution.write_asn_loop_entries(&org_path, &self.loop_members, &self.routers_to_org, &self.persistent_loops) {
+                    error!("Could not write loop_org.csv");
+                    return Err(_e);
+                }
+            }
+
+            Ok(())
+        }
+
+        /// incidents of loops sharing routers, written after the ASN attribution of the routers
+        fn build_incidents(&mut self) -> Result<(), YarrpError> {
+            let loop_incidents = LoopIncidents::new(&self.loop_members);
+            let path = self.loop_storage.get_storage_file("incidents.csv")?;
+            loop_incidents.write_incidents_csv(&path, &self.routers_to_asn, &self.loop_destinations, &self.persistent_loops)?;
+
+            self.loop_incidents = Some(loop_incidents);
             Ok(())
         }
 
-        // fn generate_preceding_router_stats(&self, ) -> Result<(), YarrpError> {
-        //     let shadowed_preceding_path = self.loop_storage.get_storage_file(SHADOWED_PRECEDING_INFO)?;
-        //
-        //     if !shadowed_preceding_path.exists() {
-        //         info!("No shadowed preceding file, not doing analysis");
-        //         return Ok(());
-        //     }
-        //
-        //     let mut reader = csv::Reader::from_path(shadowed_preceding_path)?;
-        //     let mut total_result = ShadowedPrecedingCounter::new();
-        //
-        //     // iterate through all shadowed preceding routers from file
-        //     for result in reader.deserialize() {
-        //         let obj: ShadowedPreceding = result?;
-        //         // grab asn of shadowed net and preceding router
-        //
-        //         let (shadowed_asn, preceding_asn) = self.asn_attribution.get_shadowed_preceding_asn(&obj.shadowed_net, &obj.preceding_router)?;
-        //
-        //     }
-        //
-        //     Ok(())
-        // }
+        /// tables 5 and 6, the routing domains of each shadowed destination, its loop and preceding router
+        fn generate_preceding_router_stats(&self) -> Result<(), YarrpError> {
+            let mut total_result = ShadowedPrecedingCounter::new();
+            let mut num_records: u64 = 0;
+
+            // iterate through all shadowed preceding routers from file
+            for record in self.project.shadowed_preceding()? {
+                let obj = record?;
+                // grab asn of shadowed net and preceding router
+                let (shadowed_asn, preceding_asn) = self.asn_attribution.get_shadowed_preceding_asn(&obj.shadowed_net, &obj.preceding_router)?;
+
+                let member_asn: Vec<Vec<String>> = match self.loop_members.get(&obj.loop_id) {
+                    Some(members) => members.iter()
+                        .map(|member| self.routers_to_asn.get(&member.to_string()).map(|origin| origin.asns()).unwrap_or_default())
+                        .collect(),
+                    None => Vec::new()
+                };
+                let num_domains = self.loops_to_asn.get(&obj.loop_id).map(|origins| merge_origins(origins).len()).unwrap_or(0);
+
+                total_result.add(&shadowed_asn, &preceding_asn, &member_asn, num_domains);
+                num_records += 1;
+            }
+
+            if num_records == 0 {
+                info!("No shadowed preceding information, not doing analysis");
+                return Ok(());
+            }
+
+            let tables = [("shadowed_preceding_destination.csv", total_result.destination_domain_table()),
+                ("shadowed_preceding_domains.csv", total_result.loop_domains_table())];
+            for (file_name, table) in tables.iter() {
+                let path = self.loop_storage.get_storage_sub_file("asn", file_name)?;
+                let mut csv_writer = atomic_csv_writer(&path)?;
+                csv_writer.write_record(&["key", "value"])?;
+                for (key, value) in table {
+                    csv_writer.write_record(&[*key, &value.to_string()])?;
+                }
+                commit_csv(csv_writer)?;
+            }
+
+            Ok(())
+        }
     }
 
     impl<T: 'static + Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ModeTrait for PostLoopStatsMode<T> {
@@ -982,10 +1343,36 @@ pub mod post_loop_stats_mode {
                 error!("Could not load ASN attribution!");
             }
 
+            if let Err(_e) = self.load_ptr_names() {
+                error!("Could not load PTR names!");
+            }
+
+            if self.ptr_names.is_some() {
+                if let Err(_e) = self.write_loop_members_csv() {
+                    error!("Could not write loop_members.csv!");
+                }
+            }
+
+            if let Err(_e) = self.load_geo_attribution() {
+                error!("Could not load geolocation attribution!");
+            }
+
+            if let Err(_e) = self.load_loop_amplification() {
+                error!("Could not write impact.csv!");
+            }
+
             if let Err(_e) = self.write_asn_files() {
                 error!("Could not write asn files!")
             }
 
+            if let Err(_e) = self.generate_preceding_router_stats() {
+                error!("Could not write shadowed preceding tables!");
+            }
+
+            if let Err(_e) = self.build_incidents() {
+                error!("Could not write incidents.csv!");
+            }
+
             if let Err(_e) = self.write_routers_csv() {
                 error!("Could not write routers.csv!");
             }
@@ -1022,7 +1409,7 @@ pub mod post_loop_stats_mode {
                 }
 
                 if let Some(value) = router_map.get_mut(&value_len) {
-                    value.insert(router.clone());
+                    value.insert(router.to_string());
                 } else {
                     error!("Could not get value from hashmap!");
                     exit(1);
@@ -1047,8 +1434,9 @@ pub mod post_loop_stats_mode {
             println!();
             println!("Routers with most shadowed destinations:");
 
-            let mut count_vec: Vec<(&String, &u64)> = self.router_shadowed.iter().collect();
-            count_vec.sort_by(|a, b| b.1.cmp(a.1));
+            // ordered by shadowed destinations, ties by router address
+            let mut count_vec: Vec<(&T, &u64)> = self.router_shadowed.iter().collect();
+            count_vec.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
 
             let mut count = 0;
 
@@ -1061,8 +1449,9 @@ pub mod post_loop_stats_mode {
             println!();
             println!("Loops with most destinations");
 
+            // ordered by shadowed destinations, ties by loop id
             let mut count_vec: Vec<(&String, &Vec<T>)> = self.loop_destinations.iter().collect();
-            count_vec.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+            count_vec.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
 
             count = 0;
             for (loop_id, value) in count_vec {
@@ -1100,4 +1489,46 @@ pub mod post_loop_stats_mode {
 
         fn close(&mut self) {}
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::Ipv4Addr;
+    use clap::{App, load_yaml};
+    use crate::modes::{ModeTrait, PostLoopStatsMode};
+    use crate::helpers::test_helper::{TempFixture, ProjectFixture};
+
+    #[test]
+    fn ptr_names_of_loop_members() {
+        let project_path = ProjectFixture::new("postloopstats_project").destinations("bb", &["198.51.100.3"]).build();
+        let persistent = TempFixture::file("postloopstats_persistent.id", "");
+        let routeviews = TempFixture::file("postloopstats.pfx2as", "192.0.2.0\t24\t64496\n");
+        // .10 has no name, it is ignored for the shared domain of bb
+        let ptr = TempFixture::file("postloopstats_ptr.txt", "192.0.2.8,ae-1.r01.example.net\n192.0.2.9,ae-2.r02.example.net.\n");
+
+        let yaml = load_yaml!("../../bin/yarrp_toolkit/cli.yml");
+        let matches = App::from_yaml(yaml).get_matches_from(vec![
+            "yarrp_toolkit", "-4", "postloopstats", "-s", "-p", project_path.to_str(), "-r", routeviews.to_str(),
+            "-l", persistent.to_str(), "-k", persistent.to_str(), "--ptr", ptr.to_str()]);
+        let mut mode = PostLoopStatsMode::<Ipv4Addr>::new(matches);
+        mode.do_calculations();
+
+        assert_eq!(fs::read_to_string(project_path.join("loop_members.csv")).unwrap(),
+                   "loop,router,hostname,domain\n\
+                   aa,192.0.2.8,ae-1.r01.example.net,example.net\n\
+                   aa,192.0.2.9,ae-2.r02.example.net,example.net\n\
+                   bb,192.0.2.9,ae-2.r02.example.net,example.net\n\
+                   bb,192.0.2.10,,\n");
+
+        let stats = fs::read_to_string(project_path.join("postloop_stats.csv")).unwrap();
+        assert!(stats.contains("ptr_named_routers,2\n"));
+        assert!(stats.contains("shared_domain_loops,1\n"), "bb has a single named member");
+
+        let summary = fs::read_to_string(project_path.join("loops.csv")).unwrap();
+        let shared_domains: Vec<&str> = summary.lines().skip(1)
+            .map(|line| line.split(',').nth(11).unwrap())
+            .collect();
+        assert_eq!(shared_domains, vec!["example.net", ""]);
+    }
+}
@@ -0,0 +1,495 @@
+pub mod query_mode {
+    use clap::ArgMatches;
+    use std::process::exit;
+    use log::{error, info, warn};
+    use std::collections::{HashMap, HashSet, BTreeMap, BTreeSet};
+    use std::fmt::Display;
+    use std::hash::Hash;
+    use std::str::FromStr;
+    use std::path::PathBuf;
+    use std::net::IpAddr;
+    use std::marker::PhantomData;
+    use ipnet::IpNet;
+    use itertools::sorted;
+
+    use crate::modes::{ModeTrait, ModeEnum, load_path_param};
+    use crate::structs::{YarrpError, MapSetString, QueryResult, QueryRouterResult, QueryLoopResult, QueryDestinationResult};
+    use crate::traits::IpAddrExt;
+    use crate::analytics::{LoopStorage, ASNAttribution, PtrNames};
+    use crate::analytics::{ROUTERS, IDENTIFIERS, IMPERILED, LOOPS, LOOPS_CSV};
+
+    /// Looks up router addresses, destinations and prefixes in a project
+    pub struct QueryMode<T> {
+        pub mode: ModeEnum,
+        project_path: PathBuf,
+        queries: Vec<IpNet>,
+        print_json: bool,
+        asn_attribution: Option<ASNAttribution>,
+        ptr_path: Option<PathBuf>,
+        ptr_names: Option<PtrNames>,
+        loop_members: MapSetString,
+        router_loops: MapSetString,
+        loop_preceding: MapSetString,
+        loop_destination_counts: HashMap<String, u64>,
+        results: Vec<QueryResult>,
+        ip_type: PhantomData<T>,
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> QueryMode<T> {
+        pub fn new(matches: ArgMatches) -> QueryMode<T> {
+            let mode_string = ModeEnum::Query.to_string().to_lowercase();
+            let sub_matches = matches.subcommand_matches(mode_string).unwrap();
+
+            let project_path = load_path_param(sub_matches, "project_path");
+            if !project_path.exists() || !project_path.is_dir() {
+                error!("Project path does not exist or is not a directory!");
+                exit(1);
+            }
+
+            let mut queries = Vec::new();
+            if let Some(values) = sub_matches.values_of("queries") {
+                for value in values {
+                    if let Some(query) = QueryMode::<T>::parse_query(value) {
+                        queries.push(query);
+                    } else {
+                        error!("Could not parse query {}, expected an address or prefix of the selected address family!", value);
+                        exit(1);
+                    }
+                }
+            } else {
+                error!("Could not parse any queries!");
+                exit(1);
+            }
+
+            let print_json = sub_matches.occurrences_of("json") > 0;
+
+            let mut asn_attribution = None;
+            if let Some(path) = sub_matches.value_of("routeviews") {
+                let mut attribution = ASNAttribution::new(&T::root_net());
+                if let Err(_) = attribution.load_routeviews_bgp(path) {
+                    error!("Could not load BGP data for asn attribution!");
+                    exit(1);
+                }
+                asn_attribution = Some(attribution);
+            }
+
+            let ptr_path = sub_matches.value_of("ptr").map(PathBuf::from);
+            if let Some(path) = &ptr_path {
+                if !path.exists() {
+                    error!("PTR file {} does not exist!", path.display());
+                    exit(1);
+                }
+            }
+
+            let mut query_mode = QueryMode::from_project(project_path, queries, asn_attribution, ptr_path);
+            query_mode.print_json = print_json;
+            query_mode
+        }
+
+        /// queries of addresses or prefixes against the project at project_path, printed human readable
+        pub fn from_project(project_path: PathBuf, queries: Vec<IpNet>, asn_attribution: Option<ASNAttribution>, ptr_path: Option<PathBuf>) -> QueryMode<T> {
+            QueryMode {
+                mode: ModeEnum::Query,
+                project_path,
+                queries,
+                print_json: false,
+                asn_attribution,
+                ptr_path,
+                ptr_names: None,
+                loop_members: HashMap::new(),
+                router_loops: HashMap::new(),
+                loop_preceding: HashMap::new(),
+                loop_destination_counts: HashMap::new(),
+                results: Vec::new(),
+                ip_type: PhantomData,
+            }
+        }
+
+        /// parses a prefix or a single address, which is treated as a host prefix
+        fn parse_query(value: &str) -> Option<IpNet> {
+            let network;
+            if let Ok(net) = IpNet::from_str(value) {
+                network = net;
+            } else if let Ok(addr) = IpAddr::from_str(value) {
+                network = IpNet::from(addr);
+            } else {
+                return None;
+            }
+
+            match (network, T::is_v4()) {
+                (IpNet::V4(_), true) | (IpNet::V6(_), false) => Some(network.trunc()),
+                _ => None
+            }
+        }
+
+        fn load_project(&mut self) -> Result<(), YarrpError> {
+            self.loop_members = LoopStorage::<T>::read_id_file(&self.project_path.join(IDENTIFIERS))?;
+            self.router_loops = LoopStorage::<T>::read_id_file(&self.project_path.join(ROUTERS))?;
+
+            // names of all project routers, loop members are reported with their names as well
+            if let Some(ptr_path) = &self.ptr_path {
+                let mut routers = HashSet::new();
+                for router in self.router_loops.keys() {
+                    routers.insert(IpAddr::from_str(router)?);
+                }
+                self.ptr_names = Some(PtrNames::load(ptr_path, &routers)?);
+            }
+
+            match LoopStorage::<T>::read_loop_info(&self.project_path.join(LOOPS_CSV)) {
+                Ok(loop_info) => {
+                    for (loop_id, preceding_router) in loop_info.keys() {
+                        self.loop_preceding.entry(loop_id.clone()).or_default().insert(preceding_router.clone());
+                    }
+                }
+                Err(_) => warn!("Could not read {}, preceding routers are not available!", LOOPS_CSV)
+            }
+            Ok(())
+        }
+
+        fn get_asn(&self, address: &str) -> Vec<String> {
+            let mut asn_set = BTreeSet::new();
+            if let (Some(attribution), Ok(ip_addr)) = (&self.asn_attribution, IpAddr::from_str(address)) {
+                if let Some(asn_list) = attribution.get_asn_for_ip(&ip_addr) {
+                    asn_set.extend(asn_list);
+                }
+            }
+            asn_set.into_iter().collect()
+        }
+
+        fn get_hostname(&self, address: &str) -> Option<String> {
+            self.ptr_names.as_ref()?.get_hostname(address).map(|name| name.to_string())
+        }
+
+        fn get_destination_count(&mut self, loop_id: &str) -> Result<u64, YarrpError> {
+            if let Some(count) = self.loop_destination_counts.get(loop_id) {
+                return Ok(*count);
+            }
+
+            let path = self.project_path.join(LOOPS).join(format!("{}.dest", loop_id));
+            let mut count = 0;
+            if path.exists() {
+                count = LoopStorage::<T>::read_details_file_as_string(&path)?.len() as u64;
+            } else {
+                warn!("No destination file for loop {}", loop_id);
+            }
+
+            self.loop_destination_counts.insert(loop_id.to_string(), count);
+            Ok(count)
+        }
+
+        fn query_routers(&mut self, query: &IpNet) -> Result<Vec<QueryRouterResult>, YarrpError> {
+            let mut routers = Vec::new();
+            for router in sorted(self.router_loops.keys()) {
+                if let Ok(router_ip) = IpAddr::from_str(router) {
+                    if query.contains(&router_ip) {
+                        routers.push(router.clone());
+                    }
+                }
+            }
+
+            let mut results = Vec::new();
+            for router in routers {
+                let loop_ids: Vec<String> = sorted(self.router_loops[&router].iter().cloned()).collect();
+                let mut loops = Vec::new();
+
+                for loop_id in loop_ids {
+                    let members: Vec<String> = self.loop_members.get(&loop_id)
+                        .map(|members| sorted(members.iter().cloned()).collect())
+                        .unwrap_or_default();
+
+                    let mut asn_set = BTreeSet::new();
+                    for member in &members {
+                        asn_set.extend(self.get_asn(member));
+                    }
+
+                    let preceding_routers = self.loop_preceding.get(&loop_id)
+                        .map(|routers| sorted(routers.iter().cloned()).collect())
+                        .unwrap_or_default();
+
+                    let member_hostnames = members.iter().map(|member| self.get_hostname(member)).collect();
+                    let shared_domain = self.ptr_names.as_ref().and_then(|ptr_names| ptr_names.shared_domain(&members));
+
+                    loops.push(QueryLoopResult {
+                        shadowed_destinations: self.get_destination_count(&loop_id)?,
+                        loop_id,
+                        members,
+                        member_hostnames,
+                        shared_domain,
+                        preceding_routers,
+                        asn_list: asn_set.into_iter().collect(),
+                    });
+                }
+
+                results.push(QueryRouterResult {
+                    asn_list: self.get_asn(&router),
+                    hostname: self.get_hostname(&router),
+                    router,
+                    loops,
+                });
+            }
+            Ok(results)
+        }
+
+        /// scans all detail files of a sub directory, returns query index -> destination -> file stems
+        fn scan_details(&self, sub_folder: &str, extension: &str) -> Result<Vec<BTreeMap<String, BTreeSet<String>>>, YarrpError> {
+            let mut matches = vec![BTreeMap::new(); self.queries.len()];
+            let directory = self.project_path.join(sub_folder);
+            if !directory.exists() {
+                warn!("Project has no {} directory!", sub_folder);
+                return Ok(matches);
+            }
+
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                if path.extension().and_then(|value| value.to_str()) != Some(extension) {
+                    continue;
+                }
+
+                let file_stem;
+                if let Some(value) = path.file_stem().and_then(|value| value.to_str()) {
+                    file_stem = value.to_string();
+                } else {
+                    continue;
+                }
+
+                for line in LoopStorage::<T>::read_details_file_as_string(&path)? {
+                    let destination;
+                    if let Ok(value) = IpAddr::from_str(&line) {
+                        destination = value;
+                    } else {
+                        warn!("Could not parse {} in {}", line, file_stem);
+                        continue;
+                    }
+
+                    for (index, query) in self.queries.iter().enumerate() {
+                        if query.contains(&destination) {
+                            matches[index].entry(line.clone()).or_insert_with(BTreeSet::new).insert(file_stem.clone());
+                        }
+                    }
+                }
+            }
+            Ok(matches)
+        }
+
+        /// one result per query, available after run_queries
+        pub fn results(&self) -> &[QueryResult] {
+            &self.results
+        }
+
+        pub fn run_queries(&mut self) -> Result<(), YarrpError> {
+            self.load_project()?;
+
+            info!("Scanning destination files");
+            let shadowing_loops = self.scan_details(LOOPS, "dest")?;
+            info!("Scanning imperiled files");
+            let imperiled_routers = self.scan_details(IMPERILED, "imp")?;
+
+            for (index, query) in self.queries.clone().iter().enumerate() {
+                let routers = self.query_routers(query)?;
+
+                let mut destination_keys = BTreeSet::new();
+                destination_keys.extend(shadowing_loops[index].keys().cloned());
+                destination_keys.extend(imperiled_routers[index].keys().cloned());
+
+                let mut destinations = Vec::new();
+                for destination in destination_keys {
+                    destinations.push(QueryDestinationResult {
+                        loops: shadowing_loops[index].get(&destination).map(|set| set.iter().cloned().collect()).unwrap_or_default(),
+                        imperiled_routers: imperiled_routers[index].get(&destination).map(|set| set.iter().cloned().collect()).unwrap_or_default(),
+                        destination,
+                    });
+                }
+
+                self.results.push(QueryResult {
+                    query: query.to_string(),
+                    routers,
+                    destinations,
+                });
+            }
+            Ok(())
+        }
+
+        fn print_human_readable(&self) {
+            for result in &self.results {
+                println!("Query {}", result.query);
+                if result.routers.is_empty() && result.destinations.is_empty() {
+                    println!("  Not found in project");
+                }
+
+                for router in &result.routers {
+                    match &router.hostname {
+                        Some(hostname) => println!("  Router {} {} (ASN: {})", router.router, hostname, router.asn_list.join(";")),
+                        None => println!("  Router {} (ASN: {})", router.router, router.asn_list.join(";")),
+                    }
+                    for loop_result in &router.loops {
+                        let members: Vec<String> = loop_result.members.iter().zip(&loop_result.member_hostnames)
+                            .map(|(member, hostname)| match hostname {
+                                Some(hostname) => format!("{} ({})", member, hostname),
+                                None => member.clone()
+                            })
+                            .collect();
+                        println!("    Loop {}", loop_result.loop_id);
+                        println!("      Members: {}", members.join(";"));
+                        if let Some(domain) = &loop_result.shared_domain {
+                            println!("      Shared domain: {}", domain);
+                        }
+                        println!("      Preceding routers: {}", loop_result.preceding_routers.join(";"));
+                        println!("      Shadowed destinations: {}", loop_result.shadowed_destinations);
+                        println!("      ASN: {}", loop_result.asn_list.join(";"));
+                    }
+                }
+
+                for destination in &result.destinations {
+                    println!("  Destination {}", destination.destination);
+                    println!("    Shadowed by loops: {}", destination.loops.join(";"));
+                    println!("    Imperiled routers: {}", destination.imperiled_routers.join(";"));
+                }
+                println!();
+            }
+        }
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ModeTrait for QueryMode<T> {
+        fn get_mode(&self) -> ModeEnum {
+            self.mode
+        }
+
+        fn no_input_capable(&self) -> bool {
+            true
+        }
+
+        fn parse_comment_line(&mut self, _input: &str) {
+            // Dummy implementation, just ignore comment lines
+        }
+
+        fn parse_string_line(&mut self, _input: &str) {
+            // No input parsing here!
+        }
+
+        fn do_file_rotate(&mut self, _file_number: u64, _file_name: &str) {
+            // No file rotating here
+        }
+
+        fn do_calculations(&mut self) {
+            if self.run_queries().is_err() {
+                error!("Could not query project!");
+                exit(1);
+            }
+        }
+
+        fn print_output(&self) {
+            if !self.print_json {
+                self.print_human_readable();
+                return;
+            }
+
+            match serde_json::to_string_pretty(&self.results) {
+                Ok(output) => println!("{}", output),
+                Err(_) => {
+                    error!("Could not serialize query results!");
+                    exit(1);
+                }
+            }
+        }
+
+        fn close(&mut self) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::str::FromStr;
+    use ipnet::IpNet;
+    use crate::analytics::{ASNAttribution, IMPERILED};
+    use crate::modes::QueryMode;
+    use crate::helpers::test_helper::{TempFixture, ProjectFixture};
+
+    /// the ProjectFixture loops, 192.0.2.9 imperils a third destination
+    fn fixture_project() -> TempFixture {
+        let project_path = ProjectFixture::new("query_project").build();
+        project_path.write(format!("{}/192.0.2.9.imp", IMPERILED), "198.51.100.3\n");
+        project_path
+    }
+
+    fn run_query(project_path: &TempFixture, query: &str, asn_attribution: Option<ASNAttribution>) -> QueryMode<Ipv4Addr> {
+        run_ptr_query(project_path, query, asn_attribution, None)
+    }
+
+    fn run_ptr_query(project_path: &TempFixture, query: &str, asn_attribution: Option<ASNAttribution>, ptr: Option<&TempFixture>) -> QueryMode<Ipv4Addr> {
+        let queries = vec![IpNet::from_str(query).unwrap()];
+        let ptr_path = ptr.map(|ptr| ptr.path().to_path_buf());
+        let mut query_mode = QueryMode::<Ipv4Addr>::from_project(project_path.path().to_path_buf(), queries, asn_attribution, ptr_path);
+        query_mode.run_queries().ok().unwrap();
+        query_mode
+    }
+
+    #[test]
+    fn router_lookup() {
+        let project_path = fixture_project();
+        let pfx2as = TempFixture::file("query.pfx2as", "192.0.2.0\t24\t64496\n");
+        let mut attribution = ASNAttribution::new("0.0.0.0/0");
+        attribution.load_routeviews_bgp(pfx2as.to_str()).ok().unwrap();
+
+        let query_mode = run_query(&project_path, "192.0.2.9/32", Some(attribution));
+        let result = &query_mode.results()[0];
+        assert_eq!(result.routers.len(), 1);
+        assert!(result.destinations.is_empty());
+
+        let router = &result.routers[0];
+        assert_eq!(router.router, "192.0.2.9");
+        assert_eq!(router.asn_list, vec!["64496"]);
+        let loop_ids: Vec<&str> = router.loops.iter().map(|loop_result| loop_result.loop_id.as_str()).collect();
+        assert_eq!(loop_ids, vec!["aa", "bb"]);
+        assert_eq!(router.loops[0].members, vec!["192.0.2.8", "192.0.2.9"]);
+        assert_eq!(router.loops[0].preceding_routers, vec!["192.0.2.7"]);
+        assert_eq!(router.loops[0].shadowed_destinations, 2);
+        assert_eq!(router.loops[1].shadowed_destinations, 0, "Loop without destination file");
+    }
+
+    #[test]
+    fn destination_lookup() {
+        let project_path = fixture_project();
+        let query_mode = run_query(&project_path, "198.51.100.2/32", None);
+        let result = &query_mode.results()[0];
+        assert!(result.routers.is_empty());
+        assert_eq!(result.destinations.len(), 1);
+        assert_eq!(result.destinations[0].destination, "198.51.100.2");
+        assert_eq!(result.destinations[0].loops, vec!["aa"]);
+        assert!(result.destinations[0].imperiled_routers.is_empty());
+    }
+
+    #[test]
+    fn prefix_lookup() {
+        let project_path = fixture_project();
+        let query_mode = run_query(&project_path, "198.51.100.0/24", None);
+        let result = &query_mode.results()[0];
+        let destinations: Vec<&str> = result.destinations.iter().map(|destination| destination.destination.as_str()).collect();
+        assert_eq!(destinations, vec!["198.51.100.1", "198.51.100.2", "198.51.100.3"]);
+        assert_eq!(result.destinations[2].imperiled_routers, vec!["192.0.2.9"]);
+        assert!(result.destinations[2].loops.is_empty());
+
+        let query_mode = run_query(&project_path, "192.0.2.0/29", None);
+        let result = &query_mode.results()[0];
+        assert!(result.routers.is_empty() && result.destinations.is_empty(), "Routers .8 to .10 lie outside of the prefix");
+    }
+
+    #[test]
+    fn router_lookup_with_hostnames() {
+        let project_path = fixture_project();
+        let ptr = TempFixture::file("query_ptr.txt", "192.0.2.8,ae-1.r01.example.net\n192.0.2.9 ae-2.r02.example.net.\n");
+
+        let query_mode = run_ptr_query(&project_path, "192.0.2.9/32", None, Some(&ptr));
+        let router = &query_mode.results()[0].routers[0];
+        assert_eq!(router.hostname, Some("ae-2.r02.example.net".to_string()));
+        assert_eq!(router.loops[0].member_hostnames, vec![Some("ae-1.r01.example.net".to_string()), Some("ae-2.r02.example.net".to_string())]);
+        assert_eq!(router.loops[0].shared_domain, Some("example.net".to_string()));
+        assert_eq!(router.loops[1].member_hostnames, vec![None, Some("ae-2.r02.example.net".to_string())], "Members are ordered as strings, .10 first");
+        assert_eq!(router.loops[1].shared_domain, None, "A single named member shares no domain");
+
+        let query_mode = run_query(&project_path, "192.0.2.9/32", None);
+        let router = &query_mode.results()[0].routers[0];
+        assert_eq!(router.hostname, None);
+        assert_eq!(router.loops[0].shared_domain, None);
+    }
+}
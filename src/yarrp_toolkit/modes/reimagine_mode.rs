@@ -1,11 +1,12 @@
 pub mod reimagine_mode {
-    use std::fs::File;
     use std::io::{Write};
+    use std::path::Path;
     use std::process::exit;
 
     use clap::ArgMatches;
 
-    use crate::modes::{ModeEnum, ModeTrait};
+    use crate::modes::{ModeEnum, ModeTrait, load_output_policy};
+    use crate::structs::AtomicFile;
     use std::collections::HashSet;
 
     pub struct ReimagineMode {
@@ -13,7 +14,7 @@ pub mod reimagine_mode {
         reimagine_count: u64,
         total_count: u64,
         max_ttl: String,
-        output_file: File,
+        output_file: Option<AtomicFile>,
         ip_set: HashSet<String>,
         ignored_double: u64,
     }
@@ -28,7 +29,9 @@ pub mod reimagine_mode {
             let max_ttl = sub_matches.value_of("max_ttl").unwrap().to_owned();
             let output_path = sub_matches.value_of("output").unwrap();
 
-            let output_file = File::create(output_path);
+            let output_policy = load_output_policy(&matches, true);
+
+            let output_file = AtomicFile::open(Path::new(output_path), output_policy);
             let output_file = match output_file {
                 Ok(file) => file,
                 Err(_) => {
@@ -44,7 +47,7 @@ pub mod reimagine_mode {
                 reimagine_count: 0,
                 total_count: 0,
                 max_ttl,
-                output_file,
+                output_file: Some(output_file),
                 ip_set: HashSet::new(),
                 ignored_double: 0
             }
@@ -85,7 +88,7 @@ pub mod reimagine_mode {
                 }
 
                 self.reimagine_count += 1;
-                if let Err(x) = self.output_file.write(format!("{}\n", destination).as_bytes()) {
+                if let Err(x) = self.output_file.as_mut().unwrap().write(format!("{}\n", destination).as_bytes()) {
                     eprintln!("Could not write to output filez!");
                     eprintln!("{}", x);
                     exit(1);
@@ -108,6 +111,12 @@ pub mod reimagine_mode {
         }
 
         fn close(&mut self) {
+            if let Some(output_file) = self.output_file.take() {
+                if output_file.commit().is_err() {
+                    eprintln!("Could not write the output file!");
+                    exit(1);
+                }
+            }
             self.clear();
         }
     }
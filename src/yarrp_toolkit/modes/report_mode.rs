@@ -0,0 +1,170 @@
+pub mod report_mode {
+    use clap::ArgMatches;
+    use std::process::exit;
+    use std::collections::HashSet;
+    use std::fmt::Display;
+    use std::fs;
+    use std::hash::Hash;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use log::{error, info};
+
+    use crate::modes::{ModeTrait, ModeEnum, load_output_policy, load_path_param, load_asn_attribution, parse_param};
+    use crate::structs::{AtomicFile, OutputPolicy, ReportResult, YarrpError, parse_asn};
+    use crate::traits::IpAddrExt;
+    use crate::analytics::{ASNAttribution, LoopStorage, OperatorReports, Project, render_report_markdown};
+
+    /// Writes one notification report per ASN of a project, as Markdown and JSON
+    pub struct ReportMode<T> {
+        pub mode: ModeEnum,
+        project: Project<T>,
+        asn_attribution: ASNAttribution,
+        output_path: PathBuf,
+        output_policy: OutputPolicy,
+        asns: HashSet<String>,
+        reports: OperatorReports,
+        num_reports: usize,
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ReportMode<T> {
+        pub fn new(matches: ArgMatches) -> ReportMode<T> {
+            let mode = ModeEnum::Report;
+            let mode_string = mode.to_string().to_lowercase();
+            let sub_matches = matches.subcommand_matches(mode_string).unwrap();
+
+            let project_path = load_path_param(sub_matches, "project_path");
+            let project;
+            if let Ok(value) = Project::open(&project_path) {
+                project = value;
+            } else {
+                error!("Could not open project!");
+                exit(1);
+            }
+
+            let output_path = load_path_param(sub_matches, "output");
+            if fs::create_dir_all(&output_path).is_err() {
+                error!("Could not create output directory {}!", output_path.display());
+                exit(1);
+            }
+
+            // reports are always written as a whole
+            let output_policy = load_output_policy(&matches, false);
+
+            let asn_attribution;
+            if let Ok(value) = load_asn_attribution(sub_matches, &T::root_net()) {
+                asn_attribution = value;
+            } else {
+                error!("Could not load BGP data for asn attribution!");
+                exit(1);
+            }
+
+            let mut reports = OperatorReports::new(parse_param(sub_matches, "examples", 5));
+            if let Some(path) = sub_matches.value_of("persistent_loops") {
+                if let Ok(persistent_loops) = LoopStorage::<T>::read_id_file(&PathBuf::from(path)) {
+                    reports.set_persistent_loops(persistent_loops.keys().cloned().collect());
+                } else {
+                    error!("Could not load persistent loops!");
+                    exit(1);
+                }
+            }
+
+            if let Some(path) = sub_matches.value_of("abuse_contacts") {
+                if reports.load_abuse_contacts(&PathBuf::from(path)).is_err() {
+                    error!("Could not load abuse contacts!");
+                    exit(1);
+                }
+            }
+
+            let mut asns = HashSet::new();
+            for value in sub_matches.values_of("asn").into_iter().flatten() {
+                if let Some(asn) = parse_asn(value) {
+                    asns.insert(asn.to_string());
+                } else {
+                    error!("Could not parse ASN {}!", value);
+                    exit(1);
+                }
+            }
+
+            ReportMode {
+                mode,
+                project,
+                asn_attribution,
+                output_path,
+                output_policy,
+                asns,
+                reports,
+                num_reports: 0,
+            }
+        }
+
+        fn report_paths(&self, report: &ReportResult) -> (PathBuf, PathBuf) {
+            let name = format!("AS{}", report.asn);
+            (self.output_path.join(format!("{}.md", name)), self.output_path.join(format!("{}.json", name)))
+        }
+
+        fn write_reports(&self, reports: &[ReportResult]) -> Result<(), YarrpError> {
+            // existing reports are refused before any report is written
+            for report in reports {
+                let (markdown_path, json_path) = self.report_paths(report);
+                self.output_policy.check(&markdown_path)?;
+                self.output_policy.check(&json_path)?;
+            }
+
+            for report in reports {
+                let (markdown_path, json_path) = self.report_paths(report);
+
+                let mut markdown_file = AtomicFile::create(&markdown_path)?;
+                markdown_file.write_all(render_report_markdown(report).as_bytes())?;
+                markdown_file.commit()?;
+
+                let json = match serde_json::to_string_pretty(report) {
+                    Ok(json) => json,
+                    Err(_) => return Err(YarrpError::CouldNotSerializeError)
+                };
+                let mut json_file = AtomicFile::create(&json_path)?;
+                writeln!(json_file, "{}", json)?;
+                json_file.commit()?;
+            }
+            Ok(())
+        }
+    }
+
+    impl<T: Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ModeTrait for ReportMode<T> {
+        fn get_mode(&self) -> ModeEnum {
+            self.mode
+        }
+
+        fn no_input_capable(&self) -> bool {
+            true
+        }
+
+        fn parse_comment_line(&mut self, _input: &str) {}
+
+        fn parse_string_line(&mut self, _input: &str) {}
+
+        fn do_file_rotate(&mut self, _file_number: u64, _file_name: &str) {}
+
+        fn do_calculations(&mut self) {
+            let reports = match self.reports.build(&self.project, &mut self.asn_attribution, &self.asns) {
+                Ok(reports) => reports,
+                Err(_) => {
+                    error!("Could not build reports from project!");
+                    exit(1);
+                }
+            };
+
+            if self.write_reports(&reports).is_err() {
+                error!("Could not write reports!");
+                exit(1);
+            }
+            self.num_reports = reports.len();
+        }
+
+        fn print_output(&self) {
+            info!("Wrote {} reports to {}", self.num_reports, self.output_path.display());
+        }
+
+        fn close(&mut self) {}
+    }
+}
@@ -1,12 +1,12 @@
 pub mod scatter_mode {
     use clap::ArgMatches;
 
-    use crate::modes::{ModeEnum, ModeTrait};
+    use crate::modes::{ModeEnum, ModeTrait, load_output_policy};
     use std::collections::{HashMap, HashSet};
     use std::str::FromStr;
     use log::{error, info};
-    use crate::structs::YarrpError;
-    use std::fs::File;
+    use crate::structs::{YarrpError, AtomicFile, OutputPolicy};
+    use std::path::Path;
     use std::io::Write;
     use std::process::exit;
     use crate::traits::IpAddrExt;
@@ -14,12 +14,14 @@ pub mod scatter_mode {
     use std::hash::Hash;
     use ipnet::IpNet;
     use std::marker::PhantomData;
+    use itertools::sorted;
 
     pub struct ScatterMode<T> {
         pub mode: ModeEnum,
         pub bucket_prefix: u8,
         pub target_prefix: u8,
         pub output_file: String,
+        output_policy: OutputPolicy,
         bucket_prefix_dict: HashMap<IpNet, Vec<String>>,
         ip_type: PhantomData<T>
     }
@@ -37,6 +39,10 @@ pub mod scatter_mode {
             let target_prefix = target_prefix.parse().unwrap();
 
             let output_file = sub_matches.value_of("output").unwrap().to_owned();
+            let output_policy = load_output_policy(&matches, true);
+            if output_policy.check(Path::new(&output_file)).is_err() {
+                exit(1);
+            }
 
             info!("Loading Scatter Mode...");
 
@@ -45,6 +51,7 @@ pub mod scatter_mode {
                 bucket_prefix,
                 target_prefix,
                 output_file,
+                output_policy,
                 bucket_prefix_dict: HashMap::new(),
                 ip_type: PhantomData
             }
@@ -205,13 +212,14 @@ pub mod scatter_mode {
             let mut added_lines = 0;
 
             let mut added = true;
-            let mut output_file = File::create(&self.output_file)?;
+            let mut output_file = AtomicFile::open(Path::new(&self.output_file), self.output_policy)?;
 
             while added {
                 added = false;
 
-                for item in self.bucket_prefix_dict.values() {
-                    if let Some(value) = item.get(counter) {
+                // buckets are visited ordered by prefix, so the output is stable between runs
+                for bucket in sorted(self.bucket_prefix_dict.keys()) {
+                    if let Some(value) = self.bucket_prefix_dict[bucket].get(counter) {
                         let format_string = format!("{}\n", value);
                         output_file.write(format_string.as_bytes())?;
                         // set added flag so we know, we added at *least* one value from the buckets to the output list
@@ -221,6 +229,7 @@ pub mod scatter_mode {
                 }
                 counter += 1;
             }
+            output_file.commit()?;
 
             info!("Written {} lines to the output file", added_lines);
             Ok(())
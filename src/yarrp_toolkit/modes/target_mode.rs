@@ -11,11 +11,10 @@ pub mod target_mode {
     use std::time::{SystemTime, UNIX_EPOCH};
 
     use crate::traits::IpAddrExt;
-    use crate::modes::{ModeEnum, ModeTrait, parse_param, load_path_or_default, load_path_param, read_blocklist};
+    use crate::modes::{ModeEnum, ModeTrait, parse_param, load_path_or_default, load_path_param, read_blocklist, load_output_policy};
     use std::marker::PhantomData;
     use crate::structs::CountingFile;
     use std::collections::HashSet;
-    use std::fs::remove_file;
 
     pub struct TargetMode<T> {
         mode: ModeEnum,
@@ -72,15 +71,10 @@ pub mod target_mode {
                 blocklist = HashSet::new();
             }
 
-            if output_path.exists() && output_path.is_file() {
-                if let Err(_) = remove_file(&output_path) {
-                    error!("Could not delete existing output file!");
-                    exit(1);
-                }
-            }
+            let output_policy = load_output_policy(&matches, true);
 
             let output_file;
-            if let Some(value) = CountingFile::new(&output_path) {
+            if let Some(value) = CountingFile::new(&output_path, output_policy) {
                 output_file = value;
             } else {
                 error!("Could not open output file!");
@@ -185,6 +179,11 @@ pub mod target_mode {
         }
 
         fn close(&mut self) {
+            // the target list only replaces an existing one once all targets are written
+            if self.output_file.commit().is_err() {
+                error!("Could not write output file!");
+                exit(1);
+            }
             self.clear();
         }
     }
@@ -0,0 +1,196 @@
+pub mod watch_mode {
+    use clap::ArgMatches;
+    use std::process::exit;
+    use std::path::{Path, PathBuf};
+    use std::thread::sleep;
+    use std::time::Duration;
+    use std::marker::PhantomData;
+    use log::{error, info};
+    use std::fmt::Display;
+    use std::hash::Hash;
+    use std::str::FromStr;
+
+    use crate::modes::{ModeEnum, ModeTrait, LoopsMode, load_output_policy, load_path_param, parse_param};
+    use crate::analytics::{LoopAnalysis, ProjectWatcher, ProjectOutput, prepare_project_output};
+    use crate::structs::DetailFormat;
+    use crate::traits::IpAddrExt;
+    use crate::create_dir_if_not_existing;
+
+    /// Long running loops analysis, ingests every new file of a directory into a project.
+    /// Each file is handled like a loops run with --append, stats.csv and loops.csv are refreshed after every file.
+    pub struct WatchMode<T> {
+        pub mode: ModeEnum,
+        watcher: ProjectWatcher,
+        project_output: ProjectOutput,
+        loop_storage_path: String,
+        imperiled_router_test_file: String,
+        imperiled_router_blocklist: String,
+        only_full_loops: bool,
+        detail_format: DetailFormat,
+        evidence_limit: u64,
+        min_ttl: u8,
+        max_ttl: u8,
+        interval: Duration,
+        run_once: bool,
+        ingested_files: u64,
+        phantom: PhantomData<T>,
+    }
+
+    impl<T: 'static + Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> WatchMode<T> {
+        pub fn new(matches: ArgMatches) -> WatchMode<T> {
+            let mode = ModeEnum::Watch;
+            let mode_string = mode.to_string().to_lowercase();
+            let sub_matches = matches.subcommand_matches(mode_string).unwrap();
+
+            let min_ttl = sub_matches.value_of("min_ttl").unwrap().parse().unwrap();
+            let max_ttl = sub_matches.value_of("max_ttl").unwrap().parse().unwrap();
+
+            if min_ttl >= max_ttl {
+                error!("min_ttl >= max_ttl, aborting!");
+                exit(1);
+            }
+
+            let watch_dir: PathBuf = load_path_param(sub_matches, "watch_dir");
+            if !watch_dir.is_dir() {
+                error!("{} is not a directory!", watch_dir.to_str().unwrap_or(""));
+                exit(1);
+            }
+
+            let loop_storage_path;
+            if let Some(value) = sub_matches.value_of("loop_output") {
+                loop_storage_path = value.to_owned();
+                if !create_dir_if_not_existing(&loop_storage_path) {
+                    error!("Could not create output directory!");
+                    exit(1);
+                }
+            } else {
+                error!("Could not read loop_output!");
+                exit(1);
+            }
+
+            // resuming a watch on an existing project requires --append,
+            // overwriting replaces the existing project after the first poll
+            let output_policy = load_output_policy(&matches, true);
+            let project_output;
+            if let Ok(value) = prepare_project_output(Path::new(&loop_storage_path), output_policy) {
+                project_output = value;
+            } else {
+                exit(1);
+            }
+            let loop_storage_path = project_output.write_path().to_str().unwrap().to_string();
+
+            let mut detail_format = DetailFormat::Text;
+            if sub_matches.occurrences_of("binary_details") > 0 {
+                detail_format = DetailFormat::Binary;
+            }
+
+            // a file counts as completed once it was not modified for settle seconds
+            let settle = parse_param::<u64>(sub_matches, "settle", 10);
+            let interval = parse_param::<u64>(sub_matches, "interval", 30);
+
+            let watcher;
+            if let Ok(value) = ProjectWatcher::new(&watch_dir, project_output.write_path(), Duration::from_secs(settle)) {
+                watcher = value;
+            } else {
+                error!("Could not read processed files of the project!");
+                exit(1);
+            }
+
+            WatchMode {
+                mode,
+                watcher,
+                project_output,
+                loop_storage_path,
+                imperiled_router_test_file: sub_matches.value_of("imperiled_router_test").unwrap_or("").to_string(),
+                imperiled_router_blocklist: sub_matches.value_of("imperiled_blocklist_prefixes").unwrap_or("").to_string(),
+                only_full_loops: sub_matches.occurrences_of("only_full_loops") > 0,
+                detail_format,
+                evidence_limit: parse_param::<u64>(sub_matches, "evidence", 0),
+                min_ttl,
+                max_ttl,
+                interval: Duration::from_secs(interval),
+                run_once: sub_matches.occurrences_of("once") > 0,
+                ingested_files: 0,
+                phantom: PhantomData,
+            }
+        }
+
+        fn poll(&mut self) -> u64 {
+            let loop_storage_path = self.loop_storage_path.clone();
+            let imperiled_router_test_file = self.imperiled_router_test_file.clone();
+            let imperiled_router_blocklist = self.imperiled_router_blocklist.clone();
+            let only_full_loops = self.only_full_loops;
+            let detail_format = self.detail_format;
+            let evidence_limit = self.evidence_limit;
+            let min_ttl = self.min_ttl;
+            let max_ttl = self.max_ttl;
+
+            let create_mode = || -> Box<dyn ModeTrait> {
+                let loop_analysis = LoopAnalysis::<T>::new(only_full_loops, loop_storage_path.clone(), &imperiled_router_test_file,
+                                                           &imperiled_router_blocklist, detail_format, evidence_limit);
+                Box::new(LoopsMode::from_analysis(loop_analysis, min_ttl, max_ttl))
+            };
+
+            let ingested = match self.watcher.poll_once(create_mode) {
+                Ok(value) => value,
+                Err(_) => {
+                    error!("Could not ingest new files!");
+                    exit(1);
+                }
+            };
+
+            if self.project_output.is_staged() {
+                if self.project_output.commit().is_err() {
+                    error!("Could not replace the existing project!");
+                    exit(1);
+                }
+                self.loop_storage_path = self.project_output.path().to_str().unwrap().to_string();
+                self.watcher.move_project(self.project_output.path());
+            }
+            ingested
+        }
+    }
+
+    impl<T: 'static + Display + Ord + Copy + Clone + Hash + IpAddrExt + FromStr> ModeTrait for WatchMode<T> {
+        fn get_mode(&self) -> ModeEnum {
+            self.mode
+        }
+
+        fn no_input_capable(&self) -> bool {
+            true
+        }
+
+        fn parse_comment_line(&mut self, _input: &str) {
+            // Dummy implementation, just ignore comment lines
+        }
+
+        fn parse_string_line(&mut self, _input: &str) {
+            // Input files are picked up from the watch directory
+        }
+
+        fn do_file_rotate(&mut self, _file_number: u64, _file_name: &str) {
+            // Every watched file is handled by its own loops analysis
+        }
+
+        fn do_calculations(&mut self) {
+            loop {
+                let ingested = self.poll();
+                if ingested > 0 {
+                    info!("Ingested {} new files", ingested);
+                }
+                self.ingested_files += ingested;
+
+                if self.run_once {
+                    break;
+                }
+                sleep(self.interval);
+            }
+        }
+
+        fn print_output(&self) {
+            println!("Ingested {} files into {}", self.ingested_files, self.loop_storage_path);
+        }
+
+        fn close(&mut self) {}
+    }
+}
@@ -0,0 +1,330 @@
+pub mod asn_db {
+    use std::collections::HashMap;
+    use std::fs::{self, File};
+    use std::io::{BufReader, Read, Write};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::path::Path;
+    use std::time::SystemTime;
+    use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+    use log::error;
+    use md5::{Md5, Digest};
+
+    use crate::structs::{ASNTree, ASNTreeRoot, ASNOrigin, AtomicFile, YarrpError};
+
+    const MAGIC: &[u8; 8] = b"YTASNDB\0";
+    const VERSION: u32 = 2;
+
+    /// Header of a compiled ASN database
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ASNDatabaseHeader {
+        /// MD5 of the routeviews file or RIB dump the database was built from
+        pub source_hash: [u8; 16],
+        /// size of the source in bytes
+        pub source_size: u64,
+        /// modification time of the source in nanoseconds since the epoch
+        pub source_modified: u64,
+        /// unix timestamp of the compilation
+        pub created: u64,
+        /// root network of the tree, defines the address family
+        pub network: IpNet,
+    }
+
+    /// Compiled prefix to origin table, read in one go.
+    ///
+    /// Layout (little endian): magic, version u32, source MD5, source size u64, source modification time u64, created u64,
+    /// root network with family byte (4 or 6), origin count u32 with u32 length prefixed origins in prefix2as notation
+    /// (empty for an unknown origin),
+    /// network count u32 with address, prefix length u8 and origin index u32 per network.
+    /// Addresses have 4 or 16 bytes depending on the root network.
+    pub struct ASNDatabase {
+        header: ASNDatabaseHeader,
+        data: Vec<u8>,
+        body_start: usize,
+    }
+
+    /// Bounds checked reading of little endian values
+    struct DatabaseBuffer<'a> {
+        data: &'a [u8],
+        position: usize,
+    }
+
+    impl<'a> DatabaseBuffer<'a> {
+        fn bytes(&mut self, length: usize) -> Result<&'a [u8], YarrpError> {
+            if self.data.len() - self.position < length {
+                error!("ASN database is truncated!");
+                return Err(YarrpError::CouldNotParseError);
+            }
+            let bytes = &self.data[self.position..self.position + length];
+            self.position += length;
+            Ok(bytes)
+        }
+
+        fn read_u8(&mut self) -> Result<u8, YarrpError> {
+            Ok(self.bytes(1)?[0])
+        }
+
+        fn read_u32(&mut self) -> Result<u32, YarrpError> {
+            let mut value = [0u8; 4];
+            value.copy_from_slice(self.bytes(4)?);
+            Ok(u32::from_le_bytes(value))
+        }
+
+        fn read_u64(&mut self) -> Result<u64, YarrpError> {
+            let mut value = [0u8; 8];
+            value.copy_from_slice(self.bytes(8)?);
+            Ok(u64::from_le_bytes(value))
+        }
+
+        fn read_network(&mut self, is_v4: bool) -> Result<IpNet, YarrpError> {
+            let network = if is_v4 {
+                let mut octets = [0u8; 4];
+                octets.copy_from_slice(self.bytes(4)?);
+                Ipv4Net::new(Ipv4Addr::from(octets), self.read_u8()?).map(IpNet::V4)
+            } else {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(self.bytes(16)?);
+                Ipv6Net::new(Ipv6Addr::from(octets), self.read_u8()?).map(IpNet::V6)
+            };
+
+            match network {
+                Ok(network) => Ok(network),
+                Err(_) => {
+                    error!("Invalid prefix length in ASN database!");
+                    Err(YarrpError::CouldNotParseError)
+                }
+            }
+        }
+    }
+
+    fn write_network(output: &mut Vec<u8>, network: &IpNet) {
+        match network {
+            IpNet::V4(net) => output.extend_from_slice(&net.network().octets()),
+            IpNet::V6(net) => output.extend_from_slice(&net.network().octets()),
+        }
+        output.push(network.prefix_len());
+    }
+
+    /// size and modification time of a source file, a database of a source with both unchanged is up to date
+    pub fn source_metadata(path: &Path) -> Result<(u64, u64), YarrpError> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified()?.duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+        Ok((metadata.len(), modified))
+    }
+
+    /// MD5 of a source file, used to detect stale databases
+    pub fn source_hash(path: &Path) -> Result<[u8; 16], YarrpError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut hasher = Md5::new();
+        let mut buffer = vec![0u8; 1 << 16];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+
+        let mut hash = [0u8; 16];
+        hash.copy_from_slice(&hasher.finalize());
+        Ok(hash)
+    }
+
+    fn write_family(output: &mut Vec<u8>, network: &IpNet) {
+        output.push(match network {
+            IpNet::V4(_) => 4,
+            IpNet::V6(_) => 6,
+        });
+    }
+
+    /// serialises all announced networks of the tree, compiled from source_path
+    pub fn write_asn_database(path: &Path, root: &ASNTreeRoot, source_path: &Path) -> Result<(), YarrpError> {
+        let hash = source_hash(source_path)?;
+        let (source_size, source_modified) = source_metadata(source_path)?;
+        let created = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        let networks = root.announced_networks();
+        let mut origins: Vec<&ASNOrigin> = Vec::new();
+        let mut origin_index = HashMap::new();
+        let mut entries = Vec::with_capacity(networks.len() * 21);
+
+        for (network, origin) in networks.iter() {
+            let index = *origin_index.entry(*origin).or_insert_with(|| {
+                origins.push(*origin);
+                origins.len() as u32 - 1
+            });
+            write_network(&mut entries, network);
+            entries.extend_from_slice(&index.to_le_bytes());
+        }
+
+        let mut output = Vec::with_capacity(entries.len() + origins.len() * 12 + 64);
+        output.extend_from_slice(MAGIC);
+        output.extend_from_slice(&VERSION.to_le_bytes());
+        output.extend_from_slice(&hash);
+        output.extend_from_slice(&source_size.to_le_bytes());
+        output.extend_from_slice(&source_modified.to_le_bytes());
+        output.extend_from_slice(&created.to_le_bytes());
+        write_family(&mut output, root.get_network());
+        write_network(&mut output, root.get_network());
+
+        output.extend_from_slice(&(origins.len() as u32).to_le_bytes());
+        for origin in origins {
+            // parses back to an unknown origin, unlike its displayed "undefined"
+            let origin = match origin {
+                ASNOrigin::Unknown => String::new(),
+                origin => origin.to_string(),
+            };
+            output.extend_from_slice(&(origin.len() as u32).to_le_bytes());
+            output.extend_from_slice(origin.as_bytes());
+        }
+        output.extend_from_slice(&(networks.len() as u32).to_le_bytes());
+        output.extend_from_slice(&entries);
+
+        let mut output_file = AtomicFile::create(path)?;
+        output_file.write_all(&output)?;
+        output_file.commit()
+    }
+
+    impl ASNDatabaseHeader {
+        /// whether the database was compiled from the current content of source_path,
+        /// the source is only hashed if its size is unchanged but its modification time differs
+        pub fn matches_source(&self, source_path: &Path) -> Result<bool, YarrpError> {
+            let (size, modified) = source_metadata(source_path)?;
+            if size != self.source_size {
+                return Ok(false);
+            }
+            if modified == self.source_modified {
+                return Ok(true);
+            }
+            Ok(source_hash(source_path)? == self.source_hash)
+        }
+    }
+
+    impl ASNDatabase {
+        /// reads the whole database and parses its header
+        pub fn open(path: &Path) -> Result<ASNDatabase, YarrpError> {
+            let data = fs::read(path)?;
+            let mut buffer = DatabaseBuffer { data: &data, position: 0 };
+
+            if buffer.bytes(MAGIC.len())? != MAGIC {
+                error!("{} is no ASN database!", path.to_str().unwrap_or(""));
+                return Err(YarrpError::NotCompatibleError);
+            }
+
+            let version = buffer.read_u32()?;
+            if version != VERSION {
+                error!("ASN database version {} is not supported!", version);
+                return Err(YarrpError::NotCompatibleError);
+            }
+
+            let mut source_hash = [0u8; 16];
+            source_hash.copy_from_slice(buffer.bytes(16)?);
+            let source_size = buffer.read_u64()?;
+            let source_modified = buffer.read_u64()?;
+            let created = buffer.read_u64()?;
+            let is_v4 = match buffer.read_u8()? {
+                4 => true,
+                6 => false,
+                family => {
+                    error!("Unknown address family {} in ASN database!", family);
+                    return Err(YarrpError::CouldNotParseError);
+                }
+            };
+            let network = buffer.read_network(is_v4)?;
+
+            let body_start = buffer.position;
+            Ok(ASNDatabase {
+                header: ASNDatabaseHeader { source_hash, source_size, source_modified, created, network },
+                data,
+                body_start,
+            })
+        }
+
+        pub fn header(&self) -> &ASNDatabaseHeader {
+            &self.header
+        }
+
+        pub fn build_tree(&self) -> Result<ASNTreeRoot, YarrpError> {
+            let mut buffer = DatabaseBuffer { data: &self.data, position: self.body_start };
+            let is_v4 = self.header.network.network().is_ipv4();
+
+            let origin_count = buffer.read_u32()? as usize;
+            let mut origins = Vec::with_capacity(origin_count);
+            for _ in 0..origin_count {
+                let length = buffer.read_u32()? as usize;
+                let origin = String::from_utf8_lossy(buffer.bytes(length)?);
+                origins.push(ASNOrigin::parse(&origin));
+            }
+
+            let mut root = ASNTreeRoot::new(self.header.network)?;
+            let network_count = buffer.read_u32()?;
+            for _ in 0..network_count {
+                let network = buffer.read_network(is_v4)?;
+                let origin = match origins.get(buffer.read_u32()? as usize) {
+                    Some(origin) => origin.clone(),
+                    None => {
+                        error!("Invalid origin index in ASN database!");
+                        return Err(YarrpError::CouldNotParseError);
+                    }
+                };
+
+                if !root.add_origin(network, origin) {
+                    error!("Could not add network {} to tree!", network);
+                }
+            }
+            Ok(root)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use ipnet::IpNet;
+    use crate::structs::{ASNDatabase, ASNTree, ASNTreeRoot, ASNOrigin, source_hash, write_asn_database};
+    use crate::helpers::test_helper::TempFixture;
+
+    /// offset of the family byte, after magic, version, source hash, size, modification time and creation time
+    const FAMILY_OFFSET: usize = 8 + 4 + 16 + 8 + 8 + 8;
+
+    #[test]
+    fn database_round_trip() {
+        let mut root = ASNTreeRoot::new(IpNet::from_str("0.0.0.0/0").unwrap()).ok().unwrap();
+        root.add_network(IpNet::from_str("10.0.0.0/8").unwrap(), "64496");
+        root.add_network(IpNet::from_str("10.1.0.0/16").unwrap(), "64497_64498");
+        root.add_network(IpNet::from_str("192.0.2.0/24").unwrap(), "64499,64500");
+        root.add_origin(IpNet::from_str("203.0.113.0/24").unwrap(), ASNOrigin::Unknown);
+
+        let source = TempFixture::file("asn_db.pfx2as", "10.0.0.0\t8\t64496\n");
+        let path = TempFixture::new("asn_db.bin");
+        write_asn_database(path.path(), &root, source.path()).ok().unwrap();
+
+        let database = ASNDatabase::open(path.path()).ok().unwrap();
+        assert_eq!(database.header().source_hash, source_hash(source.path()).ok().unwrap());
+        assert_eq!(database.header().source_size, 17);
+        assert_eq!(database.header().network, IpNet::from_str("0.0.0.0/0").unwrap());
+        assert!(database.header().matches_source(source.path()).ok().unwrap());
+
+        let loaded = database.build_tree().ok().unwrap();
+        assert_eq!(loaded.get_num_children(), 4);
+        let origin = |address: &str| loaded.find_node(&IpAddr::from_str(address).unwrap()).map(|node| node.get_origin().clone());
+        assert_eq!(origin("10.2.0.1"), Some(ASNOrigin::parse("64496")));
+        assert_eq!(origin("10.1.0.1"), Some(ASNOrigin::parse("64497_64498")));
+        assert_eq!(origin("192.0.2.1"), Some(ASNOrigin::parse("64499,64500")));
+        assert_eq!(origin("203.0.113.1"), Some(ASNOrigin::Unknown), "Unknown origins should stay unknown");
+        assert_eq!(origin("198.51.100.1"), None);
+
+        let mut data = fs::read(path.path()).unwrap();
+        data[FAMILY_OFFSET] = 5;
+        fs::write(path.path(), &data).unwrap();
+        assert!(ASNDatabase::open(path.path()).is_err(), "Family bytes other than 4 and 6 are invalid");
+
+        fs::write(path.path(), b"not a database").unwrap();
+        assert!(ASNDatabase::open(path.path()).is_err());
+    }
+}
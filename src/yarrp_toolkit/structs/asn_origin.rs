@@ -0,0 +1,218 @@
+pub mod asn_origin {
+    use std::collections::{HashMap, HashSet};
+    use std::fmt::{self, Display, Formatter};
+    use itertools::Itertools;
+
+    use crate::structs::stable_cmp;
+
+    /// How an AS was attributed to an address
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum ASNOriginKind {
+        Single,
+        Moas,
+        AsSet,
+    }
+
+    impl Display for ASNOriginKind {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            let kind = match self {
+                ASNOriginKind::Single => "single",
+                ASNOriginKind::Moas => "moas",
+                ASNOriginKind::AsSet => "as_set",
+            };
+            write!(f, "{}", kind)
+        }
+    }
+
+    /// Origin of an announced prefix. In prefix2as notation multiple origins (MOAS) are separated by '_',
+    /// the members of an AS_SET by ','.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub enum ASNOrigin {
+        /// no announced prefix covers the address
+        Unknown,
+        Single(String),
+        /// prefix announced by multiple origins, each a single AS or an AS set
+        Moas(Vec<ASNOrigin>),
+        /// aggregate announced with an AS_SET, any member may be the origin
+        AsSet(Vec<String>),
+    }
+
+    impl ASNOrigin {
+        pub fn parse(origin: &str) -> ASNOrigin {
+            let mut origins: Vec<ASNOrigin> = origin.split('_')
+                .filter(|item| !item.is_empty())
+                .map(ASNOrigin::parse_set)
+                .unique()
+                .collect();
+
+            match origins.len() {
+                0 => ASNOrigin::Unknown,
+                1 => origins.remove(0),
+                _ => ASNOrigin::Moas(origins),
+            }
+        }
+
+        fn parse_set(origin: &str) -> ASNOrigin {
+            let mut members: Vec<String> = origin.split(',').filter(|item| !item.is_empty()).map(|item| item.to_string()).collect();
+            members.sort_by(|a, b| stable_cmp(a, b));
+            members.dedup();
+
+            if members.len() == 1 {
+                ASNOrigin::Single(members.remove(0))
+            } else {
+                ASNOrigin::AsSet(members)
+            }
+        }
+
+        pub fn is_known(&self) -> bool {
+            *self != ASNOrigin::Unknown
+        }
+
+        pub fn kind(&self) -> Option<ASNOriginKind> {
+            match self {
+                ASNOrigin::Unknown => None,
+                ASNOrigin::Single(_) => Some(ASNOriginKind::Single),
+                ASNOrigin::Moas(_) => Some(ASNOriginKind::Moas),
+                ASNOrigin::AsSet(_) => Some(ASNOriginKind::AsSet),
+            }
+        }
+
+        /// all ASNs that may originate the prefix
+        pub fn asns(&self) -> Vec<String> {
+            self.attributions().into_iter().map(|(asn, _kind)| asn.clone()).collect()
+        }
+
+        /// all ASNs with the kind they are attributed by, members of an AS set within a MOAS count as MOAS
+        pub fn attributions(&self) -> Vec<(&String, ASNOriginKind)> {
+            match self {
+                ASNOrigin::Unknown => Vec::new(),
+                ASNOrigin::Single(asn) => vec![(asn, ASNOriginKind::Single)],
+                ASNOrigin::AsSet(members) => members.iter().map(|asn| (asn, ASNOriginKind::AsSet)).collect(),
+                ASNOrigin::Moas(origins) => origins.iter()
+                    .flat_map(|origin| origin.attributions())
+                    .map(|(asn, _kind)| (asn, ASNOriginKind::Moas))
+                    .unique_by(|(asn, _kind)| *asn)
+                    .collect(),
+            }
+        }
+    }
+
+    /// prefix2as notation, unknown origins are written as "undefined"
+    impl Display for ASNOrigin {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                ASNOrigin::Unknown => write!(f, "undefined"),
+                ASNOrigin::Single(asn) => write!(f, "{}", asn),
+                ASNOrigin::AsSet(members) => write!(f, "{}", members.join(",")),
+                ASNOrigin::Moas(origins) => write!(f, "{}", origins.iter().join("_")),
+            }
+        }
+    }
+
+    /// Routers or loops of an AS, split by the kind of attribution
+    #[derive(Default)]
+    pub struct ASNMembers {
+        pub single: HashSet<String>,
+        pub moas: HashSet<String>,
+        pub as_set: HashSet<String>,
+    }
+
+    impl ASNMembers {
+        pub fn insert(&mut self, kind: ASNOriginKind, item: &str) {
+            let set = match kind {
+                ASNOriginKind::Single => &mut self.single,
+                ASNOriginKind::Moas => &mut self.moas,
+                ASNOriginKind::AsSet => &mut self.as_set,
+            };
+            set.insert(item.to_string());
+        }
+
+        /// all routers or loops of the AS, independent of the kind of attribution
+        pub fn all(&self) -> HashSet<&String> {
+            self.single.iter().chain(&self.moas).chain(&self.as_set).collect()
+        }
+    }
+
+    /// all ASNs of the given origins
+    /// ASN of a value like AS64496, as64496 or 64496
+    pub fn parse_asn(value: &str) -> Option<u32> {
+        let value = value.trim();
+        let value = value.strip_prefix("AS").or_else(|| value.strip_prefix("as")).unwrap_or(value);
+        value.parse().ok()
+    }
+
+    pub fn flatten_origins<'a, I>(origins: I) -> HashSet<String>
+        where I: IntoIterator<Item = &'a ASNOrigin> {
+        origins.into_iter().flat_map(|origin| origin.asns()).collect()
+    }
+
+    /// merges origins sharing an ASN into one domain, so a single AS and a MOAS prefix including it
+    /// span one domain instead of two, unknown origins are left out
+    pub fn merge_origins<'a, I>(origins: I) -> Vec<HashSet<String>>
+        where I: IntoIterator<Item = &'a ASNOrigin> {
+        let mut domains: Vec<HashSet<String>> = Vec::new();
+        for origin in origins {
+            let mut merged: HashSet<String> = origin.asns().into_iter().collect();
+            if merged.is_empty() {
+                continue;
+            }
+
+            let (overlapping, mut disjoint): (Vec<HashSet<String>>, Vec<HashSet<String>>) = domains.into_iter()
+                .partition(|domain| !domain.is_disjoint(&merged));
+            for domain in overlapping {
+                merged.extend(domain);
+            }
+            disjoint.push(merged);
+            domains = disjoint;
+        }
+        domains
+    }
+
+    pub type MapASNMembers = HashMap<String, ASNMembers>;
+    pub type MapOrigin = HashMap<String, ASNOrigin>;
+    pub type MapSetOrigin = HashMap<String, HashSet<ASNOrigin>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{ASNOrigin, ASNOriginKind, merge_origins, parse_asn};
+
+    #[test]
+    fn parse_keeps_moas_and_as_set_apart() {
+        let single = ASNOrigin::parse("64496");
+        assert_eq!(single, ASNOrigin::Single("64496".to_string()));
+
+        let as_set = ASNOrigin::parse("64512,64511");
+        assert_eq!(as_set, ASNOrigin::AsSet(vec!["64511".to_string(), "64512".to_string()]));
+        assert_eq!(as_set.to_string(), "64511,64512");
+
+        let moas = ASNOrigin::parse("64510_64511,64512");
+        assert_eq!(moas.kind(), Some(ASNOriginKind::Moas));
+        assert_eq!(moas.asns(), vec!["64510".to_string(), "64511".to_string(), "64512".to_string()]);
+        assert_eq!(moas.to_string(), "64510_64511,64512");
+        assert_ne!(ASNOrigin::parse("64511_64512").kind(), ASNOrigin::parse("64511,64512").kind());
+
+        assert_eq!(ASNOrigin::parse("64496_64496"), single, "Repeated origins are no MOAS");
+        assert!(!ASNOrigin::parse("").is_known());
+    }
+
+    #[test]
+    fn merge_origins_sharing_an_asn() {
+        let origins = vec![ASNOrigin::parse("64496"), ASNOrigin::parse("64496_64497"), ASNOrigin::parse("64498"), ASNOrigin::Unknown];
+        let mut domains: Vec<usize> = merge_origins(&origins).iter().map(|domain| domain.len()).collect();
+        domains.sort();
+        assert_eq!(domains, vec![1, 2], "The single AS and the MOAS prefix including it are one domain");
+
+        let bridged = vec![ASNOrigin::parse("64496"), ASNOrigin::parse("64497"), ASNOrigin::parse("64496,64497")];
+        assert_eq!(merge_origins(&bridged).len(), 1, "An AS set bridges both of its members");
+        assert!(merge_origins(&[ASNOrigin::Unknown]).is_empty());
+    }
+
+    #[test]
+    fn asn_values() {
+        assert_eq!(parse_asn("AS64496"), Some(64496));
+        assert_eq!(parse_asn(" as64496 "), Some(64496));
+        assert_eq!(parse_asn("64496"), Some(64496));
+        assert_eq!(parse_asn("ASX"), None);
+    }
+}
@@ -1,32 +1,68 @@
 pub mod asn_tree {
-    use log::{error, info, trace};
-    use ipnet::IpNet;
-    use crate::structs::YarrpError;
+    use log::{error, trace};
+    use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+    use crate::structs::{YarrpError, ASNOrigin};
     use std::net::{IpAddr, Ipv6Addr, Ipv4Addr};
-    use std::collections::HashMap;
 
 
     pub trait ASNTree {
         fn add_network(&mut self, new_network: IpNet, asn: &str) -> bool;
-        fn add_child(&mut self, network: IpNet, asn: &str);
         fn find_node(&self, address: &IpAddr) -> Option<&ASNTreeNode>;
         fn get_network(&self) -> &IpNet;
-        fn get_asn(&self) -> &Vec<String>;
+        fn get_origin(&self) -> &ASNOrigin;
+        fn get_asn(&self) -> Vec<String>;
         fn get_num_asn(&self) -> usize;
         fn get_num_children(&self) -> u64;
     }
 
+    /// Addresses and networks are stored as 128 bit keys, IPv4 in the upper 32 bits,
+    /// so both families share the bit handling of the trie.
+    fn network_key(network: &IpNet) -> u128 {
+        address_key(&network.network())
+    }
+
+    fn address_key(address: &IpAddr) -> u128 {
+        match address {
+            IpAddr::V4(ip4) => (u32::from(*ip4) as u128) << 96,
+            IpAddr::V6(ip6) => u128::from(*ip6),
+        }
+    }
+
+    fn key_bit(key: u128, position: u8) -> usize {
+        ((key >> (127 - position as u32)) & 1) as usize
+    }
+
+    /// number of leading bits two keys have in common
+    fn common_length(first: u128, second: u128) -> u8 {
+        (first ^ second).leading_zeros() as u8
+    }
+
+    fn key_network(key: u128, prefix_len: u8, is_v4: bool) -> IpNet {
+        let network = if is_v4 {
+            Ipv4Net::new(Ipv4Addr::from((key >> 96) as u32), prefix_len).map(IpNet::V4)
+        } else {
+            Ipv6Net::new(Ipv6Addr::from(key), prefix_len).map(IpNet::V6)
+        };
+        // prefix lengths are taken from existing networks of the same family
+        network.unwrap().trunc()
+    }
+
+    /// Node of the Patricia trie, a single branch without announcements in between is stored as one node.
+    /// Glue nodes only join two branches and carry no ASN.
     pub struct ASNTreeNode {
         network: IpNet,
-        asn: Vec<String>,
-        children: Vec<ASNTreeNode>,
+        key: u128,
+        origin: ASNOrigin,
+        announced: bool,
+        children: [Option<Box<ASNTreeNode>>; 2],
         num_children: u64,
     }
 
+    /// Root of the trie for one address family, covering all prefix lengths
     pub struct ASNTreeRoot {
         network: IpNet,
-        asn: Vec<String>,
-        pub children: HashMap<u16, Vec<ASNTreeNode>>,
+        origin: ASNOrigin,
+        trie: Option<Box<ASNTreeNode>>,
         num_children: u64,
     }
 
@@ -34,158 +70,188 @@ pub mod asn_tree {
         pub fn new(network: IpNet) -> Result<ASNTreeRoot, YarrpError> {
             Ok(ASNTreeRoot {
                 network,
-                asn: vec!["root".to_string()],
-                children: HashMap::new(),
+                origin: ASNOrigin::Single("root".to_string()),
+                trie: None,
                 num_children: 0,
             })
         }
 
-        pub fn get_net_hash(network: &IpNet) -> Option<u16> {
-            let net = &network.network();
-            let hash: u16;
-
-            match net {
-                IpAddr::V4(ip4) => {
-                    if network.prefix_len() < 8 {
-                        return None;
-                    }
-                    hash = ASNTreeRoot::get_v4_addr_hash(ip4)
-                }
-                IpAddr::V6(ip6) => {
-                    if network.prefix_len() < 16 {
-                        return None;
-                    }
-                    hash = ASNTreeRoot::get_v6_addr_hash(ip6);
-                }
+        /// adds a network with an already parsed origin, see add_network
+        pub fn add_origin(&mut self, new_network: IpNet, origin: ASNOrigin) -> bool {
+            if !self.network.contains(&new_network) {
+                error!("Network {} is not part of {}!", new_network, self.network);
+                return false;
             }
 
-            trace!("Using {} as hash for network {}", &hash, &network);
-
-            return Some(hash);
+            if ASNTreeNode::insert(&mut self.trie, new_network, origin) {
+                self.num_children += 1;
+            }
+            true
         }
 
-        pub fn get_addr_hash(addr: &IpAddr) -> Option<u16> {
-            let hash: u16;
+        /// all announced networks with their origin, covering networks before the networks below them
+        pub fn announced_networks(&self) -> Vec<(&IpNet, &ASNOrigin)> {
+            let mut networks = Vec::with_capacity(self.num_children as usize);
+            let mut stack: Vec<&ASNTreeNode> = self.trie.as_deref().into_iter().collect();
 
-            match addr {
-                IpAddr::V4(ip4) => {
-                    hash = ASNTreeRoot::get_v4_addr_hash(ip4)
+            while let Some(node) = stack.pop() {
+                if node.announced {
+                    networks.push((&node.network, &node.origin));
                 }
-                IpAddr::V6(ip6) => {
-                    hash = ASNTreeRoot::get_v6_addr_hash(ip6);
+                // right child is pushed first to visit the left one first
+                for child in node.children.iter().rev().flatten() {
+                    stack.push(child);
                 }
             }
-            return Some(hash);
-        }
-
-        fn get_v4_addr_hash(net: &Ipv4Addr) -> u16 {
-            net.octets()[0] as u16
-        }
-
-        fn get_v6_addr_hash(net: &Ipv6Addr) -> u16 {
-            trace!("upper: {} ; lower {}", net.octets()[0], net.octets()[1]);
-            let mut hash = net.octets()[0] as u16;
-            hash = (hash << 8) + net.octets()[1] as u16;
-
-            hash
+            networks
         }
     }
 
     impl ASNTreeNode {
         pub fn new(network: IpNet, asn: &str) -> Result<ASNTreeNode, YarrpError> {
-            let asn_list = asn.split("_");
-            let mut asn = Vec::new();
-            for item in asn_list {
-                let item = item.split(",");
-                for subitem in item {
-                    asn.push(subitem.to_string());
-                }
-            }
+            let mut node = ASNTreeNode::glue(network);
+            node.set_origin(ASNOrigin::parse(asn));
+            Ok(node)
+        }
 
-            Ok(ASNTreeNode {
+        fn glue(network: IpNet) -> ASNTreeNode {
+            let network = network.trunc();
+            ASNTreeNode {
                 network,
-                asn,
-                children: Vec::new(),
+                key: network_key(&network),
+                origin: ASNOrigin::Unknown,
+                announced: false,
+                children: [None, None],
                 num_children: 0,
-            })
+            }
         }
-    }
 
-    impl ASNTree for ASNTreeRoot {
-        fn add_network(&mut self, new_network: IpNet, asn: &str) -> bool {
-            let net_hash;
-            if let Some(hash) = ASNTreeRoot::get_net_hash(&new_network) {
-                net_hash = hash;
-            } else {
-                info!("Ignored prefix {} due to no hash, probably below threshold.", new_network);
-                return true;
+        fn set_origin(&mut self, origin: ASNOrigin) {
+            self.origin = origin;
+            self.announced = true;
+        }
+
+        fn prefix_len(&self) -> u8 {
+            self.network.prefix_len()
+        }
+
+        /// number of announced networks in this subtree, including the node itself
+        fn announced_count(&self) -> u64 {
+            self.num_children + self.announced as u64
+        }
+
+        /// inserts the network into the subtree stored at slot, returns true if the network was not known before
+        fn insert(slot: &mut Option<Box<ASNTreeNode>>, network: IpNet, origin: ASNOrigin) -> bool {
+            let node = match slot {
+                Some(node) => node,
+                None => {
+                    let leaf = ASNTreeNode::glue(network);
+                    let leaf = slot.get_or_insert(Box::new(leaf));
+                    leaf.set_origin(origin);
+                    return true;
+                }
+            };
+
+            let key = network_key(&network);
+            let prefix_len = network.prefix_len();
+            let common = common_length(node.key, key).min(node.prefix_len()).min(prefix_len);
+
+            if common == node.prefix_len() {
+                // the node covers the new network, either it is the node itself or it goes below
+                return node.add_below(network, origin);
             }
 
-            if !self.children.contains_key(&net_hash) {
-                self.children.insert(net_hash.clone(), Vec::new());
+            let old_node = slot.take().unwrap();
+            let mut new_node;
+            if common == prefix_len {
+                // the new network covers the node
+                new_node = ASNTreeNode::glue(network);
+                new_node.set_origin(origin);
+            } else {
+                // both branch off below their common prefix
+                new_node = ASNTreeNode::glue(key_network(key, common, network.network().is_ipv4()));
+                let mut leaf = ASNTreeNode::glue(network);
+                leaf.set_origin(origin);
+                new_node.children[key_bit(key, common)] = Some(Box::new(leaf));
+                new_node.num_children += 1;
             }
 
-            if let Some(hash_children) = self.children.get_mut(&net_hash) {
-                // let children check if they are a super net
-                for child in hash_children.iter_mut() {
-                    // if we found some net that takes the new net
-                    if child.add_network(new_network, asn) {
-                        self.num_children += 1;
-                        return true;
-                    }
-                }
+            new_node.num_children += old_node.announced_count();
+            let old_bit = key_bit(old_node.key, common);
+            new_node.children[old_bit] = Some(old_node);
+            *slot = Some(Box::new(new_node));
+            true
+        }
 
-                // add to own children if not already added
-                if let Ok(leaf) = ASNTreeNode::new(new_network, asn) {
-                    hash_children.push(leaf);
-                } else {
-                    error!("Could not added network {} as new leaf!", new_network);
+        /// inserts a network covered by this node
+        fn add_below(&mut self, network: IpNet, origin: ASNOrigin) -> bool {
+            let prefix_len = network.prefix_len();
+            if prefix_len == self.prefix_len() {
+                let added = !self.announced;
+                if !added {
+                    trace!("Replacing ASN of duplicate network {}", network);
                 }
-                self.num_children += 1;
+                self.set_origin(origin);
+                return added;
             }
 
-            return true;
+            let bit = key_bit(network_key(&network), self.prefix_len());
+            let added = ASNTreeNode::insert(&mut self.children[bit], network, origin);
+            if added {
+                self.num_children += 1;
+            }
+            added
         }
+    }
 
-        fn add_child(&mut self, _network: IpNet, _asn: &str) {}
+    /// most specific announced network below node containing the key
+    fn longest_match(mut node: Option<&ASNTreeNode>, key: u128) -> Option<&ASNTreeNode> {
+        let mut best = None;
+        while let Some(current) = node {
+            let prefix_len = current.prefix_len();
+            if common_length(current.key, key) < prefix_len {
+                break;
+            }
 
+            if current.announced {
+                best = Some(current);
+            }
+
+            if prefix_len >= 128 {
+                break;
+            }
+            node = current.children[key_bit(key, prefix_len)].as_deref();
+        }
+        best
+    }
+
+    impl ASNTree for ASNTreeRoot {
+        fn add_network(&mut self, new_network: IpNet, asn: &str) -> bool {
+            self.add_origin(new_network, ASNOrigin::parse(asn))
+        }
 
         fn find_node(&self, address: &IpAddr) -> Option<&ASNTreeNode> {
             trace!("Looking for node containing {}", address);
-            let net_suffix;
-            if let Some(hash) = ASNTreeRoot::get_addr_hash(&address) {
-                net_suffix = hash;
-            } else {
-                info!("Ignored prefix {} due to no hash, probably below threshold.", address);
+            if !self.network.contains(address) {
                 return None;
             }
-            trace!("Got net suffix {} for address {}", net_suffix, address);
-
-            // check if children contain network
-            if let Some(hash_children) = self.children.get(&net_suffix) {
-                trace!("Found suffix entry for {}", &net_suffix);
-                for child in hash_children {
-                    // if child contains network, return it
-                    if let Some(node) = child.find_node(address) {
-                        return Some(node);
-                    }
-                }
-            }
-
-            // otherwise this node is the best suited one, return nothing :shrug:
-            None
+            longest_match(self.trie.as_deref(), address_key(address))
         }
 
         fn get_network(&self) -> &IpNet {
             &self.network
         }
 
-        fn get_asn(&self) -> &Vec<String> {
-            &self.asn
+        fn get_origin(&self) -> &ASNOrigin {
+            &self.origin
+        }
+
+        fn get_asn(&self) -> Vec<String> {
+            self.origin.asns()
         }
 
         fn get_num_asn(&self) -> usize {
-            self.asn.len()
+            self.origin.attributions().len()
         }
 
         fn get_num_children(&self) -> u64 {
@@ -201,27 +267,8 @@ pub mod asn_tree {
                 return false;
             }
 
-            // let children check if they are a super net
-            for child in self.children.iter_mut() {
-                // if we found some net that takes the new net
-                if child.add_network(new_network, asn) {
-                    self.num_children += 1;
-                    return true;
-                }
-            }
-
-            // add to own children if not already added
-            self.add_child(new_network, asn);
-            self.num_children += 1;
-            return true;
-        }
-
-        fn add_child(&mut self, network: IpNet, asn: &str) {
-            if let Ok(leaf) = ASNTreeNode::new(network, asn) {
-                self.children.push(leaf);
-            } else {
-                error!("Could not added network {} as new leaf!", network);
-            }
+            self.add_below(new_network, ASNOrigin::parse(asn));
+            true
         }
 
         fn find_node(&self, address: &IpAddr) -> Option<&ASNTreeNode> {
@@ -229,29 +276,23 @@ pub mod asn_tree {
             if !self.network.contains(address) {
                 return None;
             }
-
-            // check if children contain network
-            for child in &self.children {
-
-                // if child contains network, return it
-                if let Some(node) = child.find_node(address) {
-                    return Some(node);
-                }
-            }
-            // otherwise this node is the best suited one, return ourself
-            Some(self)
+            longest_match(Some(self), address_key(address))
         }
 
         fn get_network(&self) -> &IpNet {
             &self.network
         }
 
-        fn get_asn(&self) -> &Vec<String> {
-            &self.asn
+        fn get_origin(&self) -> &ASNOrigin {
+            &self.origin
+        }
+
+        fn get_asn(&self) -> Vec<String> {
+            self.origin.asns()
         }
 
         fn get_num_asn(&self) -> usize {
-            self.asn.len()
+            self.origin.attributions().len()
         }
 
         fn get_num_children(&self) -> u64 {
@@ -313,44 +354,9 @@ mod tests {
         assert!(root.add_network(net, asn));
     }
 
-    #[test]
-    fn test_ipv6_address_hash() {
-        let ip_addr = IpAddr::from_str("2001:200::66").unwrap();
-        let ip_net = IpNet::from_str("2001:200::/32").unwrap();
-
-        let hash_comparison: u16 = 0x2001;
-
-        if let Some(hash) = ASNTreeRoot::get_addr_hash(&ip_addr) {
-            assert_eq!(hash, hash_comparison, "Hash should be 0x2001");
-        } else {
-            panic!("Should have gotten a hash!");
-        }
-
-        if let Some(hash) = ASNTreeRoot::get_net_hash(&ip_net) {
-            assert_eq!(hash, hash_comparison, "Hash should be 0x2001");
-        } else {
-            panic!("Should have gotten a hash!");
-        }
-    }
-
-    #[test]
-    fn test_ipv4_address_hash() {
-        let ip_addr = IpAddr::from_str("172.18.10.66").unwrap();
-        let ip_net = IpNet::from_str("172.18.0.0/12").unwrap();
-
-        let hash_comparison: u16 = 172;
-
-        if let Some(hash) = ASNTreeRoot::get_addr_hash(&ip_addr) {
-            assert_eq!(hash, hash_comparison, "Hash should be 172");
-        } else {
-            panic!("Should have gotten a hash!");
-        }
-
-        if let Some(hash) = ASNTreeRoot::get_net_hash(&ip_net) {
-            assert_eq!(hash, hash_comparison, "Hash should be 0x2001");
-        } else {
-            panic!("Should have gotten a hash!");
-        }
+    fn lookup(root: &ASNTreeRoot, address: &str) -> Option<(String, Vec<String>)> {
+        let ip_addr = IpAddr::from_str(address).unwrap();
+        root.find_node(&ip_addr).map(|node| (node.get_network().to_string(), node.get_asn()))
     }
 
     #[test]
@@ -358,7 +364,7 @@ mod tests {
         let root = prepare_root_v6();
 
         assert_eq!(root.get_num_children(), 0, "number children should be 0");
-        assert_eq!(root.get_asn(), &vec!["root".to_string()], "ASN should be 'root'");
+        assert_eq!(root.get_asn(), vec!["root".to_string()], "ASN should be 'root'");
 
         let ip_addr = IpAddr::from_str("2001::66").unwrap();
         if let Some(_) = root.find_node(&ip_addr) {
@@ -372,7 +378,7 @@ mod tests {
         let root = prepare_root_v4();
 
         assert_eq!(root.get_num_children(), 0, "number children should be 0");
-        assert_eq!(root.get_asn(), &vec!["root".to_string()], "ASN should be 'root'");
+        assert_eq!(root.get_asn(), vec!["root".to_string()], "ASN should be 'root'");
 
         let ip_addr = IpAddr::from_str("123.0.0.1").unwrap();
         if let Some(_) = root.find_node(&ip_addr) {
@@ -381,77 +387,83 @@ mod tests {
     }
 
     #[test]
-    fn test_hashmap_prefilter_v6() {
+    fn test_other_family_rejected() {
         init();
-        let mut root = prepare_root_v6();
-
-        easy_add(&mut root, "2001::/32", "6939_1101_211722");
-        easy_add(&mut root, "2001:200::/23", "13030");
-        easy_add(&mut root, "2001:200:e000::/35", "7660");
-
-        assert_eq!(root.get_num_children(), 3, "number children should be 3");
-        assert_eq!(root.get_asn(), &vec!["root".to_string()], "ASN should be 'root'");
-
-        let net_suffix: u16 = 0x2001;
-        assert_eq!(root.children.len(), 1, "roots children hashmap should have one entry!");
+        let mut root = prepare_root_v4();
 
-        if let Some(vector) = root.children.get(&net_suffix) {
-            assert_eq!(vector.len(), 2, "hashmap entry vector should have two entries!");
-        } else {
-            panic!("Did not get a node for valid entry");
-        }
+        assert!(!root.add_network(IpNet::from_str("2001::/32").unwrap(), "1"));
+        assert_eq!(root.get_num_children(), 0, "number children should be 0");
+        assert!(lookup(&root, "2001::1").is_none());
     }
 
     #[test]
-    fn test_hashmap_prefilter_v4() {
+    fn test_short_prefixes_v6() {
         init();
         let mut root = prepare_root_v6();
 
-        easy_add(&mut root, "172.18.0.0/16", "1");
-        easy_add(&mut root, "172.19.0.0/16", "2");
-        easy_add(&mut root, "172.18.20.0/24", "3");
+        easy_add(&mut root, "2000::/12", "1");
+        easy_add(&mut root, "2400::/14", "2");
+        easy_add(&mut root, "2400:1000::/32", "3");
 
         assert_eq!(root.get_num_children(), 3, "number children should be 3");
-        assert_eq!(root.get_asn(), &vec!["root".to_string()], "ASN should be 'root'");
+        assert_eq!(lookup(&root, "2001::1"), Some(("2000::/12".to_string(), vec!["1".to_string()])));
+        assert_eq!(lookup(&root, "2401::1"), Some(("2400::/14".to_string(), vec!["2".to_string()])));
+        assert_eq!(lookup(&root, "2400:1000::1"), Some(("2400:1000::/32".to_string(), vec!["3".to_string()])));
+        assert!(lookup(&root, "2600::1").is_none());
+    }
 
-        let net_suffix: u16 = 172;
-        assert_eq!(root.children.len(), 1, "roots children hashmap should have one entry!");
+    #[test]
+    fn test_short_prefixes_v4() {
+        init();
+        let mut root = prepare_root_v4();
 
-        if let Some(vector) = root.children.get(&net_suffix) {
-            assert_eq!(vector.len(), 2, "hashmap entry vector should have two entries!");
-        } else {
-            panic!("Did not get a node for valid entry");
-        }
+        easy_add(&mut root, "8.0.0.0/7", "1");
+        easy_add(&mut root, "0.0.0.0/0", "2");
+        easy_add(&mut root, "9.9.9.0/24", "3");
+
+        assert_eq!(root.get_num_children(), 3, "number children should be 3");
+        assert_eq!(lookup(&root, "8.1.2.3"), Some(("8.0.0.0/7".to_string(), vec!["1".to_string()])));
+        assert_eq!(lookup(&root, "9.9.9.9"), Some(("9.9.9.0/24".to_string(), vec!["3".to_string()])));
+        assert_eq!(lookup(&root, "10.0.0.1"), Some(("0.0.0.0/0".to_string(), vec!["2".to_string()])), "Default route should match");
     }
 
     #[test]
-    fn test_filtered_v6() {
+    fn test_insertion_order_independent() {
         init();
-        let mut root = prepare_root_v6();
+        let networks = [("172.16.0.0/12", "1"), ("172.18.0.0/16", "2"), ("172.18.20.0/24", "3"), ("172.18.20.128/25", "4"), ("172.19.0.0/16", "5")];
+        let addresses = ["172.17.0.1", "172.18.0.1", "172.18.20.1", "172.18.20.200", "172.19.1.1", "172.20.0.1", "172.32.0.1"];
 
-        easy_add(&mut root, "2002::/12", "1");
-        easy_add(&mut root, "2003::/13", "1");
-        easy_add(&mut root, "2400::/14", "1");
-        easy_add(&mut root, "2600::/15", "1");
+        let mut forward = prepare_root_v4();
+        for (network, asn) in networks.iter() {
+            easy_add(&mut forward, network, asn);
+        }
 
-        assert_eq!(root.get_num_children(), 0, "number children should be 0");
-        assert_eq!(root.get_asn(), &vec!["root".to_string()], "ASN should be 'root'");
-    }
+        let mut backward = prepare_root_v4();
+        for (network, asn) in networks.iter().rev() {
+            easy_add(&mut backward, network, asn);
+        }
 
+        for address in addresses.iter() {
+            assert_eq!(lookup(&forward, address), lookup(&backward, address), "Lookup of {} depends on insertion order", address);
+        }
+        assert_eq!(lookup(&backward, "172.18.20.200").unwrap().1, vec!["4".to_string()]);
+        assert_eq!(lookup(&backward, "172.20.0.1").unwrap().1, vec!["1".to_string()], "Covering prefix inserted last should match");
+        assert!(lookup(&backward, "172.32.0.1").is_none());
 
+        let node = backward.find_node(&IpAddr::from_str("172.18.0.1").unwrap()).unwrap();
+        assert_eq!(node.get_num_children(), 2, "172.18.0.0/16 should hold two more specific networks");
+    }
 
     #[test]
-    fn test_filtered_v4() {
+    fn test_duplicate_network() {
         init();
         let mut root = prepare_root_v4();
 
-        easy_add(&mut root, "8.0.0.0/4", "1");
-        easy_add(&mut root, "9.0.0.0/5", "1");
-        easy_add(&mut root, "7.0.0.0/6", "1");
-        easy_add(&mut root, "4.0.0.0/7", "1");
+        easy_add(&mut root, "192.0.2.0/24", "1");
+        easy_add(&mut root, "192.0.2.0/24", "2,3");
 
-        assert_eq!(root.get_num_children(), 0, "number children should be 0");
-        assert_eq!(root.get_asn(), &vec!["root".to_string()], "ASN should be 'root'");
+        assert_eq!(root.get_num_children(), 1, "Duplicate network should be counted once");
+        assert_eq!(lookup(&root, "192.0.2.1").unwrap().1, vec!["2".to_string(), "3".to_string()]);
     }
 
     #[test]
@@ -501,7 +513,7 @@ mod tests {
     #[test]
     fn test_node_search_v4() {
         init();
-        let mut root = prepare_root_v6();
+        let mut root = prepare_root_v4();
 
         easy_add(&mut root, "172.18.0.0/16", "1_7_8");
         easy_add(&mut root, "172.19.0.0/16", "2");
@@ -536,9 +548,16 @@ mod tests {
             panic!("Should return a valid node!");
         }
 
+        let ip_addr = IpAddr::from_str("172.19.164.1").unwrap();
+        if let Some(node) = root.find_node(&ip_addr) {
+            assert_eq!(node.get_asn().get(0).unwrap(), "4", "ASN should be 4!");
+        } else {
+            panic!("Should return a valid node!");
+        }
+
         let ip_addr = IpAddr::from_str("172.17.255.255").unwrap();
         if let Some(_) = root.find_node(&ip_addr) {
             panic!("Should not result in value!");
         }
     }
-}
\ No newline at end of file
+}
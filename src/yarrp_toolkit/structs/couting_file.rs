@@ -1,10 +1,13 @@
 pub mod couting_file {
     use std::fs::{File, OpenOptions};
-    use std::path::{Path};
-    use log::{error};
-    use std::io::{Write};
+    use std::path::Path;
+    use log::{error, warn};
+    use std::io::{self, Write, Seek, SeekFrom};
     use std::fmt::Display;
 
+    use crate::structs::{AtomicFile, DetailFormat, DetailHeader, OutputPolicy, YarrpError, detect_detail_format, sort_binary_details};
+    use crate::traits::IpAddrExt;
+
     pub enum CountingEntity {
         NoWrite(CountingVoid),
         Write(CountingFile),
@@ -18,7 +21,7 @@ pub mod couting_file {
             }
         }
 
-        pub fn write_ip_line<T: Display + Clone + Copy>(&mut self, input: &T) -> bool {
+        pub fn write_ip_line<T: Display + Clone + Copy + IpAddrExt>(&mut self, input: &T) -> bool {
             match self {
                 CountingEntity::NoWrite(counting) => counting.write_ip_line(input),
                 CountingEntity::Write(counting) => counting.write_ip_line(input),
@@ -67,7 +70,7 @@ pub mod couting_file {
             return true;
         }
 
-        pub fn write_ip_line<T: Display + Clone + Copy>(&mut self, _input: &T) -> bool {
+        pub fn write_ip_line<T: Display + Clone + Copy + IpAddrExt>(&mut self, _input: &T) -> bool {
             self.line_counter += 1;
             return true;
         }
@@ -77,16 +80,56 @@ pub mod couting_file {
         }
     }
 
+    /// appended detail files are written in place, all others under a temporary name until commit
+    enum DetailWriter {
+        InPlace(File),
+        Atomic(AtomicFile),
+    }
+
+    impl Write for DetailWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self {
+                DetailWriter::InPlace(file) => file.write(buf),
+                DetailWriter::Atomic(file) => file.write(buf),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            match self {
+                DetailWriter::InPlace(file) => file.flush(),
+                DetailWriter::Atomic(file) => file.flush(),
+            }
+        }
+    }
+
+    impl Seek for DetailWriter {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            match self {
+                DetailWriter::InPlace(file) => file.seek(pos),
+                DetailWriter::Atomic(file) => file.seek(pos),
+            }
+        }
+    }
+
     pub struct CountingFile {
-        writing_file: File,
+        writing_file: Option<DetailWriter>,
         line_counter: u64,
         file_path: String,
+        format: DetailFormat,
+        binary_header: Option<DetailHeader>,
     }
 
     impl CountingFile {
-        pub fn new(file_path: &Path) -> Option<CountingFile> {
+        pub fn new(file_path: &Path, policy: OutputPolicy) -> Option<CountingFile> {
+            CountingFile::new_details(file_path, DetailFormat::Text, policy)
+        }
+
+        /// opens a detail file in the given format, appended files keep the format they were written in.
+        /// Appended lines go to file_path directly, so everything written is kept if the run exits early.
+        /// Otherwise the file is written under a temporary name and only replaces file_path on commit.
+        pub fn new_details(file_path: &Path, format: DetailFormat, policy: OutputPolicy) -> Option<CountingFile> {
             let parent;
-            let writing_file;
+            let mut writing_file;
 
             let path_str;
             if let Some(temp_path_str) = file_path.to_str() {
@@ -108,44 +151,140 @@ pub mod couting_file {
                 return None;
             }
 
-            match OpenOptions::new().append(true).create(true).open(file_path) {
-                Ok(temp_writing_file) => {
-                    writing_file = temp_writing_file;
+            if let Err(_) = policy.check(file_path) {
+                return None;
+            }
+
+            let mut format = format;
+            let mut binary_header = None;
+            let append = policy == OutputPolicy::Append;
+            if append && file_path.exists() && file_path.metadata().map(|metadata| metadata.len() > 0).unwrap_or(false) {
+                let existing_format = detect_detail_format(file_path).unwrap_or(DetailFormat::Text);
+                if existing_format != format {
+                    warn!("{} is stored as {}, continuing in that format", path_str, existing_format);
+                    format = existing_format;
+                }
+
+                if format == DetailFormat::Binary {
+                    match DetailHeader::read(file_path) {
+                        Ok(header) => binary_header = Some(header),
+                        Err(_) => {
+                            error!("Could not read header of {}!", path_str);
+                            return None;
+                        }
+                    }
+                }
+            }
+
+            if append {
+                // binary files rewrite their header count, so they can not be opened in append mode
+                let mut options = OpenOptions::new();
+                options.create(true);
+                match format {
+                    DetailFormat::Text => options.append(true),
+                    DetailFormat::Binary => options.read(true).write(true),
+                };
+
+                match options.open(file_path) {
+                    Ok(temp_writing_file) => {
+                        writing_file = DetailWriter::InPlace(temp_writing_file);
+                    }
+                    Err(error) => {
+                        error!("Could not create file ptions for counting file {}!", path_str);
+                        error!("{}", error);
+                        return None;
+                    }
+                }
+            } else {
+                match AtomicFile::create(file_path) {
+                    Ok(temp_writing_file) => {
+                        writing_file = DetailWriter::Atomic(temp_writing_file);
+                    }
+                    Err(_) => {
+                        error!("Could not create temporary file for counting file {}!", path_str);
+                        return None;
+                    }
                 }
-                Err(error) => {
-                    error!("Could not create file ptions for counting file {}!", path_str);
+            }
+
+            if format == DetailFormat::Binary {
+                if let Err(error) = writing_file.seek(SeekFrom::End(0)) {
+                    error!("Could not seek to the end of {}!", path_str);
                     error!("{}", error);
                     return None;
                 }
             }
 
             Some(CountingFile {
-                writing_file,
+                writing_file: Some(writing_file),
                 line_counter: 0,
                 file_path: path_str.to_owned(),
+                format,
+                binary_header,
             })
         }
 
-        pub fn from_str(file_path: &str) -> Option<CountingFile> {
+        pub fn from_str(file_path: &str, policy: OutputPolicy) -> Option<CountingFile> {
             let path = Path::new(file_path);
-            CountingFile::new(path)
+            CountingFile::new(path, policy)
         }
 
         pub fn write_line(&mut self, input: &str) -> bool {
+            if self.format == DetailFormat::Binary {
+                error!("Can not write text lines to binary file {}!", self.file_path);
+                return false;
+            }
+
             self.line_counter += 1;
             let formatted_line = format!("{}\n", input);
-            if let Err(_) = self.writing_file.write(formatted_line.as_bytes()) {
-                error!("Could not write data to file {}!", self.file_path);
-                // ToDo: Figure out way to handle this
+            self.write_bytes(formatted_line.as_bytes())
+        }
+
+        pub fn write_ip_line<T: Display + Clone + Copy + IpAddrExt>(&mut self, input: &T) -> bool {
+            if self.format == DetailFormat::Binary {
+                return self.write_binary_ip(input);
+            }
+
+            self.line_counter += 1;
+            let formatted_line = format!("{}\n", input);
+            self.write_bytes(formatted_line.as_bytes())
+        }
+
+        fn write_binary_ip<T: IpAddrExt>(&mut self, input: &T) -> bool {
+            if let Some(header) = &self.binary_header {
+                if header.family != DetailHeader::family_of::<T>() {
+                    error!("{} holds IPv{} addresses, can not append other family!", self.file_path, header.family);
+                    return false;
+                }
+            } else {
+                let header = DetailHeader::new::<T>(0);
+                if !self.write_bytes(&header.to_bytes()) {
+                    return false;
+                }
+                self.binary_header = Some(header);
+            }
+
+            if !self.write_bytes(&input.ls_octets()) {
                 return false;
             }
+
+            self.line_counter += 1;
+            if let Some(header) = self.binary_header.as_mut() {
+                header.count += 1;
+            }
             return true;
         }
 
-        pub fn write_ip_line<T: Display + Clone + Copy>(&mut self, input: &T) -> bool {
-            self.line_counter += 1;
-            let formatted_line = format!("{}\n", input);
-            if let Err(_) = self.writing_file.write(formatted_line.as_bytes()) {
+        fn write_bytes(&mut self, bytes: &[u8]) -> bool {
+            let writing_file = match self.writing_file.as_mut() {
+                Some(writing_file) => writing_file,
+                None => {
+                    error!("{} is already committed!", self.file_path);
+                    return false;
+                }
+            };
+
+            if let Err(_) = writing_file.write_all(bytes) {
                 error!("Could not write data to file {}!", self.file_path);
                 // ToDo: Figure out way to handle this
                 return false;
@@ -153,8 +292,50 @@ pub mod couting_file {
             return true;
         }
 
+        /// rewrites the address count in the header of a binary file, readers take the count
+        /// from the file length, so a header left behind by an early exit only lags behind
+        pub fn update_binary_header(&mut self) -> std::io::Result<()> {
+            if let (Some(header), Some(writing_file)) = (&self.binary_header, self.writing_file.as_mut()) {
+                writing_file.seek(SeekFrom::Start(0))?;
+                writing_file.write_all(&header.to_bytes())?;
+                writing_file.seek(SeekFrom::End(0))?;
+            }
+            Ok(())
+        }
+
+        /// finishes the file, a file written under a temporary name replaces file_path and
+        /// binary files are sorted. Dropping a CountingFile without commit leaves file_path untouched
+        /// unless it was appended to, appended files are finished on drop.
+        pub fn commit(&mut self) -> Result<(), YarrpError> {
+            self.update_binary_header()?;
+            match self.writing_file.take() {
+                Some(DetailWriter::InPlace(mut file)) => file.flush()?,
+                Some(DetailWriter::Atomic(file)) => file.commit()?,
+                None => return Ok(()),
+            }
+
+            if self.format == DetailFormat::Binary && self.line_counter > 0 {
+                sort_binary_details(Path::new(&self.file_path))?;
+            }
+            Ok(())
+        }
+
         pub fn len(&self) -> u64 {
             self.line_counter
         }
+
+        pub fn format(&self) -> DetailFormat {
+            self.format
+        }
+    }
+
+    impl Drop for CountingFile {
+        fn drop(&mut self) {
+            if let Some(DetailWriter::InPlace(_)) = self.writing_file {
+                if let Err(_) = self.commit() {
+                    error!("Could not finish {}!", self.file_path);
+                }
+            }
+        }
     }
 }
\ No newline at end of file
@@ -17,6 +17,9 @@ pub struct LoopDensityOutput {
 pub struct ASNLoopEntry{
     pub(crate) loop_id: String,
     pub(crate) number_asn: u64,
+    pub(crate) number_origins: u64,
+    pub(crate) number_moas: u64,
+    pub(crate) number_as_set: u64,
     pub(crate) is_persistent: bool,
     pub(crate) all_routers_assigned: bool,
     #[serde(serialize_with = "string_set_ser")]
@@ -27,6 +30,7 @@ pub struct ASNLoopEntry{
 pub struct ASNRouterEntry{
     pub(crate) router_ip: String,
     pub(crate) number_asn: u64,
+    pub(crate) origin_type: String,
     pub(crate) is_persistent: bool,
     #[serde(serialize_with = "string_set_ser")]
     pub(crate) asn_list: HashSet::<String>,
@@ -37,7 +41,12 @@ pub struct ASNShadowedResults {
     pub(crate) shadowed_asn_is_not_with_loop: u64,
     pub(crate) shadowed_asn_is_unknown: u64,
     pub(crate) shadowed_asn_with_single_asn: u64,
-    pub(crate) shadowed_asn_with_multiple_asn: u64
+    pub(crate) shadowed_asn_with_multiple_asn: u64,
+    pub(crate) shadowed_asn_is_moas: u64,
+    pub(crate) shadowed_asn_is_as_set: u64,
+    pub(crate) shadowed_rpki_valid: u64,
+    pub(crate) shadowed_rpki_invalid: u64,
+    pub(crate) shadowed_rpki_unknown: u64
 }
 
 #[derive(Serialize)]
@@ -49,12 +58,87 @@ pub struct ASNShadowedOutput {
     pub(crate) asn_in_loop: bool,
     pub(crate) all_asn_in_loop: bool,
     pub(crate) num_asn: u8,
-    pub(crate) loop_asn: u8
+    pub(crate) origin_type: String,
+    pub(crate) loop_asn: u8,
+    pub(crate) loop_origins: u8,
+    pub(crate) rpki_state: String,
+    pub(crate) country: String
 }
 
 #[derive(Serialize)]
 pub struct ASNIPAttribution{
     pub(crate) asn: String,
+    pub(crate) num_ips: u64,
+    pub(crate) announced_prefixes: u64,
+    pub(crate) announced_blocks: u64,
+    pub(crate) touched_prefixes: u64,
+    pub(crate) covered_blocks: u64,
+    pub(crate) block_coverage: f64
+}
+
+#[derive(Serialize)]
+pub struct PrefixCoverageEntry {
+    pub(crate) prefix: IpNet,
+    pub(crate) origin: String,
+    pub(crate) more_specifics: u64,
+    pub(crate) probed: u64,
+    pub(crate) shadowed: u64,
+    pub(crate) shadowed_probed: u64,
+    pub(crate) loops: u64,
+    pub(crate) coverage: f64,
+}
+
+#[derive(Serialize)]
+pub struct LoopCoverageEntry {
+    pub(crate) loop_id: String,
+    pub(crate) shadowed: u64,
+    pub(crate) unannounced: u64,
+    pub(crate) aggregate_gap: u64,
+    pub(crate) in_aggregate_gap: bool,
+    #[serde(serialize_with = "string_vec_ser")]
+    pub(crate) covering_prefixes: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CountryEntry {
+    pub(crate) country: String,
+    pub(crate) routers: u64,
+    pub(crate) loops: u64,
+    pub(crate) shadowed: u64
+}
+
+#[derive(Serialize)]
+pub struct LoopImpactEntry {
+    pub(crate) rank: u64,
+    pub(crate) loop_id: String,
+    pub(crate) loop_start_ttl: u8,
+    pub(crate) loop_len: u8,
+    pub(crate) amplification_64: f64,
+    pub(crate) amplification_128: f64,
+    pub(crate) amplification_255: f64,
+    pub(crate) shadowed: u64,
+    pub(crate) persistent: bool,
+    pub(crate) impact_score: f64
+}
+
+#[derive(Serialize)]
+pub struct IncidentEntry {
+    pub(crate) incident: u64,
+    pub(crate) member: String,
+    pub(crate) member_type: String,
+    pub(crate) community: u64,
+    pub(crate) loops: u64,
+    pub(crate) routers: u64,
+    #[serde(serialize_with = "string_set_ser")]
+    pub(crate) asn: HashSet<String>,
+    pub(crate) shadowed: u64,
+    pub(crate) persistent_loops: u64
+}
+
+#[derive(Serialize)]
+pub struct ORGIPAttribution{
+    pub(crate) org: String,
+    pub(crate) name: String,
     pub(crate) num_ips: u64
 }
 
@@ -99,4 +183,109 @@ impl ShadowedAnswer {
         result.timeout = 50;
         result
     }
-}
\ No newline at end of file
+}
+
+#[derive(Serialize)]
+pub struct QueryLoopResult {
+    pub(crate) loop_id: String,
+    pub(crate) members: Vec<String>,
+    pub(crate) member_hostnames: Vec<Option<String>>,
+    pub(crate) shared_domain: Option<String>,
+    pub(crate) preceding_routers: Vec<String>,
+    pub(crate) shadowed_destinations: u64,
+    pub(crate) asn_list: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct QueryRouterResult {
+    pub(crate) router: String,
+    pub(crate) hostname: Option<String>,
+    pub(crate) asn_list: Vec<String>,
+    pub(crate) loops: Vec<QueryLoopResult>,
+}
+
+#[derive(Serialize)]
+pub struct QueryDestinationResult {
+    pub(crate) destination: String,
+    pub(crate) loops: Vec<String>,
+    pub(crate) imperiled_routers: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct QueryResult {
+    pub(crate) query: String,
+    pub(crate) routers: Vec<QueryRouterResult>,
+    pub(crate) destinations: Vec<QueryDestinationResult>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ReportRouterResult {
+    pub(crate) router: String,
+    pub(crate) asn_list: Vec<String>,
+    pub(crate) origin_type: Option<String>,
+    pub(crate) ttls: Vec<u8>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ReportPrecedingResult {
+    pub(crate) router: String,
+    pub(crate) asn_list: Vec<String>,
+    pub(crate) ttl: u8,
+    pub(crate) loop_start_ttl: u8,
+    pub(crate) loop_stop_ttl: u8,
+    pub(crate) shadowed_nets: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ReportPrefixResult {
+    pub(crate) prefix: String,
+    pub(crate) origin: String,
+    pub(crate) shadowed_destinations: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ReportLoopResult {
+    pub(crate) loop_id: String,
+    pub(crate) persistent: Option<bool>,
+    pub(crate) members: Vec<ReportRouterResult>,
+    pub(crate) evidence_traces: u64,
+    pub(crate) preceding_routers: Vec<ReportPrecedingResult>,
+    pub(crate) shadowed_destinations: u64,
+    pub(crate) unannounced_destinations: u64,
+    pub(crate) shadowed_prefixes: Vec<ReportPrefixResult>,
+    pub(crate) example_destinations: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReportResult {
+    pub(crate) asn: String,
+    pub(crate) abuse_contacts: Vec<String>,
+    pub(crate) routers: u64,
+    pub(crate) shadowed_destinations: u64,
+    pub(crate) loops: Vec<ReportLoopResult>,
+}
+
+#[derive(Serialize)]
+pub struct EvidenceHopResult {
+    pub(crate) ttl: u8,
+    pub(crate) hop: String,
+    pub(crate) rtt_ms: f64,
+    pub(crate) r_type: i32,
+    pub(crate) r_code: i32,
+    pub(crate) in_loop: bool,
+}
+
+#[derive(Serialize)]
+pub struct EvidenceTraceResult {
+    pub(crate) destination: String,
+    pub(crate) loop_start: u8,
+    pub(crate) loop_end: u8,
+    pub(crate) hops: Vec<EvidenceHopResult>,
+}
+
+#[derive(Serialize)]
+pub struct EvidenceResult {
+    pub(crate) loop_id: String,
+    pub(crate) members: Vec<String>,
+    pub(crate) traces: Vec<EvidenceTraceResult>,
+}
@@ -0,0 +1,302 @@
+pub mod detail_file {
+    use std::fs::{self, File};
+    use std::io::{Read, Write, BufReader};
+    use std::path::Path;
+    use std::str::FromStr;
+    use std::fmt::{Display, Formatter};
+    use log::{error, warn};
+
+    use crate::structs::{YarrpError, AtomicFile};
+    use crate::traits::IpAddrExt;
+
+    pub const DETAIL_MAGIC: &[u8; 4] = b"YTDF";
+    pub const DETAIL_VERSION: u8 = 1;
+    pub const DETAIL_HEADER_LEN: usize = 16;
+
+    /// Storage format of loops/*.dest and imperiled/*.imp files
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum DetailFormat {
+        /// one textual address per line
+        Text,
+        /// DetailHeader followed by fixed width addresses in network byte order, sorted ascending
+        Binary,
+    }
+
+    impl FromStr for DetailFormat {
+        type Err = YarrpError;
+
+        fn from_str(input: &str) -> Result<DetailFormat, Self::Err> {
+            match input.to_lowercase().as_str() {
+                "text" => Ok(DetailFormat::Text),
+                "binary" => Ok(DetailFormat::Binary),
+                _ => Err(YarrpError::CouldNotParseError),
+            }
+        }
+    }
+
+    impl Display for DetailFormat {
+        fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+            match self {
+                DetailFormat::Text => write!(f, "text"),
+                DetailFormat::Binary => write!(f, "binary"),
+            }
+        }
+    }
+
+    /// Header of a binary detail file:
+    /// magic (4 bytes), version (1), address family 4 or 6 (1), reserved (2), count (u64, little endian)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct DetailHeader {
+        pub version: u8,
+        pub family: u8,
+        pub count: u64,
+    }
+
+    impl DetailHeader {
+        pub fn new<T: IpAddrExt>(count: u64) -> DetailHeader {
+            DetailHeader {
+                version: DETAIL_VERSION,
+                family: DetailHeader::family_of::<T>(),
+                count,
+            }
+        }
+
+        pub fn family_of<T: IpAddrExt>() -> u8 {
+            if T::is_v4() { 4 } else { 6 }
+        }
+
+        pub fn address_len(&self) -> usize {
+            if self.family == 4 { 4 } else { 16 }
+        }
+
+        pub fn to_bytes(&self) -> [u8; DETAIL_HEADER_LEN] {
+            let mut bytes = [0u8; DETAIL_HEADER_LEN];
+            bytes[..4].copy_from_slice(DETAIL_MAGIC);
+            bytes[4] = self.version;
+            bytes[5] = self.family;
+            bytes[8..].copy_from_slice(&self.count.to_le_bytes());
+            bytes
+        }
+
+        pub fn from_bytes(bytes: &[u8; DETAIL_HEADER_LEN]) -> Result<DetailHeader, YarrpError> {
+            if &bytes[..4] != DETAIL_MAGIC {
+                return Err(YarrpError::CouldNotParseError);
+            }
+
+            let version = bytes[4];
+            if version != DETAIL_VERSION {
+                error!("Unsupported detail file version {}", version);
+                return Err(YarrpError::NotCompatibleError);
+            }
+
+            let family = bytes[5];
+            if family != 4 && family != 6 {
+                error!("Unknown address family {} in detail file", family);
+                return Err(YarrpError::CouldNotParseError);
+            }
+
+            let mut count = [0u8; 8];
+            count.copy_from_slice(&bytes[8..]);
+
+            Ok(DetailHeader {
+                version,
+                family,
+                count: u64::from_le_bytes(count),
+            })
+        }
+
+        /// reads the header of a binary detail file
+        pub fn read(path: &Path) -> Result<DetailHeader, YarrpError> {
+            let mut bytes = [0u8; DETAIL_HEADER_LEN];
+            File::open(path)?.read_exact(&mut bytes)?;
+            DetailHeader::from_bytes(&bytes)
+        }
+    }
+
+    /// detects the format of a detail file by its magic, empty files are treated as text
+    pub fn detect_detail_format(path: &Path) -> Result<DetailFormat, YarrpError> {
+        let mut magic = [0u8; 4];
+        let mut file = File::open(path)?;
+
+        let mut read = 0;
+        while read < magic.len() {
+            let bytes = file.read(&mut magic[read..])?;
+            if bytes == 0 {
+                return Ok(DetailFormat::Text);
+            }
+            read += bytes;
+        }
+
+        if &magic == DETAIL_MAGIC {
+            Ok(DetailFormat::Binary)
+        } else {
+            Ok(DetailFormat::Text)
+        }
+    }
+
+    /// reads a binary detail file, the number of addresses is taken from the file length
+    /// as the header count is only updated with each rotation and when the writing file is closed
+    pub fn read_binary_details<T: IpAddrExt>(path: &Path) -> Result<Vec<T>, YarrpError> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut header_bytes = [0u8; DETAIL_HEADER_LEN];
+        reader.read_exact(&mut header_bytes)?;
+        let header = DetailHeader::from_bytes(&header_bytes)?;
+
+        if header.family != DetailHeader::family_of::<T>() {
+            error!("{} holds IPv{} addresses, does not match the selected address family!",
+                   path.to_str().unwrap_or(""), header.family);
+            return Err(YarrpError::NotCompatibleError);
+        }
+
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body)?;
+
+        let address_len = header.address_len();
+        if body.len() % address_len != 0 {
+            error!("{} ends with a truncated address!", path.to_str().unwrap_or(""));
+            return Err(YarrpError::CouldNotParseError);
+        }
+
+        let count = (body.len() / address_len) as u64;
+        if count != header.count {
+            warn!("{} header counts {} addresses but holds {}", path.to_str().unwrap_or(""), header.count, count);
+        }
+
+        let mut addresses = Vec::with_capacity(count as usize);
+        for octets in body.chunks(address_len) {
+            addresses.push(T::from_ls_octets(octets)?);
+        }
+        Ok(addresses)
+    }
+
+    /// writes addresses into a new binary detail file, an existing file is replaced once all addresses are written
+    pub fn write_binary_details<T: IpAddrExt>(path: &Path, addresses: &[T]) -> Result<(), YarrpError> {
+        let octets: Vec<Vec<u8>> = addresses.iter().map(|address| address.ls_octets()).collect();
+        write_sorted_octets(path, DetailHeader::new::<T>(0), octets.iter().map(|octets| octets.as_slice()).collect())
+    }
+
+    /// sorts the addresses of a binary detail file, e.g. after addresses were appended to it
+    pub fn sort_binary_details(path: &Path) -> Result<(), YarrpError> {
+        let bytes = fs::read(path)?;
+        if bytes.len() < DETAIL_HEADER_LEN {
+            error!("{} is too short for a detail file header!", path.to_str().unwrap_or(""));
+            return Err(YarrpError::CouldNotParseError);
+        }
+
+        let mut header_bytes = [0u8; DETAIL_HEADER_LEN];
+        header_bytes.copy_from_slice(&bytes[..DETAIL_HEADER_LEN]);
+        let header = DetailHeader::from_bytes(&header_bytes)?;
+
+        let body = &bytes[DETAIL_HEADER_LEN..];
+        if body.len() % header.address_len() != 0 {
+            error!("{} ends with a truncated address!", path.to_str().unwrap_or(""));
+            return Err(YarrpError::CouldNotParseError);
+        }
+        write_sorted_octets(path, header, body.chunks(header.address_len()).collect())
+    }
+
+    /// addresses are stored in network byte order, so sorting the octets sorts the addresses
+    fn write_sorted_octets(path: &Path, mut header: DetailHeader, mut octets: Vec<&[u8]>) -> Result<(), YarrpError> {
+        octets.sort();
+        header.count = octets.len() as u64;
+
+        let mut writer = AtomicFile::create(path)?;
+        writer.write_all(&header.to_bytes())?;
+        for address in octets {
+            writer.write_all(address)?;
+        }
+        writer.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::str::FromStr;
+    use std::fs;
+    use crate::helpers::test_helper::TempFixture;
+    use crate::structs::{DetailFormat, DetailHeader, CountingFile, OutputPolicy, detect_detail_format, read_binary_details, write_binary_details, temp_path};
+
+    #[test]
+    fn header_roundtrip() {
+        let header = DetailHeader::new::<Ipv6Addr>(42);
+        let parsed = DetailHeader::from_bytes(&header.to_bytes()).ok().unwrap();
+        assert_eq!(header, parsed);
+        assert_eq!(parsed.family, 6);
+        assert_eq!(parsed.address_len(), 16);
+    }
+
+    #[test]
+    fn binary_roundtrip_and_detection() {
+        let file = TempFixture::new("detail.dest");
+        let path = file.path();
+        let addresses = vec![Ipv4Addr::from_str("198.51.100.7").unwrap(), Ipv4Addr::from_str("192.0.2.1").unwrap()];
+
+        fs::write(path, "192.0.2.1\n").unwrap();
+        assert_eq!(detect_detail_format(path).ok().unwrap(), DetailFormat::Text);
+
+        write_binary_details(path, &addresses).ok().unwrap();
+        assert_eq!(detect_detail_format(path).ok().unwrap(), DetailFormat::Binary);
+        assert_eq!(fs::metadata(path).unwrap().len(), 16 + 2 * 4);
+
+        let read: Vec<Ipv4Addr> = read_binary_details(path).ok().unwrap();
+        assert_eq!(read, vec![addresses[1], addresses[0]], "Addresses are stored sorted");
+        assert!(read_binary_details::<Ipv6Addr>(path).is_err(), "Family mismatch should be refused");
+    }
+
+    #[test]
+    fn counting_file_appends_binary() {
+        let file = TempFixture::new("counting.imp");
+        let path = file.path();
+        let first = Ipv6Addr::from_str("2001:db8::2").unwrap();
+        let second = Ipv6Addr::from_str("2001:db8::1").unwrap();
+
+        {
+            let mut counting_file = CountingFile::new_details(path, DetailFormat::Binary, OutputPolicy::Overwrite).unwrap();
+            assert!(counting_file.write_ip_line(&first));
+            counting_file.commit().ok().unwrap();
+        }
+
+        // existing binary files keep their format
+        let mut counting_file = CountingFile::new_details(path, DetailFormat::Text, OutputPolicy::Append).unwrap();
+        assert_eq!(counting_file.format(), DetailFormat::Binary);
+        assert!(counting_file.write_ip_line(&second));
+
+        // written in place, so an exiting run that never drops the file keeps its addresses
+        assert!(!temp_path(path).exists());
+        let read: Vec<Ipv6Addr> = read_binary_details(path).ok().unwrap();
+        assert_eq!(read, vec![first, second]);
+
+        drop(counting_file);
+        assert_eq!(DetailHeader::read(path).ok().unwrap().count, 2);
+        let read: Vec<Ipv6Addr> = read_binary_details(path).ok().unwrap();
+        assert_eq!(read, vec![second, first], "Appended addresses are sorted once the file is closed");
+    }
+
+    #[test]
+    fn counting_file_appends_text() {
+        let file = TempFixture::file("counting.dest", "192.0.2.1\n");
+        let path = file.path();
+
+        let mut counting_file = CountingFile::new_details(path, DetailFormat::Text, OutputPolicy::Append).unwrap();
+        assert!(counting_file.write_ip_line(&Ipv4Addr::from_str("192.0.2.2").unwrap()));
+        assert_eq!(fs::read_to_string(path).unwrap(), "192.0.2.1\n192.0.2.2\n");
+        assert!(!temp_path(path).exists());
+        drop(counting_file);
+
+        {
+            let mut counting_file = CountingFile::new_details(path, DetailFormat::Text, OutputPolicy::Overwrite).unwrap();
+            assert!(counting_file.write_ip_line(&Ipv4Addr::from_str("192.0.2.3").unwrap()));
+        }
+        assert_eq!(fs::read_to_string(path).unwrap(), "192.0.2.1\n192.0.2.2\n", "Overwritten files are kept until commit");
+        assert!(!temp_path(path).exists());
+
+        let mut counting_file = CountingFile::new_details(path, DetailFormat::Text, OutputPolicy::Overwrite).unwrap();
+        assert!(counting_file.write_ip_line(&Ipv4Addr::from_str("192.0.2.3").unwrap()));
+        assert_eq!(fs::read_to_string(path).unwrap(), "192.0.2.1\n192.0.2.2\n");
+        counting_file.commit().ok().unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "192.0.2.3\n");
+        assert!(!counting_file.write_line("192.0.2.4"), "Committed files take no more lines");
+    }
+}
@@ -0,0 +1,193 @@
+pub mod evidence {
+    use std::collections::HashMap;
+    use std::fmt::{Display, Write as FmtWrite};
+    use std::io::Write;
+    use std::path::Path;
+    use std::str::FromStr;
+    use itertools::sorted;
+    use log::error;
+
+    use crate::structs::{YarrpLine, YarrpError, EvidenceHopResult, EvidenceTraceResult};
+    use crate::read_lines;
+
+    /// first line of every trace in an evidence file, followed by destination, loop start and loop end ttl
+    pub const EVIDENCE_HEADER: &str = "# trace";
+
+    /// column header of render_text, aligned with the hop lines
+    const TEXT_COLUMNS: &str = "    ttl  hop                                               rtt  type/code";
+
+    /// A complete raw trace to one destination of a loop, all answers ordered by sent ttl.
+    /// Evidence files hold a header line per trace followed by its yarrp lines.
+    pub struct EvidenceTrace<T> {
+        pub destination: T,
+        pub loop_start: u8,
+        pub loop_end: u8,
+        pub lines: Vec<YarrpLine<T>>,
+    }
+
+    impl<T: Display + FromStr + Copy> EvidenceTrace<T> {
+        pub fn new(destination: T, loop_start: u8, loop_end: u8, answers: &HashMap<u8, Vec<YarrpLine<T>>>) -> EvidenceTrace<T> {
+            let mut lines = Vec::new();
+            for ttl in sorted(answers.keys()) {
+                lines.extend(answers[ttl].iter().cloned());
+            }
+
+            EvidenceTrace {
+                destination,
+                loop_start,
+                loop_end,
+                lines,
+            }
+        }
+
+        pub fn in_loop(&self, ttl: u8) -> bool {
+            self.loop_start <= ttl && ttl <= self.loop_end
+        }
+
+        pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), YarrpError> {
+            writeln!(writer, "{} {} {} {}", EVIDENCE_HEADER, self.destination, self.loop_start, self.loop_end)?;
+            for line in &self.lines {
+                writeln!(writer, "{}", line)?;
+            }
+            Ok(())
+        }
+
+        fn parse_header(line: &str) -> Result<EvidenceTrace<T>, YarrpError> {
+            let fields: Vec<&str> = line[EVIDENCE_HEADER.len()..].split_whitespace().collect();
+            if fields.len() != 3 {
+                error!("Could not parse evidence header {}", line);
+                return Err(YarrpError::CouldNotParseError);
+            }
+
+            let destination = match T::from_str(fields[0]) {
+                Ok(value) => value,
+                Err(_) => return Err(YarrpError::CouldNotParseError),
+            };
+
+            Ok(EvidenceTrace {
+                destination,
+                loop_start: u8::from_str(fields[1])?,
+                loop_end: u8::from_str(fields[2])?,
+                lines: Vec::new(),
+            })
+        }
+
+        /// traceroute like output, answers within the loop are marked with =>
+        pub fn render_text(&self) -> String {
+            let mut output = String::new();
+            let _ = writeln!(output, "trace to {}, loop between ttl {} and {}", self.destination, self.loop_start, self.loop_end);
+            let _ = writeln!(output, "{}", TEXT_COLUMNS);
+            for line in &self.lines {
+                let marker = if self.in_loop(line.sent_ttl) { "=>" } else { "  " };
+                let rtt = format!("{:.3} ms", line.rtt as f64 / 1000.0);
+                let _ = writeln!(output, "{} {:>4}  {:<40} {:>12}  {}/{}", marker, line.sent_ttl, line.hop.to_string(), rtt, line.r_type, line.r_code);
+            }
+            output
+        }
+
+        pub fn to_result(&self) -> EvidenceTraceResult {
+            let hops = self.lines.iter().map(|line| EvidenceHopResult {
+                ttl: line.sent_ttl,
+                hop: line.hop.to_string(),
+                rtt_ms: line.rtt as f64 / 1000.0,
+                r_type: line.r_type,
+                r_code: line.r_code,
+                in_loop: self.in_loop(line.sent_ttl),
+            }).collect();
+
+            EvidenceTraceResult {
+                destination: self.destination.to_string(),
+                loop_start: self.loop_start,
+                loop_end: self.loop_end,
+                hops,
+            }
+        }
+    }
+
+    /// reads all traces of an evidence file
+    pub fn read_evidence_file<T: Display + FromStr + Copy>(path: &Path) -> Result<Vec<EvidenceTrace<T>>, YarrpError> {
+        let mut traces: Vec<EvidenceTrace<T>> = Vec::new();
+
+        for line in read_lines(path)? {
+            let line = line?;
+            if line.starts_with(EVIDENCE_HEADER) {
+                traces.push(EvidenceTrace::parse_header(&line)?);
+                continue;
+            }
+
+            let yarrp_line = match YarrpLine::new(&line) {
+                Some(value) => value,
+                None => {
+                    error!("Could not parse trace line {}", line);
+                    return Err(YarrpError::CouldNotParseError);
+                }
+            };
+
+            match traces.last_mut() {
+                Some(trace) => trace.lines.push(yarrp_line),
+                None => {
+                    error!("{} does not start with a trace header!", path.to_str().unwrap_or(""));
+                    return Err(YarrpError::CouldNotParseError);
+                }
+            }
+        }
+        Ok(traces)
+    }
+
+    /// number of traces stored in an evidence file, 0 if it does not exist
+    pub fn count_evidence_traces(path: &Path) -> Result<u64, YarrpError> {
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let mut count = 0;
+        for line in read_lines(path)? {
+            if line?.starts_with(EVIDENCE_HEADER) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::net::Ipv4Addr;
+    use crate::structs::{EvidenceTrace, YarrpLine, read_evidence_file, count_evidence_traces};
+    use crate::helpers::test_helper::{get_ipv4_hop, TempFixture};
+
+    #[test]
+    fn evidence_roundtrip_marks_loop() {
+        let file = TempFixture::new("evidence.trace");
+        let path = file.path();
+        let destination = get_ipv4_hop(254);
+
+        let mut answers = HashMap::new();
+        for ttl in 3..8 {
+            let hop = if ttl < 5 { ttl } else { 100 + ttl % 2 };
+            let line = format!("192.0.2.254 1 1 11 0 {} 192.0.2.{} 1590 0 36 84 63 0 0 27", ttl, hop);
+            answers.insert(ttl, vec![YarrpLine::<Ipv4Addr>::new(&line).unwrap()]);
+        }
+
+        let trace = EvidenceTrace::new(destination, 5, 6, &answers);
+        let mut writer = fs::File::create(path).unwrap();
+        trace.write_to(&mut writer).ok().unwrap();
+        trace.write_to(&mut writer).ok().unwrap();
+        drop(writer);
+
+        assert_eq!(count_evidence_traces(path).ok().unwrap(), 2);
+        let read: Vec<EvidenceTrace<Ipv4Addr>> = read_evidence_file(path).ok().unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].destination, destination);
+        let ttls: Vec<u8> = read[0].lines.iter().map(|line| line.sent_ttl).collect();
+        assert_eq!(ttls, vec![3, 4, 5, 6, 7], "Lines should be ordered by ttl");
+        assert_eq!(read[0].lines[2].to_string(), "192.0.2.254 1 1 11 0 5 192.0.2.101 1590 0 36 84 63 0 0 27");
+
+        let result = read[0].to_result();
+        let in_loop: Vec<bool> = result.hops.iter().map(|hop| hop.in_loop).collect();
+        assert_eq!(in_loop, vec![false, false, true, true, false]);
+        assert_eq!(read[0].render_text().lines().filter(|line| line.starts_with("=>")).count(), 2);
+    }
+}
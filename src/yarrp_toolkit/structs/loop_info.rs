@@ -69,7 +69,7 @@ pub struct AdvancedLoopOutput {
     pub(crate) preceding_router_same_asn: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct ShadowedPreceding {
     pub(crate) shadowed_net: String,
     pub(crate) preceding_router: String,
@@ -78,42 +78,152 @@ pub struct ShadowedPreceding {
 }
 
 
-// not yet used, commented so compiler is quiet
-// pub struct ShadowedPrecedingCounter {
-//     // table 5, destination domain involved
-//     pub(crate) dest_domain_involved: u64,
-//     pub(crate) only_one_address_in_dest_domain: u64,
-//     pub(crate) two_or_more_addresses_in_dest_domain: u64,
-//     pub(crate) all_addresses_in_dest_domain: u64,
-//     pub(crate) dest_domain_not_involved: u64,
-//     pub(crate) preceding_router_in_dest_domain: u64,
-//     pub(crate) preceding_router_not_in_dest_domain: u64,
-//     // table 6, number of involved domains
-//     pub(crate) only_single_domain_as_loop: u64,
-//     pub(crate) preceding_same_domain_as_loop: u64,
-//     pub(crate) preceding_not_same_domain: u64,
-//     pub(crate) multiple_domains_involved: u64,
-//     pub(crate) two_domains: u64,
-//     pub(crate) three_or_more_domains: u64
-//
-// }
-//
-// impl ShadowedPrecedingCounter {
-//     pub fn new() -> ShadowedPrecedingCounter {
-//         ShadowedPrecedingCounter{
-//             dest_domain_involved: 0,
-//             only_one_address_in_dest_domain: 0,
-//             two_or_more_addresses_in_dest_domain: 0,
-//             dest_domain_not_involved: 0,
-//             preceding_router_in_dest_domain: 0,
-//             preceding_router_not_in_dest_domain: 0,
-//             only_single_domain_as_loop: 0,
-//             preceding_same_domain_as_loop: 0,
-//             preceding_not_same_domain: 0,
-//             multiple_domains_involved: 0,
-//             two_domains: 0,
-//             three_or_more_domains: 0,
-//             all_addresses_in_dest_domain: 0
-//         }
-//     }
-// }
\ No newline at end of file
+/// Counts the shadowed destinations of shadowed_preceding.csv by the routing domains (ASes)
+/// of the destination, the loop members and the preceding router.
+#[derive(Default)]
+pub struct ShadowedPrecedingCounter {
+    // table 5, destination domain involved
+    pub(crate) dest_domain_involved: u64,
+    pub(crate) only_one_address_in_dest_domain: u64,
+    pub(crate) two_or_more_addresses_in_dest_domain: u64,
+    pub(crate) all_addresses_in_dest_domain: u64,
+    pub(crate) dest_domain_not_involved: u64,
+    pub(crate) dest_domain_unknown: u64,
+    pub(crate) preceding_router_in_dest_domain: u64,
+    pub(crate) preceding_router_not_in_dest_domain: u64,
+    // table 6, number of involved domains
+    pub(crate) only_single_domain_as_loop: u64,
+    pub(crate) preceding_same_domain_as_loop: u64,
+    pub(crate) preceding_not_same_domain: u64,
+    pub(crate) multiple_domains_involved: u64,
+    pub(crate) two_domains: u64,
+    pub(crate) three_or_more_domains: u64,
+    pub(crate) loop_domain_unknown: u64
+}
+
+fn shares_domain(a: &[String], b: &[String]) -> bool {
+    a.iter().any(|asn| b.contains(asn))
+}
+
+impl ShadowedPrecedingCounter {
+    pub fn new() -> ShadowedPrecedingCounter {
+        ShadowedPrecedingCounter::default()
+    }
+
+    /// adds one shadowed destination, member_asns holds the ASNs of every loop member (empty if unattributed)
+    /// and num_domains the distinct domains of the loop, origins sharing an ASN count as one domain
+    pub fn add(&mut self, shadowed_asns: &[String], preceding_asns: &[String], member_asns: &[Vec<String>], num_domains: usize) {
+        if shadowed_asns.is_empty() {
+            self.dest_domain_unknown += 1;
+        } else {
+            let members_in_dest = member_asns.iter().filter(|asns| shares_domain(asns, shadowed_asns)).count();
+            if members_in_dest == 0 {
+                self.dest_domain_not_involved += 1;
+            } else {
+                self.dest_domain_involved += 1;
+                if members_in_dest == member_asns.len() {
+                    self.all_addresses_in_dest_domain += 1;
+                } else if members_in_dest == 1 {
+                    self.only_one_address_in_dest_domain += 1;
+                } else {
+                    self.two_or_more_addresses_in_dest_domain += 1;
+                }
+            }
+
+            if shares_domain(preceding_asns, shadowed_asns) {
+                self.preceding_router_in_dest_domain += 1;
+            } else {
+                self.preceding_router_not_in_dest_domain += 1;
+            }
+        }
+
+        match num_domains {
+            0 => self.loop_domain_unknown += 1,
+            1 => {
+                self.only_single_domain_as_loop += 1;
+                if member_asns.iter().any(|asns| shares_domain(asns, preceding_asns)) {
+                    self.preceding_same_domain_as_loop += 1;
+                } else {
+                    self.preceding_not_same_domain += 1;
+                }
+            }
+            2 => {
+                self.multiple_domains_involved += 1;
+                self.two_domains += 1;
+            }
+            _ => {
+                self.multiple_domains_involved += 1;
+                self.three_or_more_domains += 1;
+            }
+        }
+    }
+
+    /// table 5, is the destination domain involved in the loop and is the preceding router in it
+    pub fn destination_domain_table(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("dest_domain_involved", self.dest_domain_involved),
+            ("only_one_address_in_dest_domain", self.only_one_address_in_dest_domain),
+            ("two_or_more_addresses_in_dest_domain", self.two_or_more_addresses_in_dest_domain),
+            ("all_addresses_in_dest_domain", self.all_addresses_in_dest_domain),
+            ("dest_domain_not_involved", self.dest_domain_not_involved),
+            ("dest_domain_unknown", self.dest_domain_unknown),
+            ("preceding_router_in_dest_domain", self.preceding_router_in_dest_domain),
+            ("preceding_router_not_in_dest_domain", self.preceding_router_not_in_dest_domain),
+        ]
+    }
+
+    /// table 6, how many domains are involved in the loop and is the preceding router in the same domain
+    pub fn loop_domains_table(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("only_single_domain_as_loop", self.only_single_domain_as_loop),
+            ("preceding_same_domain_as_loop", self.preceding_same_domain_as_loop),
+            ("preceding_not_same_domain", self.preceding_not_same_domain),
+            ("multiple_domains_involved", self.multiple_domains_involved),
+            ("two_domains", self.two_domains),
+            ("three_or_more_domains", self.three_or_more_domains),
+            ("loop_domain_unknown", self.loop_domain_unknown),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::ShadowedPrecedingCounter;
+
+    fn asns(values: &[&str]) -> Vec<String> {
+        values.iter().map(|asn| asn.to_string()).collect()
+    }
+
+    #[test]
+    fn count_domains() {
+        let mut counter = ShadowedPrecedingCounter::new();
+        // single domain loop within the destination domain, preceding router outside
+        counter.add(&asns(&["64496"]), &asns(&["64497"]), &[asns(&["64496"]), asns(&["64496"])], 1);
+        // one of three members in the destination domain, preceding router in it
+        counter.add(&asns(&["64496"]), &asns(&["64496"]), &[asns(&["64496"]), asns(&["64497"]), asns(&["64498"])], 3);
+        // two of three members in a MOAS destination domain
+        counter.add(&asns(&["64496", "64499"]), &[], &[asns(&["64496"]), asns(&["64499"]), Vec::new()], 2);
+        // unattributed destination and loop members
+        counter.add(&[], &asns(&["64496"]), &[Vec::new(), Vec::new()], 0);
+
+        assert_eq!(counter.destination_domain_table(), vec![
+            ("dest_domain_involved", 3),
+            ("only_one_address_in_dest_domain", 1),
+            ("two_or_more_addresses_in_dest_domain", 1),
+            ("all_addresses_in_dest_domain", 1),
+            ("dest_domain_not_involved", 0),
+            ("dest_domain_unknown", 1),
+            ("preceding_router_in_dest_domain", 1),
+            ("preceding_router_not_in_dest_domain", 2),
+        ]);
+        assert_eq!(counter.loop_domains_table(), vec![
+            ("only_single_domain_as_loop", 1),
+            ("preceding_same_domain_as_loop", 0),
+            ("preceding_not_same_domain", 1),
+            ("multiple_domains_involved", 2),
+            ("two_domains", 1),
+            ("three_or_more_domains", 1),
+            ("loop_domain_unknown", 1),
+        ]);
+    }
+}
@@ -2,21 +2,36 @@ mod yarrp_line;
 mod config;
 mod route;
 mod couting_file;
+mod detail_file;
+mod output_file;
 mod yarrp_error;
 mod asn_tree;
+mod asn_origin;
 mod zmap;
 mod csv_structs;
 mod loop_info;
+mod evidence;
+mod mrt;
+mod asn_db;
 
 pub use yarrp_line::yarrp_line::YarrpLine;
 pub use config::config::Config;
 pub use route::route::Route;
 pub use couting_file::couting_file::{CountingEntity, CountingVoid, CountingFile};
+pub use output_file::output_file::{OutputPolicy, AtomicFile, output_exists, temp_path, prepare_temp_file, atomic_csv_writer, commit_csv};
+pub use detail_file::detail_file::{DetailFormat, DetailHeader, detect_detail_format, read_binary_details, write_binary_details, sort_binary_details};
 pub use yarrp_error::yarrp_error::YarrpError;
 pub use asn_tree::asn_tree::{ASNTree, ASNTreeRoot, ASNTreeNode};
+pub use asn_origin::asn_origin::{ASNOrigin, ASNOriginKind, ASNMembers, MapASNMembers, MapOrigin, MapSetOrigin, flatten_origins, merge_origins, parse_asn};
 pub use zmap::{ZMAPLine, ZMAPClassification};
-pub use csv_structs::{LoopDensityOutput, ASNLoopEntry, ASNRouterEntry, ASNShadowedResults, ASNShadowedOutput, ShadowedAnswer, ASNIPAttribution};
-pub use loop_info::{SimpleLoopOutput, AdvancedLoopOutput, ShadowedPreceding};
+pub use csv_structs::{LoopDensityOutput, ASNLoopEntry, ASNRouterEntry, ASNShadowedResults, ASNShadowedOutput, ShadowedAnswer, ASNIPAttribution, ORGIPAttribution, CountryEntry, LoopImpactEntry, IncidentEntry, PrefixCoverageEntry, LoopCoverageEntry};
+pub use csv_structs::{QueryResult, QueryRouterResult, QueryLoopResult, QueryDestinationResult};
+pub use csv_structs::{EvidenceResult, EvidenceTraceResult, EvidenceHopResult};
+pub use csv_structs::{ReportResult, ReportLoopResult, ReportRouterResult, ReportPrecedingResult, ReportPrefixResult};
+pub use loop_info::{SimpleLoopOutput, AdvancedLoopOutput, ShadowedPreceding, ShadowedPrecedingCounter};
+pub use evidence::evidence::{EvidenceTrace, EVIDENCE_HEADER, read_evidence_file, count_evidence_traces};
+pub use mrt::mrt::{MrtRibReader, RibEntry, RibOrigin};
+pub use asn_db::asn_db::{ASNDatabase, ASNDatabaseHeader, source_hash, source_metadata, write_asn_database};
 
 use std::collections::{HashMap, HashSet};
 use ipnet::IpNet;
@@ -27,12 +42,46 @@ pub type MapVecT<T> = HashMap<String, Vec<T>>;
 pub type ASNCSVOutput = HashMap<IpNet, Vec<ASNShadowedOutput>>;
 
 use serde::{Serializer};
+use std::cmp::Ordering;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Sort key giving project and report writers a stable order:
+/// numbers (e.g. ASN) numerically, then ip addresses by address, then all other strings (e.g. loop ids) lexicographically
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+pub enum StableKey<'a> {
+    Number(u64),
+    Address(IpAddr),
+    Text(&'a str),
+}
+
+pub fn stable_key(value: &str) -> StableKey<'_> {
+    if let Ok(number) = u64::from_str(value) {
+        StableKey::Number(number)
+    } else if let Ok(address) = IpAddr::from_str(value) {
+        StableKey::Address(address)
+    } else {
+        StableKey::Text(value)
+    }
+}
+
+pub fn stable_cmp(a: &str, b: &str) -> Ordering {
+    stable_key(a).cmp(&stable_key(b))
+}
+
+/// returns the strings of a collection in the order given by stable_key
+pub fn stable_sorted<'a, I>(items: I) -> Vec<&'a String>
+    where I: IntoIterator<Item = &'a String> {
+    let mut sorted: Vec<&String> = items.into_iter().collect();
+    sorted.sort_by(|a, b| stable_cmp(a, b));
+    sorted
+}
 
 pub fn string_set_ser<S>(string_set: &HashSet<String>, s: S) -> Result<S::Ok, S::Error>
     where S: Serializer {
 
     let mut string = String::new();
-    for item in string_set {
+    for item in stable_sorted(string_set) {
         let tmp_str;
         if string.len() == 0 {
             tmp_str = item.to_string();
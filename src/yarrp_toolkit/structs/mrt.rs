@@ -0,0 +1,410 @@
+pub mod mrt {
+    use std::fmt::{self, Display, Formatter};
+    use std::io::{ErrorKind, Read};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+    use itertools::Itertools;
+    use log::{error, trace};
+
+    use crate::structs::YarrpError;
+
+    // MRT type and TABLE_DUMP_V2 subtypes, RFC 6396 and RFC 8050
+    const TABLE_DUMP_V2: u16 = 13;
+    const PEER_INDEX_TABLE: u16 = 1;
+    const RIB_IPV4_UNICAST: u16 = 2;
+    const RIB_IPV6_UNICAST: u16 = 4;
+    const RIB_IPV4_UNICAST_ADDPATH: u16 = 8;
+    const RIB_IPV6_UNICAST_ADDPATH: u16 = 10;
+
+    const MRT_HEADER_LENGTH: usize = 12;
+    /// upper bound of a record body, larger lengths come from corrupt dumps
+    const MAX_RECORD_LENGTH: usize = 16 * 1024 * 1024;
+    const ATTRIBUTE_EXTENDED_LENGTH: u8 = 0x10;
+    const ATTRIBUTE_AS_PATH: u8 = 2;
+    const SEGMENT_AS_SET: u8 = 1;
+    const SEGMENT_AS_SEQUENCE: u8 = 2;
+
+    /// Origin of a route, taken from the last segment of its AS_PATH
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub enum RibOrigin {
+        AS(u32),
+        /// an aggregated route ending with an AS_SET, members are sorted
+        ASSet(Vec<u32>),
+    }
+
+    /// prefix2as notation, members of an AS_SET are separated by ','
+    impl Display for RibOrigin {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                RibOrigin::AS(asn) => write!(f, "{}", asn),
+                RibOrigin::ASSet(members) => write!(f, "{}", members.iter().join(",")),
+            }
+        }
+    }
+
+    /// A prefix of a RIB dump with the distinct origins announced by all peers
+    pub struct RibEntry {
+        pub network: IpNet,
+        pub origins: Vec<RibOrigin>,
+    }
+
+    impl RibEntry {
+        /// origins in prefix2as notation, multiple origins are separated by '_'
+        pub fn asn_string(&self) -> String {
+            self.origins.iter().join("_")
+        }
+    }
+
+    /// Bounds checked reading of big endian values from an MRT record
+    struct MrtBuffer<'a> {
+        data: &'a [u8],
+        position: usize,
+    }
+
+    impl<'a> MrtBuffer<'a> {
+        fn new(data: &'a [u8]) -> MrtBuffer<'a> {
+            MrtBuffer { data, position: 0 }
+        }
+
+        fn remaining(&self) -> usize {
+            self.data.len() - self.position
+        }
+
+        fn bytes(&mut self, length: usize) -> Result<&'a [u8], YarrpError> {
+            if self.remaining() < length {
+                error!("MRT record is truncated!");
+                return Err(YarrpError::CouldNotParseError);
+            }
+            let bytes = &self.data[self.position..self.position + length];
+            self.position += length;
+            Ok(bytes)
+        }
+
+        fn read_u8(&mut self) -> Result<u8, YarrpError> {
+            Ok(self.bytes(1)?[0])
+        }
+
+        fn read_u16(&mut self) -> Result<u16, YarrpError> {
+            let bytes = self.bytes(2)?;
+            Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+        }
+
+        fn read_u32(&mut self) -> Result<u32, YarrpError> {
+            let bytes = self.bytes(4)?;
+            Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+    }
+
+    /// Reads the RIB entries of an uncompressed TABLE_DUMP_V2 dump (RouteViews or RIPE RIS),
+    /// unicast RIBs of both address families are returned, all other records are skipped.
+    pub struct MrtRibReader<R> {
+        reader: R,
+        peer_asns: Vec<u32>,
+    }
+
+    impl<R: Read> MrtRibReader<R> {
+        pub fn new(reader: R) -> MrtRibReader<R> {
+            MrtRibReader {
+                reader,
+                peer_asns: Vec::new(),
+            }
+        }
+
+        /// reads the next record, None at the end of the dump, a dump ending within a header is truncated
+        fn read_record(&mut self) -> Result<Option<(u16, u16, Vec<u8>)>, YarrpError> {
+            let mut header = [0u8; MRT_HEADER_LENGTH];
+            let mut header_read = 0;
+            while header_read < MRT_HEADER_LENGTH {
+                match self.reader.read(&mut header[header_read..]) {
+                    Ok(0) => break,
+                    Ok(read) => header_read += read,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+
+            if header_read == 0 {
+                return Ok(None);
+            }
+            if header_read < MRT_HEADER_LENGTH {
+                error!("MRT dump is truncated, it ends within a record header!");
+                return Err(YarrpError::CouldNotParseError);
+            }
+
+            if header.starts_with(b"BZh") || header.starts_with(&[0x1f, 0x8b]) {
+                error!("MRT dump is compressed, decompress it first!");
+                return Err(YarrpError::NotCompatibleError);
+            }
+
+            let mut buffer = MrtBuffer::new(&header[4..]);
+            let mrt_type = buffer.read_u16()?;
+            let subtype = buffer.read_u16()?;
+            let length = buffer.read_u32()? as usize;
+            if length > MAX_RECORD_LENGTH {
+                error!("MRT record of {} bytes exceeds the limit of {} bytes, the dump is corrupt!", length, MAX_RECORD_LENGTH);
+                return Err(YarrpError::CouldNotParseError);
+            }
+
+            let mut body = vec![0u8; length];
+            self.reader.read_exact(&mut body)?;
+            Ok(Some((mrt_type, subtype, body)))
+        }
+
+        pub fn next_entry(&mut self) -> Result<Option<RibEntry>, YarrpError> {
+            while let Some((mrt_type, subtype, body)) = self.read_record()? {
+                if mrt_type != TABLE_DUMP_V2 {
+                    trace!("Skipping MRT record of type {}", mrt_type);
+                    continue;
+                }
+
+                match subtype {
+                    PEER_INDEX_TABLE => self.peer_asns = parse_peer_index_table(&body)?,
+                    RIB_IPV4_UNICAST => return Ok(Some(self.parse_rib(&body, true, false)?)),
+                    RIB_IPV6_UNICAST => return Ok(Some(self.parse_rib(&body, false, false)?)),
+                    RIB_IPV4_UNICAST_ADDPATH => return Ok(Some(self.parse_rib(&body, true, true)?)),
+                    RIB_IPV6_UNICAST_ADDPATH => return Ok(Some(self.parse_rib(&body, false, true)?)),
+                    _ => trace!("Skipping TABLE_DUMP_V2 subtype {}", subtype),
+                }
+            }
+            Ok(None)
+        }
+
+        fn parse_rib(&self, body: &[u8], is_v4: bool, add_path: bool) -> Result<RibEntry, YarrpError> {
+            let mut buffer = MrtBuffer::new(body);
+            let _sequence = buffer.read_u32()?;
+            let network = parse_prefix(&mut buffer, is_v4)?;
+
+            let mut origins = Vec::new();
+            let entry_count = buffer.read_u16()?;
+            for _ in 0..entry_count {
+                let peer_index = buffer.read_u16()? as usize;
+                let _originated = buffer.read_u32()?;
+                if add_path {
+                    let _path_id = buffer.read_u32()?;
+                }
+                let attribute_length = buffer.read_u16()? as usize;
+                let attributes = buffer.bytes(attribute_length)?;
+
+                let peer_as = self.peer_asns.get(peer_index).cloned();
+                if let Some(origin) = parse_origin(attributes, peer_as)? {
+                    if !origins.contains(&origin) {
+                        origins.push(origin);
+                    }
+                }
+            }
+            origins.sort();
+
+            Ok(RibEntry {
+                network,
+                origins,
+            })
+        }
+    }
+
+    impl<R: Read> Iterator for MrtRibReader<R> {
+        type Item = Result<RibEntry, YarrpError>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.next_entry().transpose()
+        }
+    }
+
+    /// ASNs of the collector peers, RIB entries refer to them by index
+    fn parse_peer_index_table(body: &[u8]) -> Result<Vec<u32>, YarrpError> {
+        let mut buffer = MrtBuffer::new(body);
+        let _collector_id = buffer.read_u32()?;
+        let view_name_length = buffer.read_u16()? as usize;
+        buffer.bytes(view_name_length)?;
+
+        let peer_count = buffer.read_u16()?;
+        let mut peer_asns = Vec::with_capacity(peer_count as usize);
+        for _ in 0..peer_count {
+            let peer_type = buffer.read_u8()?;
+            let _peer_id = buffer.read_u32()?;
+            // bit 0 marks an IPv6 peer address, bit 1 a 4 byte AS number
+            buffer.bytes(if peer_type & 0x01 > 0 { 16 } else { 4 })?;
+            let peer_as = if peer_type & 0x02 > 0 { buffer.read_u32()? } else { buffer.read_u16()? as u32 };
+            peer_asns.push(peer_as);
+        }
+        Ok(peer_asns)
+    }
+
+    fn parse_prefix(buffer: &mut MrtBuffer, is_v4: bool) -> Result<IpNet, YarrpError> {
+        let prefix_len = buffer.read_u8()?;
+        let prefix_bytes = buffer.bytes((prefix_len as usize).div_ceil(8))?;
+
+        let network = if is_v4 {
+            let mut octets = [0u8; 4];
+            if prefix_bytes.len() > octets.len() {
+                return Err(YarrpError::CouldNotParseError);
+            }
+            octets[..prefix_bytes.len()].copy_from_slice(prefix_bytes);
+            IpNet::V4(Ipv4Net::new(Ipv4Addr::from(octets), prefix_len)?)
+        } else {
+            let mut octets = [0u8; 16];
+            if prefix_bytes.len() > octets.len() {
+                return Err(YarrpError::CouldNotParseError);
+            }
+            octets[..prefix_bytes.len()].copy_from_slice(prefix_bytes);
+            IpNet::V6(Ipv6Net::new(Ipv6Addr::from(octets), prefix_len)?)
+        };
+
+        Ok(network.trunc())
+    }
+
+    /// Origin of a route from its path attributes. TABLE_DUMP_V2 always encodes AS_PATH with 4 byte ASNs.
+    /// An empty AS_PATH is a route originated by the peer itself.
+    fn parse_origin(attributes: &[u8], peer_as: Option<u32>) -> Result<Option<RibOrigin>, YarrpError> {
+        let mut buffer = MrtBuffer::new(attributes);
+        while buffer.remaining() > 0 {
+            let flags = buffer.read_u8()?;
+            let attribute_type = buffer.read_u8()?;
+            let length = if flags & ATTRIBUTE_EXTENDED_LENGTH > 0 { buffer.read_u16()? as usize } else { buffer.read_u8()? as usize };
+            let value = buffer.bytes(length)?;
+
+            if attribute_type == ATTRIBUTE_AS_PATH {
+                return Ok(parse_as_path(value)?.or_else(|| peer_as.map(RibOrigin::AS)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// origin from the last AS_SEQUENCE or AS_SET segment, confederation segments are skipped
+    fn parse_as_path(as_path: &[u8]) -> Result<Option<RibOrigin>, YarrpError> {
+        let mut buffer = MrtBuffer::new(as_path);
+        let mut origin = None;
+
+        while buffer.remaining() > 0 {
+            let segment_type = buffer.read_u8()?;
+            let count = buffer.read_u8()?;
+            let mut asns = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                asns.push(buffer.read_u32()?);
+            }
+
+            match segment_type {
+                SEGMENT_AS_SEQUENCE => {
+                    if let Some(asn) = asns.last() {
+                        origin = Some(RibOrigin::AS(*asn));
+                    }
+                }
+                SEGMENT_AS_SET => {
+                    asns.sort_unstable();
+                    asns.dedup();
+                    origin = match asns.len() {
+                        0 => origin,
+                        1 => Some(RibOrigin::AS(asns[0])),
+                        _ => Some(RibOrigin::ASSet(asns)),
+                    };
+                }
+                _ => trace!("Skipping AS_PATH segment type {}", segment_type),
+            }
+        }
+        Ok(origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use ipnet::IpNet;
+    use crate::structs::{MrtRibReader, RibEntry, RibOrigin};
+
+    fn record(subtype: u16, body: &[u8]) -> Vec<u8> {
+        let mut record = vec![0, 0, 0, 0];
+        record.extend_from_slice(&13u16.to_be_bytes());
+        record.extend_from_slice(&subtype.to_be_bytes());
+        record.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        record.extend_from_slice(body);
+        record
+    }
+
+    fn peer_index_table() -> Vec<u8> {
+        let mut body = vec![10, 0, 0, 1, 0, 0, 0, 2];
+        // IPv4 peer with 2 byte AS 64500
+        body.extend_from_slice(&[0x00, 10, 0, 0, 2, 192, 0, 2, 1]);
+        body.extend_from_slice(&64500u16.to_be_bytes());
+        // IPv6 peer with 4 byte AS 4200000000
+        body.extend_from_slice(&[0x03, 10, 0, 0, 3]);
+        body.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        body.extend_from_slice(&4_200_000_000u32.to_be_bytes());
+        record(1, &body)
+    }
+
+    fn as_path(segments: &[(u8, &[u32])]) -> Vec<u8> {
+        let mut value = Vec::new();
+        for (segment_type, asns) in segments {
+            value.push(*segment_type);
+            value.push(asns.len() as u8);
+            for asn in asns.iter() {
+                value.extend_from_slice(&asn.to_be_bytes());
+            }
+        }
+        // ORIGIN attribute first, then AS_PATH
+        let mut attributes = vec![0x40, 1, 1, 0, 0x40, 2, value.len() as u8];
+        attributes.extend_from_slice(&value);
+        attributes
+    }
+
+    fn rib(subtype: u16, prefix: &[u8], entries: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut body = vec![0, 0, 0, 1];
+        body.extend_from_slice(prefix);
+        body.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+        for (peer_index, attributes) in entries {
+            body.extend_from_slice(&peer_index.to_be_bytes());
+            body.extend_from_slice(&[0, 0, 0, 0]);
+            body.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+            body.extend_from_slice(attributes);
+        }
+        record(subtype, &body)
+    }
+
+    #[test]
+    fn read_rib_entries() {
+        let mut dump = peer_index_table();
+        // 192.0.2.0/24, both peers see origin 64496, one with a prepended path
+        dump.extend(rib(2, &[24, 192, 0, 2], &[(0, as_path(&[(2, &[64500, 64496])])), (1, as_path(&[(2, &[64501, 64496, 64496])]))]));
+        // 198.51.100.0/22 is a MOAS prefix, one peer sees an aggregate ending in an AS_SET
+        dump.extend(rib(2, &[22, 198, 51, 100], &[(0, as_path(&[(2, &[64500, 64510])])), (1, as_path(&[(2, &[64501]), (1, &[64512, 64511, 64512])]))]));
+        // 2001:db8::/32 originated by the peer itself, empty AS_PATH
+        dump.extend(rib(4, &[32, 0x20, 0x01, 0x0d, 0xb8], &[(1, as_path(&[]))]));
+
+        let entries: Vec<RibEntry> = MrtRibReader::new(dump.as_slice()).map(|entry| entry.ok().unwrap()).collect();
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].network, IpNet::from_str("192.0.2.0/24").unwrap());
+        assert_eq!(entries[0].origins, vec![RibOrigin::AS(64496)]);
+
+        assert_eq!(entries[1].network, IpNet::from_str("198.51.100.0/22").unwrap());
+        assert_eq!(entries[1].origins, vec![RibOrigin::AS(64510), RibOrigin::ASSet(vec![64511, 64512])]);
+        assert_eq!(entries[1].asn_string(), "64510_64511,64512");
+
+        assert_eq!(entries[2].network, IpNet::from_str("2001:db8::/32").unwrap());
+        assert_eq!(entries[2].asn_string(), "4200000000", "Empty AS_PATH should fall back to the peer AS");
+    }
+
+    #[test]
+    fn compressed_dump_rejected() {
+        let dump = b"BZh91AY&SY\x00\x00\x00\x00".to_vec();
+        assert!(MrtRibReader::new(dump.as_slice()).next().unwrap().is_err());
+    }
+
+    #[test]
+    fn truncated_dump_rejected() {
+        let dump = peer_index_table();
+        assert!(MrtRibReader::new(dump.as_slice()).next().is_none(), "A complete dump ends without error");
+
+        let mut truncated = dump.clone();
+        truncated.extend_from_slice(&[0, 0, 0, 0, 0, 13]);
+        assert!(MrtRibReader::new(truncated.as_slice()).next().unwrap().is_err(), "Dump ends within a header");
+
+        let truncated = &dump[..dump.len() - 1];
+        assert!(MrtRibReader::new(truncated).next().unwrap().is_err(), "Dump ends within a body");
+
+        let mut oversized = vec![0, 0, 0, 0];
+        oversized.extend_from_slice(&13u16.to_be_bytes());
+        oversized.extend_from_slice(&2u16.to_be_bytes());
+        oversized.extend_from_slice(&u32::MAX.to_be_bytes());
+        assert!(MrtRibReader::new(oversized.as_slice()).next().unwrap().is_err(), "Record length exceeds the limit");
+    }
+}
@@ -0,0 +1,221 @@
+pub mod output_file {
+    use std::fs::{self, File};
+    use std::io::{self, Write, BufWriter, Seek, SeekFrom};
+    use std::path::{Path, PathBuf};
+    use clap::ArgMatches;
+    use log::error;
+
+    use crate::structs::YarrpError;
+
+    /// How modes treat output files that already exist
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum OutputPolicy {
+        /// refuse to touch existing output, the default
+        Refuse,
+        /// replace existing output
+        Overwrite,
+        /// keep existing output and add to it
+        Append,
+    }
+
+    impl OutputPolicy {
+        /// reads the global --overwrite and --append flags
+        pub fn from_matches(matches: &ArgMatches) -> OutputPolicy {
+            if matches.occurrences_of("overwrite") > 0 {
+                OutputPolicy::Overwrite
+            } else if matches.occurrences_of("append") > 0 {
+                OutputPolicy::Append
+            } else {
+                OutputPolicy::Refuse
+            }
+        }
+
+        /// checks whether the given file or directory may be written
+        pub fn check(&self, path: &Path) -> Result<(), YarrpError> {
+            if *self == OutputPolicy::Refuse && output_exists(path) {
+                error!("{} already exists, use --overwrite or --append!", path.to_str().unwrap_or(""));
+                return Err(YarrpError::OutputExistsError);
+            }
+            Ok(())
+        }
+    }
+
+    /// files count as existing output if they are not empty, directories if they contain anything
+    pub fn output_exists(path: &Path) -> bool {
+        if path.is_dir() {
+            fs::read_dir(path).map(|mut entries| entries.next().is_some()).unwrap_or(false)
+        } else {
+            path.metadata().map(|metadata| metadata.len() > 0).unwrap_or(false)
+        }
+    }
+
+    /// name a file is written under before it is renamed to path
+    pub fn temp_path(path: &Path) -> PathBuf {
+        let mut file_name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+        file_name.push(".tmp");
+        path.with_file_name(file_name)
+    }
+
+    /// creates the empty temporary file for path
+    pub fn prepare_temp_file(path: &Path) -> Result<PathBuf, YarrpError> {
+        let temp = temp_path(path);
+        File::create(&temp)?;
+        Ok(temp)
+    }
+
+    /// A file written under a temporary name next to its path and renamed to it by commit.
+    /// Dropping an AtomicFile without commit removes the temporary file and leaves path untouched.
+    /// Appending writes to path in place instead, as copying the existing content on every
+    /// rotation grows quadratically, commit then only flushes.
+    pub struct AtomicFile {
+        writer: Option<BufWriter<File>>,
+        path: PathBuf,
+        /// None when appending in place
+        temp_path: Option<PathBuf>,
+    }
+
+    impl AtomicFile {
+        /// starts an empty file, replacing path on commit
+        pub fn create(path: &Path) -> Result<AtomicFile, YarrpError> {
+            AtomicFile::start(path, false)
+        }
+
+        /// appends to path in place, creating it if it does not exist
+        pub fn append(path: &Path) -> Result<AtomicFile, YarrpError> {
+            AtomicFile::start(path, true)
+        }
+
+        /// opens path according to the output policy
+        pub fn open(path: &Path, policy: OutputPolicy) -> Result<AtomicFile, YarrpError> {
+            policy.check(path)?;
+            AtomicFile::start(path, policy == OutputPolicy::Append)
+        }
+
+        fn start(path: &Path, append: bool) -> Result<AtomicFile, YarrpError> {
+            if append {
+                let file = fs::OpenOptions::new().append(true).create(true).open(path)?;
+                return Ok(AtomicFile {
+                    writer: Some(BufWriter::new(file)),
+                    path: path.to_path_buf(),
+                    temp_path: None,
+                });
+            }
+
+            let temp_path = Some(prepare_temp_file(path)?);
+            let file = fs::OpenOptions::new().write(true).open(temp_path.as_ref().unwrap())?;
+
+            Ok(AtomicFile {
+                writer: Some(BufWriter::new(file)),
+                path: path.to_path_buf(),
+                temp_path,
+            })
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// flushes the temporary file and renames it to path
+        pub fn commit(mut self) -> Result<(), YarrpError> {
+            if let Some(mut writer) = self.writer.take() {
+                writer.flush()?;
+                drop(writer);
+                if let Some(temp_path) = &self.temp_path {
+                    fs::rename(temp_path, &self.path)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Write for AtomicFile {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self.writer.as_mut() {
+                Some(writer) => writer.write(buf),
+                None => Err(io::Error::other("file already committed")),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            match self.writer.as_mut() {
+                Some(writer) => writer.flush(),
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl Seek for AtomicFile {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            match self.writer.as_mut() {
+                Some(writer) => writer.seek(pos),
+                None => Err(io::Error::other("file already committed")),
+            }
+        }
+    }
+
+    impl Drop for AtomicFile {
+        fn drop(&mut self) {
+            // appended content is kept, its buffer is flushed when the writer is dropped
+            if let Some(temp_path) = &self.temp_path {
+                if self.writer.take().is_some() {
+                    let _ = fs::remove_file(temp_path);
+                }
+            }
+        }
+    }
+
+    /// csv writer writing to an AtomicFile, finish it with commit_csv
+    pub fn atomic_csv_writer(path: &Path) -> Result<csv::Writer<AtomicFile>, YarrpError> {
+        Ok(csv::Writer::from_writer(AtomicFile::create(path)?))
+    }
+
+    pub fn commit_csv(writer: csv::Writer<AtomicFile>) -> Result<(), YarrpError> {
+        match writer.into_inner() {
+            Ok(file) => file.commit(),
+            Err(_) => {
+                error!("Could not flush csv writer!");
+                Err(YarrpError::CouldNotWriteError)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use crate::helpers::test_helper::TempFixture;
+    use crate::structs::{AtomicFile, OutputPolicy, temp_path};
+
+    #[test]
+    fn commit_replaces_and_drop_keeps_original() {
+        let file = TempFixture::file("atomic.txt", "old\n");
+        let path = file.path();
+
+        assert!(AtomicFile::open(path, OutputPolicy::Refuse).is_err(), "Existing output should be refused");
+
+        {
+            let mut file = AtomicFile::open(path, OutputPolicy::Overwrite).ok().unwrap();
+            file.write_all(b"unfinished\n").unwrap();
+        }
+        assert_eq!(fs::read_to_string(path).unwrap(), "old\n");
+        assert!(!temp_path(path).exists(), "Dropped file should remove its temporary file");
+
+        let mut file = AtomicFile::open(path, OutputPolicy::Append).ok().unwrap();
+        file.write_all(b"new\n").unwrap();
+        assert!(!temp_path(path).exists(), "Appending should not copy the existing file");
+        file.commit().ok().unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "old\nnew\n");
+
+        {
+            let mut file = AtomicFile::append(path).ok().unwrap();
+            file.write_all(b"kept\n").unwrap();
+        }
+        assert_eq!(fs::read_to_string(path).unwrap(), "old\nnew\nkept\n", "Appended lines are kept without commit");
+
+        let mut file = AtomicFile::open(path, OutputPolicy::Overwrite).ok().unwrap();
+        file.write_all(b"replaced\n").unwrap();
+        file.commit().ok().unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "replaced\n");
+    }
+}
@@ -9,7 +9,8 @@ pub mod yarrp_error {
         ESError,
         CouldNotWriteError,
         CouldNotGlobError,
-        TimeError
+        TimeError,
+        OutputExistsError
     }
 
     impl From<std::io::Error> for YarrpError{
@@ -1,6 +1,7 @@
 pub mod yarrp_line {
     use log::error;
     use std::str::FromStr;
+    use std::fmt::{Display, Formatter};
 
     // a yarrp output line is structured as following
     // target sec usec type code ttl hop rtt ipid psize rsize rttl rtos mpls count
@@ -64,4 +65,13 @@ pub mod yarrp_line {
             })
         }
     }
-}
\ No newline at end of file
+
+    impl<T: Display> Display for YarrpLine<T> {
+        /// formats the line the way yarrp writes it, so it can be parsed again with YarrpLine::new
+        fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+            write!(f, "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+                   self.destination, self.sec, self.usec, self.r_type, self.r_code, self.sent_ttl, self.hop,
+                   self.rtt, self.ipid, self.psize, self.rsize, self.received_ttl, self.rtos, self.mpls, self.count)
+        }
+    }
+}
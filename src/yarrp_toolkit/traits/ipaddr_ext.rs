@@ -4,6 +4,7 @@ pub mod ipaddr_ext {
     use crate::structs::{YarrpError, YarrpLine};
     use rand::{RngCore, Rng};
     use std::str::FromStr;
+    use std::convert::TryInto;
     use log::{debug};
 
     pub enum ICMPMode {
@@ -16,6 +17,7 @@ pub mod ipaddr_ext {
         fn to_network_with_prefix_length(&self, prefix_length: u8) -> Result<IpNet, YarrpError>;
         fn to_ipaddr(&self) -> Result<IpAddr, YarrpError>;
         fn ls_octets(&self) -> Vec<u8>;
+        fn from_ls_octets(octets: &[u8]) -> Result<Self, YarrpError> where Self: Sized;
         fn check_errors<T: IpAddrExt>(yarrp_line: &YarrpLine<T>) -> ICMPMode;
         fn create_target(input: &str, rng: &mut rand_pcg::Lcg128Xsl64) -> Result<String, YarrpError>;
         fn create_network_from_string(input: &str) -> Result<IpNet, YarrpError>;
@@ -40,6 +42,11 @@ pub mod ipaddr_ext {
             Vec::from(self.octets())
         }
 
+        fn from_ls_octets(octets: &[u8]) -> Result<Self, YarrpError> {
+            let octets: [u8; 16] = octets.try_into().map_err(|_| YarrpError::CouldNotParseError)?;
+            Ok(Ipv6Addr::from(octets))
+        }
+
         fn check_errors<Ipv6Addr: IpAddrExt>(yarrp_line: &YarrpLine<Ipv6Addr>) -> ICMPMode {
             if yarrp_line.r_type == 129 && yarrp_line.r_code == 0 {
                 return ICMPMode::ICMPReply;
@@ -109,6 +116,11 @@ pub mod ipaddr_ext {
             Vec::from(self.octets())
         }
 
+        fn from_ls_octets(octets: &[u8]) -> Result<Self, YarrpError> {
+            let octets: [u8; 4] = octets.try_into().map_err(|_| YarrpError::CouldNotParseError)?;
+            Ok(Ipv4Addr::from(octets))
+        }
+
         fn check_errors<Ipv4Addr: IpAddrExt>(yarrp_line: &YarrpLine<Ipv4Addr>) -> ICMPMode {
             if yarrp_line.r_type == 9 && yarrp_line.r_code == 0 {
                 return ICMPMode::ICMPReply;